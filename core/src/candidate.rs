@@ -3,10 +3,40 @@
 //! This module provides:
 //! - `Candidate`: A single text candidate with score
 //! - `CandidateList`: Paginated list with cursor navigation
+//! - `CandidateSource`: Where a candidate came from
+//! - `CandidateFfi`: Flat, owned candidate data for FFI layers
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::{c_char, CString};
 use std::ops::Range;
 
+/// Candidates per page of a new `CandidateList` (and of the IME sessions
+/// built on it).
+pub const DEFAULT_PAGE_SIZE: usize = 5;
+
+/// Where a candidate came from.
+///
+/// The discriminants are stable and used as the `source` code in
+/// `CandidateFfi`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum CandidateSource {
+    /// Direct lexicon entry for the whole input
+    #[default]
+    Lexicon = 0,
+    /// Composed from several lexicon words
+    Composed = 1,
+    /// Next-word prediction (suggestion mode)
+    Prediction = 2,
+    /// Punctuation alternative
+    Punctuation = 3,
+    /// Lexicon entry the user has learned (selected before)
+    UserDict = 4,
+    /// Emoji for a keyword (see `Engine::load_emoji_lexicon`)
+    Emoji = 5,
+}
+
 /// A single text candidate with an associated score.
 ///
 /// Scores are on a relative scale; higher is better. Use `f32` for compactness
@@ -15,6 +45,12 @@ use std::ops::Range;
 pub struct Candidate {
     pub text: String,
     pub score: f32,
+    #[serde(default)]
+    pub source: CandidateSource,
+    /// Syllables the candidate was converted from, joined with `'`
+    /// (e.g. "xi'an"), when known.
+    #[serde(default)]
+    pub reading: Option<String>,
 }
 
 impl Candidate {
@@ -22,12 +58,68 @@ impl Candidate {
         Candidate {
             text: text.into(),
             score,
+            source: CandidateSource::default(),
+            reading: None,
+        }
+    }
+
+    /// Set the candidate's source (builder style).
+    pub fn with_source(mut self, source: CandidateSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Set the candidate's reading (builder style).
+    pub fn with_reading<T: Into<String>>(mut self, reading: T) -> Self {
+        self.reading = Some(reading.into());
+        self
+    }
+}
+
+/// Flat, owned candidate data for crossing an FFI boundary.
+///
+/// The text is an owned, null-terminated `CString`, so pointers obtained via
+/// `text_ptr()` / `as_tuple()` stay valid for as long as this value lives.
+/// Interior NUL bytes (never produced by the engine) are dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateFfi {
+    pub text: CString,
+    pub score: f32,
+    /// `CandidateSource` discriminant
+    pub source: u32,
+}
+
+impl CandidateFfi {
+    /// Flatten a candidate.
+    pub fn from_candidate(candidate: &Candidate) -> Self {
+        let bytes: Vec<u8> = candidate.text.bytes().filter(|&b| b != 0).collect();
+        Self {
+            // Cannot fail: interior NULs were filtered out above
+            text: CString::new(bytes).unwrap_or_default(),
+            score: candidate.score,
+            source: candidate.source as u32,
         }
     }
+
+    /// Pointer to the null-terminated UTF-8 text (borrowed from `self`).
+    pub fn text_ptr(&self) -> *const c_char {
+        self.text.as_ptr()
+    }
+
+    /// `(text, score, source)` view for C callers.
+    pub fn as_tuple(&self) -> (*const c_char, f32, u32) {
+        (self.text_ptr(), self.score, self.source)
+    }
+}
+
+impl From<&Candidate> for CandidateFfi {
+    fn from(candidate: &Candidate) -> Self {
+        Self::from_candidate(candidate)
+    }
 }
 
 /// A paginated list of candidates with cursor navigation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CandidateList {
     /// All available candidates
     candidates: Vec<Candidate>,
@@ -47,7 +139,7 @@ impl CandidateList {
     pub fn new() -> Self {
         Self {
             candidates: Vec::new(),
-            page_size: 5,
+            page_size: DEFAULT_PAGE_SIZE,
             current_page: 0,
             cursor: 0,
         }
@@ -67,22 +159,24 @@ impl CandidateList {
     pub fn from_candidates(candidates: Vec<Candidate>) -> Self {
         Self {
             candidates,
-            page_size: 5,
+            page_size: DEFAULT_PAGE_SIZE,
             current_page: 0,
             cursor: 0,
         }
     }
 
     /// Set the page size.
+    ///
+    /// The selected candidate stays selected: the page and cursor are
+    /// recomputed from its global index.
     pub fn set_page_size(&mut self, page_size: usize) {
+        let selected = self.selected_index();
         self.page_size = page_size.max(1);
-        // Reset to first page if current page is now out of bounds
-        if self.current_page >= self.num_pages() && self.num_pages() > 0 {
-            self.current_page = 0;
-        }
-        // Reset cursor if it's out of bounds
-        if self.cursor >= self.current_page_len() && self.current_page_len() > 0 {
-            self.cursor = 0;
+        match selected {
+            Some(index) => {
+                self.set_selected_index(index);
+            }
+            None => self.reset(),
         }
     }
 
@@ -174,6 +268,17 @@ impl CandidateList {
         }
     }
 
+    /// Highlight a candidate by its global index, switching pages as needed.
+    /// Returns false (leaving the selection alone) if the index is out of range.
+    pub fn set_selected_index(&mut self, index: usize) -> bool {
+        if index >= self.candidates.len() {
+            return false;
+        }
+        self.current_page = index / self.page_size;
+        self.cursor = index % self.page_size;
+        true
+    }
+
     /// Move cursor up (to previous candidate on current page).
     /// Returns true if the cursor moved.
     pub fn cursor_up(&mut self) -> bool {
@@ -230,6 +335,16 @@ impl CandidateList {
         }
     }
 
+    /// Convert an index within the current page to an index into the whole
+    /// list. Returns None if the page has no candidate at that index.
+    pub fn global_index(&self, page_index: usize) -> Option<usize> {
+        if page_index < self.current_page_len() {
+            Some(self.current_page * self.page_size + page_index)
+        } else {
+            None
+        }
+    }
+
     /// Select a candidate by index within the current page.
     /// Returns the selected candidate if the index is valid.
     pub fn select_by_index(&mut self, page_index: usize) -> Option<&Candidate> {
@@ -241,6 +356,78 @@ impl CandidateList {
         }
     }
 
+    /// Candidates from one source, in list order.
+    pub fn candidates_from(&self, source: CandidateSource) -> Vec<&Candidate> {
+        self.candidates
+            .iter()
+            .filter(|c| c.source == source)
+            .collect()
+    }
+
+    /// Split candidates into a high-confidence tier and the rest.
+    ///
+    /// The split happens at the first drop of at least `gap` between adjacent
+    /// scores (candidates are expected in ranked order). Without such a drop
+    /// everything lands in the first tier.
+    pub fn tier_split(&self, gap: f32) -> (Vec<&Candidate>, Vec<&Candidate>) {
+        let split = self
+            .candidates
+            .windows(2)
+            .position(|pair| pair[0].score - pair[1].score >= gap)
+            .map_or(self.candidates.len(), |i| i + 1);
+
+        let (high, other) = self.candidates.split_at(split);
+        (high.iter().collect(), other.iter().collect())
+    }
+
+    /// Remove duplicate texts, keeping the highest-scored instance of each.
+    ///
+    /// The survivor takes the position of the text's first occurrence, so
+    /// list order is otherwise preserved. The selection is clamped to the
+    /// shortened list.
+    pub fn dedup_by_text(&mut self) {
+        let mut first_pos: HashMap<String, usize> = HashMap::new();
+        let mut kept: Vec<Candidate> = Vec::with_capacity(self.candidates.len());
+        for cand in self.candidates.drain(..) {
+            match first_pos.get(&cand.text) {
+                Some(&pos) => {
+                    if cand.score > kept[pos].score {
+                        kept[pos] = cand;
+                    }
+                }
+                None => {
+                    first_pos.insert(cand.text.clone(), kept.len());
+                    kept.push(cand);
+                }
+            }
+        }
+        self.candidates = kept;
+        self.clamp_selection();
+    }
+
+    /// Keep only the candidates matching `predicate` (in order).
+    ///
+    /// The selection is clamped to the shortened list.
+    pub fn filter(&mut self, predicate: impl Fn(&Candidate) -> bool) {
+        self.candidates.retain(|c| predicate(c));
+        self.clamp_selection();
+    }
+
+    /// Move an out-of-range selection to the last candidate (or reset it
+    /// when the list is empty).
+    fn clamp_selection(&mut self) {
+        if self.candidates.is_empty() {
+            self.reset();
+        } else if self.selected_index().is_none() {
+            self.set_selected_index(self.candidates.len() - 1);
+        }
+    }
+
+    /// Flatten all candidates into owned FFI representations (in list order).
+    pub fn to_ffi_vec(&self) -> Vec<CandidateFfi> {
+        self.candidates.iter().map(CandidateFfi::from).collect()
+    }
+
     /// Clear the candidate list.
     pub fn clear(&mut self) {
         self.candidates.clear();
@@ -254,3 +441,171 @@ impl CandidateList {
         self.cursor = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn ffi_vec_matches_candidates() {
+        let list = CandidateList::from_candidates(vec![
+            Candidate::new("你好", 1.5),
+            Candidate::new("拟好", -0.5).with_source(CandidateSource::Composed),
+            Candidate::new("，", 1.0).with_source(CandidateSource::Punctuation),
+        ]);
+
+        let ffi = list.to_ffi_vec();
+        assert_eq!(ffi.len(), list.len());
+        for (flat, cand) in ffi.iter().zip(list.candidates()) {
+            assert_eq!(flat.text.to_str().unwrap(), cand.text);
+            assert_eq!(flat.score, cand.score);
+            assert_eq!(flat.source, cand.source as u32);
+        }
+        assert_eq!(ffi[0].source, 0);
+        assert_eq!(ffi[1].source, 1);
+        assert_eq!(ffi[2].source, 3);
+    }
+
+    #[test]
+    fn ffi_strings_are_owned_and_null_terminated() {
+        let ffi = {
+            let list = CandidateList::from_candidates(vec![Candidate::new("中国", 0.0)]);
+            list.to_ffi_vec()
+        };
+        // The list is gone; the flattened data still owns its text
+        let (ptr, score, source) = ffi[0].as_tuple();
+        let text = unsafe { CStr::from_ptr(ptr) };
+        assert_eq!(text.to_str().unwrap(), "中国");
+        assert_eq!(text.to_bytes_with_nul().last(), Some(&0));
+        assert_eq!(score, 0.0);
+        assert_eq!(source, CandidateSource::Lexicon as u32);
+    }
+
+    fn texts(tier: &[&Candidate]) -> Vec<String> {
+        tier.iter().map(|c| c.text.clone()).collect()
+    }
+
+    #[test]
+    fn tier_split_at_first_large_drop() {
+        let list = CandidateList::from_candidates(vec![
+            Candidate::new("你好", 10.0),
+            Candidate::new("你号", 9.5),
+            Candidate::new("拟好", 4.0),
+            Candidate::new("泥好", 3.8),
+            Candidate::new("逆号", 0.5),
+        ]);
+
+        let (high, other) = list.tier_split(2.0);
+        assert_eq!(texts(&high), vec!["你好", "你号"]);
+        assert_eq!(texts(&other), vec!["拟好", "泥好", "逆号"]);
+    }
+
+    #[test]
+    fn tier_split_uniform_scores_stay_in_first_tier() {
+        let list = CandidateList::from_candidates(vec![
+            Candidate::new("一", 1.0),
+            Candidate::new("衣", 1.0),
+            Candidate::new("医", 1.0),
+        ]);
+
+        let (high, other) = list.tier_split(0.5);
+        assert_eq!(high.len(), 3);
+        assert!(other.is_empty());
+
+        let empty = CandidateList::new();
+        let (high, other) = empty.tier_split(0.5);
+        assert!(high.is_empty() && other.is_empty());
+    }
+
+    #[test]
+    fn set_selected_index_switches_pages() {
+        let mut list = CandidateList::with_page_size(2);
+        list.set_candidates(
+            ["一", "二", "三", "四", "五"]
+                .iter()
+                .map(|t| Candidate::new(*t, 0.0))
+                .collect(),
+        );
+
+        assert!(list.set_selected_index(3));
+        assert_eq!((list.current_page(), list.cursor()), (1, 1));
+        assert_eq!(list.selected_index(), Some(3));
+        assert_eq!(list.selected_candidate().unwrap().text, "四");
+
+        assert!(!list.set_selected_index(5));
+        assert_eq!(list.selected_index(), Some(3));
+    }
+
+    #[test]
+    fn dedup_by_text_keeps_best_score() {
+        let mut list = CandidateList::from_candidates(vec![
+            Candidate::new("你好", 5.0),
+            Candidate::new("拟好", 4.0),
+            Candidate::new("你好", 7.0).with_source(CandidateSource::Composed),
+            Candidate::new("泥好", 3.0),
+            Candidate::new("拟好", 1.0),
+        ]);
+        assert!(list.set_selected_index(4));
+
+        list.dedup_by_text();
+        let summary: Vec<(&str, f32)> = list
+            .candidates()
+            .iter()
+            .map(|c| (c.text.as_str(), c.score))
+            .collect();
+        assert_eq!(summary, vec![("你好", 7.0), ("拟好", 4.0), ("泥好", 3.0)]);
+        assert_eq!(list.candidates()[0].source, CandidateSource::Composed);
+        assert_eq!(list.selected_index(), Some(2));
+    }
+
+    #[test]
+    fn filter_clamps_out_of_range_cursor() {
+        let mut list = CandidateList::with_page_size(2);
+        list.set_candidates(
+            ["一", "二", "三", "四", "五"]
+                .iter()
+                .map(|t| Candidate::new(*t, 0.0))
+                .collect(),
+        );
+        assert!(list.set_selected_index(4));
+
+        list.filter(|c| c.text != "四" && c.text != "五");
+        assert_eq!(list.len(), 3);
+        assert_eq!((list.current_page(), list.cursor()), (1, 0));
+        assert_eq!(list.selected_candidate().unwrap().text, "三");
+
+        // A selection still in range is left alone
+        assert!(list.set_selected_index(1));
+        list.filter(|c| c.text != "三");
+        assert_eq!(list.selected_candidate().unwrap().text, "二");
+
+        list.filter(|_| false);
+        assert!(list.is_empty());
+        assert_eq!(list.selected_candidate(), None);
+        assert_eq!((list.current_page(), list.cursor()), (0, 0));
+    }
+
+    #[test]
+    fn candidates_from_groups_by_source() {
+        let list = CandidateList::from_candidates(vec![
+            Candidate::new("你好", 3.0).with_source(CandidateSource::UserDict),
+            Candidate::new("拟好", 2.0),
+            Candidate::new("你号", 1.0).with_source(CandidateSource::UserDict),
+        ]);
+
+        let learned = list.candidates_from(CandidateSource::UserDict);
+        assert_eq!(texts(&learned), vec!["你好", "你号"]);
+        assert_eq!(
+            texts(&list.candidates_from(CandidateSource::Lexicon)),
+            vec!["拟好"]
+        );
+        assert!(list.candidates_from(CandidateSource::Prediction).is_empty());
+    }
+
+    #[test]
+    fn ffi_drops_interior_nul() {
+        let flat = CandidateFfi::from_candidate(&Candidate::new("a\0b", 0.0));
+        assert_eq!(flat.text.to_str().unwrap(), "ab");
+    }
+}
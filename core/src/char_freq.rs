@@ -0,0 +1,59 @@
+// core/src/char_freq.rs
+//
+// Bundled common-character table used to spot rare characters in candidates.
+//
+// The table (data/common_chars.txt) lists the most frequent Han characters
+// for both simplified and traditional text. A Han character outside the table
+// counts as rare; non-Han characters (digits, punctuation, emoji) never do.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+const COMMON_CHARS: &str = include_str!("../data/common_chars.txt");
+
+fn common_chars() -> &'static HashSet<char> {
+    static TABLE: OnceLock<HashSet<char>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        COMMON_CHARS
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    })
+}
+
+/// True for CJK ideographs (URO, Extension A and the supplementary planes).
+pub fn is_han_char(ch: char) -> bool {
+    matches!(ch,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{3FFFF}')
+}
+
+/// Check whether a character is rare (a Han character outside the common table).
+pub fn is_rare_char(ch: char) -> bool {
+    is_han_char(ch) && !common_chars().contains(&ch)
+}
+
+/// Count rare characters in a phrase.
+pub fn rare_char_count(text: &str) -> usize {
+    text.chars().filter(|&c| is_rare_char(c)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_and_rare_characters() {
+        assert!(!is_rare_char('的'));
+        assert!(!is_rare_char('們'));
+        assert!(is_rare_char('龘'));
+        assert!(!is_rare_char('a'));
+        assert!(!is_rare_char('，'));
+        assert_eq!(rare_char_count("你好"), 0);
+        assert_eq!(rare_char_count("龘龘好"), 2);
+    }
+}
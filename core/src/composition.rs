@@ -4,13 +4,14 @@
 //! including the converted text and segment boundaries. For example, typing
 //! "nihao" might show "你好" as the preedit, with segments marking each word.
 
+use serde::{Deserialize, Serialize};
 use std::ops::Range;
 
 /// A segment in the preedit composition.
 ///
 /// Segments mark boundaries between different parts of the composition,
 /// such as individual words or syllables that can be converted independently.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Segment {
     /// Byte range in the preedit text
     pub range: Range<usize>,
@@ -22,7 +23,7 @@ pub struct Segment {
 ///
 /// This represents the visual text shown to the user during input composition,
 /// along with cursor position and segment boundaries.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Composition {
     /// The preedit text to display (e.g., "你好")
     pub preedit: String,
@@ -122,4 +123,58 @@ impl Composition {
     pub fn segment_text(&self, segment: &Segment) -> &str {
         &self.preedit[segment.range.clone()]
     }
+
+    /// The preedit with the segment under the cursor wrapped in `open` and
+    /// `close`, e.g. "ni[hao]" for terminals that cannot style ranges.
+    ///
+    /// Without a segment at the cursor the plain preedit is returned.
+    pub fn marked_preedit(&self, open: &str, close: &str) -> String {
+        let Some(range) = self.segment_at_cursor().map(|seg| seg.range.clone()) else {
+            return self.preedit.clone();
+        };
+
+        let mut out = String::with_capacity(self.preedit.len() + open.len() + close.len());
+        out.push_str(&self.preedit[..range.start]);
+        out.push_str(open);
+        out.push_str(&self.preedit[range.clone()]);
+        out.push_str(close);
+        out.push_str(&self.preedit[range.end..]);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nihao() -> Composition {
+        let mut comp = Composition::with_cursor("nihao".to_string(), 2);
+        comp.add_segment(0..2, false);
+        comp.add_segment(2..5, false);
+        comp
+    }
+
+    #[test]
+    fn marked_preedit_wraps_active_segment() {
+        let mut comp = nihao();
+        assert_eq!(comp.marked_preedit("[", "]"), "ni[hao]");
+
+        comp.set_cursor(0);
+        assert_eq!(comp.marked_preedit("[", "]"), "[ni]hao");
+        assert_eq!(
+            comp.marked_preedit("\x1b[7m", "\x1b[0m"),
+            "\x1b[7mni\x1b[0mhao"
+        );
+    }
+
+    #[test]
+    fn marked_preedit_without_active_segment_is_plain() {
+        // Cursor at the end is past every segment
+        let mut comp = nihao();
+        comp.set_cursor(5);
+        assert_eq!(comp.marked_preedit("[", "]"), "nihao");
+
+        let comp = Composition::from_text("你好".to_string());
+        assert_eq!(comp.marked_preedit("[", "]"), "你好");
+    }
 }
@@ -6,9 +6,14 @@
 //!
 //! Design philosophy: Zero abstraction - just data transfer. No callbacks, no
 //! traits, no generics. Platform code reads/writes fields directly.
+//!
+//! The context (with candidates and composition types) implements serde, so
+//! a UI in another process can receive it as JSON after each key.
+
+use serde::{Deserialize, Serialize};
 
 /// Input purpose hint for context-aware input.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InputPurpose {
     /// Free-form text input (default)
     FreeForm,
@@ -47,7 +52,7 @@ impl Default for InputPurpose {
 /// - `candidate_cursor`: Which candidate is highlighted (0-based index)
 /// - `auxiliary_text`: Optional hint text (e.g., "第2页" for page indicator)
 /// - `input_purpose`: Hint about what kind of input is expected
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImeContext {
     /// Text being composed (preedit/候选)
     pub preedit_text: String,
@@ -0,0 +1,167 @@
+// core/src/dataset.rs
+//
+// Discovery of converted datasets under a data directory.
+//
+// `convert_table` writes one directory per dataset (e.g. data/converted/
+// simplified) holding a lexicon as `<name>.fst` plus a payload file
+// (`<name>.bincode`, or `<name>.redb` for older layouts), and optionally a
+// `word_bigram.bin`. This module finds those pairs so front-ends do not have
+// to probe fixed paths.
+
+use std::path::{Path, PathBuf};
+
+/// Script / phonetic system of a dataset, inferred from its directory name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetKind {
+    /// Simplified Chinese, pinyin keys
+    Simplified,
+    /// Traditional Chinese, pinyin keys
+    Traditional,
+    /// Traditional Chinese, zhuyin keys
+    Zhuyin,
+    /// Directory name does not say
+    Unknown,
+}
+
+impl DatasetKind {
+    /// Infer the kind from a directory name ("zhuyin_traditional" → Zhuyin).
+    pub fn from_dir_name(name: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        if name.contains("zhuyin") || name.contains("bopomofo") {
+            DatasetKind::Zhuyin
+        } else if name.contains("traditional") {
+            DatasetKind::Traditional
+        } else if name.contains("simplified") {
+            DatasetKind::Simplified
+        } else {
+            DatasetKind::Unknown
+        }
+    }
+}
+
+/// A lexicon artifact pair found on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetInfo {
+    /// Name of the directory holding the dataset (e.g. "simplified")
+    pub name: String,
+    /// Kind inferred from `name`
+    pub kind: DatasetKind,
+    /// Directory holding the artifacts
+    pub dir: PathBuf,
+    /// FST key index
+    pub fst_path: PathBuf,
+    /// Payload file paired with the FST (`.bincode` preferred over `.redb`)
+    pub payload_path: PathBuf,
+    /// `word_bigram.bin` next to the lexicon, if present
+    pub word_bigram_path: Option<PathBuf>,
+}
+
+/// Find all datasets in `data_dir` and its immediate subdirectories.
+///
+/// A dataset is a `<stem>.fst` file with a `<stem>.bincode` or `<stem>.redb`
+/// file next to it. Results are sorted by FST path. A missing or unreadable
+/// directory yields an empty list.
+pub fn discover_datasets<P: AsRef<Path>>(data_dir: P) -> Vec<DatasetInfo> {
+    let data_dir = data_dir.as_ref();
+    let mut dirs = vec![data_dir.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(data_dir) {
+        dirs.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+    }
+
+    let mut found: Vec<DatasetInfo> = dirs.iter().flat_map(|d| datasets_in(d)).collect();
+    found.sort_by(|a, b| a.fst_path.cmp(&b.fst_path));
+    found
+}
+
+fn datasets_in(dir: &Path) -> Vec<DatasetInfo> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let word_bigram = dir.join("word_bigram.bin");
+
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "fst"))
+        .filter_map(|fst_path| {
+            let payload_path = ["bincode", "redb"]
+                .iter()
+                .map(|ext| fst_path.with_extension(ext))
+                .find(|p| p.is_file())?;
+            Some(DatasetInfo {
+                name: name.clone(),
+                kind: DatasetKind::from_dir_name(&name),
+                dir: dir.to_path_buf(),
+                fst_path,
+                payload_path,
+                word_bigram_path: word_bigram.is_file().then(|| word_bigram.clone()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn discovers_planted_artifact_pairs() {
+        let root = std::env::temp_dir().join(format!("libchinese_datasets_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        touch(&root.join("simplified/lexicon.fst"));
+        touch(&root.join("simplified/lexicon.bincode"));
+        touch(&root.join("simplified/word_bigram.bin"));
+        touch(&root.join("zhuyin_traditional/lexicon.fst"));
+        touch(&root.join("zhuyin_traditional/lexicon.redb"));
+        touch(&root.join("traditional/lexicon.fst"));
+        touch(&root.join("traditional/lexicon.bincode"));
+        // FST without a payload is not a dataset
+        touch(&root.join("broken/lexicon.fst"));
+        // Payload without an FST is not a dataset either
+        touch(&root.join("emoji/lexicon.bincode"));
+
+        let found = discover_datasets(&root);
+        let summary: Vec<(&str, DatasetKind)> =
+            found.iter().map(|d| (d.name.as_str(), d.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("simplified", DatasetKind::Simplified),
+                ("traditional", DatasetKind::Traditional),
+                ("zhuyin_traditional", DatasetKind::Zhuyin),
+            ]
+        );
+
+        assert_eq!(
+            found[0].payload_path,
+            root.join("simplified/lexicon.bincode")
+        );
+        assert_eq!(
+            found[0].word_bigram_path,
+            Some(root.join("simplified/word_bigram.bin"))
+        );
+        assert_eq!(found[1].word_bigram_path, None);
+        assert_eq!(
+            found[2].payload_path,
+            root.join("zhuyin_traditional/lexicon.redb")
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn missing_directory_has_no_datasets() {
+        assert!(discover_datasets("/nonexistent/libchinese/data").is_empty());
+    }
+}
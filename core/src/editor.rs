@@ -4,10 +4,11 @@
 //! modes (phonetic, punctuation, suggestions). Each editor implements the
 //! `Editor` trait and processes key events in its specific context.
 
-use crate::candidate::Candidate;
+use crate::candidate::{Candidate, CandidateSource};
 use crate::engine::{Engine, SyllableParser};
 use crate::ime_engine::KeyEvent;
 use crate::session::ImeSession;
+use crate::userdict::UserDict;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -23,6 +24,10 @@ pub enum EditorResult {
     /// Text should be committed and mode should reset
     CommitAndReset(String),
 
+    /// Text covering the start of the input was committed; the rest of the
+    /// input stays in the (already updated) session
+    CommitPartial(String),
+
     /// Request to switch to a different mode
     ModeSwitch(crate::session::InputMode),
 
@@ -81,12 +86,21 @@ pub trait Editor {
 pub struct PhoneticEditor<P: SyllableParser> {
     /// Backend engine for linguistic processing
     backend: Arc<Engine<P>>,
+    /// Highlighted candidate per input seen in this composition
+    /// (see `Config::preserve_selection_on_reedit`)
+    selections: HashMap<String, usize>,
+    /// Whether input was dropped at `Config::max_input_length`
+    input_truncated: bool,
 }
 
 impl<P: SyllableParser> PhoneticEditor<P> {
     /// Create a new phonetic editor with the given backend.
     pub fn new(backend: Arc<Engine<P>>) -> Self {
-        Self { backend }
+        Self {
+            backend,
+            selections: HashMap::new(),
+            input_truncated: false,
+        }
     }
 
     /// Get a reference to the backend engine.
@@ -94,8 +108,21 @@ impl<P: SyllableParser> PhoneticEditor<P> {
         &self.backend
     }
 
+    /// Whether input was dropped because the buffer reached
+    /// `Config::max_input_length`. Cleared once input is deleted.
+    pub fn is_input_truncated(&self) -> bool {
+        self.input_truncated
+    }
+
     /// Handle character input
     fn handle_char(&mut self, ch: char, session: &mut ImeSession) -> EditorResult {
+        // A full buffer swallows the key without converting again
+        let max = self.backend.config().max_input_length;
+        if max > 0 && session.input_buffer().char_count() >= max {
+            self.input_truncated = true;
+            return EditorResult::Handled;
+        }
+
         // Add to input buffer
         session.input_buffer_mut().insert_char(ch);
 
@@ -110,6 +137,7 @@ impl<P: SyllableParser> PhoneticEditor<P> {
         let deleted = session.input_buffer_mut().delete_before();
 
         if deleted {
+            self.input_truncated = false;
             if session.input_buffer().text().is_empty() {
                 // No more input, reset
                 EditorResult::CommitAndReset(String::new())
@@ -127,6 +155,7 @@ impl<P: SyllableParser> PhoneticEditor<P> {
         let deleted = session.input_buffer_mut().delete_after();
 
         if deleted {
+            self.input_truncated = false;
             if session.input_buffer().text().is_empty() {
                 EditorResult::CommitAndReset(String::new())
             } else {
@@ -141,18 +170,17 @@ impl<P: SyllableParser> PhoneticEditor<P> {
     /// Handle space (select first candidate).
     fn handle_space(&mut self, session: &mut ImeSession) -> EditorResult {
         if session.candidates().is_empty() {
-            // No candidates, just insert space
+            // Nothing to select: keep the input if asked to, else insert a space
+            let preserve = self.backend.config().preserve_preedit_on_veto;
+            if preserve && !session.input_buffer().is_empty() {
+                return EditorResult::Handled;
+            }
             return EditorResult::CommitAndReset(" ".to_string());
         }
 
         // Select first candidate
-        if let Some(candidate) = session.candidates().selected_candidate() {
-            let text = candidate.text.clone();
-
-            // Learn the phrase
-            self.backend.commit(&text);
-
-            EditorResult::CommitAndReset(text)
+        if let Some(candidate) = session.candidates().selected_candidate().cloned() {
+            self.commit_candidate(candidate, session)
         } else {
             EditorResult::PassThrough
         }
@@ -160,10 +188,8 @@ impl<P: SyllableParser> PhoneticEditor<P> {
 
     /// Handle enter (commit selection or raw input).
     fn handle_enter(&mut self, session: &mut ImeSession) -> EditorResult {
-        if let Some(candidate) = session.candidates().selected_candidate() {
-            let text = candidate.text.clone();
-            self.backend.commit(&text);
-            EditorResult::CommitAndReset(text)
+        if let Some(candidate) = session.candidates().selected_candidate().cloned() {
+            self.commit_candidate(candidate, session)
         } else {
             // Commit raw input
             let raw = session.input_buffer().text().to_string();
@@ -171,6 +197,17 @@ impl<P: SyllableParser> PhoneticEditor<P> {
         }
     }
 
+    /// Remember the highlighted candidate for the current input.
+    fn remember_selection(&mut self, session: &ImeSession) {
+        if !self.backend.config().preserve_selection_on_reedit {
+            return;
+        }
+        if let Some(index) = session.candidates().selected_index() {
+            self.selections
+                .insert(session.input_buffer().text().to_string(), index);
+        }
+    }
+
     /// Handle number key for candidate selection (1-9).
     fn handle_number(&mut self, n: u8, session: &mut ImeSession) -> EditorResult {
         if session.candidates().is_empty() {
@@ -182,14 +219,46 @@ impl<P: SyllableParser> PhoneticEditor<P> {
         }
 
         let index = (n - 1) as usize;
-        if let Some(candidate) = session.candidates_mut().select_by_index(index) {
-            let text = candidate.text.clone();
-            self.backend.commit(&text);
-            EditorResult::CommitAndReset(text)
+        if let Some(candidate) = session.candidates_mut().select_by_index(index).cloned() {
+            self.commit_candidate(candidate, session)
         } else {
             EditorResult::PassThrough
         }
     }
+
+    /// Commit a selected candidate and learn it.
+    ///
+    /// A candidate whose reading covers only the start of the input (see
+    /// `Config::offer_prefix_candidates`) commits just that part: the
+    /// covered syllables leave the input buffer and the rest is converted
+    /// afresh.
+    fn commit_candidate(&mut self, candidate: Candidate, session: &mut ImeSession) -> EditorResult {
+        let text = candidate.text;
+        if !self.backend.allows_commit(&text) {
+            // ImeEngine handles the veto
+            return EditorResult::CommitAndReset(text);
+        }
+
+        // Learn the phrase
+        self.backend.commit(&text);
+
+        let input = session.input_buffer().text().to_string();
+        let rest = candidate
+            .reading
+            .as_deref()
+            .and_then(|reading| self.backend.unconsumed_input(&input, reading))
+            .filter(|rest| !rest.is_empty());
+        if let Some(rest) = rest {
+            let rest = rest.to_string();
+            session.input_buffer_mut().clear();
+            session.input_buffer_mut().insert_str(&rest);
+            self.update_candidates(session);
+            return EditorResult::CommitPartial(text);
+        }
+
+        self.backend.learn_segmentation(&input);
+        EditorResult::CommitAndReset(text)
+    }
 }
 
 impl<P: SyllableParser> Editor for PhoneticEditor<P> {
@@ -202,18 +271,20 @@ impl<P: SyllableParser> Editor for PhoneticEditor<P> {
             KeyEvent::Enter => self.handle_enter(session),
             KeyEvent::Number(n) => self.handle_number(n, session),
 
-            // Cursor navigation - update session but stay in mode
+            // Caret movement within the preedit; typing and deleting then
+            // happen at the caret and the whole buffer is re-segmented
             KeyEvent::Left => {
-                session.input_buffer_mut().move_left();
+                session.move_caret_left();
                 EditorResult::Handled
             }
             KeyEvent::Right => {
-                session.input_buffer_mut().move_right();
+                session.move_caret_right();
                 EditorResult::Handled
             }
             KeyEvent::Up => {
                 if !session.candidates().is_empty() {
                     session.candidates_mut().cursor_up();
+                    self.remember_selection(session);
                     EditorResult::Handled
                 } else {
                     EditorResult::PassThrough
@@ -222,6 +293,7 @@ impl<P: SyllableParser> Editor for PhoneticEditor<P> {
             KeyEvent::Down => {
                 if !session.candidates().is_empty() {
                     session.candidates_mut().cursor_down();
+                    self.remember_selection(session);
                     EditorResult::Handled
                 } else {
                     EditorResult::PassThrough
@@ -230,6 +302,7 @@ impl<P: SyllableParser> Editor for PhoneticEditor<P> {
             KeyEvent::PageUp => {
                 if !session.candidates().is_empty() {
                     session.candidates_mut().page_up();
+                    self.remember_selection(session);
                     EditorResult::Handled
                 } else {
                     EditorResult::PassThrough
@@ -238,18 +311,35 @@ impl<P: SyllableParser> Editor for PhoneticEditor<P> {
             KeyEvent::PageDown => {
                 if !session.candidates().is_empty() {
                     session.candidates_mut().page_down();
+                    self.remember_selection(session);
                     EditorResult::Handled
                 } else {
                     EditorResult::PassThrough
                 }
             }
             KeyEvent::Escape => EditorResult::CommitAndReset(String::new()),
+            // Pin (or unpin) a syllable boundary at the caret, e.g. to read
+            // "xian" as xi + an
+            KeyEvent::Ctrl('\'') => {
+                if session.input_buffer_mut().toggle_boundary() {
+                    self.update_candidates(session);
+                    EditorResult::Handled
+                } else {
+                    EditorResult::PassThrough
+                }
+            }
             // Global shortcuts handled by ImeEngine before routing
             KeyEvent::Ctrl(_) | KeyEvent::ShiftLock => EditorResult::PassThrough,
         }
     }
 
     fn update_candidates(&mut self, session: &mut ImeSession) {
+        // Input inserted in one go (not typed) is cut to the limit here
+        let max = self.backend.config().max_input_length;
+        if max > 0 && session.input_buffer_mut().truncate_chars(max) {
+            self.input_truncated = true;
+        }
+
         let input = session.input_buffer().text();
 
         if input.is_empty() {
@@ -257,23 +347,31 @@ impl<P: SyllableParser> Editor for PhoneticEditor<P> {
             return;
         }
 
-        // Get candidates from backend
-        let backend_candidates = self.backend.input(input);
-
-        // Convert to our Candidate type
-        let candidates: Vec<Candidate> = backend_candidates
-            .into_iter()
-            .map(|c| Candidate::new(c.text, c.score))
-            .collect();
+        // Get candidates from backend, honoring boundaries the user pinned
+        let boundaries = session.input_buffer().boundaries();
+        let candidates = if boundaries.is_empty() {
+            self.backend.input(input)
+        } else {
+            self.backend.input_with_boundaries(input, boundaries)
+        };
+        let remembered = self.selections.get(input).copied();
 
         session.candidates_mut().set_candidates(candidates);
+        if let Some(index) = remembered {
+            session.candidates_mut().set_selected_index(index);
+        }
 
-        // Update composition
-        session.update_composition_from_input();
+        // Update composition, as the parser displays the input
+        match self.backend.display_input(session.input_buffer().text()) {
+            Some(display) => session.update_composition_from_display(display),
+            None => session.update_composition_from_input(),
+        }
     }
 
     fn reset(&mut self) {
-        // PhoneticEditor is stateless - backend Engine handles state
+        // Selections are remembered per composition only
+        self.selections.clear();
+        self.input_truncated = false;
     }
 
     fn name(&self) -> &'static str {
@@ -378,6 +476,10 @@ impl<P: SyllableParser> Editor for SuggestionEditor<P> {
                 let index = (n - 1) as usize;
                 if let Some(candidate) = session.candidates_mut().select_by_index(index) {
                     let text = candidate.text.clone();
+                    if !self.backend.allows_commit(&text) {
+                        // ImeEngine handles the veto
+                        return EditorResult::Commit(text);
+                    }
 
                     // Learn user bigram for personalization
                     self.learn_selection(&text);
@@ -399,6 +501,9 @@ impl<P: SyllableParser> Editor for SuggestionEditor<P> {
             KeyEvent::Space => {
                 if let Some(candidate) = session.candidates().selected_candidate() {
                     let text = candidate.text.clone();
+                    if !self.backend.allows_commit(&text) {
+                        return EditorResult::Commit(text);
+                    }
 
                     // Learn user bigram
                     self.learn_selection(&text);
@@ -467,54 +572,31 @@ impl<P: SyllableParser> Editor for SuggestionEditor<P> {
             return;
         }
 
-        // Get last word from context for word bigram prediction
-        let last_word = self.context.trim();
-        
-        // Get predictions from word bigram model
-        let config = self.backend.config();
-        let lambda = config.lambda;
-        drop(config);
-        
-        let word_predictions = self.backend.model().word_bigram.get_predictions(last_word, lambda, 10);
-        
-        // Also get user-learned bigrams
-        let user_bigrams = self.backend.userdict().get_bigrams_after(last_word);
-        
-        // Merge predictions: combine word_bigram predictions with user bigrams
-        let mut combined: Vec<(String, f32)> = word_predictions;
-        
-        // Add user bigrams with a boost
-        for (word, user_count) in user_bigrams {
-            let user_boost = (1.0 + user_count as f32).ln();
-            
-            // Find if this word already exists in predictions
-            if let Some(existing) = combined.iter_mut().find(|(w, _)| w == &word) {
-                existing.1 += user_boost; // Boost existing prediction
-            } else {
-                // Add new prediction from user bigrams
-                combined.push((word, user_boost));
-            }
-        }
-        
-        // Sort by score descending
-        combined.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        combined.truncate(10);
-        
+        // Word bigram predictions merged with user-learned bigrams
+        let combined = self.backend.predict_next(&self.context, 10);
+
         if !combined.is_empty() {
             let candidates: Vec<Candidate> = combined
                 .into_iter()
-                .map(|(word, score)| Candidate::new(word, score))
+                .map(|(word, score)| {
+                    Candidate::new(word, score).with_source(CandidateSource::Prediction)
+                })
                 .collect();
             session.candidates_mut().set_candidates(candidates);
         } else {
             // Fallback to common particles if no predictions available
-            let candidates = vec![
-                Candidate::new("吗", 0.1),
-                Candidate::new("呢", 0.09),
-                Candidate::new("吧", 0.08),
-                Candidate::new("啊", 0.07),
-                Candidate::new("的", 0.06),
-            ];
+            let candidates = [
+                ("吗", 0.1),
+                ("呢", 0.09),
+                ("吧", 0.08),
+                ("啊", 0.07),
+                ("的", 0.06),
+            ]
+            .into_iter()
+            .map(|(word, score)| {
+                Candidate::new(word, score).with_source(CandidateSource::Prediction)
+            })
+            .collect();
             session.candidates_mut().set_candidates(candidates);
         }
 
@@ -549,8 +631,23 @@ pub struct PunctuationEditor {
     /// Map from ASCII punct to full-width alternatives
     punct_map: HashMap<char, Vec<&'static str>>,
 
+    /// User alternatives replacing `punct_map` for their keys
+    overrides: HashMap<char, Vec<String>>,
+
     /// Currently active punctuation key (if any)
     active_key: Option<char>,
+
+    /// Offer the Chinese form first (otherwise the ASCII key itself)
+    chinese: bool,
+
+    /// Order candidates by the previously committed character
+    smart: bool,
+
+    /// Where selections are learned and read back, if learning is enabled
+    learning: Option<UserDict>,
+
+    /// Whether the user moved the selection since activation
+    browsed: bool,
 }
 
 impl PunctuationEditor {
@@ -600,24 +697,148 @@ impl PunctuationEditor {
 
         Self {
             punct_map,
+            overrides: HashMap::new(),
             active_key: None,
+            chinese: true,
+            smart: false,
+            learning: None,
+            browsed: false,
         }
     }
 
     /// Check if a character has punctuation alternatives.
     pub fn has_alternatives(&self, ch: char) -> bool {
-        self.punct_map.contains_key(&ch)
+        self.overrides.contains_key(&ch) || self.punct_map.contains_key(&ch)
+    }
+
+    /// Replace the alternatives a punctuation key offers, in display order.
+    ///
+    /// An empty list removes the override, restoring the built-in
+    /// alternatives for `key`.
+    ///
+    /// # Example
+    /// ```
+    /// # use libchinese_core::PunctuationEditor;
+    /// let mut editor = PunctuationEditor::new();
+    /// assert!(!editor.has_alternatives('\\'));
+    /// editor.set_alternatives('\\', vec!["、".to_string(), "「".to_string()]);
+    /// assert!(editor.has_alternatives('\\'));
+    /// ```
+    pub fn set_alternatives(&mut self, key: char, alts: Vec<String>) {
+        if alts.is_empty() {
+            self.overrides.remove(&key);
+        } else {
+            self.overrides.insert(key, alts);
+        }
+    }
+
+    /// Replace all overrides with `table`.
+    ///
+    /// Keys not in `table` fall back to the built-in alternatives; empty
+    /// lists are ignored.
+    pub fn load_table(&mut self, table: HashMap<char, Vec<String>>) {
+        self.overrides.clear();
+        for (key, alts) in table {
+            self.set_alternatives(key, alts);
+        }
+    }
+
+    /// Alternatives offered for `key`: the override if set, else the
+    /// built-in ones (empty if neither).
+    pub fn alternatives(&self, key: char) -> Vec<&str> {
+        match self.overrides.get(&key) {
+            Some(alts) => alts.iter().map(String::as_str).collect(),
+            None => self.punct_map.get(&key).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Choose whether the Chinese form (true) or the ASCII key itself
+    /// (false) is offered first (see `Config::chinese_punctuation`).
+    pub fn set_chinese(&mut self, enabled: bool) {
+        self.chinese = enabled;
+    }
+
+    /// Check whether the Chinese form is offered first.
+    pub fn is_chinese(&self) -> bool {
+        self.chinese
+    }
+
+    /// Enable or disable smart punctuation (see `Config::smart_punctuation`).
+    ///
+    /// When enabled, `activate` puts the half-width form first after an
+    /// ASCII digit ("3" + "." → ".") and keeps the full-width form first
+    /// after a Han character ("好" + "." → "。").
+    pub fn set_smart(&mut self, enabled: bool) {
+        self.smart = enabled;
+    }
+
+    /// Check whether smart punctuation is enabled.
+    pub fn is_smart(&self) -> bool {
+        self.smart
+    }
+
+    /// Learn selections in `userdict` and offer the most picked form first
+    /// (see `Config::learn_punctuation_preference`). `None` disables it.
+    pub fn set_learning(&mut self, userdict: Option<UserDict>) {
+        self.learning = userdict;
+    }
+
+    /// Check whether selections are being learned.
+    pub fn is_learning(&self) -> bool {
+        self.learning.is_some()
+    }
+
+    /// Index of the alternative that suits the preceding character, if the
+    /// preceding character calls for a specific form.
+    fn contextual_index(
+        alternatives: &[&str],
+        key: char,
+        preceding: Option<char>,
+    ) -> Option<usize> {
+        let preceding = preceding?;
+        if preceding.is_ascii_digit() {
+            let half_width = key.to_string();
+            alternatives.iter().position(|&alt| alt == half_width)
+        } else if crate::char_freq::is_han_char(preceding) {
+            alternatives.iter().position(|alt| !alt.is_ascii())
+        } else {
+            None
+        }
     }
 
     /// Activate punctuation selection for a given key.
     pub fn activate(&mut self, key: char, session: &mut ImeSession) -> bool {
-        if let Some(alternatives) = self.punct_map.get(&key) {
+        if self.has_alternatives(key) {
             self.active_key = Some(key);
+            self.browsed = false;
+
+            let mut alternatives = self.alternatives(key);
+            if !self.chinese {
+                let half_width = key.to_string();
+                if let Some(index) = alternatives.iter().position(|&alt| alt == half_width) {
+                    let ascii = alternatives.remove(index);
+                    alternatives.insert(0, ascii);
+                }
+            }
+            if let Some(userdict) = &self.learning {
+                // Stable, so unpicked forms keep their default order
+                let picks = userdict.punctuation_preferences(key);
+                alternatives
+                    .sort_by_key(|alt| std::cmp::Reverse(picks.get(*alt).copied().unwrap_or(0)));
+            }
+            // The preceding character outweighs the learned preference
+            if self.smart {
+                let preceding = session.last_committed_char();
+                if let Some(index) = Self::contextual_index(&alternatives, key, preceding) {
+                    let preferred = alternatives.remove(index);
+                    alternatives.insert(0, preferred);
+                }
+            }
 
             // Set candidates
             let candidates: Vec<Candidate> = alternatives
                 .iter()
-                .map(|&s| Candidate::new(s, 1.0))
+                .map(|&s| Candidate::new(s, 1.0).with_source(CandidateSource::Punctuation))
                 .collect();
 
             session.candidates_mut().set_candidates(candidates);
@@ -639,6 +860,13 @@ impl PunctuationEditor {
             .selected_candidate()
             .map(|candidate| candidate.text.clone())
     }
+
+    /// Remember a form the user explicitly picked.
+    fn learn_selection(&self, text: &str) {
+        if let (Some(userdict), Some(key)) = (&self.learning, self.active_key) {
+            let _ = userdict.learn_punctuation(key, text);
+        }
+    }
 }
 
 impl Default for PunctuationEditor {
@@ -659,15 +887,20 @@ impl Editor for PunctuationEditor {
                 let index = (n - 1) as usize;
                 if let Some(candidate) = session.candidates_mut().select_by_index(index) {
                     let text = candidate.text.clone();
+                    self.learn_selection(&text);
                     EditorResult::CommitAndReset(text)
                 } else {
                     EditorResult::PassThrough
                 }
             }
 
-            // Space or Enter - select first candidate
+            // Space or Enter - select the highlighted candidate
             KeyEvent::Space | KeyEvent::Enter => {
                 if let Some(text) = self.select_candidate(session) {
+                    // Accepting the default or contextual pick is no preference
+                    if self.browsed {
+                        self.learn_selection(&text);
+                    }
                     EditorResult::CommitAndReset(text)
                 } else {
                     EditorResult::PassThrough
@@ -677,20 +910,24 @@ impl Editor for PunctuationEditor {
             // Up/Down for candidate navigation
             KeyEvent::Up => {
                 session.candidates_mut().cursor_up();
+                self.browsed = true;
                 EditorResult::Handled
             }
             KeyEvent::Down => {
                 session.candidates_mut().cursor_down();
+                self.browsed = true;
                 EditorResult::Handled
             }
 
             // Page navigation
             KeyEvent::PageUp => {
                 session.candidates_mut().page_up();
+                self.browsed = true;
                 EditorResult::Handled
             }
             KeyEvent::PageDown => {
                 session.candidates_mut().page_down();
+                self.browsed = true;
                 EditorResult::Handled
             }
 
@@ -721,6 +958,7 @@ impl Editor for PunctuationEditor {
 
     fn reset(&mut self) {
         self.active_key = None;
+        self.browsed = false;
     }
 
     fn name(&self) -> &'static str {
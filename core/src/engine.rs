@@ -3,7 +3,8 @@
 // Generic IME engine that works with any syllable parser.
 // This eliminates code duplication between libpinyin and libzhuyin.
 
-use crate::{Candidate, Model};
+use crate::{Candidate, CandidateSource, Lexicon, Model, DEFAULT_PAGE_SIZE};
+use serde::Serialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
 
@@ -14,147 +15,1918 @@ pub trait SyllableParser {
 
     /// Segment input into top-k best syllable sequences
     fn segment_top_k(&self, input: &str, k: usize, allow_fuzzy: bool) -> Vec<Vec<Self::Syllable>>;
+
+    /// Best segmentation of `input` that has a syllable break at each of
+    /// `boundaries` (byte offsets, e.g. pinned by the user). Returns an
+    /// empty vector if some piece between boundaries cannot be segmented.
+    fn segment_best_constrained(
+        &self,
+        input: &str,
+        boundaries: &[usize],
+        allow_fuzzy: bool,
+    ) -> Vec<Self::Syllable> {
+        let mut syllables = Vec::new();
+        for piece in crate::input_buffer::split_at_boundaries(input, boundaries) {
+            match self.segment_top_k(piece, 1, allow_fuzzy).into_iter().next() {
+                Some(seg) if !seg.is_empty() => syllables.extend(seg),
+                _ => return Vec::new(),
+            }
+        }
+        syllables
+    }
+
+    /// Fuzzy rules the parser is using, in `FuzzyMap::from_rules` syntax.
+    fn fuzzy_rules(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Number of syllables the parser recognizes.
+    fn syllable_count(&self) -> usize {
+        0
+    }
+
+    /// Whether `ch` is phonetic input for this parser beyond lowercase
+    /// letters and bopomofo (e.g. the digit and punctuation keys of a
+    /// zhuyin keyboard layout). `ImeEngine` routes such keys to the
+    /// phonetic editor instead of treating them as selection or
+    /// punctuation keys.
+    fn is_input_key(&self, _ch: char) -> bool {
+        false
+    }
+
+    /// `input` as the preedit should show it, if that differs from the
+    /// typed keys (e.g. bopomofo for keys typed on a zhuyin keyboard
+    /// layout). The display must have one char per input char.
+    fn display_input(&self, _input: &str) -> Option<String> {
+        None
+    }
+
+    /// Why `input` is malformed for this parser, as text for the user
+    /// (e.g. a key pair the active shuangpin scheme cannot decode).
+    ///
+    /// Segmentation stays lenient; this only reports the problem.
+    fn input_error(&self, _input: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Shared parsers, e.g. one parser behind the engines of a `SyncEngine`.
+impl<P: SyllableParser + ?Sized> SyllableParser for std::sync::Arc<P> {
+    type Syllable = P::Syllable;
+
+    fn segment_top_k(&self, input: &str, k: usize, allow_fuzzy: bool) -> Vec<Vec<Self::Syllable>> {
+        (**self).segment_top_k(input, k, allow_fuzzy)
+    }
+
+    fn segment_best_constrained(
+        &self,
+        input: &str,
+        boundaries: &[usize],
+        allow_fuzzy: bool,
+    ) -> Vec<Self::Syllable> {
+        (**self).segment_best_constrained(input, boundaries, allow_fuzzy)
+    }
+
+    fn fuzzy_rules(&self) -> Vec<String> {
+        (**self).fuzzy_rules()
+    }
+
+    fn syllable_count(&self) -> usize {
+        (**self).syllable_count()
+    }
+
+    fn is_input_key(&self, ch: char) -> bool {
+        (**self).is_input_key(ch)
+    }
+
+    fn display_input(&self, input: &str) -> Option<String> {
+        (**self).display_input(input)
+    }
+
+    fn input_error(&self, input: &str) -> Option<String> {
+        (**self).input_error(input)
+    }
+}
+
+/// Trait for syllable types that engines can work with.
+pub trait SyllableType {
+    /// Get the text of this syllable (e.g., "ni", "hao", "ㄋㄧˇ")
+    fn text(&self) -> &str;
+
+    /// Whether this syllable was matched via fuzzy matching
+    fn is_fuzzy(&self) -> bool;
+
+    /// Other spellings of this syllable to look up when the lexicon has no
+    /// entry for `text()` (e.g. the other tones of a zhuyin syllable).
+    fn fallback_texts(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Tone typed on this syllable ("ni3" → 3), if the parser keeps tones
+    /// apart from `text()`. Used to pick tone-tagged lexicon keys.
+    fn tone(&self) -> Option<u8> {
+        None
+    }
+}
+
+/// Sentence-final and aspect particles that `Config::trim_trailing_particles`
+/// strips to offer a shorter variant ("好的" → "好").
+pub const TRAILING_PARTICLES: &[char] = &[
+    '的', '了', '吗', '呢', '吧', '啊', '呀', '啦', '嘛', '着', '过',
+];
+
+/// Number of completions `Engine::prefetch_completions` warms per call.
+pub const PREFETCH_COMPLETIONS: usize = 5;
+
+/// Most keys `Engine::lookup_syllables` builds from fallback spellings
+/// (four syllables with five tones each).
+const MAX_FALLBACK_KEYS: usize = 625;
+
+/// Lexicon keys extending a segmentation that are checked for learned
+/// phrases (see `Config::learned_completion_boost`).
+const LEARNED_COMPLETION_KEYS: usize = 64;
+
+/// Lattice states (distinct last words) `Engine::convert_sentence` keeps
+/// per syllable position.
+const SENTENCE_BEAM_WIDTH: usize = 64;
+
+/// Longest lexicon word, in syllables, `Engine::convert_sentence` tries.
+const MAX_SENTENCE_WORD_SYLLABLES: usize = 10;
+
+/// Cheapest way to type and commit a target phrase.
+///
+/// Produced by `Engine::keystrokes_to_commit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeystrokePlan {
+    /// Raw input to type (e.g. "nihao")
+    pub input: String,
+    /// 0-based rank of the target in the candidates for `input`
+    pub selection: usize,
+    /// PageDown presses to reach the page holding the target
+    pub page_flips: usize,
+    /// Total keystrokes: input characters, page flips and one selection key
+    /// (the number key for the target's place on its page)
+    pub keystrokes: usize,
+}
+
+/// Typing cost of a corpus, produced by `Engine::evaluate_corpus`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorpusEval {
+    /// Sentences evaluated
+    pub sentences: usize,
+    /// Han characters converted
+    pub characters: usize,
+    /// Keystrokes spent on them (input, page flips and one selection key per
+    /// phrase)
+    pub keystrokes: usize,
+    /// Phrases committed
+    pub conversions: usize,
+    /// Phrases that came up as the first candidate
+    pub top1_hits: usize,
+    /// Han characters no reading could produce
+    pub unconverted: usize,
+}
+
+impl CorpusEval {
+    /// Keystrokes per converted character (0.0 if nothing was converted).
+    pub fn keystrokes_per_char(&self) -> f64 {
+        if self.characters == 0 {
+            return 0.0;
+        }
+        self.keystrokes as f64 / self.characters as f64
+    }
+
+    /// Share of phrases that were the first candidate (0.0 if none).
+    pub fn top1_accuracy(&self) -> f64 {
+        if self.conversions == 0 {
+            return 0.0;
+        }
+        self.top1_hits as f64 / self.conversions as f64
+    }
+}
+
+/// Candidates for one input counted by where they came from.
+///
+/// Produced by `Engine::candidate_origin_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OriginStats {
+    /// Candidates that are a direct lexicon entry for their reading,
+    /// including user-learned ones
+    pub lexicon: usize,
+    /// Candidates composed from several lexicon words
+    pub composed: usize,
+    /// Candidates from any other source
+    pub other: usize,
+}
+
+impl OriginStats {
+    /// Total number of candidates counted.
+    pub fn total(&self) -> usize {
+        self.lexicon + self.composed + self.other
+    }
+
+    /// Share of candidates that are direct lexicon entries (0.0 if none).
+    pub fn lexicon_ratio(&self) -> f64 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        self.lexicon as f64 / self.total() as f64
+    }
+}
+
+/// How often each phrase was chosen for each input, aggregated from a
+/// selection log.
+///
+/// Produced by `Engine::aggregate_selection_stats` and fed back with
+/// `Engine::apply_selection_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionStats {
+    counts: std::collections::BTreeMap<String, std::collections::BTreeMap<String, u64>>,
+}
+
+impl SelectionStats {
+    /// Record one selection of `phrase` for `input`.
+    pub fn record(&mut self, input: &str, phrase: &str) {
+        *self
+            .counts
+            .entry(input.to_string())
+            .or_default()
+            .entry(phrase.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// Number of times `phrase` was chosen for `input`.
+    pub fn count(&self, input: &str, phrase: &str) -> u64 {
+        self.counts
+            .get(input)
+            .and_then(|phrases| phrases.get(phrase))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Phrases chosen for `input`, most frequent first.
+    pub fn phrases_for(&self, input: &str) -> Vec<(String, u64)> {
+        let mut phrases: Vec<(String, u64)> = self
+            .counts
+            .get(input)
+            .map(|p| p.iter().map(|(k, v)| (k.clone(), *v)).collect())
+            .unwrap_or_default();
+        phrases.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        phrases
+    }
+
+    /// All (input, phrase, count) entries, sorted by input then phrase.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, u64)> {
+        self.counts.iter().flat_map(|(input, phrases)| {
+            phrases
+                .iter()
+                .map(move |(phrase, count)| (input.as_str(), phrase.as_str(), *count))
+        })
+    }
+
+    /// Number of distinct inputs.
+    pub fn input_count(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Total number of selections recorded.
+    pub fn total(&self) -> u64 {
+        self.entries().map(|(_, _, count)| count).sum()
+    }
+
+    /// Check whether no selections were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+/// Parser state included in `Engine::dump_effective_config`.
+#[derive(Serialize)]
+struct ParserSnapshot {
+    syllable_count: usize,
+    fuzzy_rules: Vec<String>,
+}
+
+/// Loaded data sizes included in `Engine::dump_effective_config`.
+#[derive(Serialize)]
+struct DatasetSnapshot {
+    lexicon_keys: usize,
+    word_bigram_words: usize,
+    word_bigram_pairs: usize,
+    quick_codes: usize,
+}
+
+#[derive(Serialize)]
+struct EngineSnapshot {
+    parser: ParserSnapshot,
+    dataset: DatasetSnapshot,
+}
+
+/// Input as typed, minus apostrophes and whitespace and lowercased, so
+/// "Ni'hao" and "nihao" are the same spelling.
+fn normalize_spelling(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| *c != '\'' && !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Sort sentence hypotheses by score, best first, then by text so equal
+/// scores come out in a stable order.
+fn sort_sentences(sentences: &mut [(String, f32)]) {
+    sentences.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+}
+
+/// Stably move longer phrases ahead within runs of near-equal scores.
+///
+/// `vec` must be sorted by score, descending. A run starts at a candidate
+/// and holds every following one scoring within `epsilon` of it; each run
+/// is reordered by character count, longest first.
+fn prefer_longer_phrases(vec: &mut [Candidate], epsilon: f32) {
+    let mut start = 0;
+    while start < vec.len() {
+        let head = vec[start].score;
+        let end = vec[start..]
+            .iter()
+            .position(|c| head - c.score > epsilon)
+            .map_or(vec.len(), |i| start + i);
+        vec[start..end].sort_by_key(|c| std::cmp::Reverse(c.text.chars().count()));
+        start = end;
+    }
+}
+
+/// Highest score in `vec`, which need not be sorted (`prefer_longer_phrases`
+/// reorders near-ties).
+fn top_score(vec: &[Candidate]) -> Option<f32> {
+    vec.iter().map(|c| c.score).reduce(f32::max)
+}
+
+/// Lowest score in `vec`; see `top_score`.
+fn bottom_score(vec: &[Candidate]) -> Option<f32> {
+    vec.iter().map(|c| c.score).reduce(f32::min)
+}
+
+/// Predicate deciding whether a text may be committed; `false` vetoes it.
+///
+/// Installed with `Engine::set_commit_filter`.
+pub type CommitFilter = Box<dyn Fn(&str) -> bool + Send>;
+
+/// Generic IME engine that combines parser and model for candidate generation.
+///
+/// Type parameter P is the parser type (e.g., Parser for pinyin, ZhuyinParser for zhuyin).
+///
+/// Note: Fuzzy matching is handled by the parser during segmentation. The engine
+/// works with the segmentations provided by the parser.
+pub struct Engine<P> {
+    model: Model,
+    parser: P,
+    limit: usize,
+    cache: RefCell<lru::LruCache<String, Vec<Candidate>>>,
+    cache_hits: RefCell<usize>,
+    cache_misses: RefCell<usize>,
+    commit_filter: RefCell<Option<CommitFilter>>,
+    emoji: RefCell<Option<Lexicon>>,
 }
 
-/// Trait for syllable types that engines can work with.
-pub trait SyllableType {
-    /// Get the text of this syllable (e.g., "ni", "hao", "ㄋㄧˇ")
-    fn text(&self) -> &str;
+impl<P: SyllableParser> Engine<P> {
+    /// Create a new engine with the given model and parser.
+    pub fn new(model: Model, parser: P) -> Self {
+        // Cache capacity from the config (the cache itself needs at least one slot)
+        let cache_capacity = model.config.borrow().max_cache_size;
+
+        Self {
+            model,
+            parser,
+            limit: 8,
+            cache: RefCell::new(lru::LruCache::new(
+                std::num::NonZeroUsize::new(cache_capacity).unwrap_or(std::num::NonZeroUsize::MIN),
+            )),
+            cache_hits: RefCell::new(0),
+            cache_misses: RefCell::new(0),
+            commit_filter: RefCell::new(None),
+            emoji: RefCell::new(None),
+        }
+    }
+
+    /// Process input and return ranked candidates.
+    ///
+    /// This implements the full IME pipeline:
+    /// 1. Check cache for previous result
+    /// 2. Parse input into syllable segmentations (parser handles fuzzy matching)
+    /// 3. For each segmentation:
+    ///    - Convert to lexicon key
+    ///    - Look up candidates in lexicon
+    ///    - Apply penalty if segmentation used fuzzy matching
+    /// 4. Merge and rank candidates
+    /// 5. Cache the result
+    pub fn input(&self, input: &str) -> Vec<Candidate> {
+        if !self.sync_cache_capacity() {
+            *self.cache_misses.borrow_mut() += 1;
+            return self.compute_candidates(input);
+        }
+
+        // Check cache first (LRU automatically updates access time)
+        if let Some(cached) = self.cache.borrow_mut().get(&input.to_string()) {
+            *self.cache_hits.borrow_mut() += 1;
+            return cached.clone();
+        }
+
+        *self.cache_misses.borrow_mut() += 1;
+
+        let vec = self.compute_candidates(input);
+
+        // Cache the result (LRU automatically handles eviction)
+        self.cache.borrow_mut().put(input.to_string(), vec.clone());
+
+        vec
+    }
+
+    /// Candidates for `input` with syllable breaks pinned at `boundaries`
+    /// (byte offsets, e.g. from `InputBuffer::boundaries`), so "xian" with
+    /// a boundary at 2 is read as xi + an rather than xian.
+    ///
+    /// Falls back to the normal segmentation if the pinned pieces cannot
+    /// be parsed. Results are not cached; with no boundaries this is
+    /// `input`.
+    pub fn input_with_boundaries(&self, input: &str, boundaries: &[usize]) -> Vec<Candidate> {
+        if boundaries.is_empty() {
+            return self.input(input);
+        }
+        *self.cache_misses.borrow_mut() += 1;
+        self.compute_candidates_constrained(input, boundaries, self.limit)
+    }
+
+    /// Convert a whole sentence to its single best Hanzi string.
+    ///
+    /// Same search as `convert_sentence_nbest` with `n = 1`. Returns the
+    /// input unchanged if it cannot be segmented.
+    pub fn convert_sentence(&self, input: &str) -> String {
+        match self.convert_sentence_nbest(input, 1).into_iter().next() {
+            Some((text, _)) => text,
+            None => input.to_string(),
+        }
+    }
+
+    /// The `n` best whole-sentence conversions of `input` with their scores,
+    /// best first.
+    ///
+    /// Searches the word lattice of each top segmentation. A lattice state
+    /// is a syllable position plus the last word, so every step is scored
+    /// with the exact bigram context (interpolated `WordBigram` probability,
+    /// minus `sentence_length_penalty`, plus the user dictionary boost);
+    /// each state keeps its `n` best partial sentences, and the best
+    /// `SENTENCE_BEAM_WIDTH` states are kept at each position. The result
+    /// is the best word sequences overall rather than a per-position greedy
+    /// choice.
+    ///
+    /// Sentences are deduplicated by text (keeping the best score, so "中国"
+    /// and "中" + "国" count once) and equal scores are ordered by text.
+    /// Syllables that start no lexicon word are passed through as typed.
+    pub fn convert_sentence_nbest(&self, input: &str, n: usize) -> Vec<(String, f32)> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let mut sentences: Vec<(String, f32)> = Vec::new();
+        for seg in self.top_segmentations(input) {
+            for (text, score) in self.sentence_lattice(&seg, n) {
+                match sentences.iter_mut().find(|(t, _)| *t == text) {
+                    Some(existing) => existing.1 = existing.1.max(score),
+                    None => sentences.push((text, score)),
+                }
+            }
+        }
+        sort_sentences(&mut sentences);
+        sentences.truncate(n);
+        sentences
+    }
+
+    /// The `n` best word sequences covering `seg`, as (text, score).
+    fn sentence_lattice(&self, seg: &[P::Syllable], n: usize) -> Vec<(String, f32)> {
+        let len = seg.len();
+        if len == 0 {
+            return Vec::new();
+        }
+
+        // states[i]: last word -> best partial sentences ending at i
+        let mut states: Vec<HashMap<String, Vec<(String, f32)>>> = vec![HashMap::new(); len + 1];
+        states[0].insert(String::new(), vec![(String::new(), 0.0)]);
+
+        for i in 0..len {
+            if states[i].is_empty() {
+                continue;
+            }
+            let mut sources: Vec<(String, Vec<(String, f32)>)> = states[i].drain().collect();
+            sources.sort_by(|a, b| b.1[0].1.total_cmp(&a.1[0].1).then_with(|| a.0.cmp(&b.0)));
+            sources.truncate(SENTENCE_BEAM_WIDTH);
+
+            // Words starting at i, with their end position
+            let mut edges: Vec<(usize, String)> = Vec::new();
+            for span in 1..=std::cmp::min(MAX_SENTENCE_WORD_SYLLABLES, len - i) {
+                for (word, _) in self.lookup_syllables(&seg[i..i + span]) {
+                    edges.push((i + span, word));
+                }
+            }
+            // Pass an unknown syllable through so the lattice stays connected
+            if !edges.iter().any(|(end, _)| *end == i + 1) {
+                edges.push((i + 1, seg[i].text().to_string()));
+            }
+
+            for (end, word) in &edges {
+                for (prev, partials) in &sources {
+                    let context = (i > 0).then_some(prev.as_str());
+                    let step = self.word_score(context, word);
+                    let slot = states[*end].entry(word.clone()).or_default();
+                    for (text, score) in partials {
+                        let text = format!("{}{}", text, word);
+                        let score = score + step;
+                        match slot.iter_mut().find(|(t, _)| *t == text) {
+                            Some(existing) => existing.1 = existing.1.max(score),
+                            None => slot.push((text, score)),
+                        }
+                    }
+                    sort_sentences(slot);
+                    slot.truncate(n);
+                }
+            }
+        }
+
+        states
+            .pop()
+            .unwrap_or_default()
+            .into_values()
+            .flatten()
+            .collect()
+    }
+
+    /// Score of `word` following `prev` (`None` at the start of a sentence).
+    ///
+    /// Same word-level scoring as the candidate DP: interpolated bigram (or
+    /// lambda-scaled unigram without context), minus the sentence length
+    /// penalty, plus the user dictionary boost.
+    fn word_score(&self, prev: Option<&str>, word: &str) -> f32 {
+        let config = self.model.config.borrow();
+        let lambda = config.lambda;
+        let sentence_length_penalty = config.sentence_length_penalty;
+        let unigram_factor = config.unigram_factor;
+        drop(config);
+
+        let unigram_prob = self.model.word_bigram.get_unigram_probability(word);
+        let prob = match prev {
+            Some(prev) => {
+                let bigram_prob = self.model.word_bigram.get_probability(prev, word);
+                lambda * bigram_prob + (1.0 - lambda) * unigram_prob
+            }
+            None => unigram_prob * (1.0 - lambda),
+        };
+        let mut score = prob.max(1e-10).ln() - sentence_length_penalty;
+
+        let user_freq = self.user_frequency(word);
+        if user_freq > 0.0 {
+            score += unigram_factor * (1.0 + user_freq).ln();
+        }
+        score
+    }
+
+    /// Warm the candidate cache for likely continuations of `prefix`.
+    ///
+    /// Looks up the most frequent lexicon keys extending `prefix` ("bei" →
+    /// "bei'jing", "bei'fang") and computes candidates for each as typed
+    /// input ("beijing"), so a later `input` call for them is a cache hit.
+    /// Inputs already cached are skipped, and prefetching does not count
+    /// toward `cache_stats`. Returns the inputs that were prefetched.
+    ///
+    /// Does nothing unless `Config::typeahead_prefetch` is enabled. Meant to
+    /// be called while the front-end is idle between keystrokes.
+    pub fn prefetch_completions(&self, prefix: &str) -> Vec<String> {
+        if !self.model.config.borrow().typeahead_prefetch || !self.sync_cache_capacity() {
+            return Vec::new();
+        }
+
+        let mut prefetched = Vec::new();
+        for key in self
+            .model
+            .lexicon
+            .key_completions(prefix, PREFETCH_COMPLETIONS)
+        {
+            let input: String = key.chars().filter(|&c| c != '\'').collect();
+            if self.cache.borrow().contains(&input) {
+                continue;
+            }
+            let candidates = self.compute_candidates(&input);
+            self.cache.borrow_mut().put(input.clone(), candidates);
+            prefetched.push(input);
+        }
+        prefetched
+    }
+
+    /// Return the `k` best candidates for `input`, with user learning applied.
+    ///
+    /// Candidates are scored exactly as by `input`, including the user
+    /// dictionary's frequencies and learned segmentations. When `k` fits in
+    /// the engine's candidate limit the cached result is reused and cut
+    /// down; a larger `k` runs the pipeline with that limit instead (not
+    /// cached). Returns fewer than `k` only if fewer candidates exist.
+    pub fn candidates_top_k(&self, input: &str, k: usize) -> Vec<Candidate> {
+        if k <= self.limit {
+            let mut candidates = self.input(input);
+            candidates.truncate(k);
+            candidates
+        } else {
+            self.compute_candidates_limited(input, k)
+        }
+    }
+
+    /// Candidates for `input` as (text, reading, score), in `input` order.
+    ///
+    /// The reading is the segmentation that produced the candidate's best
+    /// score, with syllables joined by `'`. Fuzzy matches report the
+    /// syllable they were corrected to ("zi" typed, "zhi" read). Candidates
+    /// not built from a segmentation (quick codes, numerals) fall back to
+    /// their first lexicon reading, or an empty string.
+    pub fn candidates_with_readings(&self, input: &str) -> Vec<(String, String, f32)> {
+        let candidates = self.input(input);
+
+        // Best-scoring segmentation reading of each phrase
+        let mut readings: HashMap<String, (f32, String)> = HashMap::new();
+        for seg in self.top_segmentations(input) {
+            let reading = seg.iter().map(|s| s.text()).collect::<Vec<_>>().join("'");
+            for cand in self.generate_candidates_from_segmentation(&seg) {
+                match readings.get(&cand.text) {
+                    Some((score, _)) if *score >= cand.score => {}
+                    _ => {
+                        readings.insert(cand.text, (cand.score, reading.clone()));
+                    }
+                }
+            }
+        }
+
+        let lexicon = &self.model.lexicon;
+        candidates
+            .into_iter()
+            .map(|cand| {
+                let reading = cand
+                    .reading
+                    .or_else(|| readings.remove(&cand.text).map(|(_, r)| r))
+                    .or_else(|| lexicon.reverse_lookup(&cand.text).into_iter().next())
+                    .unwrap_or_default();
+                (cand.text, reading, cand.score)
+            })
+            .collect()
+    }
+
+    /// The top candidate for `input` split into known sub-words, one
+    /// candidate per part ("beijingdaxue" → 北京, 大学).
+    ///
+    /// Uses `Model::best_compound_split`, so front-ends can offer the parts
+    /// as a group for committing piece by piece. Each part gets the top
+    /// candidate's score and its share of the reading the input was
+    /// segmented into ("shizhang" → 市 shi, 长 zhang); parts get no reading
+    /// when the reading does not have one syllable per character. Empty
+    /// unless `Config::offer_compound_splits` is enabled, or when the top
+    /// candidate has no split.
+    pub fn compound_split_group(&self, input: &str) -> Vec<Candidate> {
+        if !self.model.config.borrow().offer_compound_splits {
+            return Vec::new();
+        }
+        let Some((text, reading, score)) = self.candidates_with_readings(input).into_iter().next()
+        else {
+            return Vec::new();
+        };
+        let Some(parts) = self.model.best_compound_split(&text) else {
+            return Vec::new();
+        };
+
+        let syllables: Vec<&str> = reading.split('\'').filter(|s| !s.is_empty()).collect();
+        let per_char = syllables.len() == text.chars().count();
+        let mut offset = 0;
+        parts
+            .into_iter()
+            .map(|part| {
+                let len = part.chars().count();
+                let cand = Candidate::new(part, score);
+                let cand = if per_char {
+                    cand.with_reading(syllables[offset..offset + len].join("'"))
+                } else {
+                    cand
+                };
+                offset += len;
+                cand
+            })
+            .collect()
+    }
+
+    /// The part of `input` left after the syllables of `reading`.
+    ///
+    /// Syllables are matched literally against the start of the input,
+    /// skipping `'` separators, so "bei'jing" leaves "daxue" of
+    /// "beijingdaxue". Returns `None` if the reading does not spell a prefix
+    /// of the input (e.g. a fuzzy-corrected syllable, or a reading longer
+    /// than the input).
+    pub fn unconsumed_input<'a>(&self, input: &'a str, reading: &str) -> Option<&'a str> {
+        let mut rest = input;
+        for syllable in reading.split('\'').filter(|s| !s.is_empty()) {
+            rest = rest.trim_start_matches('\'').strip_prefix(syllable)?;
+        }
+        Some(rest.trim_start_matches('\''))
+    }
+
+    /// Run the candidate pipeline for `input`, bypassing the cache.
+    fn compute_candidates(&self, input: &str) -> Vec<Candidate> {
+        self.compute_candidates_limited(input, self.limit)
+    }
+
+    /// Whether the parser takes `ch` as phonetic input (see
+    /// `SyllableParser::is_input_key`).
+    pub fn is_input_key(&self, ch: char) -> bool {
+        self.parser.is_input_key(ch)
+    }
+
+    /// Whether `ch` typed on an empty preedit starts a shape code: it opens
+    /// a non-empty `Config::shape_code_prefix` and the lexicon has a shape
+    /// index.
+    pub fn starts_shape_code(&self, ch: char) -> bool {
+        self.model.lexicon.has_shape_index()
+            && self.model.config.borrow().shape_code_prefix.starts_with(ch)
+    }
+
+    /// `input` as the preedit should show it, if that differs from the
+    /// typed keys (see `SyllableParser::display_input`).
+    pub fn display_input(&self, input: &str) -> Option<String> {
+        self.parser.display_input(input)
+    }
+
+    /// Problem the parser found in `input`, if any (see
+    /// `SyllableParser::input_error`). `ImeEngine` shows it in the
+    /// auxiliary text.
+    pub fn input_error(&self, input: &str) -> Option<String> {
+        self.parser.input_error(input)
+    }
+
+    /// Top segmentations of the input (parser already applied fuzzy matching).
+    fn top_segmentations(&self, input: &str) -> Vec<Vec<P::Syllable>> {
+        // Use an adaptive k computed from input length to balance
+        // recall vs CPU work. Parser internally uses dynamic beam width scaling
+        // (see parser.rs:840-842) so k has a non-linear effect on parser cost.
+        let input_len = input.len();
+        
+        // Hardcoded segmentation limits (previously in Config)
+        let short_k: usize = 4;
+        let long_k: usize = 8;
+        let max_k: usize = 12;
+
+        // Heuristic: keep small inputs low, increase gradually for longer inputs,
+        // but clamp to a max. This mirrors upstream piecewise/proportional rules.
+        let k = if input_len <= 6 {
+            short_k
+        } else {
+            // add one extra segmentation per ~4 extra chars beyond 6
+            let extra = (input_len.saturating_sub(6)) / 4;
+            let computed = long_k.saturating_add(extra);
+            std::cmp::min(computed, max_k)
+        };
+
+        self.parser.segment_top_k(input, k, true)
+    }
+
+    /// Run the candidate pipeline, keeping at most `limit` candidates.
+    fn compute_candidates_limited(&self, input: &str, limit: usize) -> Vec<Candidate> {
+        self.compute_candidates_constrained(input, &[], limit)
+    }
+
+    /// Run the candidate pipeline with syllable breaks pinned at
+    /// `boundaries` (see `input_with_boundaries`).
+    fn compute_candidates_constrained(
+        &self,
+        input: &str,
+        boundaries: &[usize],
+        limit: usize,
+    ) -> Vec<Candidate> {
+        // Input behind the shape-code prefix is a code, not phonetic input
+        if let Some(code) = self.shape_code(input) {
+            return self.shape_code_candidates(&code, limit);
+        }
+
+        // Literal apostrophes are no syllable boundary; the typed text itself
+        // is offered first instead (see `Config::apostrophe_mode`)
+        let literal = self.without_literal_apostrophes(input);
+        let pinned = if boundaries.is_empty() || literal.is_some() {
+            Vec::new()
+        } else {
+            SyllableParser::segment_best_constrained(&self.parser, input, boundaries, true)
+        };
+        let segs = if pinned.is_empty() {
+            self.top_segmentations(literal.as_deref().unwrap_or(input))
+        } else {
+            vec![pinned]
+        };
+
+        // Map from phrase -> best Candidate (keep highest score)
+        let mut best: HashMap<String, Candidate> = HashMap::new();
+
+        // A segmentation the user taught by splitting this input manually
+        // (see `learn_segmentation`) is re-parsed with explicit separators and
+        // its candidates get a boost so they outrank the parser's own choice.
+        // Boundaries pinned for this input take precedence.
+        let preferred = match boundaries.is_empty() {
+            true => self.model.userdict.preferred_segmentation(input),
+            false => None,
+        };
+        if let Some(preferred) = preferred {
+            let boost = self.model.config.borrow().segmentation_learning_boost;
+            if let Some(seg) = self.segment_split_input(&preferred.join("'")) {
+                for mut cand in self.generate_candidates_from_segmentation(&seg) {
+                    cand.score += boost;
+                    best.insert(cand.text.clone(), cand);
+                }
+            }
+        }
+
+        // Top candidate of each distinct segmentation, labeled with its reading
+        let offer_variants = self.model.config.borrow().offer_segmentation_variants;
+        let mut variants: Vec<Candidate> = Vec::new();
+
+        // Learned phrases the input is a partial reading of
+        let completion_boost = self.model.config.borrow().learned_completion_boost;
+        if completion_boost > 0.0 {
+            for cand in self.learned_completions(&segs, completion_boost) {
+                match best.get(&cand.text) {
+                    Some(existing) if existing.score >= cand.score => {}
+                    _ => {
+                        best.insert(cand.text.clone(), cand);
+                    }
+                }
+            }
+        }
+
+        // Phrases for the leading syllables of the best segmentation
+        let offer_prefixes = self.model.config.borrow().offer_prefix_candidates;
+        let prefix_candidates = match segs.first() {
+            Some(seg) if offer_prefixes && literal.is_none() => self.prefix_candidates(seg),
+            _ => Vec::new(),
+        };
+
+        // Phrases spelling the whole input get an extra multiplicative boost
+        let whole_input_boost = self.model.config.borrow().whole_input_phrase_boost;
+        let length_match_boost = self.model.config.borrow().syllable_phrase_match_boost;
+        let normalized_input = normalize_spelling(input);
+
+        for seg in segs.into_iter() {
+            // For each segmentation, generate candidates by trying all possible word boundaries
+            // e.g., [ni,hao,wo,shi] can be: "你好"+"我是", "你"+"好"+"我是", etc.
+            let mut candidates = self.generate_candidates_from_segmentation(&seg);
+
+            if whole_input_boost > 0.0 && whole_input_boost != 1.0 {
+                self.apply_whole_input_boost(
+                    &seg,
+                    &normalized_input,
+                    whole_input_boost,
+                    &mut candidates,
+                );
+            }
+
+            // One character per syllable: neither over- nor under-converted
+            if length_match_boost != 0.0 {
+                for cand in candidates.iter_mut() {
+                    if cand.text.chars().count() == seg.len() {
+                        cand.score += length_match_boost;
+                    }
+                }
+            }
+
+            if offer_variants {
+                let reading = seg.iter().map(|s| s.text()).collect::<Vec<_>>().join("'");
+                let top = candidates.iter().max_by(|a, b| {
+                    a.score
+                        .partial_cmp(&b.score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                if let Some(top) = top {
+                    if !variants
+                        .iter()
+                        .any(|v| v.reading.as_deref() == Some(reading.as_str()))
+                    {
+                        variants.push(top.clone().with_reading(reading));
+                    }
+                }
+            }
+
+            // Merge candidates: keep the best score seen for this exact phrase
+            for cand in candidates.into_iter() {
+                match best.get(&cand.text) {
+                    Some(existing) if existing.score >= cand.score => {}
+                    _ => {
+                        best.insert(cand.text.clone(), cand.clone());
+                    }
+                }
+            }
+        }
+
+        // Collect, sort and return top results
+        let mut vec: Vec<Candidate> = best.into_values().collect();
+
+        let config = self.model.config.borrow();
+
+        // Demote candidates containing rare characters
+        if config.rare_char_penalty > 0.0 {
+            for cand in vec.iter_mut() {
+                let rare = crate::char_freq::rare_char_count(&cand.text);
+                cand.score -= config.rare_char_penalty * rare as f32;
+            }
+        }
+
+        // Demote phrases the user keeps passing over for this input
+        if config.rejection_penalty > 0.0 {
+            let rejections = self.model.userdict.rejections_for(&normalized_input);
+            if !rejections.is_empty() {
+                for cand in vec.iter_mut().chain(variants.iter_mut()) {
+                    if let Some(&count) = rejections.get(&cand.text) {
+                        cand.score -= config.rejection_penalty * (1.0 + count as f32).ln();
+                    }
+                }
+            }
+        }
+
+        // Filter out masked phrases
+        if !config.masked_phrases.is_empty() {
+            vec.retain(|c| !config.is_masked(&c.text));
+        }
+        let numeral_candidates = config.numeral_candidates;
+        let trim_particles = config.trim_trailing_particles;
+        let exclude_raw = config.exclude_raw_spelling;
+        let length_bias = config
+            .sort_by_phrase_length
+            .then_some(config.length_bias_epsilon);
+        drop(config);
+
+        // Sort by score (higher is better)
+        vec.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some(epsilon) = length_bias {
+            prefer_longer_phrases(&mut vec, epsilon);
+        }
+
+        if trim_particles {
+            self.insert_trimmed_particle_candidates(&mut vec);
+        }
+
+        if numeral_candidates {
+            self.insert_numeral_candidates(input, &mut vec);
+        }
+
+        self.insert_quick_code_candidates(input, &mut vec);
+
+        // Hide the typed spelling itself, wherever it came from
+        if exclude_raw {
+            let is_raw = |c: &Candidate| {
+                c.text.is_ascii()
+                    && c.text
+                        .chars()
+                        .filter(|ch| *ch != '\'' && !ch.is_whitespace())
+                        .map(|ch| ch.to_ascii_lowercase())
+                        .eq(normalized_input.chars())
+            };
+            vec.retain(|c| !is_raw(c));
+            variants.retain(|c| !is_raw(c));
+        }
+
+        if literal.is_some() {
+            vec.retain(|c| c.text != input);
+            let score = vec.first().map(|c| c.score).unwrap_or(0.0);
+            vec.insert(0, Candidate::new(input, score));
+        }
+
+        if vec.len() > limit {
+            vec.truncate(limit);
+        }
+
+        if offer_variants {
+            self.merge_segmentation_variants(variants, &mut vec, limit);
+        }
+
+        self.append_prefix_candidates(prefix_candidates, &mut vec);
+
+        self.insert_emoji_candidates(&normalized_input, &mut vec);
+
+        vec
+    }
+
+    /// The shape code in `input`, if it starts with a non-empty
+    /// `Config::shape_code_prefix` and the lexicon has a shape index.
+    fn shape_code(&self, input: &str) -> Option<String> {
+        if !self.model.lexicon.has_shape_index() {
+            return None;
+        }
+        let config = self.model.config.borrow();
+        let prefix = config.shape_code_prefix.as_str();
+        if prefix.is_empty() {
+            return None;
+        }
+        input
+            .strip_prefix(prefix)
+            .filter(|code| !code.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Candidates for a shape code, scored like full-key phonetic matches.
+    fn shape_code_candidates(&self, code: &str, limit: usize) -> Vec<Candidate> {
+        let config = self.model.config.borrow();
+        let mut phrases: Vec<String> = Vec::new();
+        for (phrase, _) in self.model.lexicon.shape_lookup_with_freq(code) {
+            if !config.is_masked(&phrase) && !phrases.contains(&phrase) {
+                phrases.push(phrase);
+            }
+        }
+        drop(config);
+
+        let mut vec: Vec<Candidate> = phrases
+            .into_iter()
+            .map(|phrase| {
+                let score = self.full_key_score(&phrase);
+                Candidate::new(phrase, score)
+            })
+            .collect();
+        vec.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        vec.truncate(limit);
+        vec
+    }
+
+    /// `input` with its literal apostrophes removed, or `None` when every
+    /// apostrophe in it separates syllables.
+    fn without_literal_apostrophes(&self, input: &str) -> Option<String> {
+        let mode = self.model.config.borrow().apostrophe_mode;
+        let literal: Vec<usize> = input
+            .match_indices('\'')
+            .map(|(i, _)| i)
+            .filter(|&i| !mode.separates_at(input, i))
+            .collect();
+        if literal.is_empty() {
+            return None;
+        }
+        Some(
+            input
+                .char_indices()
+                .filter(|(i, _)| !literal.contains(i))
+                .map(|(_, c)| c)
+                .collect(),
+        )
+    }
+
+    /// Commit a phrase to user learning.
+    ///
+    /// Records user selection to boost future rankings.
+    /// Drops the cached candidate lists so updated frequencies are reflected
+    /// immediately: a learned phrase can change the best path of any input
+    /// that composes it, including abbreviated and fuzzy spellings.
+    pub fn commit(&self, phrase: &str) {
+        // Learn the phrase in the user dictionary (increments frequency by 1)
+        self.model.userdict.learn(phrase);
+
+        self.cache.borrow_mut().clear();
+    }
+
+    /// Record that the user skipped `rejected_phrase` for `input`, picking a
+    /// lower candidate instead.
+    ///
+    /// Each rejection demotes the phrase a little further for this input
+    /// only (see `Config::rejection_penalty`); its ranking for other inputs
+    /// and its learned frequency are unaffected.
+    pub fn record_rejection(&self, input: &str, rejected_phrase: &str) {
+        let key = normalize_spelling(input);
+        if key.is_empty() || rejected_phrase.is_empty() {
+            return;
+        }
+        let _ = self.model.userdict.learn_rejection(&key, rejected_phrase);
+
+        // Only inputs with the same spelling see the rejection
+        let mut cache = self.cache.borrow_mut();
+        let stale: Vec<String> = cache
+            .iter()
+            .filter(|(cached, _)| normalize_spelling(cached) == key)
+            .map(|(cached, _)| cached.clone())
+            .collect();
+        for cached in stale {
+            cache.pop(&cached);
+        }
+    }
+
+    /// Install a filter that can veto commits (e.g. to block certain words).
+    ///
+    /// `ImeEngine` asks the filter before committing any non-empty text, and
+    /// editors do not learn a vetoed phrase. Whether the preedit survives a
+    /// veto is controlled by `Config::preserve_preedit_on_veto`.
+    pub fn set_commit_filter<F>(&self, filter: F)
+    where
+        F: Fn(&str) -> bool + Send + 'static,
+    {
+        *self.commit_filter.borrow_mut() = Some(Box::new(filter));
+    }
+
+    /// Remove the commit filter, allowing all commits again.
+    pub fn clear_commit_filter(&self) {
+        self.commit_filter.borrow_mut().take();
+    }
+
+    /// Check whether the commit filter allows committing `text`.
+    ///
+    /// Always true when no filter is installed.
+    pub fn allows_commit(&self, text: &str) -> bool {
+        self.commit_filter
+            .borrow()
+            .as_ref()
+            .is_none_or(|filter| filter(text))
+    }
+
+    /// Load an emoji lexicon (`<name>.fst` plus `<name>.bincode`, as
+    /// `convert_table` builds from `emoji.table`) for emoji candidates.
+    ///
+    /// Input spelling a keyword ("xiao", "haha") then also offers its emoji,
+    /// tagged `CandidateSource::Emoji`, after the text candidates (or ahead
+    /// of them with `Config::emoji_priority`). Masked emoji are skipped.
+    pub fn load_emoji_lexicon<Q: AsRef<std::path::Path>>(&self, fst_path: Q) -> Result<(), String> {
+        let fst_path = fst_path.as_ref();
+        let lexicon =
+            Lexicon::load_from_fst_bincode(fst_path, &fst_path.with_extension("bincode"))?;
+        *self.emoji.borrow_mut() = Some(lexicon);
+        self.clear_cache();
+        Ok(())
+    }
+
+    /// Whether an emoji lexicon is loaded.
+    pub fn has_emoji_lexicon(&self) -> bool {
+        self.emoji.borrow().is_some()
+    }
+
+    /// Learn words and word bigrams from a block of text (e.g. pasted by the user).
+    ///
+    /// The text is split into runs of Han characters; each run is tokenized
+    /// into known lexicon words by greedy longest match. Every word is learned
+    /// into the user dictionary along with the bigram from the previous word.
+    /// Characters that do not form a known word break the bigram chain.
+    ///
+    /// Returns the number of words learned.
+    pub fn train_from_text(&self, text: &str) -> usize {
+        // Longest phrase worth probing (mirrors MAX_LONG_LOOKUP_SYLLABLES)
+        const MAX_WORD_CHARS: usize = 10;
+
+        let chars: Vec<char> = text.chars().collect();
+        let lexicon = &self.model.lexicon;
+        let userdict = &self.model.userdict;
+
+        let mut learned = 0;
+        let mut prev: Option<String> = None;
+        let mut i = 0;
+
+        while i < chars.len() {
+            if !crate::char_freq::is_han_char(chars[i]) {
+                prev = None;
+                i += 1;
+                continue;
+            }
+
+            // Extent of the current Han run
+            let run_end = (i..chars.len())
+                .find(|&j| !crate::char_freq::is_han_char(chars[j]))
+                .unwrap_or(chars.len());
+            let max_len = std::cmp::min(MAX_WORD_CHARS, run_end - i);
+
+            let word = (1..=max_len)
+                .rev()
+                .map(|len| chars[i..i + len].iter().collect::<String>())
+                .find(|w| lexicon.contains_phrase(w));
+
+            match word {
+                Some(word) => {
+                    userdict.learn(&word);
+                    if let Some(p) = &prev {
+                        userdict.learn_bigram(p, &word);
+                    }
+                    i += word.chars().count();
+                    prev = Some(word);
+                    learned += 1;
+                }
+                None => {
+                    prev = None;
+                    i += 1;
+                }
+            }
+        }
+
+        if learned > 0 {
+            self.clear_cache();
+        }
+
+        learned
+    }
+
+    /// Aggregate a selection log of (input, chosen phrase) pairs.
+    ///
+    /// Entries with an empty input or phrase, and masked phrases, are
+    /// skipped. The log is not applied; see `apply_selection_stats`.
+    pub fn aggregate_selection_stats(&self, log: &[(String, String)]) -> SelectionStats {
+        let config = self.model.config.borrow();
+        let mut stats = SelectionStats::default();
+        for (input, phrase) in log {
+            if input.is_empty() || phrase.is_empty() || config.is_masked(phrase) {
+                continue;
+            }
+            stats.record(input, phrase);
+        }
+        stats
+    }
+
+    /// Seed the user dictionary from aggregated selection statistics.
+    ///
+    /// Each chosen phrase is learned as many times as it was selected, in
+    /// bulk, so it ranks as if the user had picked it that often. Returns
+    /// the number of (input, phrase) entries applied.
+    pub fn apply_selection_stats(&self, stats: &SelectionStats) -> usize {
+        let applied = stats
+            .entries()
+            .filter(|(_, phrase, count)| {
+                self.model.userdict.learn_with_count(phrase, *count).is_ok()
+            })
+            .count();
+
+        if applied > 0 {
+            self.clear_cache();
+        }
+
+        applied
+    }
+
+    /// Compute the minimal input + selection needed to commit a phrase.
+    ///
+    /// Readings come from the lexicon's reverse index, so this returns `None`
+    /// unless `Lexicon::build_reverse_index` was called. Each reading is tried
+    /// as typed without separators ("nihao") and, if different, with them
+    /// ("xi'an"), along with every shorter prefix of those inputs that
+    /// already lists the target (an abbreviation or completion). A target
+    /// past the first page of `DEFAULT_PAGE_SIZE` candidates costs a
+    /// PageDown per page. The plan with the fewest keystrokes wins, ties
+    /// going to the better-ranked candidate. Returns `None` if the phrase has
+    /// no reading or never shows up among the candidates.
+    pub fn keystrokes_to_commit(&self, target: &str) -> Option<KeystrokePlan> {
+        self.keystrokes_to_commit_with_page_size(target, DEFAULT_PAGE_SIZE)
+    }
+
+    /// `keystrokes_to_commit` for a front-end showing `page_size`
+    /// candidates per page (0 is treated as 1).
+    pub fn keystrokes_to_commit_with_page_size(
+        &self,
+        target: &str,
+        page_size: usize,
+    ) -> Option<KeystrokePlan> {
+        let page_size = page_size.max(1);
+        let mut best: Option<KeystrokePlan> = None;
+
+        for reading in self.model.lexicon.reverse_lookup(target) {
+            let unsplit = reading.replace('\'', "");
+            let mut inputs = vec![unsplit.clone()];
+            if unsplit != reading {
+                inputs.push(reading);
+            }
+
+            for full in inputs {
+                // Shortest prefix first; a longer one can only cost more
+                // keystrokes than a plan already found
+                let ends = full
+                    .char_indices()
+                    .skip(1)
+                    .map(|(i, _)| i)
+                    .chain(std::iter::once(full.len()));
+                for end in ends {
+                    let input = &full[..end];
+                    let typed = input.chars().count();
+                    if best.as_ref().is_some_and(|b| typed + 1 > b.keystrokes) {
+                        break;
+                    }
+                    if input.ends_with('\'') {
+                        continue;
+                    }
+
+                    let candidates = self.input(input);
+                    let Some(selection) = candidates.iter().position(|c| c.text == target) else {
+                        continue;
+                    };
+
+                    let page_flips = selection / page_size;
+                    let plan = KeystrokePlan {
+                        input: input.to_string(),
+                        selection,
+                        page_flips,
+                        keystrokes: typed + page_flips + 1,
+                    };
+                    let better = match &best {
+                        None => true,
+                        Some(b) => (plan.keystrokes, plan.selection) < (b.keystrokes, b.selection),
+                    };
+                    if better {
+                        best = Some(plan);
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Count the candidates `input` returns by their `CandidateSource`.
+    ///
+    /// Uses the same (cached) candidate list as `input`, so the counts
+    /// describe exactly what a front-end would show.
+    pub fn candidate_origin_stats(&self, input: &str) -> OriginStats {
+        let mut stats = OriginStats::default();
+        for cand in self.input(input) {
+            match cand.source {
+                CandidateSource::Lexicon | CandidateSource::UserDict => stats.lexicon += 1,
+                CandidateSource::Composed => stats.composed += 1,
+                _ => stats.other += 1,
+            }
+        }
+        stats
+    }
+
+    /// Estimate the typing cost of a corpus with the current model.
+    ///
+    /// Each sentence is split into Han runs, and each run is typed phrase by
+    /// phrase: the longest lexicon phrase that `keystrokes_to_commit` can
+    /// reach is committed with its cheapest plan. Characters outside Han runs
+    /// are ignored; Han characters with no reachable reading are counted in
+    /// `unconverted`. Nothing is learned, so the result is deterministic for
+    /// a given model.
+    pub fn evaluate_corpus(&self, sentences: &[&str]) -> CorpusEval {
+        // Longest phrase worth probing (mirrors MAX_LONG_LOOKUP_SYLLABLES)
+        const MAX_WORD_CHARS: usize = 10;
+
+        let mut eval = CorpusEval::default();
+
+        for sentence in sentences {
+            eval.sentences += 1;
+            let chars: Vec<char> = sentence.chars().collect();
+            let mut i = 0;
+
+            while i < chars.len() {
+                if !crate::char_freq::is_han_char(chars[i]) {
+                    i += 1;
+                    continue;
+                }
 
-    /// Whether this syllable was matched via fuzzy matching
-    fn is_fuzzy(&self) -> bool;
-}
+                let run_end = (i..chars.len())
+                    .find(|&j| !crate::char_freq::is_han_char(chars[j]))
+                    .unwrap_or(chars.len());
+                let max_len = std::cmp::min(MAX_WORD_CHARS, run_end - i);
 
-/// Generic IME engine that combines parser and model for candidate generation.
-///
-/// Type parameter P is the parser type (e.g., Parser for pinyin, ZhuyinParser for zhuyin).
-///
-/// Note: Fuzzy matching is handled by the parser during segmentation. The engine
-/// works with the segmentations provided by the parser.
-pub struct Engine<P> {
-    model: Model,
-    parser: P,
-    limit: usize,
-    cache: RefCell<lru::LruCache<String, Vec<Candidate>>>,
-    cache_hits: RefCell<usize>,
-    cache_misses: RefCell<usize>,
-}
+                let step = (1..=max_len).rev().find_map(|len| {
+                    let word: String = chars[i..i + len].iter().collect();
+                    if !self.model.lexicon.contains_phrase(&word) {
+                        return None;
+                    }
+                    self.keystrokes_to_commit(&word).map(|plan| (len, plan))
+                });
+
+                match step {
+                    Some((len, plan)) => {
+                        eval.characters += len;
+                        eval.keystrokes += plan.keystrokes;
+                        eval.conversions += 1;
+                        if plan.selection == 0 {
+                            eval.top1_hits += 1;
+                        }
+                        i += len;
+                    }
+                    None => {
+                        eval.unconverted += 1;
+                        i += 1;
+                    }
+                }
+            }
+        }
 
-impl<P: SyllableParser> Engine<P> {
-    /// Create a new engine with the given model and parser.
-    pub fn new(model: Model, parser: P) -> Self {
-        // Default cache capacity
-        let cache_capacity = 1000;
+        eval
+    }
 
-        Self {
-            model,
-            parser,
-            limit: 8,
-            cache: RefCell::new(lru::LruCache::new(
-                std::num::NonZeroUsize::new(cache_capacity)
-                    .unwrap_or(std::num::NonZeroUsize::new(1000).unwrap()),
-            )),
-            cache_hits: RefCell::new(0),
-            cache_misses: RefCell::new(0),
+    /// Predict words likely to follow `context`, best first.
+    ///
+    /// Word bigram predictions (log interpolated probabilities) are merged
+    /// with the user's learned bigrams, which add `ln(1 + count)`, halved for
+    /// every `Config::prediction_recency_halflife_secs` since last use.
+    pub fn predict_next(&self, context: &str, limit: usize) -> Vec<(String, f32)> {
+        let last_word = context.trim();
+        if last_word.is_empty() {
+            return Vec::new();
+        }
+
+        let lambda = self.model.config.borrow().lambda;
+        let halflife = self.model.config.borrow().prediction_recency_halflife_secs;
+        let mut combined = self
+            .model
+            .word_bigram
+            .get_predictions(last_word, lambda, limit);
+
+        let now = crate::userdict::system_now();
+        for (word, user_count, last_used) in
+            self.model.userdict.get_bigrams_after_with_time(last_word)
+        {
+            let mut user_boost = (1.0 + user_count as f32).ln();
+            // Stale bigrams fade: the boost halves every `halflife` seconds
+            if halflife > 0 {
+                let age = now.duration_since(last_used).unwrap_or_default();
+                user_boost *= 0.5_f32.powf(age.as_secs_f32() / halflife as f32);
+            }
+            if let Some(existing) = combined.iter_mut().find(|(w, _)| w == &word) {
+                existing.1 += user_boost;
+            } else {
+                combined.push((word, user_boost));
+            }
         }
+
+        combined.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        combined.truncate(limit);
+        combined
     }
 
-    /// Process input and return ranked candidates.
+    /// Confidence in the top prediction after `context`, in [0, 1].
     ///
-    /// This implements the full IME pipeline:
-    /// 1. Check cache for previous result
-    /// 2. Parse input into syllable segmentations (parser handles fuzzy matching)
-    /// 3. For each segmentation:
-    ///    - Convert to lexicon key
-    ///    - Look up candidates in lexicon
-    ///    - Apply penalty if segmentation used fuzzy matching
-    /// 4. Merge and rank candidates
-    /// 5. Cache the result
-    pub fn input(&self, input: &str) -> Vec<Candidate> {
-        // Check cache first (LRU automatically updates access time)
-        if let Some(cached) = self.cache.borrow_mut().get(&input.to_string()) {
-            *self.cache_hits.borrow_mut() += 1;
-            return cached.clone();
+    /// This is the top `predict_next` score turned back into a probability
+    /// and clamped, so a learned user bigram counts as fully confident and
+    /// no prediction at all as 0.0.
+    pub fn prediction_confidence(&self, context: &str) -> f32 {
+        self.predict_next(context, 1)
+            .first()
+            .map(|(_, score)| score.exp().clamp(0.0, 1.0))
+            .unwrap_or(0.0)
+    }
+
+    /// Learn the user's segmentation from a manually split input.
+    ///
+    /// If the input contains apostrophes (e.g. "xi'an"), the resulting
+    /// syllables are recorded for the unsplit input ("xian") so the next time
+    /// it is typed without separators the same split is preferred. Inputs
+    /// without apostrophes are ignored.
+    pub fn learn_segmentation(&self, input: &str) {
+        if !input.contains('\'') {
+            return;
         }
 
-        *self.cache_misses.borrow_mut() += 1;
+        let Some(seg) = self.segment_split_input(input) else {
+            return;
+        };
+        let syllables: Vec<String> = seg.iter().map(|s| s.text().to_string()).collect();
+        if syllables.len() < 2 {
+            return;
+        }
 
-        // Get top segmentations from parser (parser already applied fuzzy matching)
-        // Use an adaptive k computed from input length to balance
-        // recall vs CPU work. Parser internally uses dynamic beam width scaling
-        // (see parser.rs:840-842) so k has a non-linear effect on parser cost.
-        let input_len = input.len();
-        
-        // Hardcoded segmentation limits (previously in Config)
-        let short_k: usize = 4;
-        let long_k: usize = 8;
-        let max_k: usize = 12;
+        let _ = self.model.userdict.learn_segmentation(input, &syllables);
+        self.clear_cache();
+    }
 
-        // Heuristic: keep small inputs low, increase gradually for longer inputs,
-        // but clamp to a max. This mirrors upstream piecewise/proportional rules.
-        let k = if input_len <= 6 {
-            short_k
+    /// Segment input with apostrophes as hard boundaries.
+    ///
+    /// Each apostrophe-separated piece is segmented on its own, so no syllable
+    /// spans a separator. Returns `None` if any piece cannot be segmented.
+    fn segment_split_input(&self, input: &str) -> Option<Vec<P::Syllable>> {
+        let mut out = Vec::new();
+        for piece in input.split('\'').filter(|p| !p.is_empty()) {
+            let seg = self
+                .parser
+                .segment_top_k(piece, 1, false)
+                .into_iter()
+                .next()?;
+            out.extend(seg);
+        }
+
+        if out.is_empty() {
+            None
         } else {
-            // add one extra segmentation per ~4 extra chars beyond 6
-            let extra = (input_len.saturating_sub(6)) / 4;
-            let computed = long_k.saturating_add(extra);
-            std::cmp::min(computed, max_k)
+            Some(out)
+        }
+    }
+
+    /// Insert Chinese and Arabic numeral forms when the input spells a number.
+    ///
+    /// The numerals go right after the top lexicon candidate (or first, if
+    /// there are no other candidates), so "yibai" still shows the best phrase
+    /// first but 一百 / 100 are one keystroke away. Any existing candidate with
+    /// the same text is replaced.
+    fn insert_numeral_candidates(&self, input: &str, vec: &mut Vec<Candidate>) {
+        let Some((chinese, arabic)) = crate::numeral::numeral_forms(input) else {
+            return;
         };
 
-        let segs = self.parser.segment_top_k(input, k, true);
+        vec.retain(|c| c.text != chinese && c.text != arabic);
 
-        // Map from phrase -> best Candidate (keep highest score)
-        let mut best: HashMap<String, Candidate> = HashMap::new();
+        let pos = if vec.is_empty() { 0 } else { 1 };
+        let score = vec.first().map(|c| c.score).unwrap_or(0.0);
+        vec.insert(pos, Candidate::new(arabic, score));
+        vec.insert(pos, Candidate::new(chinese, score));
+    }
 
-        for seg in segs.into_iter() {
-            // For each segmentation, generate candidates by trying all possible word boundaries
-            // e.g., [ni,hao,wo,shi] can be: "你好"+"我是", "你"+"好"+"我是", etc.
-            let candidates = self.generate_candidates_from_segmentation(&seg);
+    /// Offer a particle-less variant right after each candidate ending in a
+    /// trailing particle, when the trimmed phrase is itself a lexicon entry.
+    ///
+    /// The variant takes the original's score; existing candidates with the
+    /// same text are left where they are.
+    fn insert_trimmed_particle_candidates(&self, vec: &mut Vec<Candidate>) {
+        let config = self.model.config.borrow();
+        let mut seen: std::collections::HashSet<String> =
+            vec.iter().map(|c| c.text.clone()).collect();
+        let mut out: Vec<Candidate> = Vec::with_capacity(vec.len());
+
+        for cand in vec.drain(..) {
+            let trimmed = match cand.text.strip_suffix(TRAILING_PARTICLES) {
+                Some(t) if !t.is_empty() => Some(t.to_string()),
+                _ => None,
+            };
+            let score = cand.score;
+            out.push(cand);
+
+            if let Some(trimmed) = trimmed {
+                if self.model.lexicon.contains_phrase(&trimmed)
+                    && !config.is_masked(&trimmed)
+                    && seen.insert(trimmed.clone())
+                {
+                    out.push(Candidate::new(trimmed, score));
+                }
+            }
+        }
 
-            // Merge candidates: keep the best score seen for this exact phrase
-            for cand in candidates.into_iter() {
-                match best.get(&cand.text) {
-                    Some(existing) if existing.score >= cand.score => {}
-                    _ => {
-                        best.insert(cand.text.clone(), cand.clone());
+        *vec = out;
+    }
+
+    /// Label candidates with the reading of the segmentation they top, and
+    /// append any segmentation's top candidate that did not make the list.
+    ///
+    /// Each text appears once; a text topping several segmentations keeps the
+    /// first (best-ranked) segmentation's reading. Appended tops take the
+    /// places of the lowest-ranked candidates that top no segmentation, so
+    /// the list stays within `limit`.
+    fn merge_segmentation_variants(
+        &self,
+        variants: Vec<Candidate>,
+        vec: &mut Vec<Candidate>,
+        limit: usize,
+    ) {
+        let config = self.model.config.borrow();
+        let mut tops: std::collections::HashSet<String> = std::collections::HashSet::new();
+        let mut missing: Vec<Candidate> = Vec::new();
+        for variant in variants {
+            if config.is_masked(&variant.text) || !tops.insert(variant.text.clone()) {
+                continue;
+            }
+            match vec.iter_mut().find(|c| c.text == variant.text) {
+                Some(existing) => {
+                    if existing.reading.is_none() {
+                        existing.reading = variant.reading;
                     }
                 }
+                None => missing.push(variant),
             }
         }
 
-        // Collect, sort and return top results
-        let mut vec: Vec<Candidate> = best.into_values().collect();
+        let mut excess = (vec.len() + missing.len()).saturating_sub(limit);
+        let mut index = vec.len();
+        while excess > 0 && index > 0 {
+            index -= 1;
+            if !tops.contains(&vec[index].text) {
+                vec.remove(index);
+                excess -= 1;
+            }
+        }
+        missing.truncate(missing.len() - excess);
+        vec.extend(missing);
+    }
+
+    /// Phrases for the leading syllables of `seg` (all but the last), longest
+    /// prefix first, each labeled with the prefix reading.
+    ///
+    /// Scored like full-key matches; masked phrases are skipped.
+    fn prefix_candidates(&self, seg: &[P::Syllable]) -> Vec<Candidate> {
+        let mut out: Vec<Candidate> = Vec::new();
+        for len in (1..seg.len()).rev() {
+            let key = seg[..len]
+                .iter()
+                .map(|s| s.text())
+                .collect::<Vec<_>>()
+                .join("'");
+            let mut group: Vec<Candidate> = Vec::new();
+            for phrase in self.model.lexicon.lookup(&key) {
+                if self.model.config.borrow().is_masked(&phrase)
+                    || out.iter().chain(group.iter()).any(|c| c.text == phrase)
+                {
+                    continue;
+                }
+                let score = self.full_key_score(&phrase);
+                group.push(Candidate::new(phrase, score).with_reading(key.clone()));
+            }
+            group.sort_by(|a, b| {
+                b.score
+                    .partial_cmp(&a.score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            out.extend(group);
+        }
+        out
+    }
+
+    /// Append prefix candidates after the whole-input ones.
+    ///
+    /// Texts already in the list are skipped. Scores are capped at the lowest
+    /// score before them so none outranks a whole-input candidate.
+    fn append_prefix_candidates(&self, prefixes: Vec<Candidate>, vec: &mut Vec<Candidate>) {
+        for mut cand in prefixes {
+            if vec.iter().any(|c| c.text == cand.text) {
+                continue;
+            }
+            if let Some(bottom) = bottom_score(vec) {
+                cand.score = cand.score.min(bottom);
+            }
+            vec.push(cand);
+        }
+    }
+
+    /// Add emoji for a keyword spelled by the input, after the text
+    /// candidates or, with `Config::emoji_priority`, ahead of them.
+    ///
+    /// Emoji keep the lexicon's frequency order; texts already listed and
+    /// masked emoji are skipped.
+    fn insert_emoji_candidates(&self, keyword: &str, vec: &mut Vec<Candidate>) {
+        let emoji = self.emoji.borrow();
+        let Some(lexicon) = emoji.as_ref() else {
+            return;
+        };
+        let mut entries = lexicon.lookup_with_freq(keyword);
+        if entries.is_empty() {
+            return;
+        }
+        entries.sort_by_key(|(_, freq)| std::cmp::Reverse(*freq));
 
-        // Filter out masked phrases
         let config = self.model.config.borrow();
-        if !config.masked_phrases.is_empty() {
-            vec.retain(|c| !config.is_masked(&c.text));
+        let mut symbols: Vec<String> = Vec::new();
+        for (symbol, _) in entries {
+            if !config.is_masked(&symbol)
+                && !symbols.contains(&symbol)
+                && !vec.iter().any(|c| c.text == symbol)
+            {
+                symbols.push(symbol);
+            }
         }
+        let priority = config.emoji_priority;
         drop(config);
 
-        // Sort by score (higher is better)
-        vec.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        if priority {
+            let top = top_score(vec).unwrap_or(0.0);
+            let count = symbols.len();
+            for (i, symbol) in symbols.into_iter().enumerate() {
+                let score = top + (count - i) as f32;
+                let cand = Candidate::new(symbol, score).with_source(CandidateSource::Emoji);
+                vec.insert(i, cand);
+            }
+        } else {
+            let bottom = bottom_score(vec).unwrap_or(0.0);
+            for (i, symbol) in symbols.into_iter().enumerate() {
+                let score = bottom - (i + 1) as f32;
+                vec.push(Candidate::new(symbol, score).with_source(CandidateSource::Emoji));
+            }
+        }
+    }
 
-        if vec.len() > self.limit {
-            vec.truncate(self.limit);
+    /// Put phrases for an exact quick code match ahead of everything else.
+    ///
+    /// Each phrase gets a score above the current top candidate (keeping the
+    /// table's order), and any phonetic candidate with the same text is dropped.
+    fn insert_quick_code_candidates(&self, input: &str, vec: &mut Vec<Candidate>) {
+        let phrases = self.model.quick_code_phrases(input);
+        if phrases.is_empty() {
+            return;
         }
 
-        // Cache the result (LRU automatically handles eviction)
-        self.cache.borrow_mut().put(input.to_string(), vec.clone());
+        let config = self.model.config.borrow();
+        let phrases: Vec<&String> = phrases.iter().filter(|p| !config.is_masked(p)).collect();
+        drop(config);
 
-        vec
+        vec.retain(|c| !phrases.contains(&&c.text));
+
+        let top = top_score(vec).unwrap_or(0.0);
+        let count = phrases.len();
+        for (i, phrase) in phrases.into_iter().enumerate() {
+            let score = top + (count - i) as f32;
+            vec.insert(i, Candidate::new(phrase.clone(), score));
+        }
     }
 
-    /// Commit a phrase to user learning.
+    /// Boost lexicon phrases whose reading is the whole input.
     ///
-    /// Records user selection to boost future rankings.
-    /// Clears cache to reflect updated frequencies immediately.
-    pub fn commit(&self, phrase: &str) {
-        // Learn the phrase in the user dictionary (increments frequency by 1)
-        self.model.userdict.learn(phrase);
+    /// Only multi-syllable exact segmentations qualify: the syllables, joined,
+    /// must spell `normalized_input` and none may be fuzzy. Phrases stored
+    /// under that full key get `ln(boost)` added to their score.
+    fn apply_whole_input_boost(
+        &self,
+        seg: &[P::Syllable],
+        normalized_input: &str,
+        boost: f32,
+        candidates: &mut [Candidate],
+    ) {
+        if seg.len() < 2 || seg.iter().any(|s| s.is_fuzzy()) {
+            return;
+        }
+        let reading: String = seg.iter().map(|s| s.text()).collect();
+        if reading != normalized_input {
+            return;
+        }
 
-        // Clear cache so updated frequencies are reflected immediately
-        self.clear_cache();
+        let full_key = seg.iter().map(|s| s.text()).collect::<Vec<_>>().join("'");
+        let phrases = self.model.lexicon.lookup(&full_key);
+        for cand in candidates.iter_mut() {
+            if phrases.contains(&cand.text) {
+                cand.score += boost.ln();
+            }
+        }
+    }
+
+    /// Learned phrases stored under a lexicon key that extends one of `segs`.
+    ///
+    /// For "beijingda" segmented as bei'jing'da, a user-learned 北京大学 under
+    /// "bei'jing'da'xue" qualifies. Each is scored like a full-key match (see
+    /// `full_key_score`) plus `boost`.
+    fn learned_completions(&self, segs: &[Vec<P::Syllable>], boost: f32) -> Vec<Candidate> {
+        let mut seen: std::collections::HashSet<String> = std::collections::HashSet::new();
+        let mut completions = Vec::new();
+        for seg in segs {
+            let prefix = seg.iter().map(|s| s.text()).collect::<Vec<_>>().join("'");
+            for key in self
+                .model
+                .lexicon
+                .key_completions(&prefix, LEARNED_COMPLETION_KEYS)
+            {
+                for phrase in self.model.lexicon.lookup(&key) {
+                    if self.user_frequency(&phrase) <= 0.0 || !seen.insert(phrase.clone()) {
+                        continue;
+                    }
+
+                    let score = self.full_key_score(&phrase) + boost;
+                    completions.push(
+                        Candidate::new(phrase, score)
+                            .with_source(CandidateSource::UserDict)
+                            .with_reading(key.clone()),
+                    );
+                }
+            }
+        }
+        completions
+    }
+
+    /// Learned frequency of a phrase for the user-dictionary boost,
+    /// recency-weighted per `Config::recency_frequency_alpha`.
+    fn user_frequency(&self, phrase: &str) -> f32 {
+        let alpha = self.model.config.borrow().recency_frequency_alpha;
+        let now = crate::userdict::unix_now();
+        self.model
+            .userdict
+            .recency_weighted_frequency(phrase, alpha, now) as f32
+    }
+
+    /// Score of a phrase matching a whole lexicon key on its own.
+    ///
+    /// Same word-level scoring as DP paths, with no context (start of
+    /// sentence): pure unigram, one sentence-length penalty, the user
+    /// dictionary boost and `full_key_boost`.
+    fn full_key_score(&self, phrase: &str) -> f32 {
+        let config = self.model.config.borrow();
+
+        // Get unigram probability from word_bigram model (from interpolation2.text)
+        let unigram_prob = self.model.word_bigram.get_unigram_probability(phrase);
+
+        let lambda = config.lambda;
+        let sentence_length_penalty = config.sentence_length_penalty;
+        let unigram_factor = config.unigram_factor;
+        let full_key_boost = config.full_key_boost;
+        drop(config);
+
+        // Use pure unigram: log(P(w) * unigram_lambda)
+        let safe_prob = (unigram_prob * (1.0 - lambda)).max(1e-10);
+        let mut score = safe_prob.ln();
+
+        // Apply sentence length penalty (one word)
+        score -= sentence_length_penalty;
+
+        // Userdict boost
+        let user_freq = self.user_frequency(phrase);
+        if user_freq > 0.0 {
+            score += unigram_factor * (1.0 + user_freq).ln();
+        }
+
+        // Apply full-key boost to prefer exact dictionary matches
+        score + full_key_boost
+    }
+
+    /// Lexicon entries for a run of syllables.
+    ///
+    /// Syllables typed with tones look up tone-tagged keys first (see
+    /// `lookup_toned_syllables`). Then the exact key is looked up. If it
+    /// has no entries, the keys spelled with each syllable's
+    /// `SyllableType::fallback_texts` are tried instead, unless there are
+    /// more than `MAX_FALLBACK_KEYS` of them.
+    fn lookup_syllables(&self, syllables: &[P::Syllable]) -> Vec<(String, u32)> {
+        let toned = self.lookup_toned_syllables(syllables);
+        if !toned.is_empty() {
+            return toned;
+        }
+
+        let key = syllables
+            .iter()
+            .map(|s| s.text())
+            .collect::<Vec<&str>>()
+            .join("'");
+        let entries = self.model.lexicon.lookup_with_freq(&key);
+        if !entries.is_empty() {
+            return entries;
+        }
+
+        let spellings: Vec<Vec<String>> = syllables.iter().map(|s| s.fallback_texts()).collect();
+        if spellings.iter().all(|alts| alts.is_empty()) {
+            return entries;
+        }
+        let spellings: Vec<Vec<String>> = syllables
+            .iter()
+            .zip(spellings)
+            .map(|(s, mut alts)| {
+                alts.insert(0, s.text().to_string());
+                alts
+            })
+            .collect();
+        self.lookup_spellings(&spellings)
+    }
+
+    /// Entries for syllables typed with tones, under tone-tagged keys
+    /// ("ni3'hao3") whose tones the typed ones accept (see
+    /// `tone::tone_matches` and `Config::neutral_tone_matches_any`); a
+    /// syllable typed without a tone takes any tone.
+    ///
+    /// Empty if no syllable has a tone or the lexicon has no tone-tagged
+    /// key for them, in which case the toneless key is used.
+    fn lookup_toned_syllables(&self, syllables: &[P::Syllable]) -> Vec<(String, u32)> {
+        if syllables.iter().all(|s| s.tone().is_none()) {
+            return Vec::new();
+        }
+
+        let neutral_any = self.model.config.borrow().neutral_tone_matches_any;
+        let spellings: Vec<Vec<String>> = syllables
+            .iter()
+            .map(|s| {
+                let mut alts: Vec<String> = (1..=crate::tone::NEUTRAL_TONE)
+                    .filter(|&t| crate::tone::tone_matches(s.tone(), Some(t), neutral_any))
+                    .map(|t| format!("{}{}", s.text(), t))
+                    .collect();
+                if s.tone().is_none() {
+                    alts.insert(0, s.text().to_string());
+                }
+                alts
+            })
+            .collect();
+        self.lookup_spellings(&spellings)
+    }
+
+    /// Entries under every key spelled with one alternative per syllable,
+    /// or none if there are more than `MAX_FALLBACK_KEYS` such keys. A
+    /// phrase found under several keys keeps its highest frequency.
+    fn lookup_spellings(&self, spellings: &[Vec<String>]) -> Vec<(String, u32)> {
+        let combinations = spellings
+            .iter()
+            .try_fold(1usize, |total, alts| total.checked_mul(alts.len()));
+        if combinations.is_none_or(|total| total > MAX_FALLBACK_KEYS) {
+            return Vec::new();
+        }
+
+        let mut keys: Vec<String> = vec![String::new()];
+        for alts in spellings {
+            let mut extended = Vec::with_capacity(keys.len() * alts.len());
+            for prefix in &keys {
+                let sep = if prefix.is_empty() { "" } else { "'" };
+                for alt in alts {
+                    extended.push(format!("{}{}{}", prefix, sep, alt));
+                }
+            }
+            keys = extended;
+        }
+
+        let key_refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let found = self.model.lexicon.has_keys_batch(&key_refs);
+        let mut merged: Vec<(String, u32)> = Vec::new();
+        for (key, _) in key_refs.iter().zip(found).filter(|(_, hit)| *hit) {
+            for (phrase, freq) in self.model.lexicon.lookup_with_freq(key) {
+                match merged.iter_mut().find(|(p, _)| *p == phrase) {
+                    Some(existing) => existing.1 = existing.1.max(freq),
+                    None => merged.push((phrase, freq)),
+                }
+            }
+        }
+        merged
     }
 
     /// Generate candidates from a segmentation by trying all possible word combinations.
@@ -171,44 +1943,17 @@ impl<P: SyllableParser> Engine<P> {
         let mut results: Vec<Candidate> = Vec::new();
 
         // First: try the FULL segmentation as a single lexicon key (supports long dictionary entries)
-        let full_key = seg
-            .iter()
-            .map(|s| s.text())
-            .collect::<Vec<&str>>()
-            .join("'");
-        let full_entries = self.model.lexicon.lookup_with_freq(&full_key);
+        let full_entries = self.lookup_syllables(seg);
         if !full_entries.is_empty() {
             // Score full-key matches using the same word-level unigram/bigram scoring as DP paths
             for (phrase, _) in full_entries.into_iter() {
-                let config = self.model.config.borrow();
-
-                // Get unigram probability from word_bigram model (from interpolation2.text)
-                let unigram_prob = self.model.word_bigram.get_unigram_probability(&phrase);
-
-                let lambda = config.lambda;
-                let sentence_length_penalty = config.sentence_length_penalty;
-                let unigram_factor = config.unigram_factor;
-                let full_key_boost = config.full_key_boost;
-                drop(config);
-
-                // For full-key matches, we have no context (start of sentence)
-                // Use pure unigram: log(P(w) * unigram_lambda)
-                let safe_prob = (unigram_prob * (1.0 - lambda)).max(1e-10);
-                let mut score = safe_prob.ln();
-
-                // Apply sentence length penalty (one word)
-                score -= sentence_length_penalty;
-
-                // Userdict boost
-                let user_freq = self.model.userdict.frequency(&phrase);
-                if user_freq > 0 {
-                    score += unigram_factor * (1.0 + (user_freq as f32)).ln();
-                }
-
-                // Apply full-key boost to prefer exact dictionary matches
-                score += full_key_boost;
-
-                results.push(Candidate::new(phrase, score));
+                let score = self.full_key_score(&phrase);
+                let source = if self.user_frequency(&phrase) > 0.0 {
+                    CandidateSource::UserDict
+                } else {
+                    CandidateSource::Lexicon
+                };
+                results.push(Candidate::new(phrase, score).with_source(source));
             }
             // If a full dictionary match exists, include it but continue to also try composed variants
         }
@@ -234,15 +1979,8 @@ impl<P: SyllableParser> Engine<P> {
 
             // First, cheap composition for common shorter words
             for len in 1..=std::cmp::min(MAX_SHORT_SYLLABLES, n - i) {
-                // Build lexicon key for syllables [i..i+len)
-                let word_key: String = seg[i..i + len]
-                    .iter()
-                    .map(|s| s.text())
-                    .collect::<Vec<&str>>()
-                    .join("'");
-
-                // Look up this word in lexicon with frequencies
-                let candidates = self.model.lexicon.lookup_with_freq(&word_key);
+                // Look up the word for syllables [i..i+len) with frequencies
+                let candidates = self.lookup_syllables(&seg[i..i + len]);
 
                 for (word_text, _) in candidates {
                     // Use word-level unigram/bigram scoring (matching upstream libpinyin)
@@ -287,9 +2025,9 @@ impl<P: SyllableParser> Engine<P> {
 
                     // Userdict boost: upstream modifies lexicon frequencies directly with unigram_factor
                     // We use a separate userdict, so multiply by unigram_factor to match upstream effect
-                    let user_freq = self.model.userdict.frequency(&word_text);
-                    if user_freq > 0 {
-                        let boost = unigram_factor * (1.0 + (user_freq as f32)).ln();
+                    let user_freq = self.user_frequency(&word_text);
+                    if user_freq > 0.0 {
+                        let boost = unigram_factor * (1.0 + user_freq).ln();
                         word_score += boost;
                     }
 
@@ -365,9 +2103,9 @@ impl<P: SyllableParser> Engine<P> {
                     word_score -= sentence_length_penalty;
 
                     // Userdict boost: use unigram_factor from config to match upstream
-                    let user_freq = self.model.userdict.frequency(&word_text);
-                    if user_freq > 0 {
-                        word_score += unigram_factor * (1.0 + (user_freq as f32)).ln();
+                    let user_freq = self.user_frequency(&word_text);
+                    if user_freq > 0.0 {
+                        word_score += unigram_factor * (1.0 + user_freq).ln();
                     }
 
                     let mut new_path = current_path.clone();
@@ -391,8 +2129,24 @@ impl<P: SyllableParser> Engine<P> {
         // Extract candidates from the best path that reaches the end and include them
         if let Some(final_path) = &best_path[n] {
             let full_text: String = final_path.iter().map(|(t, _)| t.as_str()).collect();
-            let total_score: f32 = final_path.iter().map(|(_, s)| s).sum();
-            results.push(Candidate::new(full_text, total_score));
+            let mut total_score: f32 = final_path.iter().map(|(_, s)| s).sum();
+            let mut source = if final_path.len() > 1 {
+                CandidateSource::Composed
+            } else {
+                CandidateSource::Lexicon
+            };
+
+            // A composition the user learned as one phrase (see
+            // `ImeEngine::commit_as_phrase`) gets the userdict boost as a whole
+            if final_path.len() > 1 {
+                let user_freq = self.user_frequency(&full_text);
+                if user_freq > 0.0 {
+                    let unigram_factor = self.model.config.borrow().unigram_factor;
+                    total_score += unigram_factor * (1.0 + user_freq).ln();
+                    source = CandidateSource::UserDict;
+                }
+            }
+            results.push(Candidate::new(full_text, total_score).with_source(source));
         }
 
         results
@@ -430,6 +2184,21 @@ impl<P: SyllableParser> Engine<P> {
         self.cache.borrow().cap().get()
     }
 
+    /// Bring the cache capacity in line with `Config::max_cache_size`.
+    ///
+    /// Returns false if caching is disabled (size 0).
+    fn sync_cache_capacity(&self) -> bool {
+        let size = self.model.config.borrow().max_cache_size;
+        let Some(size) = std::num::NonZeroUsize::new(size) else {
+            return false;
+        };
+        let mut cache = self.cache.borrow_mut();
+        if cache.cap() != size {
+            cache.resize(size);
+        }
+        true
+    }
+
     /// Clear the cache (useful for testing or memory management).
     pub fn clear_cache(&self) {
         self.cache.borrow_mut().clear();
@@ -458,7 +2227,37 @@ impl<P: SyllableParser> Engine<P> {
     }
 
     /// Get mutable reference to the configuration.
+    ///
+    /// Drops the cached candidate lists, since most settings change what
+    /// `input` returns; the hit/miss statistics are kept.
     pub fn config_mut(&self) -> std::cell::RefMut<'_, crate::Config> {
+        self.cache.borrow_mut().clear();
         self.model.config.borrow_mut()
     }
+
+    /// Dump the effective configuration as TOML for support snapshots.
+    ///
+    /// The output starts with every `Config` field (defaults filled in), so
+    /// it loads back with `Config::from_toml_str`. It is followed by a
+    /// `[parser]` table with the active fuzzy rules and syllable count, and
+    /// a `[dataset]` table with the sizes of the loaded data.
+    pub fn dump_effective_config(&self) -> Result<String, toml::ser::Error> {
+        let mut out = self.config().to_toml_string()?;
+
+        let snapshot = EngineSnapshot {
+            parser: ParserSnapshot {
+                syllable_count: self.parser.syllable_count(),
+                fuzzy_rules: self.parser.fuzzy_rules(),
+            },
+            dataset: DatasetSnapshot {
+                lexicon_keys: self.model.lexicon.key_count(),
+                word_bigram_words: self.model.word_bigram.len(),
+                word_bigram_pairs: self.model.word_bigram.total_bigrams(),
+                quick_codes: self.model.quick_codes.len(),
+            },
+        };
+        out.push('\n');
+        out.push_str(&toml::to_string_pretty(&snapshot)?);
+        Ok(out)
+    }
 }
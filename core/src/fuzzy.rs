@@ -10,6 +10,9 @@ pub struct FuzzyRule {
     pub to: String,
     /// Penalty for using this fuzzy match (higher = more penalty)
     pub penalty: f32,
+    /// Whether `to` may also stand in for `from` (`a=b`), or only `from`
+    /// for `to` (`a>b`)
+    pub bidirectional: bool,
 }
 
 impl FuzzyRule {
@@ -18,8 +21,53 @@ impl FuzzyRule {
             from: from.to_string(),
             to: to.to_string(),
             penalty,
+            bidirectional: true,
         }
     }
+
+    /// Parse a rule string: `"a=b"` (both directions) or `"a>b"` (typed
+    /// `a` may be read as `b`, not the reverse), each with an optional
+    /// `":penalty"`.
+    ///
+    /// A missing or unparsable penalty becomes `default_penalty`. Returns
+    /// `None` if either side is empty or there is no `=` / `>`.
+    ///
+    /// # Example
+    /// ```
+    /// use libchinese_core::fuzzy::FuzzyRule;
+    ///
+    /// let rule = FuzzyRule::parse("s>sh:0.5", 1.0).unwrap();
+    /// assert_eq!((rule.from.as_str(), rule.to.as_str()), ("s", "sh"));
+    /// assert_eq!(rule.penalty, 0.5);
+    /// assert!(!rule.bidirectional);
+    /// ```
+    pub fn parse(rule: &str, default_penalty: f32) -> Option<Self> {
+        // Parse formats: "a=b", "a>b", optionally followed by ":penalty"
+        let (pair, penalty) = match rule.split_once(':') {
+            Some((pair, penalty_str)) => (
+                pair,
+                penalty_str.trim().parse::<f32>().unwrap_or(default_penalty),
+            ),
+            None => (rule, default_penalty),
+        };
+        let (a, b, bidirectional) = match pair.split_once('=') {
+            Some((a, b)) => (a, b, true),
+            None => {
+                let (a, b) = pair.split_once('>')?;
+                (a, b, false)
+            }
+        };
+        let (a, b) = (a.trim(), b.trim());
+        if a.is_empty() || b.is_empty() {
+            return None;
+        }
+        Some(Self {
+            from: a.to_string(),
+            to: b.to_string(),
+            penalty,
+            bidirectional,
+        })
+    }
 }
 
 /// Represents fuzzy alternatives for phonetic units (syllables).
@@ -46,8 +94,8 @@ impl FuzzyMap {
     /// Build a `FuzzyMap` from a list of fuzzy rule strings.
     ///
     /// The rules are expected to be textual fuzzy pairs like `"zh=z"` or
-    /// `"zh=z:1.5"` (with optional penalty).
-    /// Pairs are inserted bidirectionally by default.
+    /// `"zh=z:1.5"` (with optional penalty), inserted bidirectionally, or
+    /// directional rules like `"s>sh"` (see `FuzzyRule::parse`).
     pub fn from_rules(rules: &[String]) -> Self {
         let mut fm = FuzzyMap {
             map: HashMap::new(),
@@ -55,28 +103,7 @@ impl FuzzyMap {
         };
 
         for pair in rules.iter() {
-            // Parse formats: "a=b" or "a=b:penalty"
-            if let Some((rule, penalty_str)) = pair.split_once(':') {
-                // Has explicit penalty
-                let penalty = penalty_str
-                    .trim()
-                    .parse::<f32>()
-                    .unwrap_or(fm.default_penalty);
-                if let Some((a, b)) = rule.split_once('=') {
-                    let a = a.trim().to_string();
-                    let b = b.trim().to_string();
-                    if !a.is_empty() && !b.is_empty() {
-                        fm.add_rule(&a, &b, penalty);
-                    }
-                }
-            } else if let Some((a, b)) = pair.split_once('=') {
-                // No explicit penalty, use default
-                let a = a.trim().to_string();
-                let b = b.trim().to_string();
-                if !a.is_empty() && !b.is_empty() {
-                    fm.add_rule(&a, &b, fm.default_penalty);
-                }
-            } else {
+            if !fm.add_rule_str(pair) {
                 // Single token - just ensure it has an entry
                 let token = pair.trim().to_string();
                 if !token.is_empty() {
@@ -88,6 +115,50 @@ impl FuzzyMap {
         fm
     }
 
+    /// Add one rule written as in `from_rules` (`"zh=z"`, `"zh=z:1.5"` or
+    /// the directional `"s>sh"`).
+    ///
+    /// Returns false, adding nothing, if `rule` does not parse (see
+    /// `FuzzyRule::parse`).
+    pub fn add_rule_str(&mut self, rule: &str) -> bool {
+        let Some(rule) = FuzzyRule::parse(rule, self.default_penalty) else {
+            return false;
+        };
+        if rule.bidirectional {
+            self.add_rule(&rule.from, &rule.to, rule.penalty);
+        } else {
+            self.add_rule_unidirectional(&rule.from, &rule.to, rule.penalty);
+        }
+        true
+    }
+
+    /// Remove the fuzzy pair between `lhs` and `rhs`, in both directions.
+    ///
+    /// Returns true if either direction was present.
+    pub fn remove_rule(&mut self, lhs: &str, rhs: &str) -> bool {
+        let forward = self.remove_direction(lhs, rhs);
+        let backward = self.remove_direction(rhs, lhs);
+        forward || backward
+    }
+
+    /// Remove every rule.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn remove_direction(&mut self, from: &str, to: &str) -> bool {
+        let Some(alts) = self.map.get_mut(from) else {
+            return false;
+        };
+        let before = alts.len();
+        alts.retain(|(alt, _)| alt != to);
+        let removed = alts.len() != before;
+        if alts.is_empty() {
+            self.map.remove(from);
+        }
+        removed
+    }
+
     /// Add a fuzzy rule (bidirectional by default).
     pub fn add_rule(&mut self, from: &str, to: &str, penalty: f32) {
         let from = from.to_string();
@@ -109,6 +180,30 @@ impl FuzzyMap {
         self.map.entry(from).or_default().push((to, penalty));
     }
 
+    /// Active rules in `from_rules` syntax ("a=b:penalty"), sorted.
+    ///
+    /// Bidirectional pairs are listed once; unidirectional rules are listed
+    /// in their own direction as "a>b:penalty".
+    pub fn rules(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        for (from, alts) in &self.map {
+            for (to, penalty) in alts {
+                let reverse = self
+                    .map
+                    .get(to)
+                    .is_some_and(|back| back.iter().any(|(s, _)| s == from));
+                if reverse && from > to {
+                    continue;
+                }
+                let op = if reverse { '=' } else { '>' };
+                out.push(format!("{}{}{}:{:?}", from, op, to, penalty));
+            }
+        }
+        out.sort();
+        out.dedup();
+        out
+    }
+
     /// Return alternatives for a syllable including the syllable itself.
     ///
     /// Returns a vector of (alternative, penalty) pairs.
@@ -131,6 +226,22 @@ impl FuzzyMap {
         out
     }
 
+    /// Like `alternatives`, but keep at most `max` alternatives besides the
+    /// syllable itself, preferring the lowest penalties. `max == 0` means no
+    /// limit.
+    ///
+    /// Alternatives with equal penalty keep their rule order.
+    pub fn alternatives_limited(&self, syllable: &str, max: usize) -> Vec<(String, f32)> {
+        let mut out = self.alternatives(syllable);
+        if max == 0 || out.len() <= max + 1 {
+            return out;
+        }
+        // The original stays first; only the alternatives compete
+        out[1..].sort_by(|a, b| a.1.total_cmp(&b.1));
+        out.truncate(max + 1);
+        out
+    }
+
     /// Convenience: apply fuzzy expansion to a sequence of syllables,
     /// producing a list of sequences with per-syllable alternatives and total penalties.
     ///
@@ -175,3 +286,51 @@ impl FuzzyMap {
         results
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(list: &[&str]) -> Vec<String> {
+        list.iter().map(|r| r.to_string()).collect()
+    }
+
+    fn has(map: &FuzzyMap, from: &str, to: &str) -> bool {
+        map.alternatives(from).iter().any(|(alt, _)| alt == to)
+    }
+
+    #[test]
+    fn rule_strings_parse_with_direction() {
+        let rule = FuzzyRule::parse("zh=z:1.5", 1.0).unwrap();
+        assert_eq!((rule.from.as_str(), rule.to.as_str()), ("zh", "z"));
+        assert_eq!(rule.penalty, 1.5);
+        assert!(rule.bidirectional);
+
+        let rule = FuzzyRule::parse(" s > sh ", 1.0).unwrap();
+        assert_eq!((rule.from.as_str(), rule.to.as_str()), ("s", "sh"));
+        assert_eq!(rule.penalty, 1.0);
+        assert!(!rule.bidirectional);
+
+        let rule = FuzzyRule::parse("s>sh:oops", 2.0).unwrap();
+        assert_eq!(rule.penalty, 2.0);
+
+        for bad in ["s", "s>", "=sh", ">:1.0", ""] {
+            assert!(FuzzyRule::parse(bad, 1.0).is_none(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn directional_rule_only_substitutes_one_way() {
+        let map = FuzzyMap::from_rules(&rules(&["s>sh:0.5", "l=n"]));
+        assert!(has(&map, "s", "sh"));
+        assert!(!has(&map, "sh", "s"));
+        assert!(has(&map, "l", "n"));
+        assert!(has(&map, "n", "l"));
+        assert_eq!(map.rules(), vec!["l=n:1.0", "s>sh:0.5"]);
+
+        // Listed rules rebuild the same map
+        let rebuilt = FuzzyMap::from_rules(&map.rules());
+        assert_eq!(rebuilt.rules(), map.rules());
+        assert!(!has(&rebuilt, "sh", "s"));
+    }
+}
@@ -7,12 +7,14 @@
 
 use super::context::ImeContext;
 use super::editor::{Editor, EditorResult, PhoneticEditor, PunctuationEditor, SuggestionEditor};
-use super::session::{ImeSession, InputMode};
+use super::keybindings::KeyBindings;
+use super::session::{ImeSession, InputMode, SessionStats};
 use crate::engine::{Engine, SyllableParser};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Key event types that the IME can process.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum KeyEvent {
     /// Character input (a-z, punctuation, etc.)
     Char(char),
@@ -55,6 +57,16 @@ pub enum KeyResult {
     NotHandled,
 }
 
+/// Whether a character starts or extends phonetic input:
+/// - ASCII lowercase (pinyin: a-z)
+/// - Bopomofo/Zhuyin characters (U+3105-U+3129)
+/// - Zhuyin tone marks (ˊˇˋ˙)
+fn is_phonetic_char(ch: char) -> bool {
+    ch.is_ascii_lowercase()
+        || ('\u{3105}'..='\u{3129}').contains(&ch)
+        || matches!(ch, 'ˊ' | 'ˇ' | 'ˋ' | '˙')
+}
+
 /// IME engine with session management.
 ///
 /// This struct combines the backend Engine with a session that tracks
@@ -75,6 +87,23 @@ pub struct ImeEngine<P: SyllableParser> {
 
     /// Context for platform communication
     context: ImeContext,
+
+    /// Key bindings for semantic actions (select, commit, ...)
+    key_bindings: KeyBindings,
+
+    /// `Config::punctuation_overrides` last loaded into `punct_editor`
+    punctuation_overrides: HashMap<String, Vec<String>>,
+
+    /// Phrases committed one after another for the current or last input
+    /// (see `commit_as_phrase`)
+    phrase_parts: Vec<String>,
+
+    /// Whether the input `phrase_parts` belongs to is finished
+    phrase_complete: bool,
+
+    /// Whether the last key moved through the candidate list, so that
+    /// selection keys which are also phonetic input select
+    browsing_candidates: bool,
 }
 
 impl<P: SyllableParser> ImeEngine<P> {
@@ -85,8 +114,13 @@ impl<P: SyllableParser> ImeEngine<P> {
             phonetic_editor: PhoneticEditor::new(backend_arc.clone()),
             punct_editor: PunctuationEditor::new(),
             suggestion_editor: SuggestionEditor::new(backend_arc),
-            session: ImeSession::with_page_size(5),
+            session: ImeSession::new(),
             context: ImeContext::new(),
+            key_bindings: KeyBindings::default(),
+            punctuation_overrides: HashMap::new(),
+            phrase_parts: Vec::new(),
+            phrase_complete: false,
+            browsing_candidates: false,
         }
     }
 
@@ -98,8 +132,13 @@ impl<P: SyllableParser> ImeEngine<P> {
             phonetic_editor: PhoneticEditor::new(backend.clone()),
             punct_editor: PunctuationEditor::new(),
             suggestion_editor: SuggestionEditor::new(backend),
-            session: ImeSession::with_page_size(5),
+            session: ImeSession::new(),
             context: ImeContext::new(),
+            key_bindings: KeyBindings::default(),
+            punctuation_overrides: HashMap::new(),
+            phrase_parts: Vec::new(),
+            phrase_complete: false,
+            browsing_candidates: false,
         }
     }
 
@@ -117,6 +156,23 @@ impl<P: SyllableParser> ImeEngine<P> {
         engine
     }
 
+    /// Change the candidate page size mid-session (0 is treated as 1).
+    ///
+    /// The selected candidate stays selected, on whichever page now holds
+    /// it, and the context is refreshed for an active composition.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.session.candidates_mut().set_page_size(page_size);
+        if self.session.is_active() {
+            self.session.sync_to_context(&mut self.context);
+            self.update_auxiliary_text();
+        }
+    }
+
+    /// Current candidate page size.
+    pub fn page_size(&self) -> usize {
+        self.session.candidates().page_size()
+    }
+
     /// Get a reference to the context for reading IME state.
     pub fn context(&self) -> &ImeContext {
         &self.context
@@ -132,8 +188,118 @@ impl<P: SyllableParser> ImeEngine<P> {
         &self.session
     }
 
-    /// Reset the IME to initial state.
+    /// Get the current key bindings.
+    pub fn key_bindings(&self) -> &KeyBindings {
+        &self.key_bindings
+    }
+
+    /// Get a mutable reference to the key bindings for rebinding actions.
+    pub fn key_bindings_mut(&mut self) -> &mut KeyBindings {
+        &mut self.key_bindings
+    }
+
+    /// Replace the key bindings.
+    pub fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.key_bindings = bindings;
+    }
+
+    /// Preview candidates for a hypothetical input.
+    ///
+    /// Runs conversion against the backend without touching the session or
+    /// context, so it is safe to call mid-composition (e.g. for a hover
+    /// preview in the UI).
+    pub fn preview_candidates(&self, input: &str) -> Vec<crate::Candidate> {
+        self.phonetic_editor.backend().input(input)
+    }
+
+    /// Reset the IME to initial state and clear the session statistics.
     pub fn reset(&mut self) {
+        self.clear_state();
+        self.session.reset_stats();
+        self.phrase_parts.clear();
+    }
+
+    /// Learn the phrases committed one after another for the last input as
+    /// one user phrase ("北" then "京" for "beijing" → "北京").
+    ///
+    /// Works on the input being composed or, once it is finished, on the
+    /// last one until new input starts. Returns the learned phrase, or
+    /// `None` if fewer than two phrases were committed, or if punctuation,
+    /// raw text or a mode switch came in between. See also
+    /// `Config::learn_partial_commits_as_phrase`.
+    pub fn commit_as_phrase(&mut self) -> Option<String> {
+        if self.phrase_parts.len() < 2 {
+            return None;
+        }
+        let phrase = self.phrase_parts.concat();
+        self.phrase_parts.clear();
+        self.phonetic_editor.backend().commit(&phrase);
+        Some(phrase)
+    }
+
+    /// Record what an editor committed for `commit_as_phrase`.
+    ///
+    /// Only Han text committed from phonetic input counts; anything else
+    /// committed (or vetoed), punctuation and mode switches end the run.
+    fn track_phrase_parts(&mut self, result: &EditorResult, vetoed: bool) {
+        let mode = self.session.mode();
+        let phonetic = mode == InputMode::Phonetic && !vetoed;
+        let (text, finished) = match result {
+            EditorResult::CommitPartial(text) => (text, false),
+            EditorResult::Commit(text) | EditorResult::CommitAndReset(text) => (text, true),
+            EditorResult::ModeSwitch(_) => {
+                self.phrase_parts.clear();
+                return;
+            }
+            _ => {
+                if phonetic {
+                    // Typing after a finished input starts a new one
+                    if self.phrase_complete {
+                        self.phrase_parts.clear();
+                        self.phrase_complete = false;
+                    }
+                } else if mode != InputMode::Suggestion {
+                    self.phrase_parts.clear();
+                }
+                return;
+            }
+        };
+
+        if !phonetic || text.is_empty() || !text.chars().all(crate::char_freq::is_han_char) {
+            self.phrase_parts.clear();
+            return;
+        }
+        if self.phrase_complete {
+            self.phrase_parts.clear();
+        }
+        self.phrase_parts.push(text.clone());
+        self.phrase_complete = finished;
+
+        let learn = self
+            .phonetic_editor
+            .backend()
+            .config()
+            .learn_partial_commits_as_phrase;
+        if finished && learn {
+            self.commit_as_phrase();
+        }
+    }
+
+    /// Forget what adapted during this session, keeping long-term learning.
+    ///
+    /// Resets like `reset` and also forgets the last committed character,
+    /// any pending suggestions and the backend's candidate cache. The user
+    /// dictionary and config are left alone, so phrases learned so far keep
+    /// their boost.
+    pub fn reset_session_adaptation(&mut self) {
+        self.reset();
+        self.session.set_last_committed_char(None);
+        self.suggestion_editor.reset();
+        self.phonetic_editor.backend().clear_cache();
+    }
+
+    /// Clear composition state after a commit or mode toggle.
+    fn clear_state(&mut self) {
         self.session.clear();
         self.context.clear();
         self.phonetic_editor.reset();
@@ -161,12 +327,40 @@ impl<P: SyllableParser> ImeEngine<P> {
 
         // Check if text meets minimum length requirement
         let char_count = committed_text.chars().count();
-        let should_activate = char_count >= config.min_suggestion_trigger_length;
+        let long_enough = char_count >= config.min_suggestion_trigger_length;
+        // A single Han character may qualify too, but never punctuation
+        let single_char = config.single_char_association
+            && char_count == 1
+            && committed_text.chars().all(crate::char_freq::is_han_char);
+        let min_confidence = config.min_suggestion_confidence;
 
         // Drop config borrow before mutating self
         drop(config);
 
-        if !should_activate {
+        if !long_enough && !single_char {
+            return;
+        }
+
+        // A single character without predictions would only get the
+        // fallback particles
+        if !long_enough
+            && self
+                .phonetic_editor
+                .backend()
+                .predict_next(committed_text, 1)
+                .is_empty()
+        {
+            return;
+        }
+
+        // Don't pop up weak predictions
+        if min_confidence > 0.0
+            && self
+                .phonetic_editor
+                .backend()
+                .prediction_confidence(committed_text)
+                < min_confidence
+        {
             return;
         }
 
@@ -191,25 +385,145 @@ impl<P: SyllableParser> ImeEngine<P> {
         // Clear commit text from previous key
         self.context.commit_text.clear();
 
+        // Translate bound keys to the keys editors understand.
+        // A default key whose action was rebound elsewhere is not handled.
+        let key = match self.key_bindings.resolve(key) {
+            Some(key) => key,
+            None => return KeyResult::NotHandled,
+        };
+
         // Translate selection key characters to Number events
         // This allows configurable selection keys (e.g., asdfghjkl vs 123456789)
+        let backend = self.phonetic_editor.backend();
+        let (smart_punctuation, learn_punctuation, chinese_punctuation) = {
+            let config = backend.config();
+            (
+                config.smart_punctuation,
+                config.learn_punctuation_preference,
+                config.chinese_punctuation,
+            )
+        };
+        let learning = learn_punctuation.then(|| backend.userdict().clone());
+        {
+            let config = backend.config();
+            if config.punctuation_overrides != self.punctuation_overrides {
+                self.punctuation_overrides = config.punctuation_overrides.clone();
+                let table = self
+                    .punctuation_overrides
+                    .iter()
+                    .filter_map(|(key, alts)| {
+                        let mut chars = key.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(ch), None) => Some((ch, alts.clone())),
+                            _ => None,
+                        }
+                    })
+                    .collect();
+                self.punct_editor.load_table(table);
+            }
+        }
+        self.punct_editor.set_chinese(chinese_punctuation);
+        self.punct_editor.set_smart(smart_punctuation);
+        self.punct_editor.set_learning(learning);
+
         let key = if let KeyEvent::Char(ch) = key {
             let config = self.phonetic_editor.backend().config();
-            if let Some(index) = config.selection_key_index(ch) {
-                drop(config);
-                // Convert to 1-based number (index 0 → number 1, etc.)
-                KeyEvent::Number((index + 1) as u8)
-            } else {
-                drop(config);
-                key
+            // A selection key that is also phonetic input ("asdfghjkl", or
+            // digits on a zhuyin layout) only selects candidates on screen
+            // with select_keys_priority, or after moving through the list
+            let selects = !self.is_phonetic_key(ch)
+                || ((config.select_keys_priority || self.browsing_candidates)
+                    && !self.session.candidates().is_empty());
+            match config.selection_key_index(ch) {
+                Some(index) if selects => {
+                    drop(config);
+                    // Convert to 1-based number (index 0 → number 1, etc.)
+                    KeyEvent::Number((index + 1) as u8)
+                }
+                _ => {
+                    drop(config);
+                    key
+                }
             }
         } else {
             key
         };
 
+        let selected_rank = self.selected_rank(&key);
+        let result = self.dispatch_key(key.clone());
+        self.track_last_committed(&key, result);
+        self.browsing_candidates = result == KeyResult::Handled
+            && self.session.mode() == InputMode::Phonetic
+            && matches!(
+                key,
+                KeyEvent::Up | KeyEvent::Down | KeyEvent::PageUp | KeyEvent::PageDown
+            );
+
+        if result == KeyResult::Handled {
+            let stats = self.session.stats_mut();
+            stats.keystrokes += 1;
+            if key == KeyEvent::Backspace {
+                stats.backspaces += 1;
+            }
+            if !self.context.commit_text.is_empty() {
+                stats.record_commit(selected_rank);
+            }
+        }
+
+        result
+    }
+
+    /// Get the statistics collected since the last `reset`.
+    pub fn stats(&self) -> &SessionStats {
+        self.session.stats()
+    }
+
+    /// Remember the last character the application received, from the
+    /// commit text or from a passed-through character key.
+    fn track_last_committed(&mut self, key: &KeyEvent, result: KeyResult) {
+        if let Some(ch) = self.context.commit_text.chars().last() {
+            self.session.set_last_committed_char(Some(ch));
+        } else if result == KeyResult::NotHandled {
+            let ch = match *key {
+                KeyEvent::Char(ch) => Some(ch),
+                KeyEvent::Number(n) => char::from_digit(n as u32, 10),
+                _ => None,
+            };
+            self.session.set_last_committed_char(ch);
+        }
+    }
+
+    /// Whether `ch` is phonetic input, including keys the parser's keyboard
+    /// layout types phonetic symbols with.
+    fn is_phonetic_key(&self, ch: char) -> bool {
+        is_phonetic_char(ch) || self.phonetic_editor.backend().is_input_key(ch)
+    }
+
+    /// Rank of the candidate a selection key would pick, if any.
+    fn selected_rank(&self, key: &KeyEvent) -> Option<usize> {
+        if !matches!(
+            self.session.mode(),
+            InputMode::Phonetic | InputMode::Punctuation | InputMode::Suggestion
+        ) {
+            return None;
+        }
+
+        let candidates = self.session.candidates();
+        match key {
+            KeyEvent::Space | KeyEvent::Enter => candidates.selected_index(),
+            KeyEvent::Number(n) if (1..=9).contains(n) => {
+                candidates.global_index((*n - 1) as usize)
+            }
+            _ => None,
+        }
+    }
+
+    /// Route a translated key to the global shortcuts or the active editor.
+    fn dispatch_key(&mut self, key: KeyEvent) -> KeyResult {
         // Handle global shortcuts first (before mode routing)
         match key {
             KeyEvent::ShiftLock => {
+                self.phrase_parts.clear();
                 // Toggle passthrough mode
                 if self.session.mode() == InputMode::Passthrough {
                     self.session.set_mode(InputMode::Init);
@@ -229,6 +543,7 @@ impl<P: SyllableParser> ImeEngine<P> {
                 if self.session.mode() == InputMode::Passthrough {
                     return KeyResult::NotHandled;
                 }
+                self.phrase_parts.clear();
 
                 let was_phonetic = self.session.mode() == InputMode::Phonetic;
 
@@ -238,14 +553,14 @@ impl<P: SyllableParser> ImeEngine<P> {
                         let text = self.session.input_buffer().text().to_string();
                         self.context.commit_text = text;
                     }
-                    self.reset();
+                    self.clear_state();
                     // After reset from phonetic, we're done (stay in Init)
                     return KeyResult::Handled;
                 }
 
                 // Toggle: if in punctuation, go to init; else go to punctuation
                 if self.session.mode() == InputMode::Punctuation {
-                    self.reset();
+                    self.clear_state();
                 } else {
                     self.session.set_mode(InputMode::Punctuation);
                     self.session.activate();
@@ -266,16 +581,11 @@ impl<P: SyllableParser> ImeEngine<P> {
         // Route to appropriate editor based on current mode
         let result = match self.session.mode() {
             InputMode::Init => {
-                // Check if this is phonetic input or punctuation
-                // Accept:
-                // - ASCII lowercase (pinyin: a-z)
-                // - Bopomofo/Zhuyin characters (U+3105-U+3129)
-                // - Zhuyin tone marks (ˊˇˋ˙)
-                let is_phonetic_input = matches!(key, KeyEvent::Char(ch) if
-                    ch.is_ascii_lowercase()
-                    || ('\u{3105}'..='\u{3129}').contains(&ch)  // Bopomofo block
-                    || matches!(ch, 'ˊ' | 'ˇ' | 'ˋ' | '˙')      // Tone marks
-                );
+                // Check if this is phonetic input (or a shape code, whose
+                // prefix may be a punctuation key) or punctuation
+                let is_phonetic_input = matches!(key, KeyEvent::Char(ch)
+                    if self.is_phonetic_key(ch)
+                        || self.phonetic_editor.backend().starts_shape_code(ch));
 
                 if is_phonetic_input {
                     // Activate phonetic mode
@@ -296,8 +606,9 @@ impl<P: SyllableParser> ImeEngine<P> {
                 }
             }
             InputMode::Phonetic => {
-                // Check for punctuation trigger
-                if matches!(key, KeyEvent::Char(',')) {
+                // Check for punctuation trigger (unless the layout types
+                // phonetic input on ',')
+                if matches!(key, KeyEvent::Char(',')) && !self.is_phonetic_key(',') {
                     // Switch to punctuation mode
                     self.session.set_mode(InputMode::Punctuation);
                     self.punct_editor.activate(',', &mut self.session);
@@ -314,6 +625,18 @@ impl<P: SyllableParser> ImeEngine<P> {
             }
         };
 
+        // Let the commit filter veto non-empty commits
+        let vetoed = match &result {
+            EditorResult::Commit(text) | EditorResult::CommitAndReset(text) => {
+                !text.is_empty() && !self.phonetic_editor.backend().allows_commit(text)
+            }
+            _ => false,
+        };
+        self.track_phrase_parts(&result, vetoed);
+        if vetoed {
+            return self.handle_vetoed_commit();
+        }
+
         // Handle editor result
         match result {
             EditorResult::Handled => {
@@ -324,11 +647,7 @@ impl<P: SyllableParser> ImeEngine<P> {
             }
             EditorResult::Commit(text) => {
                 // Apply full-width conversion if enabled
-                let text = if self.phonetic_editor.backend().config().is_fullwidth() {
-                    crate::utils::to_fullwidth(&text)
-                } else {
-                    text
-                };
+                let text = self.convert_commit_text(text);
 
                 // Commit but stay active
                 self.context.commit_text = text.clone();
@@ -340,20 +659,26 @@ impl<P: SyllableParser> ImeEngine<P> {
 
                 KeyResult::Handled
             }
+            EditorResult::CommitPartial(text) => {
+                // Apply full-width conversion if enabled
+                let text = self.convert_commit_text(text);
+
+                // The rest of the input is still composing: no suggestions
+                self.context.commit_text = text;
+                self.session.sync_to_context(&mut self.context);
+                self.update_auxiliary_text();
+                KeyResult::Handled
+            }
             EditorResult::CommitAndReset(text) => {
                 // Apply full-width conversion if enabled
-                let text = if self.phonetic_editor.backend().config().is_fullwidth() {
-                    crate::utils::to_fullwidth(&text)
-                } else {
-                    text
-                };
+                let text = self.convert_commit_text(text);
 
                 // Commit and prepare for auto-suggestion
                 let committed_text = text.clone();
                 if !text.is_empty() {
                     self.context.commit_text = text;
                 }
-                self.reset();
+                self.clear_state();
 
                 // Auto-enter suggestion mode after reset if enabled
                 self.maybe_auto_suggest(&committed_text);
@@ -372,6 +697,36 @@ impl<P: SyllableParser> ImeEngine<P> {
         }
     }
 
+    /// Apply `Config::full_width_enabled` to text about to be committed.
+    ///
+    /// `Config::chinese_punctuation` is not applied here: it only decides
+    /// which form a punctuation key offers first (see `PunctuationEditor`),
+    /// so raw input committed with Enter ("a<b") keeps its ASCII.
+    fn convert_commit_text(&self, text: String) -> String {
+        if self.phonetic_editor.backend().config().is_fullwidth() {
+            crate::utils::to_fullwidth(&text)
+        } else {
+            text
+        }
+    }
+
+    /// Commit nothing after a veto; keep or clear the composition depending
+    /// on `Config::preserve_preedit_on_veto`.
+    fn handle_vetoed_commit(&mut self) -> KeyResult {
+        let preserve = self
+            .phonetic_editor
+            .backend()
+            .config()
+            .preserve_preedit_on_veto;
+        if preserve {
+            self.session.sync_to_context(&mut self.context);
+            self.update_auxiliary_text();
+        } else {
+            self.clear_state();
+        }
+        KeyResult::Handled
+    }
+
     /// Update auxiliary text based on current mode and state.
     fn update_auxiliary_text(&mut self) {
         if !self.session.is_active() && self.session.mode() != InputMode::Passthrough {
@@ -383,7 +738,13 @@ impl<P: SyllableParser> ImeEngine<P> {
             InputMode::Init => String::new(),
             InputMode::Phonetic => {
                 let num_candidates = self.session.candidates().len();
-                if num_candidates > 0 {
+                let input = self.session.input_buffer().text();
+                if let Some(error) = self.phonetic_editor.backend().input_error(input) {
+                    format!("拼音 | {}", error)
+                } else if self.phonetic_editor.is_input_truncated() {
+                    let max = self.phonetic_editor.backend().config().max_input_length;
+                    format!("拼音 | 输入过长，最多 {} 个字符", max)
+                } else if num_candidates > 0 {
                     format!("拼音 | {} 个候选 | Space/数字选择", num_candidates)
                 } else {
                     "拼音 | 输入拼音...".to_string()
@@ -432,6 +793,30 @@ impl<P: SyllableParser> ImeEngine<P> {
         self.phonetic_editor.backend().config().is_fullwidth()
     }
 
+    /// Toggle Chinese punctuation on/off.
+    pub fn toggle_chinese_punctuation(&mut self) {
+        self.phonetic_editor
+            .backend()
+            .config_mut()
+            .toggle_chinese_punctuation();
+    }
+
+    /// Set Chinese punctuation explicitly.
+    pub fn set_chinese_punctuation(&mut self, enabled: bool) {
+        self.phonetic_editor
+            .backend()
+            .config_mut()
+            .set_chinese_punctuation(enabled);
+    }
+
+    /// Check if Chinese punctuation is enabled.
+    pub fn is_chinese_punctuation(&self) -> bool {
+        self.phonetic_editor
+            .backend()
+            .config()
+            .is_chinese_punctuation()
+    }
+
     /// Set the selection keys string (e.g., "asdfghjkl" or "123456789").
     pub fn set_select_keys(&mut self, keys: &str) {
         self.phonetic_editor
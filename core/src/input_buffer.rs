@@ -4,11 +4,34 @@
 //! the cursor position within this buffer. This is separate from the visual
 //! preedit composition which shows converted text.
 
+/// Split `input` at the given byte offsets, skipping offsets that are out
+/// of order, at either end, or not on a character boundary.
+///
+/// ```
+/// use libchinese_core::input_buffer::split_at_boundaries;
+///
+/// assert_eq!(split_at_boundaries("xian", &[2]), vec!["xi", "an"]);
+/// assert_eq!(split_at_boundaries("xian", &[0, 4]), vec!["xian"]);
+/// ```
+pub fn split_at_boundaries<'a>(input: &'a str, boundaries: &[usize]) -> Vec<&'a str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for &b in boundaries {
+        if b > start && b < input.len() && input.is_char_boundary(b) {
+            pieces.push(&input[start..b]);
+            start = b;
+        }
+    }
+    pieces.push(&input[start..]);
+    pieces
+}
+
 /// Input buffer tracking raw input and cursor position.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InputBuffer {
     text: String,
-    cursor: usize, // Byte offset, not char offset
+    cursor: usize,          // Byte offset, not char offset
+    boundaries: Vec<usize>, // Pinned syllable boundaries, sorted byte offsets
 }
 
 impl InputBuffer {
@@ -17,6 +40,7 @@ impl InputBuffer {
         Self {
             text: String::new(),
             cursor: 0,
+            boundaries: Vec::new(),
         }
     }
 
@@ -40,24 +64,93 @@ impl InputBuffer {
         self.text.is_empty()
     }
 
+    /// Get the length of the buffer in characters.
+    pub fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Cut the buffer to its first `max_chars` characters, keeping the
+    /// cursor inside it. Returns true if anything was cut.
+    pub fn truncate_chars(&mut self, max_chars: usize) -> bool {
+        match self.text.char_indices().nth(max_chars) {
+            Some((end, _)) => {
+                self.text.truncate(end);
+                self.cursor = self.cursor.min(end);
+                self.boundaries.retain(|&b| b < end);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Clear the buffer and reset cursor.
     pub fn clear(&mut self) {
         self.text.clear();
         self.cursor = 0;
+        self.boundaries.clear();
+    }
+
+    /// Get the pinned syllable boundaries (sorted byte offsets).
+    pub fn boundaries(&self) -> &[usize] {
+        &self.boundaries
+    }
+
+    /// Pin a syllable boundary at the cursor, or unpin it if one is
+    /// already there. Returns false if the cursor is at either end of the
+    /// buffer, where a boundary means nothing.
+    pub fn toggle_boundary(&mut self) -> bool {
+        if self.cursor == 0 || self.cursor >= self.text.len() {
+            return false;
+        }
+        match self.boundaries.binary_search(&self.cursor) {
+            Ok(idx) => {
+                self.boundaries.remove(idx);
+            }
+            Err(idx) => self.boundaries.insert(idx, self.cursor),
+        }
+        true
     }
 
     /// Insert a character at the cursor position.
     pub fn insert_char(&mut self, ch: char) {
         self.text.insert(self.cursor, ch);
+        self.shift_boundaries(self.cursor, ch.len_utf8());
         self.cursor += ch.len_utf8();
     }
 
     /// Insert a string at the cursor position.
     pub fn insert_str(&mut self, s: &str) {
         self.text.insert_str(self.cursor, s);
+        self.shift_boundaries(self.cursor, s.len());
         self.cursor += s.len();
     }
 
+    /// Move boundaries after an insertion of `len` bytes at `at`.
+    fn shift_boundaries(&mut self, at: usize, len: usize) {
+        for b in &mut self.boundaries {
+            if *b >= at {
+                *b += len;
+            }
+        }
+    }
+
+    /// Fix up boundaries after removing the byte range `start..end`.
+    /// Boundaries inside the range collapse onto `start`; boundaries that
+    /// end up at either end of the buffer are dropped.
+    fn remove_boundaries(&mut self, start: usize, end: usize) {
+        let removed = end - start;
+        for b in &mut self.boundaries {
+            if *b >= end {
+                *b -= removed;
+            } else if *b > start {
+                *b = start;
+            }
+        }
+        let len = self.text.len();
+        self.boundaries.retain(|&b| b > 0 && b < len);
+        self.boundaries.dedup();
+    }
+
     /// Delete the character before the cursor (backspace).
     /// Returns true if a character was deleted.
     pub fn delete_before(&mut self) -> bool {
@@ -74,7 +167,8 @@ impl InputBuffer {
             }
         }
 
-        self.text.remove(prev);
+        let ch = self.text.remove(prev);
+        self.remove_boundaries(prev, prev + ch.len_utf8());
         self.cursor = prev;
         true
     }
@@ -88,7 +182,8 @@ impl InputBuffer {
 
         // Remove character at cursor
         if self.text.is_char_boundary(self.cursor) {
-            self.text.remove(self.cursor);
+            let ch = self.text.remove(self.cursor);
+            self.remove_boundaries(self.cursor, self.cursor + ch.len_utf8());
             true
         } else {
             false
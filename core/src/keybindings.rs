@@ -0,0 +1,137 @@
+//! Configurable key bindings for IME actions.
+//!
+//! Editors understand a fixed set of keys (Space selects, Down moves the
+//! cursor, Enter commits, Ctrl+period toggles punctuation,
+//! Ctrl+apostrophe pins a syllable boundary). `KeyBindings` maps
+//! semantic actions to the keys that trigger them, and `ImeEngine` translates
+//! incoming keys to the editor keys before routing. The default bindings match
+//! the built-in behavior.
+
+use crate::ime_engine::KeyEvent;
+use std::collections::HashMap;
+
+/// Semantic actions that can be bound to keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    /// Select the highlighted (initially first) candidate. Default: Space
+    SelectFirst,
+    /// Move the candidate cursor to the next candidate. Default: Down
+    NextCandidate,
+    /// Commit the composition (selection if any, raw input otherwise). Default: Enter
+    CommitRaw,
+    /// Toggle punctuation mode. Default: Ctrl+period
+    TogglePunctuation,
+    /// Pin or unpin a syllable boundary at the caret. Default: Ctrl+apostrophe
+    ToggleBoundary,
+}
+
+impl KeyAction {
+    /// All bindable actions.
+    pub const ALL: [KeyAction; 5] = [
+        KeyAction::SelectFirst,
+        KeyAction::NextCandidate,
+        KeyAction::CommitRaw,
+        KeyAction::TogglePunctuation,
+        KeyAction::ToggleBoundary,
+    ];
+
+    /// The key editors handle for this action (also the default binding).
+    pub fn editor_key(self) -> KeyEvent {
+        match self {
+            KeyAction::SelectFirst => KeyEvent::Space,
+            KeyAction::NextCandidate => KeyEvent::Down,
+            KeyAction::CommitRaw => KeyEvent::Enter,
+            KeyAction::TogglePunctuation => KeyEvent::Ctrl('.'),
+            KeyAction::ToggleBoundary => KeyEvent::Ctrl('\''),
+        }
+    }
+}
+
+/// Mapping from keys to semantic actions.
+///
+/// A key triggers at most one action; an action may have several keys.
+/// If an action's default key is rebound elsewhere and not bound to anything
+/// else, pressing it is no longer handled by the IME.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: HashMap<KeyEvent, KeyAction>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = KeyAction::ALL
+            .iter()
+            .map(|&action| (action.editor_key(), action))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// Create bindings matching the built-in behavior.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind an action to a single key, replacing its previous bindings.
+    ///
+    /// Returns the other action the key was bound to, which loses it.
+    ///
+    /// # Example
+    /// ```
+    /// # use libchinese_core::{KeyAction, KeyBindings, KeyEvent};
+    /// let mut bindings = KeyBindings::new();
+    /// let displaced = bindings.bind(KeyAction::SelectFirst, KeyEvent::Enter);
+    /// assert_eq!(displaced, Some(KeyAction::CommitRaw));
+    /// assert_eq!(bindings.action_for(&KeyEvent::Enter), Some(KeyAction::SelectFirst));
+    /// assert_eq!(bindings.action_for(&KeyEvent::Space), None);
+    /// ```
+    pub fn bind(&mut self, action: KeyAction, key: KeyEvent) -> Option<KeyAction> {
+        self.bindings.retain(|_, a| *a != action);
+        self.bindings.insert(key, action)
+    }
+
+    /// Add an extra key for an action, keeping its existing bindings.
+    ///
+    /// Returns the other action the key was bound to, which loses it.
+    pub fn add_binding(&mut self, action: KeyAction, key: KeyEvent) -> Option<KeyAction> {
+        self.bindings
+            .insert(key, action)
+            .filter(|&previous| previous != action)
+    }
+
+    /// Remove all keys bound to an action.
+    pub fn unbind(&mut self, action: KeyAction) {
+        self.bindings.retain(|_, a| *a != action);
+    }
+
+    /// Action triggered by a key, if any.
+    pub fn action_for(&self, key: &KeyEvent) -> Option<KeyAction> {
+        self.bindings.get(key).copied()
+    }
+
+    /// Keys bound to an action.
+    pub fn keys_for(&self, action: KeyAction) -> Vec<KeyEvent> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+
+    /// Translate an incoming key to the key editors understand.
+    ///
+    /// Returns `None` for a default action key that is no longer bound, so
+    /// the old binding stops working after a rebind.
+    pub(crate) fn resolve(&self, key: KeyEvent) -> Option<KeyEvent> {
+        if let Some(action) = self.action_for(&key) {
+            return Some(action.editor_key());
+        }
+
+        if KeyAction::ALL.iter().any(|a| a.editor_key() == key) {
+            return None;
+        }
+
+        Some(key)
+    }
+}
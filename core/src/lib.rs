@@ -12,7 +12,7 @@
 //! - `Lexicon` - Pinyin/Zhuyin → Hanzi dictionary lookup
 //! - `UserDict` - Persistent user learning and frequency adaptation
 //! - `Config` - Configuration and feature flags
-use fst::Map;
+use fst::{IntoStreamer, Map, Streamer};
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::HashMap as AHashMap;
@@ -21,7 +21,9 @@ use std::io::Read;
 use std::sync::Arc;
 
 pub mod word_bigram;
-pub use word_bigram::WordBigram;
+pub use word_bigram::{
+    DefaultBackoff, KneserNey, Smoothing, StupidBackoff, WordBigram, WordTrigram,
+};
 
 pub mod trie;
 pub use trie::TrieNode;
@@ -30,14 +32,32 @@ pub mod fuzzy;
 pub use fuzzy::FuzzyMap;
 
 pub mod engine;
-pub use engine::{Engine, SyllableParser, SyllableType};
+pub use engine::{
+    CommitFilter, CorpusEval, Engine, KeystrokePlan, OriginStats, SelectionStats, SyllableParser,
+    SyllableType,
+};
+
+pub mod sync_engine;
+pub use sync_engine::SyncEngine;
 
 pub mod userdict;
-pub use userdict::UserDict;
+pub use userdict::{
+    ImportMode, MergePolicy, UserDict, UserDictSnapshot, RECENCY_HALF_LIFE_SECS, SNAPSHOT_VERSION,
+};
+
+pub mod numeral;
+
+pub mod char_freq;
+
+pub mod dataset;
+pub use dataset::{discover_datasets, DatasetInfo, DatasetKind};
+
+pub mod tone;
+pub use tone::NEUTRAL_TONE;
 
 // IME modules (flattened from ime/ subdirectory)
 pub mod candidate;
-pub use candidate::{Candidate, CandidateList};
+pub use candidate::{Candidate, CandidateFfi, CandidateList, CandidateSource, DEFAULT_PAGE_SIZE};
 
 pub mod composition;
 pub use composition::{Composition, Segment};
@@ -49,7 +69,7 @@ pub mod input_buffer;
 pub use input_buffer::InputBuffer;
 
 pub mod session;
-pub use session::{ImeSession, InputMode};
+pub use session::{ImeSession, InputMode, SessionStats};
 
 pub mod editor;
 pub use editor::{Editor, EditorResult, PhoneticEditor, PunctuationEditor, SuggestionEditor};
@@ -57,12 +77,22 @@ pub use editor::{Editor, EditorResult, PhoneticEditor, PunctuationEditor, Sugges
 pub mod ime_engine;
 pub use ime_engine::{ImeEngine, KeyEvent, KeyResult};
 
+pub mod keybindings;
+pub use keybindings::{KeyAction, KeyBindings};
+
+mod payloads;
+use payloads::{FstBytes, LazyPayloads, Payloads};
+
 /// Generic configuration for IME core functionality.
 ///
 /// This config contains only language-agnostic fields. Language-specific options
 /// (pinyin corrections, zhuyin keyboard layouts, etc.) belong in `PinyinConfig`
 /// or `ZhuyinConfig` in their respective crates.
+///
+/// Fields missing from a TOML file fall back to their `Default` values, so
+/// configs written by older versions keep loading as new options are added.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
     /// Fuzzy equivalence rules (e.g., "zh=z", "an=ang")
     /// Language crates should populate this with appropriate defaults
@@ -73,19 +103,154 @@ pub struct Config {
     pub auto_suggestion: bool,
     /// Minimum committed text length to trigger auto-suggestion (chars)
     pub min_suggestion_trigger_length: usize,
+    /// Also trigger auto-suggestion after committing a single Han character
+    /// ("的"), when something is predicted to follow it. Punctuation and
+    /// other non-Han characters never trigger it. Default: false.
+    pub single_char_association: bool,
+    /// Minimum confidence of the top prediction to trigger auto-suggestion,
+    /// in [0, 1] (see `Engine::prediction_confidence`). Default: 0.0.
+    pub min_suggestion_confidence: f32,
+    /// Age at which a learned bigram's prediction boost halves, in seconds.
+    /// Default: 0 (no decay; the boost depends on the count alone).
+    pub prediction_recency_halflife_secs: u64,
 
     // Full/Half Width Settings
     /// Enable full-width character conversion (ASCII to full-width)
     pub full_width_enabled: bool,
+    /// Type Chinese punctuation (",." → "，。"): punctuation keys offer the
+    /// Chinese form first, ahead of the ASCII one. Other committed text,
+    /// such as raw input committed with Enter, is left as typed.
+    /// Independent of `full_width_enabled`, so half-width letters can go
+    /// with Chinese punctuation. Default: true.
+    pub chinese_punctuation: bool,
 
     // Candidate Selection
     /// Keys for selecting candidates (default: "123456789", alternative: "asdfghjkl")
     /// Must contain at least 1 character. First char selects 1st candidate, etc.
     pub select_keys: String,
+    /// Let a selection key that is also phonetic input ("asdfghjkl", or
+    /// digits on a zhuyin keyboard layout) select a candidate while
+    /// candidates are visible, instead of extending the input. Without
+    /// candidates such keys always type. Disabled by default; such keys
+    /// also select right after Up/Down/PageUp/PageDown moved through the
+    /// candidates.
+    pub select_keys_priority: bool,
 
     // Phrase Masking
     /// Set of phrases to hide from candidate suggestions
     pub masked_phrases: std::collections::HashSet<String>,
+    /// Drop candidates that just repeat the typed spelling ("nihao" for
+    /// input "nihao"). Disabled by default.
+    pub exclude_raw_spelling: bool,
+
+    // Numeral Candidates
+    /// Offer numeral candidates for numeral pinyin ("yibai" → 一百 / 100)
+    /// and for digit input ("100" → 一百). Disabled by default.
+    pub numeral_candidates: bool,
+
+    // Shape Codes
+    /// Prefix that routes the rest of the input to the lexicon's shape-code
+    /// index (see `Lexicon::load_shape_index`) instead of phonetic
+    /// segmentation (";onf" → 你 for Cangjie). Default: ";". Empty disables
+    /// the routing.
+    pub shape_code_prefix: String,
+
+    // Segmentation Variants
+    /// Make sure the top candidate of every distinct segmentation of the
+    /// input is offered, labeled with its reading ("xian" → 先 as "xian",
+    /// 西安 as "xi'an"). Disabled by default.
+    pub offer_segmentation_variants: bool,
+
+    // Particle Trimming
+    /// For candidates ending in a particle (的, 了, 吗, ...), also offer the
+    /// phrase without it when that is a lexicon entry. Disabled by default.
+    pub trim_trailing_particles: bool,
+
+    // Compound Splits
+    /// Let `Engine::compound_split_group` offer the top candidate split into
+    /// known sub-words ("北京大学" → 北京 + 大学), so front-ends can show the
+    /// parts as a group for committing piece by piece. Disabled by default.
+    pub offer_compound_splits: bool,
+
+    // Partial Commit
+    /// Also offer phrases for the leading syllables of the input, after the
+    /// whole-input candidates ("beijingdaxue" → 北京 as "bei'jing").
+    /// Selecting one commits it and keeps the rest ("daxue") composing.
+    /// Disabled by default.
+    pub offer_prefix_candidates: bool,
+    /// Learn the phrases committed one after another for one input ("北",
+    /// then "京" for "beijing") as one user phrase ("北京") when the input
+    /// is finished (see `ImeEngine::commit_as_phrase`). Disabled by default.
+    pub learn_partial_commits_as_phrase: bool,
+
+    // Phrase Length Preference
+    /// Among candidates whose scores are within `length_bias_epsilon` of
+    /// each other, rank longer phrases first (北京大学 before 北京), for users
+    /// who prefer whole-phrase conversion. Disabled by default: pure score
+    /// order.
+    pub sort_by_phrase_length: bool,
+    /// Score difference still considered a tie by `sort_by_phrase_length`.
+    /// Default: 0.5.
+    pub length_bias_epsilon: f32,
+
+    // Emoji
+    /// Rank emoji for the input (see `Engine::load_emoji_lexicon`) ahead of
+    /// the text candidates instead of after them. Disabled by default.
+    pub emoji_priority: bool,
+
+    // Tone Input
+    /// Let a syllable typed with the neutral tone ("ma5" or "ma0") match any
+    /// stored tone rather than only the neutral one. Default: true.
+    pub neutral_tone_matches_any: bool,
+
+    // Candidate Cache
+    /// Most inputs whose candidate lists `Engine` keeps, least recently used
+    /// evicted first. 0 disables the cache. Default: 1000.
+    pub max_cache_size: usize,
+
+    // Type-ahead Prefetch
+    /// Let `Engine::prefetch_completions` warm the candidate cache for the
+    /// most frequent lexicon keys extending the current input ("bei" →
+    /// "beijing", "beifang"). Disabled by default.
+    pub typeahead_prefetch: bool,
+
+    // Smart Punctuation
+    /// Pick the punctuation form from the previously committed character:
+    /// half-width after a digit ("3.14"), full-width after Han ("好。").
+    /// Disabled by default.
+    pub smart_punctuation: bool,
+    /// Remember which form the user picks for each punctuation key and offer
+    /// the most picked one first. Disabled by default.
+    pub learn_punctuation_preference: bool,
+    /// Alternatives replacing the built-in ones for a punctuation key, in
+    /// display order, keyed by the key as a one-character string
+    /// (`"\\" = ["、", "「", "」"]`). Unlisted keys keep the built-in
+    /// alternatives; longer keys are ignored. Default: empty.
+    pub punctuation_overrides: std::collections::HashMap<String, Vec<String>>,
+
+    // Input Length
+    /// Most characters the phonetic input buffer holds; keys typed beyond
+    /// it are swallowed and the auxiliary text says so, which keeps a pasted
+    /// blob from triggering a huge segmentation. 0 means no limit.
+    /// Default: 64.
+    pub max_input_length: usize,
+
+    // Commit Failure
+    /// Keep the preedit and candidates when a commit filter vetoes a commit
+    /// or Space finds no candidate to select, instead of clearing the input.
+    /// Disabled by default.
+    pub preserve_preedit_on_veto: bool,
+
+    // Selection Memory
+    /// Restore the highlighted candidate when editing returns to an input
+    /// seen earlier in the same composition (type, move the cursor,
+    /// backspace, retype). Disabled by default.
+    pub preserve_selection_on_reedit: bool,
+
+    // Apostrophe Handling
+    /// Whether a typed apostrophe separates syllables ("xi'an" → 西安) or
+    /// is kept as literal text ("O'Brien"). Default: `Separator`.
+    pub apostrophe_mode: ApostropheMode,
 
     // Parser Penalty Settings (for fuzzy matching and error correction)
     /// Penalty for correction rules (ue/ve, v/u in pinyin, or keyboard shuffles in zhuyin)
@@ -94,6 +259,9 @@ pub struct Config {
     /// Penalty multiplier for fuzzy matching rules (z/zh, c/ch, s/sh, etc.)
     /// Default: 100. This value is multiplied by the rule's weight from the fuzzy map.
     pub fuzzy_penalty_multiplier: i32,
+    /// Most fuzzy alternatives tried per syllable, keeping the lowest-penalty
+    /// ones. Bounds search growth with large rule sets. Default: 0 (no limit).
+    pub max_fuzzy_alternatives: usize,
     /// Penalty for incomplete syllable matches (partial input like "n" → "ni")
     /// Default: 500. Only applies to pinyin parser with allow_fuzzy enabled.
     pub incomplete_penalty: i32,
@@ -103,10 +271,42 @@ pub struct Config {
     /// Cost penalty for unknown segments in cost calculation
     /// Default: 10.0. Added to segment cost for unrecognized characters.
     pub unknown_cost: f32,
+    /// Which inexact match type wins when a correction, a fuzzy alternative
+    /// and an incomplete syllable all cover the same input at equal base cost.
+    /// Default: `CorrectionFirst`. See [`MatchPriority`] for the guarantee.
+    pub match_priority: MatchPriority,
 
     /// Boost (additive) applied to score for exact full-key matches.
     /// Larger values prefer exact dictionary entries over composed alternatives.
     pub full_key_boost: f32,
+    /// Multiplier on the probability of a multi-syllable lexicon phrase whose
+    /// reading is the entire input ("shishi" → 实时 rather than 是事). Applied
+    /// on top of `full_key_boost`, as `ln(boost)` in log space, and only for
+    /// exact (non-fuzzy) readings. Default: 1.0 (no effect).
+    pub whole_input_phrase_boost: f32,
+    /// Boost (additive) for candidates with exactly one character per
+    /// segmented syllable, demoting phrases that convert the syllables into
+    /// fewer or more characters. Default: 0.0 (disabled).
+    pub syllable_phrase_match_boost: f32,
+    /// Penalty subtracted from a candidate's score per rare character it
+    /// contains (Han characters outside the bundled common-character table).
+    /// Default: 0.0 (disabled). Around 2.0 demotes exotic conversions.
+    pub rare_char_penalty: f32,
+    /// Boost (additive) for candidates from a segmentation the user taught
+    /// by splitting the input manually (e.g. "xi'an" for "xian").
+    pub segmentation_learning_boost: f32,
+    /// Penalty for candidates the user passed over for the same input (see
+    /// `Engine::record_rejection`), applied as `penalty * ln(1 + rejections)`.
+    pub rejection_penalty: f32,
+    /// Share of the user-dictionary boost driven by recency rather than raw
+    /// count, in [0, 1] (see `UserDict::recency_weighted_frequency`). At 1.0
+    /// a phrase used today outranks one used often long ago. Default: 0.0.
+    pub recency_frequency_alpha: f32,
+    /// Boost (additive) for learned phrases whose reading extends the input
+    /// ("beijingda" → 北京大学 once the user has committed it). Such phrases
+    /// are offered as completions only while this is positive, scored as a
+    /// full-key match plus the boost. Default: 0.0 (disabled).
+    pub learned_completion_boost: f32,
     /// Lambda parameter for interpolation model (unigram/bigram mixing)
     /// Lambda is the weight for bigram probability: score = λ*P(w2|w1) + (1-λ)*P(w2)
     /// Upstream libpinyin default: 0.293 (trained via deleted interpolation)
@@ -130,20 +330,79 @@ impl Default for Config {
             // Suggestion mode - auto-enter after commits of 2+ chars
             auto_suggestion: true,
             min_suggestion_trigger_length: 2,
+            single_char_association: false,
+            min_suggestion_confidence: 0.0,
+            prediction_recency_halflife_secs: 0,
             // Full/half width - disabled by default
             full_width_enabled: false,
+            // Chinese punctuation - on, as the punctuation editor always did
+            chinese_punctuation: true,
             // Selection keys - default to numbers 1-9
             select_keys: "123456789".to_string(),
+            select_keys_priority: false,
             // Phrase masking - empty by default
             masked_phrases: std::collections::HashSet::new(),
+            exclude_raw_spelling: false,
+            // Numeral candidates - opt-in
+            numeral_candidates: false,
+            // Shape-code escape, unused by pinyin and zhuyin input
+            shape_code_prefix: ";".to_string(),
+            // Segmentation variants - opt-in
+            offer_segmentation_variants: false,
+            // Particle trimming - opt-in
+            trim_trailing_particles: false,
+            // Compound splits - opt-in
+            offer_compound_splits: false,
+            // Prefix candidates - opt-in
+            offer_prefix_candidates: false,
+            learn_partial_commits_as_phrase: false,
+            // Phrase length preference - opt-in, only breaks near-ties
+            sort_by_phrase_length: false,
+            length_bias_epsilon: 0.5,
+            // Emoji follow the text candidates
+            emoji_priority: false,
+            // Neutral tone is often typed loosely - accept any tone for it
+            neutral_tone_matches_any: true,
+            // Candidate cache - enough for a long editing session
+            max_cache_size: 1000,
+            // Type-ahead prefetch - opt-in
+            typeahead_prefetch: false,
+            // Smart punctuation - opt-in
+            smart_punctuation: false,
+            learn_punctuation_preference: false,
+            punctuation_overrides: std::collections::HashMap::new(),
+            // Input length - longer than any phrase anyone types at once
+            max_input_length: 64,
+            // Vetoed commits clear the input unless asked otherwise
+            preserve_preedit_on_veto: false,
+            // Selection memory - opt-in
+            preserve_selection_on_reedit: false,
+            // Apostrophes split syllables, as they always have
+            apostrophe_mode: ApostropheMode::Separator,
             // Parser penalties - balanced defaults for fuzzy matching
             correction_penalty: 200,
             fuzzy_penalty_multiplier: 100,
+            max_fuzzy_alternatives: 0,
             incomplete_penalty: 500,
             unknown_penalty: 1000,
             unknown_cost: 10.0,
+            match_priority: MatchPriority::CorrectionFirst,
             // Exact-match boost: prefer full-key dictionary entries slightly
             full_key_boost: 2.0,
+            // Whole-input phrase boost - neutral unless configured
+            whole_input_phrase_boost: 1.0,
+            // Syllable/character count match boost - opt-in
+            syllable_phrase_match_boost: 0.0,
+            // Rare-character demotion - opt-in
+            rare_char_penalty: 0.0,
+            // Learned segmentations should beat the parser's own choice
+            segmentation_learning_boost: 3.0,
+            // Each rejection demotes a little more, with diminishing effect
+            rejection_penalty: 1.0,
+            // Raw learned counts - recency weighting is opt-in
+            recency_frequency_alpha: 0.0,
+            // Completing learned phrases from a partial reading - opt-in
+            learned_completion_boost: 0.0,
             // Lambda for interpolation: upstream default 0.293 (trained)
             // We'll start with a similar value
             lambda: 0.3,
@@ -202,6 +461,21 @@ impl Config {
         self.full_width_enabled
     }
 
+    /// Toggle Chinese punctuation on/off.
+    pub fn toggle_chinese_punctuation(&mut self) {
+        self.chinese_punctuation = !self.chinese_punctuation;
+    }
+
+    /// Set Chinese punctuation explicitly.
+    pub fn set_chinese_punctuation(&mut self, enabled: bool) {
+        self.chinese_punctuation = enabled;
+    }
+
+    /// Check if Chinese punctuation is enabled.
+    pub fn is_chinese_punctuation(&self) -> bool {
+        self.chinese_punctuation
+    }
+
     // ========== Phrase Masking API ==========
 
     /// Add a phrase to the mask list (hide from suggestions).
@@ -231,6 +505,249 @@ impl Config {
         phrases
     }
 
+    /// Enable or disable hiding candidates equal to the typed spelling.
+    pub fn set_exclude_raw_spelling(&mut self, enabled: bool) {
+        self.exclude_raw_spelling = enabled;
+    }
+
+    /// Check if candidates equal to the typed spelling are hidden.
+    pub fn exclude_raw_spelling_enabled(&self) -> bool {
+        self.exclude_raw_spelling
+    }
+
+    // ========== Numeral Candidates ==========
+
+    /// Enable or disable numeral candidates (一百 / 100 for "yibai").
+    pub fn set_numeral_candidates(&mut self, enabled: bool) {
+        self.numeral_candidates = enabled;
+    }
+
+    /// Check if numeral candidates are enabled.
+    pub fn numeral_candidates_enabled(&self) -> bool {
+        self.numeral_candidates
+    }
+
+    // ========== Shape Codes ==========
+
+    /// Set the prefix that routes input to the shape-code index ("" disables).
+    pub fn set_shape_code_prefix(&mut self, prefix: &str) {
+        self.shape_code_prefix = prefix.to_string();
+    }
+
+    /// Get the shape-code prefix.
+    pub fn get_shape_code_prefix(&self) -> &str {
+        &self.shape_code_prefix
+    }
+
+    // ========== Segmentation Variants ==========
+
+    /// Enable or disable one labeled candidate per distinct segmentation.
+    pub fn set_offer_segmentation_variants(&mut self, enabled: bool) {
+        self.offer_segmentation_variants = enabled;
+    }
+
+    /// Check if segmentation variants are offered.
+    pub fn segmentation_variants_enabled(&self) -> bool {
+        self.offer_segmentation_variants
+    }
+
+    // ========== Particle Trimming ==========
+
+    /// Enable or disable trimmed variants of candidates ending in a particle.
+    pub fn set_trim_trailing_particles(&mut self, enabled: bool) {
+        self.trim_trailing_particles = enabled;
+    }
+
+    /// Check if particle trimming is enabled.
+    pub fn trim_trailing_particles_enabled(&self) -> bool {
+        self.trim_trailing_particles
+    }
+
+    // ========== Compound Splits ==========
+
+    /// Enable or disable the split of the top candidate into sub-words.
+    pub fn set_offer_compound_splits(&mut self, enabled: bool) {
+        self.offer_compound_splits = enabled;
+    }
+
+    /// Check if compound splits are offered.
+    pub fn compound_splits_enabled(&self) -> bool {
+        self.offer_compound_splits
+    }
+
+    // ========== Partial Commit ==========
+
+    /// Enable or disable candidates for the leading syllables of the input.
+    pub fn set_offer_prefix_candidates(&mut self, enabled: bool) {
+        self.offer_prefix_candidates = enabled;
+    }
+
+    /// Check if prefix candidates are offered.
+    pub fn prefix_candidates_enabled(&self) -> bool {
+        self.offer_prefix_candidates
+    }
+
+    /// Enable or disable learning partial commits as one phrase.
+    pub fn set_learn_partial_commits_as_phrase(&mut self, enabled: bool) {
+        self.learn_partial_commits_as_phrase = enabled;
+    }
+
+    /// Check if partial commits are learned as one phrase.
+    pub fn partial_commit_learning_enabled(&self) -> bool {
+        self.learn_partial_commits_as_phrase
+    }
+
+    // ========== Phrase Length Preference ==========
+
+    /// Enable or disable ranking longer phrases first among near-equal scores.
+    pub fn set_sort_by_phrase_length(&mut self, enabled: bool) {
+        self.sort_by_phrase_length = enabled;
+    }
+
+    /// Check if longer phrases win near-ties.
+    pub fn phrase_length_sort_enabled(&self) -> bool {
+        self.sort_by_phrase_length
+    }
+
+    /// Set the score difference `sort_by_phrase_length` still treats as a tie.
+    /// Default: 0.5
+    pub fn set_length_bias_epsilon(&mut self, epsilon: f32) {
+        self.length_bias_epsilon = epsilon;
+    }
+
+    /// Get the score difference treated as a tie by `sort_by_phrase_length`.
+    pub fn get_length_bias_epsilon(&self) -> f32 {
+        self.length_bias_epsilon
+    }
+
+    // ========== Emoji ==========
+
+    /// Set whether emoji candidates rank ahead of text candidates.
+    pub fn set_emoji_priority(&mut self, enabled: bool) {
+        self.emoji_priority = enabled;
+    }
+
+    /// Check if emoji candidates rank ahead of text candidates.
+    pub fn emoji_priority_enabled(&self) -> bool {
+        self.emoji_priority
+    }
+
+    // ========== Tone Input ==========
+
+    /// Set whether a neutral-tone syllable matches any stored tone.
+    pub fn set_neutral_tone_matches_any(&mut self, enabled: bool) {
+        self.neutral_tone_matches_any = enabled;
+    }
+
+    /// Check whether a neutral-tone syllable matches any stored tone.
+    pub fn neutral_tone_matches_any(&self) -> bool {
+        self.neutral_tone_matches_any
+    }
+
+    // ========== Candidate Cache ==========
+
+    /// Set how many inputs' candidate lists the engine caches (0 disables)
+    pub fn set_max_cache_size(&mut self, size: usize) {
+        self.max_cache_size = size;
+    }
+
+    /// Get the candidate cache capacity
+    pub fn get_max_cache_size(&self) -> usize {
+        self.max_cache_size
+    }
+
+    // ========== Type-ahead Prefetch ==========
+
+    /// Enable or disable type-ahead candidate prefetch
+    pub fn set_typeahead_prefetch(&mut self, enabled: bool) {
+        self.typeahead_prefetch = enabled;
+    }
+
+    /// Check whether type-ahead candidate prefetch is enabled
+    pub fn typeahead_prefetch_enabled(&self) -> bool {
+        self.typeahead_prefetch
+    }
+
+    // ========== Smart Punctuation ==========
+
+    /// Enable or disable context-sensitive punctuation forms
+    pub fn set_smart_punctuation(&mut self, enabled: bool) {
+        self.smart_punctuation = enabled;
+    }
+
+    /// Check whether context-sensitive punctuation forms are enabled
+    pub fn smart_punctuation_enabled(&self) -> bool {
+        self.smart_punctuation
+    }
+
+    /// Enable or disable learning of preferred punctuation forms
+    pub fn set_learn_punctuation_preference(&mut self, enabled: bool) {
+        self.learn_punctuation_preference = enabled;
+    }
+
+    /// Check whether preferred punctuation forms are learned
+    pub fn learn_punctuation_preference_enabled(&self) -> bool {
+        self.learn_punctuation_preference
+    }
+
+    /// Override the alternatives a punctuation key offers (an empty list
+    /// restores the built-in ones)
+    pub fn set_punctuation_alternatives(&mut self, key: char, alts: Vec<String>) {
+        if alts.is_empty() {
+            self.punctuation_overrides.remove(&key.to_string());
+        } else {
+            self.punctuation_overrides.insert(key.to_string(), alts);
+        }
+    }
+
+    // ========== Input Length ==========
+
+    /// Set the most characters the input buffer holds (0 = no limit)
+    pub fn set_max_input_length(&mut self, length: usize) {
+        self.max_input_length = length;
+    }
+
+    /// Get the input buffer length limit
+    pub fn get_max_input_length(&self) -> usize {
+        self.max_input_length
+    }
+
+    // ========== Commit Failure ==========
+
+    /// Keep (true) or clear (false) the preedit after a failed commit
+    pub fn set_preserve_preedit_on_veto(&mut self, enabled: bool) {
+        self.preserve_preedit_on_veto = enabled;
+    }
+
+    /// Check whether the preedit is kept after a failed commit
+    pub fn preserve_preedit_on_veto_enabled(&self) -> bool {
+        self.preserve_preedit_on_veto
+    }
+
+    // ========== Selection Memory ==========
+
+    /// Enable or disable restoring the highlighted candidate on re-edit.
+    pub fn set_preserve_selection_on_reedit(&mut self, enabled: bool) {
+        self.preserve_selection_on_reedit = enabled;
+    }
+
+    /// Check if the highlighted candidate is restored on re-edit.
+    pub fn preserve_selection_on_reedit_enabled(&self) -> bool {
+        self.preserve_selection_on_reedit
+    }
+
+    // ========== Apostrophe Handling ==========
+
+    /// Set how typed apostrophes are interpreted.
+    pub fn set_apostrophe_mode(&mut self, mode: ApostropheMode) {
+        self.apostrophe_mode = mode;
+    }
+
+    /// Get how typed apostrophes are interpreted.
+    pub fn get_apostrophe_mode(&self) -> ApostropheMode {
+        self.apostrophe_mode
+    }
+
     // ========== Selection Keys Management ==========
 
     /// Set the selection keys string.
@@ -252,6 +769,17 @@ impl Config {
         &self.select_keys
     }
 
+    /// Let letter selection keys select (true) or type (false) while
+    /// candidates are visible.
+    pub fn set_select_keys_priority(&mut self, enabled: bool) {
+        self.select_keys_priority = enabled;
+    }
+
+    /// Check if letter selection keys select while candidates are visible.
+    pub fn select_keys_priority_enabled(&self) -> bool {
+        self.select_keys_priority
+    }
+
     /// Check if a character is a selection key and return its index (0-based).
     /// Returns None if the character is not a selection key.
     pub fn selection_key_index(&self, ch: char) -> Option<usize> {
@@ -284,6 +812,18 @@ impl Config {
         self.fuzzy_penalty_multiplier
     }
 
+    /// Set how many fuzzy alternatives are tried per syllable, keeping the
+    /// lowest-penalty ones. 0 means no limit.
+    /// Default: 0
+    pub fn set_max_fuzzy_alternatives(&mut self, max: usize) {
+        self.max_fuzzy_alternatives = max;
+    }
+
+    /// Get the current cap on fuzzy alternatives per syllable.
+    pub fn get_max_fuzzy_alternatives(&self) -> usize {
+        self.max_fuzzy_alternatives
+    }
+
     /// Set the incomplete penalty (partial input like "n" → "ni").
     /// Only applies to pinyin parser with allow_fuzzy enabled.
     /// Default: 500
@@ -319,6 +859,126 @@ impl Config {
     pub fn get_unknown_cost(&self) -> f32 {
         self.unknown_cost
     }
+
+    /// Set which inexact match type wins ties between corrections, fuzzy
+    /// alternatives and incomplete syllables.
+    /// Default: `MatchPriority::CorrectionFirst`
+    pub fn set_match_priority(&mut self, priority: MatchPriority) {
+        self.match_priority = priority;
+    }
+
+    /// Get the current match priority.
+    pub fn get_match_priority(&self) -> MatchPriority {
+        self.match_priority
+    }
+}
+
+/// Kind of inexact syllable match the parser can fall back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Common typo correction ("lue" → "lve", "juen" → "jun")
+    Correction,
+    /// Fuzzy equivalence rule ("zi" → "zhi", "an" → "ang")
+    Fuzzy,
+    /// Prefix of a longer syllable ("zh" → "zha")
+    Incomplete,
+}
+
+/// Ordering of inexact match types in the pinyin parser.
+///
+/// Each match type adds a fixed surcharge to the segment cost: 0.5 for the
+/// first-ranked type, 0.8 for the second and 2.0 for the last. The gaps are
+/// wider than any length or content adjustment between two segments covering
+/// the same input, so for a given span the higher-ranked type always wins.
+/// Exact syllables carry no surcharge and beat all three.
+///
+/// Incomplete matches are only tried where nothing else matched unless they
+/// are ranked ahead of another type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MatchPriority {
+    /// Correction, then fuzzy, then incomplete (the historical behavior).
+    #[default]
+    CorrectionFirst,
+    /// Fuzzy, then correction, then incomplete.
+    FuzzyFirst,
+    /// Incomplete, then correction, then fuzzy.
+    IncompleteFirst,
+}
+
+impl MatchPriority {
+    /// Match types from highest to lowest priority.
+    pub fn order(self) -> [MatchKind; 3] {
+        match self {
+            MatchPriority::CorrectionFirst => [
+                MatchKind::Correction,
+                MatchKind::Fuzzy,
+                MatchKind::Incomplete,
+            ],
+            MatchPriority::FuzzyFirst => [
+                MatchKind::Fuzzy,
+                MatchKind::Correction,
+                MatchKind::Incomplete,
+            ],
+            MatchPriority::IncompleteFirst => [
+                MatchKind::Incomplete,
+                MatchKind::Correction,
+                MatchKind::Fuzzy,
+            ],
+        }
+    }
+
+    /// Position of a match type in the ordering (0 = highest priority).
+    pub fn rank(self, kind: MatchKind) -> usize {
+        self.order().iter().position(|k| *k == kind).unwrap_or(2)
+    }
+
+    /// Segment cost surcharge for a match type under this ordering.
+    pub fn surcharge(self, kind: MatchKind) -> f32 {
+        [0.5, 0.8, 2.0][self.rank(kind)]
+    }
+}
+
+/// Interpretation of an apostrophe typed into the phonetic input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ApostropheMode {
+    /// Always a syllable separator ("xi'an" → xi + an).
+    #[default]
+    Separator,
+    /// Always literal text, offered verbatim as a candidate.
+    Literal,
+    /// A separator between pinyin syllables, literal inside Latin words:
+    /// "xi'an" separates, while "O'Brien" keeps its apostrophe.
+    Smart,
+}
+
+impl ApostropheMode {
+    /// Whether the apostrophe at byte offset `index` of `input` separates
+    /// syllables.
+    ///
+    /// In `Smart` mode the letters on either side decide: pinyin is typed in
+    /// lowercase, so an uppercase letter in the adjoining word marks Latin
+    /// text and keeps the apostrophe literal.
+    ///
+    /// # Example
+    /// ```
+    /// # use libchinese_core::ApostropheMode;
+    /// assert!(ApostropheMode::Smart.separates_at("xi'an", 2));
+    /// assert!(!ApostropheMode::Smart.separates_at("O'Brien", 1));
+    /// ```
+    pub fn separates_at(self, input: &str, index: usize) -> bool {
+        match self {
+            ApostropheMode::Separator => true,
+            ApostropheMode::Literal => false,
+            ApostropheMode::Smart => {
+                let is_pinyin_word = |word: &mut dyn Iterator<Item = char>| {
+                    word.take_while(|c| c.is_ascii_alphabetic())
+                        .all(|c| c.is_ascii_lowercase())
+                };
+                is_pinyin_word(&mut input[..index].chars().rev())
+                    && is_pinyin_word(&mut input[index + 1..].chars())
+            }
+        }
+    }
 }
 
 /// Utility helpers.
@@ -380,6 +1040,170 @@ pub(crate) struct LexEntry {
     pub freq: u32,
 }
 
+/// Automaton accepting exactly the keys of a sorted key set, used to
+/// intersect the lexicon FST with a batch of keys in one search.
+struct KeySetAutomaton<'a> {
+    fst: &'a fst::raw::Fst<Vec<u8>>,
+}
+
+impl fst::Automaton for KeySetAutomaton<'_> {
+    type State = Option<fst::raw::CompiledAddr>;
+
+    fn start(&self) -> Self::State {
+        Some(self.fst.root().addr())
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        state.is_some_and(|addr| self.fst.node(addr).is_final())
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let node = self.fst.node((*state)?);
+        node.find_input(byte).map(|i| node.transition(i).addr)
+    }
+}
+
+/// Automaton accepting keys that start with a typed prefix, where the
+/// prefix may leave out the `'` syllable separators of the key.
+///
+/// "beij" matches "bei'jing": a `'` in the key that the prefix does not
+/// spell is skipped. A `'` typed in the prefix still has to be in the key.
+struct KeyPrefixAutomaton<'a> {
+    prefix: &'a [u8],
+}
+
+impl<'a> KeyPrefixAutomaton<'a> {
+    fn new(prefix: &'a str) -> Self {
+        Self {
+            prefix: prefix.as_bytes(),
+        }
+    }
+
+    /// Whether `key` starts with the prefix (see the type docs).
+    fn matches(&self, key: &str) -> bool {
+        use fst::Automaton;
+        let mut state = self.start();
+        for byte in key.bytes() {
+            if self.is_match(&state) {
+                return true;
+            }
+            state = self.accept(&state, byte);
+        }
+        self.is_match(&state)
+    }
+
+    /// Whether a matching `key` spells more than the prefix.
+    fn extends(&self, key: &str) -> bool {
+        let spelled = |bytes: &[u8]| bytes.iter().filter(|b| **b != b'\'').count();
+        spelled(key.as_bytes()) > spelled(self.prefix)
+    }
+}
+
+impl fst::Automaton for KeyPrefixAutomaton<'_> {
+    /// Bytes of the prefix matched so far; `None` once the key diverges.
+    type State = Option<usize>;
+
+    fn start(&self) -> Self::State {
+        Some(0)
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        *state == Some(self.prefix.len())
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn will_always_match(&self, state: &Self::State) -> bool {
+        self.is_match(state)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let matched = (*state)?;
+        match self.prefix.get(matched) {
+            None => Some(matched),
+            Some(&expected) if expected == byte => Some(matched + 1),
+            // An untyped syllable separator in the key
+            Some(_) if byte == b'\'' => Some(matched),
+            Some(_) => None,
+        }
+    }
+}
+
+/// A mismatch between a lexicon's FST index and its payload groups, as
+/// reported by `Lexicon::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexiconError {
+    /// An FST key (phonetic or shape code) points past the last payload
+    /// group, so looking it up finds nothing
+    IndexOutOfRange {
+        key: String,
+        index: u64,
+        /// Number of payload groups
+        groups: usize,
+    },
+    /// A payload group without entries
+    EmptyGroup { index: usize },
+}
+
+impl std::fmt::Display for LexiconError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexiconError::IndexOutOfRange { key, index, groups } => write!(
+                f,
+                "key \"{}\" points to payload group {} of {}",
+                key, index, groups
+            ),
+            LexiconError::EmptyGroup { index } => write!(f, "payload group {} is empty", index),
+        }
+    }
+}
+
+impl std::error::Error for LexiconError {}
+
+/// How `Lexicon::merge` combines another lexicon's phrases with its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergePriority {
+    /// Put the merged lexicon's phrases before (true) or after (false) the
+    /// existing ones for each key
+    pub prepend: bool,
+    /// Keep one copy of a phrase found on both sides, at its first position
+    /// and with the higher frequency
+    pub dedupe: bool,
+}
+
+impl MergePriority {
+    /// Combine one key's (phrase, frequency) lists from both sides.
+    fn combine(self, ours: Vec<(String, u32)>, theirs: Vec<(String, u32)>) -> Vec<(String, u32)> {
+        let combined: Vec<(String, u32)> = if self.prepend {
+            theirs.into_iter().chain(ours).collect()
+        } else {
+            ours.into_iter().chain(theirs).collect()
+        };
+        if !self.dedupe {
+            return combined;
+        }
+
+        let mut deduped: Vec<(String, u32)> = Vec::with_capacity(combined.len());
+        for (phrase, freq) in combined {
+            match deduped.iter_mut().find(|(p, _)| *p == phrase) {
+                Some(existing) => existing.1 = existing.1.max(freq),
+                None => deduped.push((phrase, freq)),
+            }
+        }
+        deduped
+    }
+}
+
+/// Separates the shape code from the phrase in shape-index keys (see
+/// `Lexicon::load_shape_index`).
+pub const SHAPE_KEY_SEPARATOR: char = '\t';
+
 /// Lookups map a pinyin-sequence key (e.g. "nihao") to a list of Chinese
 /// phrases. Uses FST for key indexing and bincode for payload storage.
 #[derive(Debug, Clone, Default)]
@@ -387,9 +1211,15 @@ pub struct Lexicon {
     // In-memory map for dynamic entries
     map: AHashMap<String, Vec<String>>,
     // FST map for key -> index lookups
-    fst_map: Option<Map<Vec<u8>>>,
+    fst_map: Option<Map<FstBytes>>,
     // Bincode-serialized payload vector (index -> Vec<LexEntry>)
-    payloads: Option<Vec<Vec<LexEntry>>>,
+    payloads: Option<Payloads>,
+    // FST map for shape code -> payload index lookups (see load_shape_index)
+    shape_map: Option<Map<FstBytes>>,
+    // Phrase -> keys index for reverse lookups (see build_reverse_index)
+    reverse: Option<AHashMap<String, Vec<String>>>,
+    // Lexicons merged in at runtime, in merge order
+    merged: Vec<(Lexicon, MergePriority)>,
 }
 
 impl Lexicon {
@@ -398,6 +1228,9 @@ impl Lexicon {
             map: AHashMap::new(),
             fst_map: None,
             payloads: None,
+            shape_map: None,
+            reverse: None,
+            merged: Vec::new(),
         }
     }
 
@@ -406,26 +1239,161 @@ impl Lexicon {
         let key = key.into();
         let phrase = phrase.into();
         self.map.entry(key).or_default().push(phrase);
+        self.refresh_reverse_index();
     }
 
-    /// Lookup candidates for a given pinyin key.
-    pub fn lookup(&self, key: &str) -> Vec<String> {
-        // Prefer in-memory map entries
-        if let Some(v) = self.map.get(key) {
-            return v.clone();
+    /// Merge another lexicon into this one; lookups query both.
+    ///
+    /// `other` is kept as it is, FST-backed or in-memory, and its phrases
+    /// are combined with this lexicon's per key as `priority` says. Phrases
+    /// from in-memory entries have frequency 0, so when deduping, a copy
+    /// with a real frequency wins. Lexicons merged later combine with the
+    /// result of earlier merges.
+    ///
+    /// Key lookups (`lookup`, `lookup_with_freq`, `has_key`,
+    /// `has_keys_batch`, `keys_with_prefix`), shape-code lookups and reverse
+    /// lookups cover merged lexicons; `key_count`, `key_completions` and
+    /// `homophone_groups` only report this lexicon's own keys.
+    pub fn merge(&mut self, other: Lexicon, priority: MergePriority) {
+        self.merged.push((other, priority));
+        self.refresh_reverse_index();
+    }
+
+    /// Build the phrase → keys reverse index used by `reverse_lookup` and
+    /// `contains_phrase`.
+    ///
+    /// The index is opt-in: until this (or `load_reverse_index`) is called,
+    /// reverse lookups find nothing. It covers both in-memory and FST
+    /// entries and is rebuilt by `insert` and `merge` once it exists, so
+    /// build it after bulk inserts. The index holds every distinct phrase
+    /// plus a copy of each key it appears under, which is about as large as
+    /// the payload text itself and, unlike the payloads, is always resident
+    /// in memory.
+    ///
+    /// Engine features that go from a phrase back to its reading or check
+    /// that a phrase exists (`Engine::keystrokes_to_commit`, compound splits,
+    /// trailing particle trimming, text training and readings of user
+    /// phrases) need it.
+    pub fn build_reverse_index(&mut self) {
+        let mut entries: AHashMap<String, Vec<(String, u32)>> = AHashMap::new();
+        self.collect_reverse_entries(&mut entries);
+
+        let index = entries
+            .into_iter()
+            .map(|(phrase, mut keys)| {
+                // Most frequent reading first; a key seen twice keeps its
+                // higher frequency
+                keys.sort_by(|(a, fa), (b, fb)| fb.cmp(fa).then_with(|| a.cmp(b)));
+                let mut seen = std::collections::HashSet::new();
+                keys.retain(|(key, _)| seen.insert(key.clone()));
+                (phrase, keys.into_iter().map(|(key, _)| key).collect())
+            })
+            .collect();
+        self.reverse = Some(index);
+    }
+
+    /// Whether a reverse index has been built or loaded.
+    pub fn has_reverse_index(&self) -> bool {
+        self.reverse.is_some()
+    }
+
+    /// Find the keys (readings) that produce a phrase, e.g. "你好" → ["ni'hao"].
+    ///
+    /// A polyphonic phrase has one key per reading ("行" → ["xing", "hang"]),
+    /// most frequent first; in-memory entries count as frequency 0 and ties
+    /// are in key order. Empty until `build_reverse_index` has been called.
+    pub fn reverse_lookup(&self, phrase: &str) -> Vec<String> {
+        self.reverse
+            .as_ref()
+            .and_then(|index| index.get(phrase))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Check whether a phrase exists under any key. Uses the reverse index,
+    /// so it is always false until `build_reverse_index` has been called.
+    pub fn contains_phrase(&self, phrase: &str) -> bool {
+        self.reverse
+            .as_ref()
+            .is_some_and(|index| index.contains_key(phrase))
+    }
+
+    /// Save the reverse index to a bincode file.
+    ///
+    /// Phrases are written in sorted order, so the same lexicon always
+    /// produces the same file. Fails if no index has been built.
+    pub fn save_reverse_index<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let index = self
+            .reverse
+            .as_ref()
+            .ok_or("reverse index not built (see build_reverse_index)")?;
+        let sorted: std::collections::BTreeMap<&String, &Vec<String>> = index.iter().collect();
+        let bytes =
+            bincode::serialize(&sorted).map_err(|e| format!("serialize reverse index: {}", e))?;
+        std::fs::write(path, bytes)
+            .map_err(|e| format!("write reverse index {}: {}", path.display(), e))
+    }
+
+    /// Load a reverse index written by `save_reverse_index`.
+    ///
+    /// The loaded index replaces any built one and serves `reverse_lookup`
+    /// and `contains_phrase` until the lexicon is modified, which rebuilds
+    /// it. It is not checked against the lexicon, so it must come from the
+    /// same data.
+    pub fn load_reverse_index<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("read reverse index {}: {}", path.display(), e))?;
+        let index: AHashMap<String, Vec<String>> = bincode::deserialize(&bytes)
+            .map_err(|e| format!("deserialize reverse index: {}", e))?;
+        self.reverse = Some(index);
+        Ok(())
+    }
+
+    /// Rebuild the reverse index after a modification, if one is in use.
+    fn refresh_reverse_index(&mut self) {
+        if self.reverse.is_some() {
+            self.build_reverse_index();
+        }
+    }
+
+    /// Add (key, frequency) for every phrase of this and merged lexicons.
+    fn collect_reverse_entries(&self, index: &mut AHashMap<String, Vec<(String, u32)>>) {
+        for (key, phrases) in &self.map {
+            for phrase in phrases {
+                index
+                    .entry(phrase.clone())
+                    .or_default()
+                    .push((key.clone(), 0));
+            }
         }
 
-        // FST + bincode lookup
         if let (Some(map), Some(payloads)) = (&self.fst_map, &self.payloads) {
-            if let Some(idx) = map.get(key) {
-                let index = idx as usize;
-                if let Some(entries) = payloads.get(index) {
-                    return entries.iter().map(|e| e.utf8.clone()).collect();
+            let keys = Self::keys_by_index(map);
+            payloads.for_each(|idx, entries| {
+                for key in keys.get(idx).into_iter().flatten() {
+                    for entry in entries {
+                        index
+                            .entry(entry.utf8.clone())
+                            .or_default()
+                            .push((key.clone(), entry.freq));
+                    }
                 }
-            }
+            });
         }
 
-        Vec::new()
+        for (other, _) in &self.merged {
+            other.collect_reverse_entries(index);
+        }
+    }
+
+    /// Lookup candidates for a given pinyin key.
+    pub fn lookup(&self, key: &str) -> Vec<String> {
+        self.lookup_with_freq(key)
+            .into_iter()
+            .map(|(phrase, _)| phrase)
+            .collect()
     }
 
     /// Lookup that also returns the lexicon frequency for each phrase (if available).
@@ -433,6 +1401,21 @@ impl Lexicon {
     /// For in-memory `map` entries the frequency is unknown (0). For FST/bincode
     /// entries the stored `LexEntry.freq` is returned.
     pub fn lookup_with_freq(&self, key: &str) -> Vec<(String, u32)> {
+        let mut result = self.own_lookup_with_freq(key);
+
+        for (other, priority) in &self.merged {
+            let theirs = other.lookup_with_freq(key);
+            if theirs.is_empty() {
+                continue;
+            }
+            result = priority.combine(result, theirs);
+        }
+
+        result
+    }
+
+    /// `lookup_with_freq` over this lexicon's own entries, ignoring merges.
+    fn own_lookup_with_freq(&self, key: &str) -> Vec<(String, u32)> {
         // Prefer in-memory map entries
         if let Some(v) = self.map.get(key) {
             return v.iter().cloned().map(|s| (s, 0)).collect();
@@ -464,10 +1447,192 @@ impl Lexicon {
 
         // Check FST index without touching payloads
         if let Some(map) = &self.fst_map {
-            return map.get(key).is_some();
+            if map.get(key).is_some() {
+                return true;
+            }
+        }
+
+        self.merged.iter().any(|(other, _)| other.has_key(key))
+    }
+
+    /// Check many keys at once; the result is parallel to `keys`.
+    ///
+    /// Equivalent to calling `has_key` for each key, but the FST is searched
+    /// in a single pass: the keys are compiled into an automaton and only the
+    /// FST paths shared with it are visited.
+    pub fn has_keys_batch(&self, keys: &[&str]) -> Vec<bool> {
+        let mut found: std::collections::HashSet<Vec<u8>> = std::collections::HashSet::new();
+
+        if let Some(map) = &self.fst_map {
+            let mut sorted: Vec<&str> = keys.to_vec();
+            sorted.sort_unstable();
+            sorted.dedup();
+            if let Ok(query) = fst::Set::from_iter(sorted) {
+                let mut stream = map
+                    .search(KeySetAutomaton {
+                        fst: query.as_fst(),
+                    })
+                    .into_stream();
+                while let Some((key, _)) = stream.next() {
+                    found.insert(key.to_vec());
+                }
+            }
+        }
+
+        let mut result: Vec<bool> = keys
+            .iter()
+            .map(|k| self.map.contains_key(*k) || found.contains(k.as_bytes()))
+            .collect();
+        for (other, _) in &self.merged {
+            for (hit, theirs) in result.iter_mut().zip(other.has_keys_batch(keys)) {
+                *hit |= theirs;
+            }
+        }
+        result
+    }
+
+    /// Keys strictly extending `prefix`, most frequent first, at most `limit`.
+    ///
+    /// The prefix may leave out the key's `'` separators, so "beij" finds
+    /// "bei'jing" (see `keys_with_prefix`). A key ranks by the highest
+    /// frequency among its phrases; ties go to the shorter key, then
+    /// alphabetically. In-memory entries carry no frequency and count as 0.
+    pub fn key_completions(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let matcher = KeyPrefixAutomaton::new(prefix);
+        let mut ranked: AHashMap<String, u32> = AHashMap::new();
+
+        for key in self.map.keys() {
+            if matcher.matches(key) && matcher.extends(key) {
+                ranked.entry(key.clone()).or_insert(0);
+            }
+        }
+
+        if let (Some(map), Some(payloads)) = (&self.fst_map, &self.payloads) {
+            let mut stream = map.search(&matcher).into_stream();
+            while let Some((key, idx)) = stream.next() {
+                let key = String::from_utf8_lossy(key).into_owned();
+                if !matcher.extends(&key) {
+                    continue;
+                }
+                let freq = payloads
+                    .get(idx as usize)
+                    .and_then(|entries| entries.iter().map(|e| e.freq).max())
+                    .unwrap_or(0);
+                let best = ranked.entry(key).or_insert(0);
+                *best = (*best).max(freq);
+            }
+        }
+
+        let mut keys: Vec<(String, u32)> = ranked.into_iter().collect();
+        keys.sort_by(|(a, fa), (b, fb)| {
+            fb.cmp(fa)
+                .then_with(|| a.len().cmp(&b.len()))
+                .then_with(|| a.cmp(b))
+        });
+        keys.into_iter().take(limit).map(|(k, _)| k).collect()
+    }
+
+    /// Keys starting with `prefix` (including `prefix` itself), sorted
+    /// lexicographically, at most `limit`.
+    ///
+    /// The prefix is the typed input, which may leave out the key's `'`
+    /// syllable separators: "beij" finds "bei'jing". FST keys are read with
+    /// an automaton search over the index alone; no payloads are decoded.
+    /// In-memory entries are merged in.
+    pub fn keys_with_prefix(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let matcher = KeyPrefixAutomaton::new(prefix);
+        let mut keys: Vec<String> = self
+            .map
+            .keys()
+            .filter(|k| matcher.matches(k))
+            .cloned()
+            .collect();
+
+        if let Some(map) = &self.fst_map {
+            let mut stream = map.search(&matcher).into_stream();
+            let mut taken = 0;
+            while let Some((key, _)) = stream.next() {
+                if taken == limit {
+                    break;
+                }
+                keys.push(String::from_utf8_lossy(key).into_owned());
+                taken += 1;
+            }
+        }
+
+        for (other, _) in &self.merged {
+            keys.extend(other.keys_with_prefix(prefix, limit));
+        }
+
+        keys.sort_unstable();
+        keys.dedup();
+        keys.truncate(limit);
+        keys
+    }
+
+    /// Keys starting with `prefix` paired with their phrases, as
+    /// `keys_with_prefix` orders and limits them.
+    pub fn lookup_prefix(&self, prefix: &str, limit: usize) -> Vec<(String, Vec<String>)> {
+        self.keys_with_prefix(prefix, limit)
+            .into_iter()
+            .map(|key| {
+                let phrases = self.lookup(&key);
+                (key, phrases)
+            })
+            .collect()
+    }
+
+    /// Groups of phrases sharing a reading, e.g. ("shi'shi", [实时, 事实, ...]).
+    ///
+    /// Only keys with more than `min_group_size` distinct phrases are
+    /// reported. In-memory entries are merged with FST entries for the same
+    /// key; groups are sorted by key and phrases keep their lexicon order.
+    pub fn homophone_groups(&self, min_group_size: usize) -> Vec<(String, Vec<String>)> {
+        let mut groups: AHashMap<String, Vec<String>> = AHashMap::new();
+
+        if let (Some(map), Some(payloads)) = (&self.fst_map, &self.payloads) {
+            let keys = Self::keys_by_index(map);
+            payloads.for_each(|idx, entries| {
+                for key in keys.get(idx).into_iter().flatten() {
+                    groups
+                        .entry(key.clone())
+                        .or_default()
+                        .extend(entries.iter().map(|e| e.utf8.clone()));
+                }
+            });
         }
 
-        false
+        for (key, phrases) in &self.map {
+            groups
+                .entry(key.clone())
+                .or_default()
+                .extend(phrases.iter().cloned());
+        }
+
+        let mut result: Vec<(String, Vec<String>)> = groups
+            .into_iter()
+            .filter_map(|(key, phrases)| {
+                let mut seen = std::collections::HashSet::new();
+                let phrases: Vec<String> = phrases
+                    .into_iter()
+                    .filter(|p| seen.insert(p.clone()))
+                    .collect();
+                (phrases.len() > min_group_size).then_some((key, phrases))
+            })
+            .collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result
+    }
+
+    /// Number of distinct keys (in-memory entries plus FST keys).
+    pub fn key_count(&self) -> usize {
+        let fst_keys = self.fst_map.as_ref().map_or(0, |m| m.len());
+        let extra = self
+            .map
+            .keys()
+            .filter(|k| self.fst_map.as_ref().is_none_or(|m| !m.contains_key(k)))
+            .count();
+        fst_keys + extra
     }
 
     /// Compute total frequency of all lexicon entries (for unigram probability normalization).
@@ -477,11 +1642,11 @@ impl Lexicon {
         let mut total: u64 = 0;
 
         if let Some(payloads) = &self.payloads {
-            for entries in payloads {
+            payloads.for_each(|_, entries| {
                 for entry in entries {
                     total += entry.freq as u64;
                 }
-            }
+            });
         }
 
         total
@@ -501,10 +1666,9 @@ impl Lexicon {
         // Load FST
         let mut f =
             File::open(fst_path).map_err(|e| format!("open fst {}: {}", fst_path.display(), e))?;
-        let mut buf = Vec::new();
-        f.read_to_end(&mut buf)
+        let mut fst_bytes = Vec::new();
+        f.read_to_end(&mut fst_bytes)
             .map_err(|e| format!("read fst: {}", e))?;
-        let map = Map::new(buf).map_err(|e| format!("fst map: {}", e))?;
 
         // Load bincode payloads
         let mut f = File::open(bincode_path)
@@ -512,19 +1676,264 @@ impl Lexicon {
         let mut buf = Vec::new();
         f.read_to_end(&mut buf)
             .map_err(|e| format!("read bincode: {}", e))?;
-        let payloads: Vec<Vec<LexEntry>> =
-            bincode::deserialize(&buf).map_err(|e| format!("deserialize bincode: {}", e))?;
+
+        Self::from_fst_bincode_bytes(fst_bytes, &buf)
+    }
+
+    /// Build a lexicon from the contents of FST + bincode artifacts.
+    ///
+    /// Same format as `load_from_fst_bincode`, for hosts that receive the
+    /// artifacts as bytes rather than files (e.g. a `fetch`ed `Uint8Array`
+    /// in the browser).
+    pub fn from_fst_bincode_bytes(
+        fst_bytes: Vec<u8>,
+        bincode_bytes: &[u8],
+    ) -> Result<Self, String> {
+        let map = Map::new(FstBytes::Owned(fst_bytes)).map_err(|e| format!("fst map: {}", e))?;
+        let payloads: Vec<Vec<LexEntry>> = bincode::deserialize(bincode_bytes)
+            .map_err(|e| format!("deserialize bincode: {}", e))?;
+
+        Ok(Self {
+            map: AHashMap::new(),
+            fst_map: Some(map),
+            payloads: Some(Payloads::Eager(payloads)),
+            shape_map: None,
+            reverse: None,
+            merged: Vec::new(),
+        })
+    }
+
+    /// Encode key → (phrase, frequency) groups as FST + bincode artifacts,
+    /// the format `convert_table` writes and `from_fst_bincode_bytes` reads.
+    ///
+    /// Keys may come in any order but must be distinct. Useful for tests and
+    /// for building small dictionaries without running `convert_table`.
+    pub fn encode_fst_bincode(
+        groups: &[(&str, &[(&str, u32)])],
+    ) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let mut groups = groups.to_vec();
+        groups.sort_by_key(|(key, _)| *key);
+
+        let mut builder = fst::MapBuilder::memory();
+        let mut payloads = Vec::with_capacity(groups.len());
+        for (idx, (key, phrases)) in groups.iter().enumerate() {
+            builder
+                .insert(key, idx as u64)
+                .map_err(|e| format!("fst insert {}: {}", key, e))?;
+            payloads.push(
+                phrases
+                    .iter()
+                    .map(|(text, freq)| LexEntry {
+                        utf8: text.to_string(),
+                        token: 0,
+                        freq: *freq,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let fst_bytes = builder
+            .into_inner()
+            .map_err(|e| format!("fst build: {}", e))?;
+        let bincode_bytes =
+            bincode::serialize(&payloads).map_err(|e| format!("serialize bincode: {}", e))?;
+        Ok((fst_bytes, bincode_bytes))
+    }
+
+    /// Write the artifacts of `encode_fst_bincode` to `fst_path` and
+    /// `bincode_path`, creating missing parent directories.
+    pub fn write_fst_bincode<P: AsRef<std::path::Path>>(
+        fst_path: P,
+        bincode_path: P,
+        groups: &[(&str, &[(&str, u32)])],
+    ) -> Result<(), String> {
+        let (fst_bytes, bincode_bytes) = Self::encode_fst_bincode(groups)?;
+        for (path, bytes) in [
+            (fst_path.as_ref(), fst_bytes),
+            (bincode_path.as_ref(), bincode_bytes),
+        ] {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("create {}: {}", parent.display(), e))?;
+            }
+            std::fs::write(path, bytes).map_err(|e| format!("write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Load lexicon from FST + bincode artifacts without reading them fully.
+    ///
+    /// The FST is memory-mapped and payload groups are decoded from the
+    /// bincode file when a lookup needs them, so memory stays flat for large
+    /// dictionaries. Up to `cache_size` decoded groups are kept (0 disables
+    /// the cache). Prefer `load_from_fst_bincode` for small dictionaries,
+    /// where decoding everything up front is cheap and lookups are faster.
+    ///
+    /// The files must not be modified while the lexicon is alive.
+    pub fn load_from_fst_bincode_mmap<P: AsRef<std::path::Path>>(
+        fst_path: P,
+        bincode_path: P,
+        cache_size: usize,
+    ) -> Result<Self, String> {
+        let fst_path = fst_path.as_ref();
+
+        let f =
+            File::open(fst_path).map_err(|e| format!("open fst {}: {}", fst_path.display(), e))?;
+        // SAFETY: the mapping is read-only and the caller keeps the file
+        // unchanged for the lexicon's lifetime (documented above).
+        let mmap = unsafe { memmap2::Mmap::map(&f) }.map_err(|e| format!("mmap fst: {}", e))?;
+        let map =
+            Map::new(FstBytes::Mapped(Arc::new(mmap))).map_err(|e| format!("fst map: {}", e))?;
+
+        let payloads = LazyPayloads::open(bincode_path.as_ref(), cache_size)?;
 
         Ok(Self {
             map: AHashMap::new(),
             fst_map: Some(map),
-            payloads: Some(payloads),
+            payloads: Some(Payloads::Lazy(payloads)),
+            shape_map: None,
+            reverse: None,
+            merged: Vec::new(),
         })
     }
+
+    /// Load a shape-code index (`<name>.shape.fst`) next to the phonetic one.
+    ///
+    /// The artifact is an FST map, in the same format as `<name>.fst`, with
+    /// one key per (code, phrase) pair: the shape code, `SHAPE_KEY_SEPARATOR`
+    /// and the phrase (e.g. Cangjie "onf\t你"). Each key points at the
+    /// payload group holding the phrase under its phonetic key, so shape
+    /// lookups share the frequencies in `<name>.bincode`. Build it with
+    /// `fst::MapBuilder`, inserting keys in byte order. Query it with
+    /// `shape_lookup_with_freq`.
+    pub fn load_shape_index<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let buf =
+            std::fs::read(path).map_err(|e| format!("read shape fst {}: {}", path.display(), e))?;
+        let map = Map::new(FstBytes::Owned(buf)).map_err(|e| format!("shape fst map: {}", e))?;
+        self.shape_map = Some(map);
+        Ok(())
+    }
+
+    /// Whether this or a merged lexicon has a shape-code index.
+    pub fn has_shape_index(&self) -> bool {
+        self.shape_map.is_some() || self.merged.iter().any(|(other, _)| other.has_shape_index())
+    }
+
+    /// Phrases and frequencies for an exact shape code (empty without a
+    /// shape index or if the code is unknown).
+    ///
+    /// Merged lexicons' shape indexes are combined by their merge priority,
+    /// as in `lookup_with_freq`.
+    pub fn shape_lookup_with_freq(&self, code: &str) -> Vec<(String, u32)> {
+        let mut result = self.own_shape_lookup_with_freq(code);
+        for (other, priority) in &self.merged {
+            let theirs = other.shape_lookup_with_freq(code);
+            if !theirs.is_empty() {
+                result = priority.combine(result, theirs);
+            }
+        }
+        result
+    }
+
+    /// `shape_lookup_with_freq` over this lexicon's own shape index.
+    fn own_shape_lookup_with_freq(&self, code: &str) -> Vec<(String, u32)> {
+        let (Some(map), Some(payloads)) = (&self.shape_map, &self.payloads) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}{}", code, SHAPE_KEY_SEPARATOR);
+        let mut result = Vec::new();
+        let mut stream = map.range().ge(&prefix).into_stream();
+        while let Some((key, idx)) = stream.next() {
+            let Some(phrase) = key.strip_prefix(prefix.as_bytes()) else {
+                break;
+            };
+            let phrase = String::from_utf8_lossy(phrase);
+            let freq = payloads
+                .get(idx as usize)
+                .and_then(|entries| entries.iter().find(|e| e.utf8 == phrase).map(|e| e.freq));
+            // A phrase missing from its group is a dangling key
+            if let Some(freq) = freq {
+                result.push((phrase.into_owned(), freq));
+            }
+        }
+        result.sort_by(|(a, fa), (b, fb)| fb.cmp(fa).then_with(|| a.cmp(b)));
+        result
+    }
+
+    /// Number of payload groups currently decoded in memory.
+    ///
+    /// All of them for `load_from_fst_bincode`; only the cached ones for
+    /// `load_from_fst_bincode_mmap`.
+    pub fn resident_payloads(&self) -> usize {
+        self.payloads.as_ref().map_or(0, |p| p.resident())
+    }
+
+    /// Check that the FST indexes and payload groups fit together: every
+    /// phonetic and shape-code key must point at an existing payload
+    /// group, and no group may be empty. Merged lexicons are checked too.
+    ///
+    /// Lookups treat a dangling index as an unknown key, so a mismatched
+    /// `lexicon.fst` / `lexicon.bincode` pair would otherwise only show up
+    /// as missing candidates. Returns every problem found.
+    pub fn validate(&self) -> Result<(), Vec<LexiconError>> {
+        let mut errors = Vec::new();
+        let groups = self.payloads.as_ref().map_or(0, |p| p.len());
+
+        for map in [&self.fst_map, &self.shape_map].into_iter().flatten() {
+            let mut stream = map.stream();
+            while let Some((key, index)) = stream.next() {
+                if index as usize >= groups {
+                    errors.push(LexiconError::IndexOutOfRange {
+                        key: String::from_utf8_lossy(key).into_owned(),
+                        index,
+                        groups,
+                    });
+                }
+            }
+        }
+
+        if let Some(payloads) = &self.payloads {
+            payloads.for_each(|index, group| {
+                if group.is_empty() {
+                    errors.push(LexiconError::EmptyGroup { index });
+                }
+            });
+        }
+
+        for (lexicon, _) in &self.merged {
+            if let Err(merged) = lexicon.validate() {
+                errors.extend(merged);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// FST keys grouped by the payload index they point to.
+    fn keys_by_index(map: &Map<FstBytes>) -> Vec<Vec<String>> {
+        let mut keys: Vec<Vec<String>> = Vec::new();
+        let mut stream = map.stream();
+        while let Some((key, idx)) = stream.next() {
+            let idx = idx as usize;
+            if keys.len() <= idx {
+                keys.resize(idx + 1, Vec::new());
+            }
+            keys[idx].push(String::from_utf8_lossy(key).into_owned());
+        }
+        keys
+    }
 }
 
 // UserDict is implemented in `core::userdict` and exported above.
 
+/// Most splits `Model::compound_splits` enumerates for one phrase.
+const MAX_COMPOUND_SPLITS: usize = 32;
+
 /// High-level Model combining lexicon, word bigram model and user dictionary.
 ///
 /// Downstream engine implementations (lang-specific) will use this Model to
@@ -535,6 +1944,9 @@ pub struct Model {
     pub word_bigram: Arc<WordBigram>,
     pub userdict: UserDict,
     pub config: RefCell<Config>,
+    /// Exact code → phrase table (quick codes / shape codes).
+    /// Phrases for a code that matches the whole input are ranked first.
+    pub quick_codes: Arc<AHashMap<String, Vec<String>>>,
 }
 
 impl Model {
@@ -550,6 +1962,551 @@ impl Model {
             word_bigram: Arc::new(word_bigram),
             userdict,
             config: RefCell::new(config),
+            quick_codes: Arc::new(AHashMap::new()),
+        }
+    }
+
+    /// Attach a user-defined quick code table (code → phrases).
+    ///
+    /// When the whole input exactly matches a code, its phrases are surfaced
+    /// ahead of all phonetic candidates, in the order given.
+    ///
+    /// # Example
+    /// ```
+    /// # use libchinese_core::{Config, Lexicon, Model, UserDict, WordBigram};
+    /// # use std::collections::HashMap;
+    /// # let path = std::env::temp_dir().join(format!("quick_codes_doc_{}.redb", std::process::id()));
+    /// # let userdict = UserDict::new(&path).unwrap();
+    /// let mut codes = HashMap::new();
+    /// codes.insert("zg".to_string(), vec!["中国".to_string()]);
+    /// let model = Model::new(Lexicon::new(), WordBigram::new(), userdict, Config::default())
+    ///     .with_quick_codes(codes);
+    /// assert_eq!(model.quick_code_phrases("zg"), ["中国".to_string()]);
+    /// ```
+    pub fn with_quick_codes(mut self, codes: AHashMap<String, Vec<String>>) -> Self {
+        self.quick_codes = Arc::new(codes);
+        self
+    }
+
+    /// Phrases registered for an exact quick code (empty if none).
+    pub fn quick_code_phrases(&self, code: &str) -> &[String] {
+        self.quick_codes
+            .get(code)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Ways to split a phrase into two or more lexicon phrases.
+    ///
+    /// "北京大学" → [["北京", "大学"], ["北京", "大", "学"], ...]. Splits with
+    /// fewer parts come first, and among those, longer leading parts; the
+    /// phrase itself is never listed. At most `MAX_COMPOUND_SPLITS` splits
+    /// are enumerated (longest leading parts first), so a long phrase of
+    /// single-character words stays cheap. Uses the lexicon's reverse index
+    /// (see `Lexicon::build_reverse_index`).
+    pub fn compound_splits(&self, phrase: &str) -> Vec<Vec<String>> {
+        let chars: Vec<char> = phrase.chars().collect();
+        let parts_left = self.min_parts_from(&chars);
+        let mut splits = Vec::new();
+        self.collect_splits(&chars, 0, &parts_left, &mut Vec::new(), &mut splits);
+        splits.retain(|parts| parts.len() > 1);
+        splits.sort_by_key(|parts| parts.len());
+        splits
+    }
+
+    /// The split of `phrase` with the fewest lexicon phrases, preferring
+    /// longer leading parts on ties; `None` if it has no split into two or
+    /// more parts.
+    ///
+    /// A dynamic program over split points, so unlike `compound_splits` it
+    /// never enumerates alternatives.
+    pub fn best_compound_split(&self, phrase: &str) -> Option<Vec<String>> {
+        let chars: Vec<char> = phrase.chars().collect();
+        let parts_left = self.min_parts_from(&chars);
+        let n = chars.len();
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        while start < n {
+            // Fewest parts for the rest, then the longest part; the whole
+            // phrase is not a split of itself
+            let longest = if start == 0 { n - 1 } else { n - start };
+            let end = (start + 1..=start + longest)
+                .filter(|&end| {
+                    parts_left[end].is_some()
+                        && self
+                            .lexicon
+                            .contains_phrase(&chars[start..end].iter().collect::<String>())
+                })
+                .min_by_key(|&end| (parts_left[end], std::cmp::Reverse(end)))?;
+            parts.push(chars[start..end].iter().collect());
+            start = end;
+        }
+        Some(parts)
+    }
+
+    /// Fewest lexicon phrases covering `chars[i..]`, for every `i` (`None`
+    /// where no split exists; `Some(0)` at the end).
+    fn min_parts_from(&self, chars: &[char]) -> Vec<Option<usize>> {
+        let n = chars.len();
+        let mut parts_left = vec![None; n + 1];
+        parts_left[n] = Some(0);
+        for start in (0..n).rev() {
+            parts_left[start] = (start + 1..=n)
+                .filter_map(|end| {
+                    let rest = parts_left[end]?;
+                    let part: String = chars[start..end].iter().collect();
+                    self.lexicon.contains_phrase(&part).then_some(rest + 1)
+                })
+                .min();
+        }
+        parts_left
+    }
+
+    /// Depth-first over known prefixes of `chars[start..]`, longest first.
+    ///
+    /// Only descends into suffixes that can be split (`parts_left`), so each
+    /// branch ends in a split and the cap bounds the work.
+    fn collect_splits(
+        &self,
+        chars: &[char],
+        start: usize,
+        parts_left: &[Option<usize>],
+        parts: &mut Vec<String>,
+        out: &mut Vec<Vec<String>>,
+    ) {
+        if start == chars.len() {
+            out.push(parts.clone());
+            return;
+        }
+        for end in (start + 1..=chars.len()).rev() {
+            if out.len() >= MAX_COMPOUND_SPLITS {
+                return;
+            }
+            if parts_left[end].is_none() {
+                continue;
+            }
+            let part: String = chars[start..end].iter().collect();
+            if self.lexicon.contains_phrase(&part) {
+                parts.push(part);
+                self.collect_splits(chars, end, parts_left, parts, out);
+                parts.pop();
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fst_lexicon(keys: &[&str]) -> Lexicon {
+        let mut sorted = keys.to_vec();
+        sorted.sort_unstable();
+        let fst_map = Map::from_iter(sorted.iter().enumerate().map(|(i, k)| (*k, i as u64)))
+            .and_then(|m| m.map_data(FstBytes::Owned))
+            .expect("build fst");
+        let payloads = sorted.iter().map(|_| Vec::new()).collect();
+        Lexicon {
+            fst_map: Some(fst_map),
+            payloads: Some(Payloads::Eager(payloads)),
+            ..Lexicon::default()
+        }
+    }
+
+    #[test]
+    fn batch_key_check_matches_single_lookups() {
+        let mut lexicon = fst_lexicon(&["ni", "nihao", "hao", "zhongguo", "zhong"]);
+        lexicon.insert("xian", "西安");
+
+        let keys = [
+            "nihao", "ni", "nih", "zhongguo", "", "xian", "hao", "haoa", "zhon", "ni", "abc",
+        ];
+        let batch = lexicon.has_keys_batch(&keys);
+        let single: Vec<bool> = keys.iter().map(|k| lexicon.has_key(k)).collect();
+        assert_eq!(batch, single);
+        assert_eq!(
+            batch,
+            vec![true, true, false, true, false, true, true, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn prefix_enumeration_merges_fst_and_memory_keys() {
+        let mut lexicon = fst_lexicon(&["ni", "nihao", "nihaoma", "nin", "hao"]);
+        lexicon.insert("nihaoya", "你好呀");
+        lexicon.insert("ni", "你");
+
+        assert_eq!(
+            lexicon.keys_with_prefix("ni", 10),
+            vec!["ni", "nihao", "nihaoma", "nihaoya", "nin"]
+        );
+        assert_eq!(
+            lexicon.keys_with_prefix("nihao", 2),
+            vec!["nihao", "nihaoma"]
+        );
+        assert!(lexicon.keys_with_prefix("zh", 10).is_empty());
+        assert!(lexicon.keys_with_prefix("ni", 0).is_empty());
+
+        let pairs = lexicon.lookup_prefix("nihao", 10);
+        assert_eq!(
+            pairs,
+            vec![
+                ("nihao".to_string(), vec![]),
+                ("nihaoma".to_string(), vec![]),
+                ("nihaoya".to_string(), vec!["你好呀".to_string()]),
+            ]
+        );
+        assert_eq!(lexicon.lookup_prefix("ni", 1)[0].1, vec!["你".to_string()]);
+    }
+
+    #[test]
+    fn prefix_enumeration_crosses_syllable_boundaries() {
+        let mut lexicon = fst_lexicon(&["bei", "bei'fang", "bei'jing", "bei'jing'shi"]);
+        lexicon.insert("bei'jing'ren", "北京人");
+
+        assert_eq!(
+            lexicon.keys_with_prefix("beij", 10),
+            vec!["bei'jing", "bei'jing'ren", "bei'jing'shi"]
+        );
+        assert_eq!(
+            lexicon.keys_with_prefix("beijings", 10),
+            vec!["bei'jing'shi"]
+        );
+        assert_eq!(lexicon.keys_with_prefix("beij", 1), vec!["bei'jing"]);
+        assert_eq!(lexicon.keys_with_prefix("bei'f", 10), vec!["bei'fang"]);
+        assert!(lexicon.keys_with_prefix("beif'a", 10).is_empty());
+        assert_eq!(
+            lexicon.lookup_prefix("beijingr", 10),
+            vec![("bei'jing'ren".to_string(), vec!["北京人".to_string()])]
+        );
+    }
+
+    #[test]
+    fn merged_lexicons_union_lookups() {
+        let mut keys = ["ni", "yi'sheng"];
+        keys.sort_unstable();
+        let fst_map = Map::from_iter(keys.iter().enumerate().map(|(i, k)| (*k, i as u64)))
+            .and_then(|m| m.map_data(FstBytes::Owned))
+            .expect("build fst");
+        let entry = |utf8: &str, freq| LexEntry {
+            utf8: utf8.to_string(),
+            token: 0,
+            freq,
+        };
+        let base = Lexicon {
+            fst_map: Some(fst_map),
+            payloads: Some(Payloads::Eager(vec![
+                vec![entry("你", 100)],
+                vec![entry("医生", 80), entry("一声", 20)],
+            ])),
+            ..Lexicon::default()
+        };
+
+        let mut medical = Lexicon::new();
+        medical.insert("yi'sheng", "医圣");
+        medical.insert("yi'sheng", "医生");
+        medical.insert("xin'ji", "心悸");
+
+        let mut appended = base.clone();
+        appended.merge(
+            medical.clone(),
+            MergePriority {
+                prepend: false,
+                dedupe: true,
+            },
+        );
+        assert_eq!(
+            appended.lookup_with_freq("yi'sheng"),
+            vec![
+                ("医生".to_string(), 80),
+                ("一声".to_string(), 20),
+                ("医圣".to_string(), 0),
+            ]
+        );
+        assert_eq!(appended.lookup("xin'ji"), vec!["心悸"]);
+        assert_eq!(appended.lookup("ni"), vec!["你"]);
+        assert!(appended.has_key("xin'ji"));
+        assert_eq!(
+            appended.has_keys_batch(&["xin'ji", "ni", "zhong"]),
+            vec![true, true, false]
+        );
+        assert_eq!(appended.keys_with_prefix("yi", 10), vec!["yi'sheng"]);
+        appended.build_reverse_index();
+        assert_eq!(appended.reverse_lookup("心悸"), vec!["xin'ji"]);
+
+        let mut prepended = base.clone();
+        prepended.merge(
+            medical,
+            MergePriority {
+                prepend: true,
+                dedupe: false,
+            },
+        );
+        assert_eq!(
+            prepended.lookup("yi'sheng"),
+            vec!["医圣", "医生", "医生", "一声"]
+        );
+
+        // Untouched base lexicon keeps its own entries only
+        assert!(!base.has_key("xin'ji"));
+    }
+
+    #[test]
+    fn batch_key_check_without_fst() {
+        let mut lexicon = Lexicon::new();
+        lexicon.insert("ni", "你");
+        assert_eq!(lexicon.has_keys_batch(&["ni", "hao"]), vec![true, false]);
+        assert!(lexicon.has_keys_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn key_completions_extend_prefix() {
+        let mut lexicon = fst_lexicon(&["bei", "bei'jing", "bei'fang", "bei'jing'shi", "nan"]);
+        lexicon.insert("bei'ke", "贝壳");
+
+        assert_eq!(
+            lexicon.key_completions("bei", 10),
+            vec!["bei'ke", "bei'fang", "bei'jing", "bei'jing'shi"]
+        );
+        assert_eq!(
+            lexicon.key_completions("bei", 2),
+            vec!["bei'ke", "bei'fang"]
+        );
+        assert!(lexicon.key_completions("nan", 10).is_empty());
+
+        // The typed prefix may cross a syllable boundary without a separator
+        assert_eq!(
+            lexicon.key_completions("beij", 10),
+            vec!["bei'jing", "bei'jing'shi"]
+        );
+        assert_eq!(lexicon.key_completions("beijing", 10), vec!["bei'jing'shi"]);
+        assert_eq!(
+            lexicon.key_completions("bei'j", 10),
+            vec!["bei'jing", "bei'jing'shi"]
+        );
+        assert!(lexicon.key_completions("be'ij", 10).is_empty());
+    }
+
+    #[test]
+    fn homophone_groups_skip_singletons() {
+        let mut lexicon = Lexicon::new();
+        lexicon.insert("shi'shi", "实时");
+        lexicon.insert("shi'shi", "事实");
+        lexicon.insert("shi'shi", "史诗");
+        lexicon.insert("ni'hao", "你好");
+        lexicon.insert("xian", "先");
+        lexicon.insert("xian", "先");
+        lexicon.insert("xian", "线");
+
+        assert_eq!(
+            lexicon.homophone_groups(1),
+            vec![
+                (
+                    "shi'shi".to_string(),
+                    vec!["实时".to_string(), "事实".to_string(), "史诗".to_string()]
+                ),
+                ("xian".to_string(), vec!["先".to_string(), "线".to_string()]),
+            ]
+        );
+        assert_eq!(lexicon.homophone_groups(2).len(), 1);
+        assert!(lexicon.homophone_groups(3).is_empty());
+    }
+
+    #[test]
+    fn mmap_loader_decodes_payloads_on_demand() {
+        let dir = std::env::temp_dir().join(format!("libchinese_mmap_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fst_path = dir.join("lexicon.fst");
+        let bincode_path = dir.join("lexicon.bincode");
+
+        // 2000 keys with a few phrases each
+        let mut keys: Vec<String> = (0..2000).map(|i| format!("k{:04}", i)).collect();
+        keys.sort();
+        let payloads: Vec<Vec<LexEntry>> = (0..keys.len())
+            .map(|i| {
+                (0..3)
+                    .map(|j| LexEntry {
+                        utf8: format!("词{}_{}", i, j),
+                        token: 0,
+                        freq: (i * 3 + j) as u32,
+                    })
+                    .collect()
+            })
+            .collect();
+        let fst =
+            Map::from_iter(keys.iter().enumerate().map(|(i, k)| (k.as_str(), i as u64))).unwrap();
+        std::fs::write(&fst_path, fst.as_fst().as_bytes()).unwrap();
+        std::fs::write(&bincode_path, bincode::serialize(&payloads).unwrap()).unwrap();
+
+        let eager = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).unwrap();
+        let mut lazy = Lexicon::load_from_fst_bincode_mmap(&fst_path, &bincode_path, 16).unwrap();
+        assert_eq!(eager.resident_payloads(), 2000);
+        assert_eq!(lazy.resident_payloads(), 0);
+
+        for key in ["k0000", "k0042", "k1999", "k0042", "k0500", "missing"] {
+            assert_eq!(lazy.lookup_with_freq(key), eager.lookup_with_freq(key));
+        }
+        // Four distinct groups were decoded; nothing else is resident
+        assert_eq!(lazy.resident_payloads(), 4);
+
+        // Whole-lexicon scans stream the file without filling the cache
+        assert_eq!(
+            lazy.compute_total_frequency(),
+            eager.compute_total_frequency()
+        );
+        lazy.build_reverse_index();
+        assert_eq!(lazy.reverse_lookup("词42_1"), vec!["k0042"]);
+        assert_eq!(lazy.resident_payloads(), 4);
+
+        let uncached = Lexicon::load_from_fst_bincode_mmap(&fst_path, &bincode_path, 0).unwrap();
+        assert_eq!(uncached.lookup("k0001"), eager.lookup("k0001"));
+        assert_eq!(uncached.resident_payloads(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn encoded_artifacts_load_back() {
+        let (fst_bytes, bincode_bytes) = Lexicon::encode_fst_bincode(&[
+            ("ni'hao", &[("你好", 50)]),
+            ("hao", &[("好", 10), ("号", 5)]),
+        ])
+        .unwrap();
+        let lexicon = Lexicon::from_fst_bincode_bytes(fst_bytes, &bincode_bytes).unwrap();
+        assert_eq!(
+            lexicon.lookup_with_freq("hao"),
+            vec![("好".to_string(), 10), ("号".to_string(), 5)]
+        );
+        assert_eq!(lexicon.lookup("ni'hao"), vec!["你好"]);
+
+        // Keys must be distinct
+        assert!(
+            Lexicon::encode_fst_bincode(&[("hao", &[("好", 1)]), ("hao", &[("号", 1)])]).is_err()
+        );
+    }
+
+    #[test]
+    fn reverse_index_round_trips_through_file() {
+        let mut lexicon = Lexicon::new();
+        lexicon.insert("ni'hao", "你好");
+        lexicon.insert("xi'an", "西安");
+        lexicon.insert("xian", "西安");
+        lexicon.insert("xian", "先");
+
+        let path = std::env::temp_dir().join(format!(
+            "libchinese_reverse_index_{}.bin",
+            std::process::id()
+        ));
+        assert!(lexicon.save_reverse_index(&path).is_err());
+        lexicon.build_reverse_index();
+        lexicon.save_reverse_index(&path).unwrap();
+
+        // An empty lexicon answers from the loaded index alone
+        let mut loaded = Lexicon::new();
+        loaded.load_reverse_index(&path).unwrap();
+        for phrase in ["你好", "西安", "先", "不在"] {
+            assert_eq!(
+                loaded.reverse_lookup(phrase),
+                lexicon.reverse_lookup(phrase)
+            );
+        }
+        assert_eq!(loaded.reverse_lookup("西安"), vec!["xi'an", "xian"]);
+        assert!(loaded.contains_phrase("先"));
+
+        // Modifying the lexicon rebuilds the index from its own entries
+        loaded.insert("hao", "好");
+        assert!(loaded.reverse_lookup("你好").is_empty());
+        assert_eq!(loaded.reverse_lookup("好"), vec!["hao"]);
+
+        let _ = std::fs::remove_file(&path);
+        assert!(Lexicon::new().load_reverse_index(&path).is_err());
+    }
+
+    #[test]
+    fn reverse_lookup_orders_readings_by_frequency() {
+        let keys = ["hang", "hang'ye", "xing", "yin'hang"];
+        let fst_map = Map::from_iter(keys.iter().enumerate().map(|(i, k)| (*k, i as u64)))
+            .and_then(|m| m.map_data(FstBytes::Owned))
+            .expect("build fst");
+        let entry = |utf8: &str, freq| LexEntry {
+            utf8: utf8.to_string(),
+            token: 0,
+            freq,
+        };
+        let mut lexicon = Lexicon {
+            fst_map: Some(fst_map),
+            payloads: Some(Payloads::Eager(vec![
+                vec![entry("行", 40), entry("航", 30)],
+                vec![entry("行业", 50)],
+                vec![entry("行", 900), entry("星", 300)],
+                vec![entry("银行", 70)],
+            ])),
+            ..Lexicon::default()
+        };
+        // Nothing is found until the index is built
+        assert!(lexicon.reverse_lookup("行").is_empty());
+        assert!(!lexicon.contains_phrase("行"));
+        lexicon.build_reverse_index();
+
+        assert_eq!(lexicon.reverse_lookup("行"), vec!["xing", "hang"]);
+        assert_eq!(lexicon.reverse_lookup("银行"), vec!["yin'hang"]);
+        assert!(lexicon.reverse_lookup("走").is_empty());
+
+        // In-memory readings have no frequency and go last
+        lexicon.insert("heng", "行");
+        assert_eq!(lexicon.reverse_lookup("行"), vec!["xing", "hang", "heng"]);
+    }
+
+    #[test]
+    fn validate_reports_out_of_range_index_and_empty_group() {
+        let entry = |utf8: &str| LexEntry {
+            utf8: utf8.to_string(),
+            token: 0,
+            freq: 1,
+        };
+        let fst_bytes = |pairs: &[(&str, u64)]| {
+            Map::from_iter(pairs.iter().copied())
+                .expect("build fst")
+                .into_fst()
+                .into_inner()
+        };
+
+        let good = Lexicon::from_fst_bincode_bytes(
+            fst_bytes(&[("hao", 0), ("ni", 1)]),
+            &bincode::serialize(&vec![vec![entry("好")], vec![entry("你")]]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(good.validate(), Ok(()));
+
+        // "zhong" points past the two payload groups, and group 1 is empty
+        let fst = fst_bytes(&[("hao", 0), ("ni", 1), ("zhong", 5)]);
+        let bincode = bincode::serialize(&vec![vec![entry("好")], vec![]]).unwrap();
+        let expected = vec![
+            LexiconError::IndexOutOfRange {
+                key: "zhong".to_string(),
+                index: 5,
+                groups: 2,
+            },
+            LexiconError::EmptyGroup { index: 1 },
+        ];
+
+        let eager = Lexicon::from_fst_bincode_bytes(fst.clone(), &bincode).unwrap();
+        assert!(eager.lookup("zhong").is_empty());
+        assert_eq!(eager.validate(), Err(expected.clone()));
+
+        let dir = std::env::temp_dir().join(format!("lexicon_validate_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lexicon.fst"), &fst).unwrap();
+        std::fs::write(dir.join("lexicon.bincode"), &bincode).unwrap();
+        let lazy = Lexicon::load_from_fst_bincode_mmap(
+            dir.join("lexicon.fst"),
+            dir.join("lexicon.bincode"),
+            0,
+        )
+        .unwrap();
+        assert_eq!(lazy.validate(), Err(expected));
+        drop(lazy);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,326 @@
+// core/src/numeral.rs
+//
+// Chinese numeral conversion helpers.
+//
+// Used by the engine to offer numeral candidates when the input spells out a
+// number in pinyin ("yibaiershi" → 一百二十 / 120) or consists only of digits
+// ("120" → 一百二十). Controlled by `Config.numeral_candidates`.
+
+use unicode_normalization::UnicodeNormalization;
+
+const DIGITS: [&str; 10] = ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+
+/// Units inside a four-digit section (ones, tens, hundreds, thousands).
+const SECTION_UNITS: [&str; 4] = ["", "十", "百", "千"];
+
+/// Units between four-digit sections. Five sections cover the full `u64` range.
+const BIG_UNITS: [&str; 5] = ["", "万", "亿", "万亿", "亿亿"];
+
+/// Numeral syllables and the token they produce.
+///
+/// Listed longest-first so that greedy matching prefers "bai" over "ba" and
+/// "qian" over "qi"; the matcher still backtracks if a longer match dead-ends.
+const NUMERAL_SYLLABLES: &[(&str, NumeralToken)] = &[
+    ("liang", NumeralToken::Digit(2)),
+    ("qian", NumeralToken::Unit(1000)),
+    ("ling", NumeralToken::Digit(0)),
+    ("san", NumeralToken::Digit(3)),
+    ("liu", NumeralToken::Digit(6)),
+    ("jiu", NumeralToken::Digit(9)),
+    ("shi", NumeralToken::Unit(10)),
+    ("bai", NumeralToken::Unit(100)),
+    ("wan", NumeralToken::Unit(10_000)),
+    ("yi", NumeralToken::Digit(1)),
+    ("er", NumeralToken::Digit(2)),
+    ("si", NumeralToken::Digit(4)),
+    ("wu", NumeralToken::Digit(5)),
+    ("qi", NumeralToken::Digit(7)),
+    ("ba", NumeralToken::Digit(8)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumeralToken {
+    Digit(u64),
+    Unit(u64),
+}
+
+/// Convert a number to its Chinese numeral spelling.
+///
+/// Follows the usual reading rules: zeros inside a number collapse to a single
+/// 零, trailing zeros are dropped, and a number starting with 10-19 (or
+/// 10-19 万 / 亿) is written without the leading 一 (十, 十一, 十五万, ...).
+///
+/// # Example
+/// ```
+/// # use libchinese_core::numeral::to_chinese_numeral;
+/// assert_eq!(to_chinese_numeral(120), "一百二十");
+/// assert_eq!(to_chinese_numeral(10_005), "一万零五");
+/// ```
+pub fn to_chinese_numeral(n: u64) -> String {
+    if n == 0 {
+        return DIGITS[0].to_string();
+    }
+
+    // Split into four-digit sections, least significant first
+    let mut sections = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        sections.push((rest % 10_000) as u16);
+        rest /= 10_000;
+    }
+
+    let mut out = String::new();
+    let mut pending_zero = false;
+    for (idx, &section) in sections.iter().enumerate().rev() {
+        if section == 0 {
+            // An empty section between non-empty ones reads as a single 零
+            if !out.is_empty() {
+                pending_zero = true;
+            }
+            continue;
+        }
+        if !out.is_empty() && (pending_zero || section < 1000) {
+            out.push_str(DIGITS[0]);
+        }
+        // 一十二 → 十二, 一十五万 → 十五万 (only at the very start, 一百一十
+        // and 一亿零一十万 keep their 一)
+        let leading = out.is_empty();
+        push_section(section, &mut out);
+        if leading && (10..20).contains(&section) {
+            out.remove(0);
+        }
+        out.push_str(BIG_UNITS[idx]);
+        pending_zero = false;
+    }
+
+    out
+}
+
+/// Append a single four-digit section (1..=9999) without its big unit.
+fn push_section(section: u16, out: &mut String) {
+    let digits = [
+        (section / 1000) as usize,
+        (section / 100 % 10) as usize,
+        (section / 10 % 10) as usize,
+        (section % 10) as usize,
+    ];
+
+    let mut pending_zero = false;
+    let mut started = false;
+    for (pos, &d) in digits.iter().enumerate() {
+        if d == 0 {
+            pending_zero = started;
+            continue;
+        }
+        if pending_zero {
+            out.push_str(DIGITS[0]);
+            pending_zero = false;
+        }
+        out.push_str(DIGITS[d]);
+        out.push_str(SECTION_UNITS[3 - pos]);
+        started = true;
+    }
+}
+
+/// Parse pinyin that spells out a number (e.g. "yibaiershi" → 120).
+///
+/// Tone marks ("yībǎi èrshí") and tone digits ("yi1bai3") are ignored, as are
+/// apostrophes and spaces. Returns `None` if the input is not made entirely of
+/// numeral syllables, is a single syllable (too ambiguous to be worth offering)
+/// or does not form a well-ordered number.
+pub fn parse_pinyin_numeral(input: &str) -> Option<u64> {
+    let normalized = strip_tones(input);
+    if normalized.is_empty() {
+        return None;
+    }
+
+    let tokens = tokenize(&normalized)?;
+    if tokens.len() < 2 {
+        return None;
+    }
+
+    tokens_to_value(&tokens)
+}
+
+/// Both numeral forms for an input, as (Chinese, Arabic).
+///
+/// Accepts either numeral pinyin ("yibai" → ("一百", "100")) or a plain digit
+/// string ("100" → ("一百", "100")).
+pub fn numeral_forms(input: &str) -> Option<(String, String)> {
+    let value = if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+        input.parse::<u64>().ok()?
+    } else {
+        parse_pinyin_numeral(input)?
+    };
+
+    Some((to_chinese_numeral(value), value.to_string()))
+}
+
+/// Lowercase, drop combining tone marks, tone digits and separators.
+fn strip_tones(input: &str) -> String {
+    input
+        .nfd()
+        .filter(|c| !('\u{0300}'..='\u{036F}').contains(c))
+        .filter(|c| !c.is_ascii_digit() && *c != '\'' && !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Split input into numeral tokens, backtracking when a greedy match dead-ends.
+fn tokenize(input: &str) -> Option<Vec<NumeralToken>> {
+    if input.is_empty() {
+        return Some(Vec::new());
+    }
+
+    for (syllable, token) in NUMERAL_SYLLABLES {
+        if let Some(rest) = input.strip_prefix(syllable) {
+            if let Some(mut tail) = tokenize(rest) {
+                tail.insert(0, *token);
+                return Some(tail);
+            }
+        }
+    }
+
+    None
+}
+
+/// Evaluate a token sequence such as [1, 百, 2, 十] → 120.
+///
+/// Units within a 万 section must strictly decrease, two digits may only be
+/// adjacent across a 零, and a bare leading 十 counts as 一十. A trailing
+/// digit right after a unit counts in the next lower unit, as in spoken
+/// shorthand: 一百二 = 120, 三万五 = 35000.
+fn tokens_to_value(tokens: &[NumeralToken]) -> Option<u64> {
+    let mut total: u64 = 0;
+    let mut section: u64 = 0;
+    let mut digit: Option<u64> = None;
+    let mut last_unit: u64 = u64::MAX;
+    let mut after_zero = false;
+    // Unit of the previous token, if the previous token was a unit
+    let mut prev_unit: Option<u64> = None;
+    // Unit the pending digit directly follows
+    let mut digit_after: Option<u64> = None;
+
+    for token in tokens {
+        match *token {
+            NumeralToken::Digit(0) => {
+                if digit.is_some() {
+                    return None;
+                }
+                after_zero = true;
+                prev_unit = None;
+            }
+            NumeralToken::Digit(d) => {
+                if digit.is_some() {
+                    return None;
+                }
+                digit = Some(d);
+                digit_after = prev_unit.take();
+            }
+            NumeralToken::Unit(10_000) => {
+                section += digit.take().unwrap_or(0);
+                if section == 0 {
+                    return None;
+                }
+                total = total.checked_add(section.checked_mul(10_000)?)?;
+                section = 0;
+                last_unit = u64::MAX;
+                after_zero = false;
+                prev_unit = Some(10_000);
+            }
+            NumeralToken::Unit(unit) => {
+                if unit >= last_unit {
+                    return None;
+                }
+                let d = match digit.take() {
+                    Some(d) => d,
+                    // 十二 = 12, but 百 / 千 need an explicit digit
+                    None if unit == 10 && section == 0 && !after_zero => 1,
+                    None => return None,
+                };
+                section += d * unit;
+                last_unit = unit;
+                after_zero = false;
+                prev_unit = Some(unit);
+            }
+        }
+    }
+
+    if let Some(d) = digit {
+        // 一百二 → 一百二十, 三万五 → 三万五千; 一百零二 stays 102
+        section += d * digit_after.map_or(1, |unit| unit / 10);
+    }
+    total.checked_add(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chinese_numeral_spelling() {
+        assert_eq!(to_chinese_numeral(0), "零");
+        assert_eq!(to_chinese_numeral(7), "七");
+        assert_eq!(to_chinese_numeral(10), "十");
+        assert_eq!(to_chinese_numeral(15), "十五");
+        assert_eq!(to_chinese_numeral(100), "一百");
+        assert_eq!(to_chinese_numeral(110), "一百一十");
+        assert_eq!(to_chinese_numeral(120), "一百二十");
+        assert_eq!(to_chinese_numeral(1005), "一千零五");
+        assert_eq!(to_chinese_numeral(10_005), "一万零五");
+        assert_eq!(to_chinese_numeral(20_300), "二万零三百");
+        assert_eq!(to_chinese_numeral(100_000_000), "一亿");
+    }
+
+    #[test]
+    fn leading_yi_shi_dropped_in_big_sections() {
+        assert_eq!(to_chinese_numeral(100_000), "十万");
+        assert_eq!(to_chinese_numeral(150_000), "十五万");
+        assert_eq!(to_chinese_numeral(1_000_000_000), "十亿");
+        assert_eq!(to_chinese_numeral(1_100_000), "一百一十万");
+        assert_eq!(to_chinese_numeral(100_100_000), "一亿零一十万");
+    }
+
+    #[test]
+    fn parse_numeral_pinyin() {
+        assert_eq!(parse_pinyin_numeral("yibai"), Some(100));
+        assert_eq!(parse_pinyin_numeral("yibaiershi"), Some(120));
+        assert_eq!(parse_pinyin_numeral("yibaièrshí"), Some(120));
+        assert_eq!(parse_pinyin_numeral("yi1bai3er4shi2"), Some(120));
+        assert_eq!(parse_pinyin_numeral("shier"), Some(12));
+        assert_eq!(parse_pinyin_numeral("liangqianlingwu"), Some(2005));
+        assert_eq!(parse_pinyin_numeral("sanwanwuqian"), Some(35_000));
+    }
+
+    #[test]
+    fn parse_trailing_digit_after_unit() {
+        assert_eq!(parse_pinyin_numeral("yibaier"), Some(120));
+        assert_eq!(parse_pinyin_numeral("yiqianwu"), Some(1500));
+        assert_eq!(parse_pinyin_numeral("sanwanwu"), Some(35_000));
+        assert_eq!(parse_pinyin_numeral("shiwu"), Some(15));
+        assert_eq!(parse_pinyin_numeral("yibailinger"), Some(102));
+        assert_eq!(parse_pinyin_numeral("yiwanlingwu"), Some(10_005));
+    }
+
+    #[test]
+    fn parse_rejects_non_numerals() {
+        assert_eq!(parse_pinyin_numeral("nihao"), None);
+        assert_eq!(parse_pinyin_numeral("yi"), None);
+        assert_eq!(parse_pinyin_numeral("yier"), None);
+        assert_eq!(parse_pinyin_numeral("shibai"), None);
+        assert_eq!(parse_pinyin_numeral("bai"), None);
+    }
+
+    #[test]
+    fn numeral_forms_offers_both_spellings() {
+        assert_eq!(
+            numeral_forms("yibaièrshí"),
+            Some(("一百二十".to_string(), "120".to_string()))
+        );
+        assert_eq!(
+            numeral_forms("120"),
+            Some(("一百二十".to_string(), "120".to_string()))
+        );
+        assert_eq!(numeral_forms("ni"), None);
+    }
+}
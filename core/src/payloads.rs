@@ -0,0 +1,186 @@
+//! Storage behind `Lexicon`'s FST index and payload groups.
+//!
+//! `Lexicon::load_from_fst_bincode` reads both files into memory.
+//! `Lexicon::load_from_fst_bincode_mmap` maps the FST instead and decodes
+//! payload groups from the bincode file on demand: loading only records the
+//! file offset of every group, and an optional LRU keeps recently used groups
+//! decoded.
+
+use crate::LexEntry;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Bytes of the lexicon FST, read into memory or memory-mapped.
+#[derive(Clone)]
+pub(crate) enum FstBytes {
+    Owned(Vec<u8>),
+    Mapped(Arc<Mmap>),
+}
+
+impl AsRef<[u8]> for FstBytes {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            FstBytes::Owned(bytes) => bytes,
+            FstBytes::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// Payload groups (FST value → entries) of a lexicon.
+#[derive(Debug, Clone)]
+pub(crate) enum Payloads {
+    /// Every group decoded at load time
+    Eager(Vec<Vec<LexEntry>>),
+    /// Groups decoded from the bincode file when requested
+    Lazy(LazyPayloads),
+}
+
+impl Payloads {
+    /// The entries of one group.
+    pub(crate) fn get(&self, index: usize) -> Option<Cow<'_, [LexEntry]>> {
+        match self {
+            Payloads::Eager(groups) => groups.get(index).map(|g| Cow::Borrowed(g.as_slice())),
+            Payloads::Lazy(lazy) => lazy.get(index).map(|g| Cow::Owned(g.to_vec())),
+        }
+    }
+
+    /// Visit every group in index order (lazy groups are streamed, not cached).
+    pub(crate) fn for_each(&self, mut f: impl FnMut(usize, &[LexEntry])) {
+        match self {
+            Payloads::Eager(groups) => {
+                for (index, group) in groups.iter().enumerate() {
+                    f(index, group);
+                }
+            }
+            Payloads::Lazy(lazy) => lazy.for_each(f),
+        }
+    }
+
+    /// Number of groups, decoded or not.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Payloads::Eager(groups) => groups.len(),
+            Payloads::Lazy(lazy) => lazy.offsets.len(),
+        }
+    }
+
+    /// Number of groups currently decoded in memory.
+    pub(crate) fn resident(&self) -> usize {
+        match self {
+            Payloads::Eager(groups) => groups.len(),
+            Payloads::Lazy(lazy) => lazy.resident(),
+        }
+    }
+}
+
+/// Payload groups read from a bincode `Vec<Vec<LexEntry>>` file on demand.
+#[derive(Clone)]
+pub(crate) struct LazyPayloads {
+    /// File offset of each group's length prefix
+    offsets: Arc<[u64]>,
+    state: Arc<Mutex<LazyState>>,
+}
+
+struct LazyState {
+    reader: BufReader<File>,
+    cache: Option<lru::LruCache<usize, Arc<Vec<LexEntry>>>>,
+}
+
+impl std::fmt::Debug for LazyPayloads {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyPayloads")
+            .field("groups", &self.offsets.len())
+            .field("resident", &self.resident())
+            .finish()
+    }
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+impl LazyPayloads {
+    /// Index the groups of a bincode payload file, keeping up to
+    /// `cache_size` decoded groups (0 disables caching).
+    ///
+    /// Only length prefixes are read: bincode writes each `Vec` and `String`
+    /// as a little-endian u64 length, and a `LexEntry` ends with two u32s.
+    pub(crate) fn open(path: &Path, cache_size: usize) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("open bincode {}: {}", path.display(), e))?;
+        let mut reader = BufReader::new(file);
+        let err = |e: std::io::Error| format!("index bincode {}: {}", path.display(), e);
+
+        let groups = read_u64(&mut reader).map_err(err)?;
+        let mut offsets = Vec::new();
+        let mut pos = 8u64;
+        for _ in 0..groups {
+            offsets.push(pos);
+            let entries = read_u64(&mut reader).map_err(err)?;
+            pos += 8;
+            for _ in 0..entries {
+                let text_len = read_u64(&mut reader).map_err(err)?;
+                // Skip the text plus token and freq
+                let skip = text_len.saturating_add(8);
+                reader.seek_relative(skip as i64).map_err(err)?;
+                pos += 8 + skip;
+            }
+        }
+
+        Ok(Self {
+            offsets: offsets.into(),
+            state: Arc::new(Mutex::new(LazyState {
+                reader,
+                cache: NonZeroUsize::new(cache_size).map(lru::LruCache::new),
+            })),
+        })
+    }
+
+    fn get(&self, index: usize) -> Option<Arc<Vec<LexEntry>>> {
+        let offset = *self.offsets.get(index)?;
+        let mut state = self.state.lock().ok()?;
+        if let Some(group) = state.cache.as_mut().and_then(|c| c.get(&index)) {
+            return Some(group.clone());
+        }
+
+        state.reader.seek(SeekFrom::Start(offset)).ok()?;
+        let group: Vec<LexEntry> = bincode::deserialize_from(&mut state.reader).ok()?;
+        let group = Arc::new(group);
+        if let Some(cache) = state.cache.as_mut() {
+            cache.put(index, group.clone());
+        }
+        Some(group)
+    }
+
+    fn for_each(&self, mut f: impl FnMut(usize, &[LexEntry])) {
+        let Some(&first) = self.offsets.first() else {
+            return;
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.reader.seek(SeekFrom::Start(first)).is_err() {
+            return;
+        }
+        for index in 0..self.offsets.len() {
+            match bincode::deserialize_from::<_, Vec<LexEntry>>(&mut state.reader) {
+                Ok(group) => f(index, &group),
+                Err(_) => return,
+            }
+        }
+    }
+
+    fn resident(&self) -> usize {
+        self.state
+            .lock()
+            .map(|s| s.cache.as_ref().map_or(0, |c| c.len()))
+            .unwrap_or(0)
+    }
+}
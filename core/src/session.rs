@@ -4,7 +4,7 @@
 //! composition, candidates) into a cohesive session that tracks state across
 //! multiple key events.
 
-use crate::candidate::CandidateList;
+use crate::candidate::{CandidateList, DEFAULT_PAGE_SIZE};
 use crate::composition::Composition;
 use crate::context::ImeContext;
 use crate::input_buffer::InputBuffer;
@@ -24,6 +24,45 @@ pub enum InputMode {
     Passthrough,
 }
 
+/// Usage counters accumulated over an IME session.
+///
+/// Updated by `ImeEngine::process_key` for every key the IME handles and
+/// cleared by `ImeEngine::reset`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    /// Keys handled by the IME
+    pub keystrokes: usize,
+    /// Commits of any kind (candidate, raw input, punctuation)
+    pub commits: usize,
+    /// Backspace presses handled by the IME
+    pub backspaces: usize,
+    /// Commits made by picking a candidate (Space, Enter or a number key)
+    pub selections: usize,
+    /// Sum of the 0-based ranks of the picked candidates
+    pub selected_rank_total: usize,
+}
+
+impl SessionStats {
+    /// Average 0-based rank of the picked candidates, or None if nothing
+    /// was picked yet.
+    pub fn average_selected_rank(&self) -> Option<f32> {
+        if self.selections == 0 {
+            None
+        } else {
+            Some(self.selected_rank_total as f32 / self.selections as f32)
+        }
+    }
+
+    /// Record a commit, with the rank of the picked candidate if any.
+    pub fn record_commit(&mut self, selected_rank: Option<usize>) {
+        self.commits += 1;
+        if let Some(rank) = selected_rank {
+            self.selections += 1;
+            self.selected_rank_total += rank;
+        }
+    }
+}
+
 /// IME session state combining all session components.
 ///
 /// This struct manages the state across multiple key events. It contains:
@@ -50,6 +89,12 @@ pub struct ImeSession {
 
     /// Whether the session is active (has state)
     active: bool,
+
+    /// Usage statistics (kept across commits)
+    stats: SessionStats,
+
+    /// Last character committed to (or passed through to) the application
+    last_committed: Option<char>,
 }
 
 impl ImeSession {
@@ -58,9 +103,11 @@ impl ImeSession {
         Self {
             input_buffer: InputBuffer::new(),
             composition: Composition::new(),
-            candidates: CandidateList::with_page_size(5),
+            candidates: CandidateList::with_page_size(DEFAULT_PAGE_SIZE),
             mode: InputMode::Init,
             active: false,
+            stats: SessionStats::default(),
+            last_committed: None,
         }
     }
 
@@ -72,6 +119,8 @@ impl ImeSession {
             candidates: CandidateList::with_page_size(page_size),
             mode: InputMode::Init,
             active: false,
+            stats: SessionStats::default(),
+            last_committed: None,
         }
     }
 
@@ -125,7 +174,37 @@ impl ImeSession {
         self.active = true;
     }
 
+    /// Get the session statistics.
+    pub fn stats(&self) -> &SessionStats {
+        &self.stats
+    }
+
+    /// Get a mutable reference to the session statistics.
+    pub fn stats_mut(&mut self) -> &mut SessionStats {
+        &mut self.stats
+    }
+
+    /// Reset the session statistics.
+    pub fn reset_stats(&mut self) {
+        self.stats = SessionStats::default();
+    }
+
+    /// Last character the application received, if known.
+    ///
+    /// Used to pick context-sensitive punctuation forms. Kept across `clear`.
+    pub fn last_committed_char(&self) -> Option<char> {
+        self.last_committed
+    }
+
+    /// Record the last character the application received.
+    pub fn set_last_committed_char(&mut self, ch: Option<char>) {
+        self.last_committed = ch;
+    }
+
     /// Clear all session state and return to Init mode.
+    ///
+    /// Statistics and the last committed character are kept; use
+    /// `reset_stats` to clear the statistics.
     pub fn clear(&mut self) {
         self.input_buffer.clear();
         self.composition.clear();
@@ -136,9 +215,63 @@ impl ImeSession {
 
     /// Update the composition from the current input buffer.
     /// This is typically called after the input buffer changes.
+    ///
+    /// The preedit caret follows the input buffer cursor.
     pub fn update_composition_from_input(&mut self) {
         let input_text = self.input_buffer.text().to_string();
-        self.composition = Composition::from_text(input_text);
+        self.update_composition_from_display(input_text);
+    }
+
+    /// Like `update_composition_from_input`, showing `display` in the
+    /// preedit: the input converted char for char (e.g. zhuyin keys as
+    /// bopomofo, see `SyllableParser::display_input`).
+    pub fn update_composition_from_display(&mut self, display: String) {
+        self.composition = Composition::with_cursor(display, 0);
+        self.composition.cursor = self.preedit_offset(self.input_buffer.cursor());
+
+        // Pinned syllable boundaries split the preedit into segments
+        let boundaries: Vec<usize> = self
+            .input_buffer
+            .boundaries()
+            .iter()
+            .map(|&b| self.preedit_offset(b))
+            .collect();
+        if !boundaries.is_empty() {
+            let mut start = 0;
+            for end in boundaries {
+                self.composition.add_segment(start..end, false);
+                start = end;
+            }
+            self.composition
+                .add_segment(start..self.composition.preedit.len(), false);
+        }
+    }
+
+    /// Preedit byte offset for a byte offset in the input buffer; the
+    /// preedit has one char per input char.
+    fn preedit_offset(&self, input_offset: usize) -> usize {
+        let chars = self.input_buffer.text()[..input_offset].chars().count();
+        let preedit = &self.composition.preedit;
+        preedit
+            .char_indices()
+            .nth(chars)
+            .map_or(preedit.len(), |(i, _)| i)
+    }
+
+    /// Move the preedit caret one character left.
+    /// Returns true if the caret moved.
+    pub fn move_caret_left(&mut self) -> bool {
+        let moved = self.input_buffer.move_left();
+        self.composition.cursor = self.preedit_offset(self.input_buffer.cursor());
+        moved
+    }
+
+    /// Move the preedit caret one character right.
+    /// Returns true if the caret moved.
+    pub fn move_caret_right(&mut self) -> bool {
+        let moved = self.input_buffer.move_right();
+        self.composition.cursor = self.preedit_offset(self.input_buffer.cursor());
+        moved
     }
 
     /// Sync session state to an ImeContext for platform communication.
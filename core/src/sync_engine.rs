@@ -0,0 +1,138 @@
+//! Thread-safe engine for concurrent lookups.
+//!
+//! `Engine` keeps its config, result cache and statistics in `RefCell`s, so it
+//! cannot be shared between threads. `SyncEngine` holds the read-only parts of
+//! a model (lexicon, word bigrams, user dictionary, quick codes) and the
+//! parser behind `Arc`s, and the config behind an `RwLock`. Each lookup runs
+//! on a short-lived `Engine` built from those shared parts, so the lexicon
+//! and n-gram data are only ever read; the config lock is taken for reading
+//! once per engine, and for writing only by `set_config`.
+//!
+//! The short-lived engines are built with `max_cache_size` 0, so they do not
+//! allocate a result cache that would be dropped after one lookup. They also
+//! start without any per-engine state: no emoji lexicon
+//! (`Engine::load_emoji_lexicon`), no commit filter
+//! (`Engine::set_commit_filter`) and the default candidate limit. Lookups
+//! through a `SyncEngine` therefore never include emoji candidates.
+//!
+//! Use it for batch work such as converting a document; interactive input
+//! should keep using `Engine`, which caches results between keystrokes.
+
+use crate::engine::{Engine, SyllableParser};
+use crate::{Candidate, Config, Lexicon, Model, UserDict, WordBigram};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// An engine that can be shared between threads.
+pub struct SyncEngine<P> {
+    lexicon: Arc<Lexicon>,
+    word_bigram: Arc<WordBigram>,
+    userdict: UserDict,
+    quick_codes: Arc<HashMap<String, Vec<String>>>,
+    config: RwLock<Config>,
+    parser: Arc<P>,
+}
+
+impl<P: SyllableParser> SyncEngine<P> {
+    /// Create a thread-safe engine from a model and parser.
+    pub fn new(model: Model, parser: P) -> Self {
+        Self {
+            lexicon: model.lexicon,
+            word_bigram: model.word_bigram,
+            userdict: model.userdict,
+            quick_codes: model.quick_codes,
+            config: RwLock::new(model.config.into_inner()),
+            parser: Arc::new(parser),
+        }
+    }
+
+    /// Snapshot of the current configuration.
+    pub fn config(&self) -> Config {
+        self.config
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replace the configuration. Lookups already running keep the
+    /// configuration they started with.
+    pub fn set_config(&self, config: Config) {
+        *self
+            .config
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = config;
+    }
+
+    /// A single-threaded engine over the shared data and the current config,
+    /// without a result cache (see the module docs).
+    fn engine(&self) -> Engine<Arc<P>> {
+        let mut config = self.config();
+        config.max_cache_size = 0;
+        let model = Model {
+            lexicon: Arc::clone(&self.lexicon),
+            word_bigram: Arc::clone(&self.word_bigram),
+            userdict: self.userdict.clone(),
+            config: RefCell::new(config),
+            quick_codes: Arc::clone(&self.quick_codes),
+        };
+        Engine::new(model, Arc::clone(&self.parser))
+    }
+
+    /// Candidates for one input (see `Engine::input`).
+    pub fn input(&self, input: &str) -> Vec<Candidate> {
+        self.engine().input(input)
+    }
+
+    /// Candidates for each input, in order.
+    ///
+    /// With the `parallel` feature (on by default) the inputs are spread
+    /// over rayon's thread pool.
+    ///
+    /// # Example
+    /// ```
+    /// # use libchinese_core::{Config, Lexicon, Model, SyncEngine, UserDict, WordBigram};
+    /// # use libchinese_core::{SyllableParser, SyllableType};
+    /// # struct Whole;
+    /// # struct Word(String);
+    /// # impl SyllableType for Word {
+    /// #     fn text(&self) -> &str { &self.0 }
+    /// #     fn is_fuzzy(&self) -> bool { false }
+    /// # }
+    /// # impl SyllableParser for Whole {
+    /// #     type Syllable = Word;
+    /// #     fn segment_top_k(&self, input: &str, _k: usize, _fuzzy: bool) -> Vec<Vec<Word>> {
+    /// #         vec![vec![Word(input.to_string())]]
+    /// #     }
+    /// # }
+    /// let mut lexicon = Lexicon::new();
+    /// lexicon.insert("zhong", "中");
+    /// lexicon.insert("wen", "文");
+    /// let userdict = UserDict::in_memory().unwrap();
+    /// let model = Model::new(lexicon, WordBigram::new(), userdict, Config::default());
+    /// let engine = SyncEngine::new(model, Whole);
+    ///
+    /// let inputs = vec!["zhong".to_string(), "wen".to_string()];
+    /// let results = engine.candidates_batch(&inputs);
+    /// assert_eq!(results[0][0].text, "中");
+    /// assert_eq!(results[1][0].text, "文");
+    /// ```
+    pub fn candidates_batch(&self, inputs: &[String]) -> Vec<Vec<Candidate>>
+    where
+        P: Send + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            inputs
+                .par_iter()
+                .map_init(|| self.engine(), |engine, input| engine.input(input))
+                .collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            let engine = self.engine();
+            inputs.iter().map(|input| engine.input(input)).collect()
+        }
+    }
+}
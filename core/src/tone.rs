@@ -0,0 +1,140 @@
+// core/src/tone.rs
+//
+// Tone digits on phonetic syllables ("ma3", "ma5").
+//
+// Tones are numbered 1-4, with the neutral (fifth) tone canonically written
+// as 5. Users also type it as 0 or leave it off entirely; both spellings
+// normalize to `NEUTRAL_TONE`, while a syllable without a digit carries no
+// tone at all and matches every reading.
+
+/// Canonical digit for the neutral (light) tone.
+pub const NEUTRAL_TONE: u8 = 5;
+
+/// Normalize a typed tone digit: 1-4 stay as they are, 0 and 5 become
+/// `NEUTRAL_TONE`, anything else is not a tone.
+pub fn normalize_tone(digit: u8) -> Option<u8> {
+    match digit {
+        1..=4 => Some(digit),
+        0 | 5 => Some(NEUTRAL_TONE),
+        _ => None,
+    }
+}
+
+/// Split a trailing tone digit off a syllable.
+///
+/// `"ma3"` → `("ma", Some(3))`, `"ma0"` → `("ma", Some(5))`,
+/// `"ma"` → `("ma", None)`. A digit that is not a tone (6-9) is left on the
+/// syllable.
+pub fn split_tone(syllable: &str) -> (&str, Option<u8>) {
+    let Some(last) = syllable.chars().last() else {
+        return (syllable, None);
+    };
+    let tone = last.to_digit(10).and_then(|d| normalize_tone(d as u8));
+    match tone {
+        Some(tone) => (&syllable[..syllable.len() - 1], Some(tone)),
+        None => (syllable, None),
+    }
+}
+
+/// Split a tone-marked pinyin vowel into its bare vowel and tone.
+///
+/// `'ǎ'` → `Some(('a', 3))`, `'ǘ'` → `Some(('ü', 2))`. Unmarked characters,
+/// including a bare `'ü'`, give `None`.
+pub fn strip_tone_mark(c: char) -> Option<(char, u8)> {
+    const MARKED: [(char, [char; 4]); 6] = [
+        ('a', ['ā', 'á', 'ǎ', 'à']),
+        ('e', ['ē', 'é', 'ě', 'è']),
+        ('i', ['ī', 'í', 'ǐ', 'ì']),
+        ('o', ['ō', 'ó', 'ǒ', 'ò']),
+        ('u', ['ū', 'ú', 'ǔ', 'ù']),
+        ('ü', ['ǖ', 'ǘ', 'ǚ', 'ǜ']),
+    ];
+    let lower = c.to_lowercase().next()?;
+    MARKED.iter().find_map(|(base, marks)| {
+        let tone = marks.iter().position(|&m| m == lower)?;
+        Some((*base, tone as u8 + 1))
+    })
+}
+
+/// Check whether a typed tone accepts a stored tone.
+///
+/// No typed tone accepts anything. A typed neutral tone accepts any stored
+/// tone when `neutral_matches_any` is set (see
+/// `Config::neutral_tone_matches_any`), otherwise only the neutral tone.
+/// Other tones must match exactly; an untoned stored reading accepts any
+/// typed tone.
+pub fn tone_matches(typed: Option<u8>, stored: Option<u8>, neutral_matches_any: bool) -> bool {
+    match (typed, stored) {
+        (None, _) | (_, None) => true,
+        (Some(NEUTRAL_TONE), _) if neutral_matches_any => true,
+        (Some(t), Some(s)) => Some(t) == normalize_tone(s),
+    }
+}
+
+/// Filter tone-tagged readings (e.g. `["ma1", "ma2"]`) down to those a typed
+/// syllable (e.g. `"ma5"`) accepts. Readings of other syllables never match.
+pub fn matching_readings<'a>(
+    typed: &str,
+    readings: &[&'a str],
+    neutral_matches_any: bool,
+) -> Vec<&'a str> {
+    let (base, tone) = split_tone(typed);
+    readings
+        .iter()
+        .copied()
+        .filter(|reading| {
+            let (reading_base, reading_tone) = split_tone(reading);
+            reading_base == base && tone_matches(tone, reading_tone, neutral_matches_any)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MA: [&str; 5] = ["ma1", "ma2", "ma3", "ma4", "ma5"];
+
+    #[test]
+    fn neutral_tone_spellings_normalize() {
+        assert_eq!(split_tone("ma5"), ("ma", Some(NEUTRAL_TONE)));
+        assert_eq!(split_tone("ma0"), ("ma", Some(NEUTRAL_TONE)));
+        assert_eq!(split_tone("ma1"), ("ma", Some(1)));
+        assert_eq!(split_tone("ma"), ("ma", None));
+        assert_eq!(split_tone("ma7"), ("ma7", None));
+    }
+
+    #[test]
+    fn tone_marks_split_into_vowel_and_tone() {
+        assert_eq!(strip_tone_mark('ǎ'), Some(('a', 3)));
+        assert_eq!(strip_tone_mark('Ó'), Some(('o', 2)));
+        assert_eq!(strip_tone_mark('ǜ'), Some(('ü', 4)));
+        assert_eq!(strip_tone_mark('a'), None);
+        assert_eq!(strip_tone_mark('ü'), None);
+    }
+
+    #[test]
+    fn neutral_tone_matches_all_readings() {
+        assert_eq!(matching_readings("ma5", &MA, true), MA.to_vec());
+        assert_eq!(matching_readings("ma0", &MA, true), MA.to_vec());
+        assert_eq!(matching_readings("ma", &MA, true), MA.to_vec());
+        assert_eq!(matching_readings("ma1", &MA, true), vec!["ma1"]);
+    }
+
+    #[test]
+    fn strict_neutral_tone_only_matches_neutral() {
+        assert_eq!(matching_readings("ma5", &MA, false), vec!["ma5"]);
+        assert_eq!(matching_readings("ma0", &MA, false), vec!["ma5"]);
+        assert_eq!(matching_readings("ma3", &MA, false), vec!["ma3"]);
+        assert!(matching_readings("ma1", &["mi1", "mo1"], false).is_empty());
+    }
+
+    #[test]
+    fn default_config_lets_neutral_match_any() {
+        let config = crate::Config::default();
+        assert_eq!(
+            matching_readings("ma5", &MA, config.neutral_tone_matches_any),
+            MA.to_vec()
+        );
+    }
+}
@@ -52,6 +52,16 @@ impl TrieNode {
         node.is_end
     }
 
+    /// Number of words stored in the trie.
+    pub fn word_count(&self) -> usize {
+        let own = usize::from(self.is_end);
+        own + self
+            .children
+            .values()
+            .map(|child| child.word_count())
+            .sum::<usize>()
+    }
+
     /// Walk the trie starting at a position in `input` and return all matched
     /// prefixes.
     ///
@@ -88,4 +98,60 @@ impl TrieNode {
         }
         res
     }
+
+    /// Find the shortest word that strictly extends `prefix`.
+    ///
+    /// Used for incomplete input such as "zh" → "zha". Ties between words of
+    /// equal length are broken alphabetically so the result is deterministic.
+    pub fn first_completion(&self, prefix: &str) -> Option<String> {
+        let mut node = self;
+        for ch in prefix.chars() {
+            node = node.children.get(&ch)?;
+        }
+
+        let mut level: Vec<&TrieNode> = node.children.values().map(|c| c.as_ref()).collect();
+        while !level.is_empty() {
+            let best = level
+                .iter()
+                .filter(|n| n.is_end)
+                .filter_map(|n| n.word.as_ref())
+                .min();
+            if let Some(word) = best {
+                return Some(word.clone());
+            }
+            level = level
+                .iter()
+                .flat_map(|n| n.children.values().map(|c| c.as_ref()))
+                .collect();
+        }
+        None
+    }
+
+    /// All words strictly extending `prefix`, shortest first, then
+    /// alphabetically.
+    pub fn completions(&self, prefix: &str) -> Vec<String> {
+        let mut node = self;
+        for ch in prefix.chars() {
+            match node.children.get(&ch) {
+                Some(child) => node = child,
+                None => return Vec::new(),
+            }
+        }
+
+        let mut words = Vec::new();
+        let mut stack: Vec<&TrieNode> = node.children.values().map(|c| c.as_ref()).collect();
+        while let Some(n) = stack.pop() {
+            if let Some(word) = n.word.as_ref().filter(|_| n.is_end) {
+                words.push(word.clone());
+            }
+            stack.extend(n.children.values().map(|c| c.as_ref()));
+        }
+        words.sort_by(|a, b| {
+            a.chars()
+                .count()
+                .cmp(&b.chars().count())
+                .then_with(|| a.cmp(b))
+        });
+        words
+    }
 }
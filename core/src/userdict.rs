@@ -1,10 +1,107 @@
 //! Redb-first UserDict implementation for core.
 //!
 //! This file exports `UserDict` whose public API is small and test-friendly.
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use redb::backends::InMemoryBackend;
 use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+
+/// How `UserDict::merge_from` combines counts present in both dictionaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Add the counts (same as having learned everything on one device)
+    Sum,
+    /// Keep the larger count
+    Max,
+    /// Mean of both counts, rounded up
+    Average,
+}
+
+impl MergePolicy {
+    /// Combine a local and an incoming count.
+    pub fn combine(self, local: u64, incoming: u64) -> u64 {
+        match self {
+            MergePolicy::Sum => local.saturating_add(incoming),
+            MergePolicy::Max => local.max(incoming),
+            MergePolicy::Average => local.saturating_add(incoming).div_ceil(2),
+        }
+    }
+}
+
+/// How `UserDict::import_snapshot` treats what the dictionary already has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add the snapshot's counts to the existing ones; existing learned
+    /// segmentations win, and the later last-used time is kept
+    Merge,
+    /// Clear the dictionary, then load the snapshot
+    Replace,
+}
+
+/// Snapshot format written by `UserDict::export_snapshot`.
+///
+/// Version 2 added `punctuation`.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Everything a `UserDict` has learned, in a serde-friendly form for backups
+/// and migration (e.g. as JSON).
+///
+/// Missing fields deserialize as empty, so snapshots from older versions
+/// still import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserDictSnapshot {
+    /// Format version (`SNAPSHOT_VERSION` when exported)
+    pub version: u32,
+    /// Phrase → learned count
+    pub phrases: BTreeMap<String, u64>,
+    /// (w1, w2, count) for each learned bigram
+    pub bigrams: Vec<(String, String, u64)>,
+    /// Input → learned segmentation, syllables joined with `'`
+    pub segmentations: BTreeMap<String, String>,
+    /// (input, phrase, count) for each rejected candidate
+    pub rejections: Vec<(String, String, u64)>,
+    /// Phrase → Unix time (seconds) last learned
+    pub last_used: BTreeMap<String, u64>,
+    /// (w1, w2, Unix time) each bigram was last learned
+    pub bigram_last_used: Vec<(String, String, u64)>,
+    /// (punctuation key, chosen form, count) for each learned selection
+    pub punctuation: Vec<(String, String, u64)>,
+}
+
+/// Age at which a phrase's recency weight halves (14 days, in seconds).
+///
+/// See `UserDict::recency_weighted_frequency`.
+pub const RECENCY_HALF_LIFE_SECS: u64 = 14 * 24 * 60 * 60;
+
+/// Current wall-clock time.
+///
+/// `SystemTime::now` panics on wasm32-unknown-unknown, so the browser clock
+/// is read there instead.
+pub(crate) fn system_now() -> SystemTime {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64)
+    }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    {
+        SystemTime::now()
+    }
+}
+
+/// Current Unix time in seconds (0 if the clock is before the epoch).
+pub(crate) fn unix_now() -> u64 {
+    system_now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A learned bigram (w1, w2) with its count.
+type BigramCount = ((String, String), u64);
 
 /// UserDict backed by `redb`.
 #[derive(Clone, Debug)]
@@ -24,6 +121,14 @@ impl UserDict {
         })
     }
 
+    /// Create a user dict kept in memory only, for hosts without a
+    /// filesystem (e.g. the browser). Nothing is persisted; use
+    /// `export_snapshot` / `import_snapshot` to carry learned data over.
+    pub fn in_memory() -> Result<Self, redb::Error> {
+        let db = Database::builder().create_with_backend(InMemoryBackend::new())?;
+        Ok(UserDict { db: Arc::new(db) })
+    }
+
     fn table_def() -> TableDefinition<'static, &'static str, u64> {
         TableDefinition::new("user_dict")
     }
@@ -32,6 +137,29 @@ impl UserDict {
         TableDefinition::new("user_bigram")
     }
 
+    fn segmentation_table_def() -> TableDefinition<'static, &'static str, &'static str> {
+        TableDefinition::new("user_segmentation")
+    }
+
+    fn rejection_table_def() -> TableDefinition<'static, &'static str, u64> {
+        TableDefinition::new("user_rejection")
+    }
+
+    /// Punctuation selections, keyed "key\0choice" like bigrams
+    fn punctuation_table_def() -> TableDefinition<'static, &'static str, u64> {
+        TableDefinition::new("user_punctuation")
+    }
+
+    /// Phrase → Unix time (seconds) it was last learned.
+    fn last_used_table_def() -> TableDefinition<'static, &'static str, u64> {
+        TableDefinition::new("user_last_used")
+    }
+
+    /// Bigram key ("w1\0w2") → Unix time (seconds) it was last learned.
+    fn bigram_last_used_table_def() -> TableDefinition<'static, &'static str, u64> {
+        TableDefinition::new("user_bigram_last_used")
+    }
+
     /// Encode bigram key as "w1\0w2" for redb storage.
     fn encode_bigram_key(w1: &str, w2: &str) -> String {
         format!("{}\0{}", w1, w2)
@@ -54,6 +182,17 @@ impl UserDict {
 
     /// Learn with a custom delta.
     pub fn learn_with_count(&self, phrase: &str, delta: u64) -> Result<(), redb::Error> {
+        self.learn_with_count_at(phrase, delta, unix_now())
+    }
+
+    /// Learn with a custom delta, recording `timestamp` (Unix seconds) as
+    /// the time the phrase was last used.
+    pub fn learn_with_count_at(
+        &self,
+        phrase: &str,
+        delta: u64,
+        timestamp: u64,
+    ) -> Result<(), redb::Error> {
         // Read current value in a read transaction to avoid borrow conflicts
         let cur = {
             let r = self.db.begin_read()?;
@@ -81,11 +220,56 @@ impl UserDict {
             let mut table = w.open_table(Self::table_def())?;
             let new = cur.saturating_add(delta);
             table.insert(&phrase, &new)?;
+
+            let mut last_used = w.open_table(Self::last_used_table_def())?;
+            last_used.insert(&phrase, &timestamp)?;
         }
         w.commit()?;
         Ok(())
     }
 
+    /// Unix time (seconds) the phrase was last learned, if recorded.
+    ///
+    /// Phrases only added through `add_phrase` or `set_frequency` have no
+    /// timestamp; `merge_from` keeps the later of both devices' times.
+    pub fn last_used(&self, phrase: &str) -> Option<u64> {
+        self.last_used_result(phrase).ok().flatten()
+    }
+
+    fn last_used_result(&self, phrase: &str) -> Result<Option<u64>, redb::Error> {
+        let r = self.db.begin_read()?;
+        match r.open_table(Self::last_used_table_def()) {
+            Ok(table) => Ok(table.get(&phrase)?.map(|v| v.value())),
+            Err(e) => {
+                if matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    Ok(None)
+                } else {
+                    Err(e.into())
+                }
+            }
+        }
+    }
+
+    /// Learned frequency blended with how recently the phrase was used.
+    ///
+    /// Returns `freq * ((1 - alpha) + alpha * recency)`, where recency is 1.0
+    /// for a phrase used at `now` and halves every `RECENCY_HALF_LIFE_SECS`.
+    /// Phrases without a timestamp have recency 0. `alpha` is clamped to
+    /// [0, 1]; 0 gives the raw frequency.
+    pub fn recency_weighted_frequency(&self, phrase: &str, alpha: f32, now: u64) -> f64 {
+        let freq = self.frequency(phrase);
+        let alpha = f64::from(alpha.clamp(0.0, 1.0));
+        if freq == 0 || alpha == 0.0 {
+            return freq as f64;
+        }
+
+        let recency = self.last_used(phrase).map_or(0.0, |at| {
+            let age = now.saturating_sub(at) as f64;
+            0.5_f64.powf(age / RECENCY_HALF_LIFE_SECS as f64)
+        });
+        freq as f64 * ((1.0 - alpha) + alpha * recency)
+    }
+
     /// Get frequency for phrase.
     pub fn frequency(&self, phrase: &str) -> u64 {
         self.frequency_result(phrase).unwrap_or(0)
@@ -184,6 +368,18 @@ impl UserDict {
         w1: &str,
         w2: &str,
         delta: u64,
+    ) -> Result<(), redb::Error> {
+        self.learn_bigram_with_count_at(w1, w2, delta, unix_now())
+    }
+
+    /// Learn a bigram with a custom count delta, recording `timestamp` (Unix
+    /// seconds) as the time it was last used.
+    pub fn learn_bigram_with_count_at(
+        &self,
+        w1: &str,
+        w2: &str,
+        delta: u64,
+        timestamp: u64,
     ) -> Result<(), redb::Error> {
         let key = Self::encode_bigram_key(w1, w2);
 
@@ -214,6 +410,9 @@ impl UserDict {
             let mut table = w.open_table(Self::bigram_table_def())?;
             let new = cur.saturating_add(delta);
             table.insert(key.as_str(), &new)?;
+
+            let mut last_used = w.open_table(Self::bigram_last_used_table_def())?;
+            last_used.insert(key.as_str(), &timestamp)?;
         }
         w.commit()?;
         Ok(())
@@ -290,6 +489,83 @@ impl UserDict {
         Ok(out)
     }
 
+    /// Like `get_bigrams_after`, with the time each bigram was last learned.
+    ///
+    /// Bigrams learned before timestamps were recorded report
+    /// `UNIX_EPOCH`. Sorted by following word.
+    pub fn get_bigrams_after_with_time(&self, w1: &str) -> Vec<(String, u64, SystemTime)> {
+        self.get_bigrams_after_with_time_result(w1)
+            .unwrap_or_default()
+    }
+
+    fn get_bigrams_after_with_time_result(
+        &self,
+        w1: &str,
+    ) -> Result<Vec<(String, u64, SystemTime)>, redb::Error> {
+        let mut out = Vec::new();
+        let r = self.db.begin_read()?;
+        let prefix = format!("{}\0", w1);
+
+        let bigrams = match r.open_table(Self::bigram_table_def()) {
+            Ok(table) => table,
+            Err(redb::TableError::TableDoesNotExist(_)) => return Ok(out),
+            Err(e) => return Err(e.into()),
+        };
+        let times = match r.open_table(Self::bigram_last_used_table_def()) {
+            Ok(table) => Some(table),
+            Err(redb::TableError::TableDoesNotExist(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        for item in bigrams.range(prefix.as_str()..)? {
+            let (key, count) = item?;
+            let key_str = key.value();
+            if !key_str.starts_with(&prefix) {
+                break;
+            }
+            let Some((_, w2)) = Self::decode_bigram_key(key_str) else {
+                continue;
+            };
+            let secs = match &times {
+                Some(times) => times.get(key_str)?.map_or(0, |v| v.value()),
+                None => 0,
+            };
+            out.push((w2, count.value(), UNIX_EPOCH + Duration::from_secs(secs)));
+        }
+        Ok(out)
+    }
+
+    /// Export every learned bigram as ((w1, w2), count), sorted by pair.
+    ///
+    /// Meant for auditing what the dictionary has learned (see the
+    /// `export_userdict` tool).
+    pub fn export_bigrams(&self) -> Vec<((String, String), u64)> {
+        self.export_bigrams_result().unwrap_or_default()
+    }
+
+    fn export_bigrams_result(&self) -> Result<Vec<BigramCount>, redb::Error> {
+        let mut out = Vec::new();
+        let r = self.db.begin_read()?;
+        match r.open_table(Self::bigram_table_def()) {
+            Ok(table) => {
+                for item in table.iter()? {
+                    let (key, count) = item?;
+                    if let Some(pair) = Self::decode_bigram_key(key.value()) {
+                        out.push((pair, count.value()));
+                    }
+                }
+            }
+            Err(e) => {
+                if matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    // empty
+                } else {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(out)
+    }
+
     // ========== User Phrase Management API for GUI ==========
 
     /// List all phrases in user dictionary (alias for iter_all for clarity).
@@ -340,6 +616,64 @@ impl UserDict {
         self.add_phrase(phrase, new_freq)
     }
 
+    /// Set a phrase's frequency, replacing whatever was learned.
+    ///
+    /// A frequency of 0 removes the phrase. Used by importers that replace
+    /// counts rather than add to them.
+    pub fn set_frequency(&self, phrase: &str, freq: u64) -> Result<(), redb::Error> {
+        if freq == 0 {
+            return self.forget(phrase).map(|_| ());
+        }
+        self.add_phrase(phrase, freq)
+    }
+
+    /// Remove a phrase entirely, undoing everything learned for it.
+    ///
+    /// Returns whether the phrase was present.
+    pub fn forget(&self, phrase: &str) -> Result<bool, redb::Error> {
+        let w = self.db.begin_write()?;
+        let removed = w.open_table(Self::table_def())?.remove(&phrase)?.is_some();
+        w.open_table(Self::last_used_table_def())?.remove(&phrase)?;
+        w.commit()?;
+        Ok(removed)
+    }
+
+    /// Age all learned phrase and bigram counts by `factor`.
+    ///
+    /// Each count becomes `floor(count * factor)`; counts reaching zero are
+    /// deleted, as is everything for a factor of 0 or less. The whole pass
+    /// is one transaction, so a failure leaves the dictionary unchanged.
+    pub fn decay(&self, factor: f64) -> Result<(), redb::Error> {
+        // NaN becomes 0.0 here too
+        let factor = factor.max(0.0);
+
+        let w = self.db.begin_write()?;
+        {
+            for (def, stamps) in [
+                (Self::table_def(), Self::last_used_table_def()),
+                (Self::bigram_table_def(), Self::bigram_last_used_table_def()),
+            ] {
+                let mut table = w.open_table(def)?;
+                let mut last_used = w.open_table(stamps)?;
+                let entries: Vec<(String, u64)> = table
+                    .iter()?
+                    .map(|item| item.map(|(k, v)| (k.value().to_string(), v.value())))
+                    .collect::<Result<_, _>>()?;
+                for (key, count) in entries {
+                    let aged = (count as f64 * factor).floor() as u64;
+                    if aged == 0 {
+                        table.remove(key.as_str())?;
+                        last_used.remove(key.as_str())?;
+                    } else {
+                        table.insert(key.as_str(), &aged)?;
+                    }
+                }
+            }
+        }
+        w.commit()?;
+        Ok(())
+    }
+
     /// Search phrases by prefix (for GUI filtering).
     ///
     /// Returns all phrases starting with the given prefix.
@@ -366,4 +700,374 @@ impl UserDict {
         }
         Ok(results)
     }
+
+    // ========== Segmentation Learning API ==========
+
+    /// Record the user's preferred segmentation for an input.
+    ///
+    /// The input is stored without apostrophes ("xian"), the segmentation as
+    /// apostrophe-joined syllables ("xi'an"). A later call overwrites the
+    /// previous preference.
+    pub fn learn_segmentation(&self, input: &str, syllables: &[String]) -> Result<(), redb::Error> {
+        let key = input.replace('\'', "");
+        let value = syllables.join("'");
+
+        let w = self.db.begin_write()?;
+        {
+            let mut table = w.open_table(Self::segmentation_table_def())?;
+            table.insert(key.as_str(), value.as_str())?;
+        }
+        w.commit()?;
+        Ok(())
+    }
+
+    /// Get the learned segmentation for an input, if any.
+    pub fn preferred_segmentation(&self, input: &str) -> Option<Vec<String>> {
+        self.preferred_segmentation_result(input).unwrap_or(None)
+    }
+
+    fn preferred_segmentation_result(
+        &self,
+        input: &str,
+    ) -> Result<Option<Vec<String>>, redb::Error> {
+        let r = self.db.begin_read()?;
+        match r.open_table(Self::segmentation_table_def()) {
+            Ok(table) => {
+                if let Some(v) = table.get(input)? {
+                    Ok(Some(v.value().split('\'').map(|s| s.to_string()).collect()))
+                } else {
+                    Ok(None)
+                }
+            }
+            Err(e) => {
+                if matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    Ok(None)
+                } else {
+                    Err(e.into())
+                }
+            }
+        }
+    }
+
+    /// Forget the learned segmentation for an input.
+    pub fn forget_segmentation(&self, input: &str) -> Result<(), redb::Error> {
+        let w = self.db.begin_write()?;
+        {
+            let mut table = w.open_table(Self::segmentation_table_def())?;
+            table.remove(input)?;
+        }
+        w.commit()?;
+        Ok(())
+    }
+
+    // ========== Rejection Learning API ==========
+
+    /// Record that the user passed over `phrase` when it was offered for
+    /// `input` (stored with the same "input\0phrase" keys as bigrams).
+    pub fn learn_rejection(&self, input: &str, phrase: &str) -> Result<(), redb::Error> {
+        let key = Self::encode_bigram_key(input, phrase);
+
+        let w = self.db.begin_write()?;
+        {
+            let mut table = w.open_table(Self::rejection_table_def())?;
+            let cur = table.get(key.as_str())?.map(|v| v.value()).unwrap_or(0);
+            table.insert(key.as_str(), &cur.saturating_add(1))?;
+        }
+        w.commit()?;
+        Ok(())
+    }
+
+    /// How often `phrase` was rejected for `input`.
+    pub fn rejection_count(&self, input: &str, phrase: &str) -> u64 {
+        self.rejections_for(input).get(phrase).copied().unwrap_or(0)
+    }
+
+    /// All phrases rejected for `input`, with their rejection counts.
+    pub fn rejections_for(&self, input: &str) -> HashMap<String, u64> {
+        self.rejections_for_result(input).unwrap_or_default()
+    }
+
+    fn rejections_for_result(&self, input: &str) -> Result<HashMap<String, u64>, redb::Error> {
+        self.pair_counts_after(Self::rejection_table_def(), input)
+    }
+
+    /// Counts of all "first\0second" keys in a pair table, by second.
+    fn pair_counts_after(
+        &self,
+        def: TableDefinition<'static, &'static str, u64>,
+        first: &str,
+    ) -> Result<HashMap<String, u64>, redb::Error> {
+        let mut out = HashMap::new();
+        let prefix = format!("{}\0", first);
+        let r = self.db.begin_read()?;
+        match r.open_table(def) {
+            Ok(table) => {
+                for item in table.range(prefix.as_str()..)? {
+                    let (key, count) = item?;
+                    let Some(second) = key.value().strip_prefix(&prefix) else {
+                        break;
+                    };
+                    out.insert(second.to_string(), count.value());
+                }
+            }
+            Err(e) => {
+                if !matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    // ========== Punctuation Learning API ==========
+
+    /// Record that the user picked `choice` among the forms offered for the
+    /// punctuation `key` ("，" or "、" for ',').
+    pub fn learn_punctuation(&self, key: char, choice: &str) -> Result<(), redb::Error> {
+        let key = Self::encode_bigram_key(&key.to_string(), choice);
+
+        let w = self.db.begin_write()?;
+        {
+            let mut table = w.open_table(Self::punctuation_table_def())?;
+            let cur = table.get(key.as_str())?.map(|v| v.value()).unwrap_or(0);
+            table.insert(key.as_str(), &cur.saturating_add(1))?;
+        }
+        w.commit()?;
+        Ok(())
+    }
+
+    /// How often each form of the punctuation `key` was picked.
+    pub fn punctuation_preferences(&self, key: char) -> HashMap<String, u64> {
+        self.pair_counts_after(Self::punctuation_table_def(), &key.to_string())
+            .unwrap_or_default()
+    }
+
+    // ========== Sync API ==========
+
+    /// Merge learning from another user dictionary (e.g. from another device).
+    ///
+    /// Phrase, bigram, rejection and punctuation counts present in both
+    /// dictionaries are combined with `policy`; entries only in `other` are
+    /// copied as they are. Learned segmentations are unioned, keeping this
+    /// dictionary's choice on conflict, and last-used times keep the later
+    /// one. Everything is written in a single transaction.
+    pub fn merge_from(&self, other: &UserDict, policy: MergePolicy) -> Result<(), redb::Error> {
+        let phrases = other.u64_table_entries(Self::table_def())?;
+        let bigrams = other.u64_table_entries(Self::bigram_table_def())?;
+        let rejections = other.u64_table_entries(Self::rejection_table_def())?;
+        let punctuation = other.u64_table_entries(Self::punctuation_table_def())?;
+        let segmentations = other.segmentation_entries()?;
+        let last_used = other.u64_table_entries(Self::last_used_table_def())?;
+        let bigram_last_used = other.u64_table_entries(Self::bigram_last_used_table_def())?;
+
+        let w = self.db.begin_write()?;
+        {
+            for (def, entries) in [
+                (Self::table_def(), phrases),
+                (Self::bigram_table_def(), bigrams),
+                (Self::rejection_table_def(), rejections),
+                (Self::punctuation_table_def(), punctuation),
+            ] {
+                let mut table = w.open_table(def)?;
+                for (key, incoming) in entries {
+                    let local = table.get(key.as_str())?.map(|v| v.value());
+                    let merged = match local {
+                        Some(local) => policy.combine(local, incoming),
+                        None => incoming,
+                    };
+                    table.insert(key.as_str(), &merged)?;
+                }
+            }
+
+            let mut table = w.open_table(Self::segmentation_table_def())?;
+            for (input, syllables) in segmentations {
+                if table.get(input.as_str())?.is_none() {
+                    table.insert(input.as_str(), syllables.as_str())?;
+                }
+            }
+
+            // Whichever device used an entry last decides its recency
+            for (def, entries) in [
+                (Self::last_used_table_def(), last_used),
+                (Self::bigram_last_used_table_def(), bigram_last_used),
+            ] {
+                let mut table = w.open_table(def)?;
+                for (key, incoming) in entries {
+                    let local = table.get(key.as_str())?.map_or(0, |v| v.value());
+                    table.insert(key.as_str(), &local.max(incoming))?;
+                }
+            }
+        }
+        w.commit()?;
+        Ok(())
+    }
+
+    /// All raw (key, count) pairs of a count table.
+    fn u64_table_entries(
+        &self,
+        def: TableDefinition<'static, &'static str, u64>,
+    ) -> Result<Vec<(String, u64)>, redb::Error> {
+        let r = self.db.begin_read()?;
+        Self::read_u64_entries(&r, def)
+    }
+
+    fn read_u64_entries(
+        r: &redb::ReadTransaction,
+        def: TableDefinition<'static, &'static str, u64>,
+    ) -> Result<Vec<(String, u64)>, redb::Error> {
+        let mut out = Vec::new();
+        match r.open_table(def) {
+            Ok(table) => {
+                for item in table.iter()? {
+                    let (k, v) = item?;
+                    out.push((k.value().to_string(), v.value()));
+                }
+            }
+            Err(e) => {
+                if !matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    // ========== Snapshot API ==========
+
+    /// Copy everything learned into a `UserDictSnapshot`.
+    ///
+    /// All tables are read in one transaction, so the snapshot is
+    /// consistent even while other handles keep learning.
+    pub fn export_snapshot(&self) -> UserDictSnapshot {
+        self.export_snapshot_result().unwrap_or_default()
+    }
+
+    fn export_snapshot_result(&self) -> Result<UserDictSnapshot, redb::Error> {
+        let r = self.db.begin_read()?;
+        let entries = |def| Self::read_u64_entries(&r, def);
+        let pairs = |entries: Vec<(String, u64)>| -> Vec<(String, String, u64)> {
+            entries
+                .into_iter()
+                .filter_map(|(key, v)| Self::decode_bigram_key(&key).map(|(a, b)| (a, b, v)))
+                .collect()
+        };
+
+        let mut segmentations = BTreeMap::new();
+        match r.open_table(Self::segmentation_table_def()) {
+            Ok(table) => {
+                for item in table.iter()? {
+                    let (k, v) = item?;
+                    segmentations.insert(k.value().to_string(), v.value().to_string());
+                }
+            }
+            Err(e) => {
+                if !matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Ok(UserDictSnapshot {
+            version: SNAPSHOT_VERSION,
+            phrases: entries(Self::table_def())?.into_iter().collect(),
+            bigrams: pairs(entries(Self::bigram_table_def())?),
+            segmentations,
+            rejections: pairs(entries(Self::rejection_table_def())?),
+            last_used: entries(Self::last_used_table_def())?.into_iter().collect(),
+            bigram_last_used: pairs(entries(Self::bigram_last_used_table_def())?),
+            punctuation: pairs(entries(Self::punctuation_table_def())?),
+        })
+    }
+
+    /// Load a snapshot written by `export_snapshot`, in one transaction.
+    pub fn import_snapshot(
+        &self,
+        snap: &UserDictSnapshot,
+        mode: ImportMode,
+    ) -> Result<(), redb::Error> {
+        let pairs = |entries: &[(String, String, u64)]| -> Vec<(String, u64)> {
+            entries
+                .iter()
+                .map(|(a, b, v)| (Self::encode_bigram_key(a, b), *v))
+                .collect()
+        };
+        let counts = [
+            (
+                Self::table_def(),
+                snap.phrases.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+            ),
+            (Self::bigram_table_def(), pairs(&snap.bigrams)),
+            (Self::rejection_table_def(), pairs(&snap.rejections)),
+            (Self::punctuation_table_def(), pairs(&snap.punctuation)),
+        ];
+        let times = [
+            (
+                Self::last_used_table_def(),
+                snap.last_used
+                    .iter()
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect(),
+            ),
+            (
+                Self::bigram_last_used_table_def(),
+                pairs(&snap.bigram_last_used),
+            ),
+        ];
+
+        let w = self.db.begin_write()?;
+        {
+            if mode == ImportMode::Replace {
+                for (def, _) in counts.iter().chain(times.iter()) {
+                    w.delete_table(*def)?;
+                }
+                w.delete_table(Self::segmentation_table_def())?;
+            }
+
+            // After a Replace the tables are empty, so adding sets the counts
+            for (def, entries) in counts {
+                let mut table = w.open_table(def)?;
+                for (key, count) in entries {
+                    let cur = table.get(key.as_str())?.map_or(0, |v| v.value());
+                    table.insert(key.as_str(), &cur.saturating_add(count))?;
+                }
+            }
+
+            for (def, entries) in times {
+                let mut table = w.open_table(def)?;
+                for (key, at) in entries {
+                    let cur = table.get(key.as_str())?.map_or(0, |v| v.value());
+                    table.insert(key.as_str(), &cur.max(at))?;
+                }
+            }
+
+            let mut table = w.open_table(Self::segmentation_table_def())?;
+            for (input, syllables) in &snap.segmentations {
+                if table.get(input.as_str())?.is_none() {
+                    table.insert(input.as_str(), syllables.as_str())?;
+                }
+            }
+        }
+        w.commit()?;
+        Ok(())
+    }
+
+    /// All (input, apostrophe-joined syllables) pairs of learned segmentations.
+    fn segmentation_entries(&self) -> Result<Vec<(String, String)>, redb::Error> {
+        let mut out = Vec::new();
+        let r = self.db.begin_read()?;
+        match r.open_table(Self::segmentation_table_def()) {
+            Ok(table) => {
+                for item in table.iter()? {
+                    let (k, v) = item?;
+                    out.push((k.value().to_string(), v.value().to_string()));
+                }
+            }
+            Err(e) => {
+                if !matches!(e, redb::TableError::TableDoesNotExist(_)) {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(out)
+    }
 }
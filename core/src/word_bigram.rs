@@ -2,7 +2,9 @@
 //
 // Word-level bigram model for phrase-to-phrase transitions.
 // Stores P(word2 | word1) to score word sequences in candidate generation.
+// How unseen transitions are scored is pluggable through `Smoothing`.
 
+use crate::Config;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
@@ -107,6 +109,182 @@ impl WordBigram {
         }
     }
 
+    /// Blend another model into this one, e.g. to adapt a news-trained model
+    /// with chat text without retraining from the raw corpora.
+    ///
+    /// Every unigram and bigram count becomes
+    /// `round(weight_self × self + weight_other × other)`, but at least 1 so
+    /// a weighted-down entry is not lost; entries only one model has are
+    /// added at their weighted count. Each context's total is blended the
+    /// same way rather than recounted, so bigrams dropped by `prune` keep
+    /// their share and the survivors keep their probabilities. Blending counts (rather
+    /// than log-probabilities) keeps the result a proper distribution, and a
+    /// bigram both models know ends up with a probability between the two.
+    /// The caveat is that each model's say in a context is its weight times
+    /// its count there, so a model trained on more text dominates unless its
+    /// weight is lowered to compensate.
+    pub fn merge(&mut self, other: &WordBigram, weight_self: f64, weight_other: f64) {
+        let mut unigrams: HashMap<String, f64> = HashMap::new();
+        let mut bigrams: HashMap<String, HashMap<String, f64>> = HashMap::new();
+        let mut totals: HashMap<String, f64> = HashMap::new();
+        for (model, weight) in [(&*self, weight_self), (other, weight_other)] {
+            let weight = weight.max(0.0);
+            for (word1, &total) in &model.totals {
+                *totals.entry(word1.clone()).or_default() += total as f64 * weight;
+            }
+            for (word, &count) in &model.unigram_counts {
+                *unigrams.entry(word.clone()).or_default() += count as f64 * weight;
+            }
+            for (word1, entries) in &model.data {
+                let following = bigrams.entry(word1.clone()).or_default();
+                for entry in entries {
+                    *following.entry(entry.word.clone()).or_default() +=
+                        entry.count as f64 * weight;
+                }
+            }
+        }
+
+        let to_count = |weighted: f64| {
+            if weighted > 0.0 {
+                weighted.round().clamp(1.0, u32::MAX as f64) as u32
+            } else {
+                0
+            }
+        };
+        let mut merged = WordBigram::new();
+        for (word, weighted) in unigrams {
+            let count = to_count(weighted);
+            if count > 0 {
+                merged.add_unigram(word, count);
+            }
+        }
+        for (word1, following) in bigrams {
+            for (word2, weighted) in following {
+                let count = to_count(weighted);
+                if count > 0 {
+                    merged.add_bigram(word1.clone(), word2, count);
+                }
+            }
+        }
+        for (word1, total) in merged.totals.iter_mut() {
+            let blended = totals.get(word1).copied().map_or(0, to_count);
+            *total = (*total).max(blended);
+        }
+        *self = merged;
+    }
+
+    /// Drop bigrams whose ln P(word2 | word1) is below `min_logprob`.
+    ///
+    /// Totals are kept, so the remaining bigrams keep their probabilities and
+    /// pruned transitions fall back to the unigram. Unigrams are untouched.
+    /// Returns the number of bigrams removed.
+    pub fn prune(&mut self, min_logprob: f64) -> usize {
+        let mut removed = 0;
+        for (word1, entries) in self.data.iter_mut() {
+            let total = self.totals.get(word1).copied().unwrap_or(0);
+            let before = entries.len();
+            entries.retain(|e| total > 0 && (e.count as f64 / total as f64).ln() >= min_logprob);
+            removed += before - entries.len();
+        }
+        self.drop_empty_contexts();
+        removed
+    }
+
+    /// Keep only the `k` most frequent continuations of each word1.
+    ///
+    /// Ties are broken by word so the result is deterministic. Like `prune`,
+    /// totals and unigrams are left alone. Returns the number of bigrams
+    /// removed.
+    pub fn prune_to_topk_per_context(&mut self, k: usize) -> usize {
+        let mut removed = 0;
+        for entries in self.data.values_mut() {
+            if entries.len() > k {
+                entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+                removed += entries.len() - k;
+                entries.truncate(k);
+            }
+        }
+        self.drop_empty_contexts();
+        removed
+    }
+
+    /// Forget word1 entries left without any bigram.
+    fn drop_empty_contexts(&mut self) {
+        self.data.retain(|_, entries| !entries.is_empty());
+        let data = &self.data;
+        self.totals.retain(|word1, _| data.contains_key(word1));
+    }
+
+    /// Log score of a word sequence under the default interpolated backoff.
+    ///
+    /// See `score_sequence_with` to compare other smoothing strategies.
+    pub fn score_sequence(&self, words: &[String], cfg: &Config) -> f64 {
+        self.score_sequence_with(words, &DefaultBackoff, cfg)
+    }
+
+    /// Score many candidate continuations of the same context at once.
+    ///
+    /// Each score equals `score_sequence` of `prefix_context` followed by the
+    /// candidate. The context's own score and the table of words seen after
+    /// its last word are computed once and shared, and nothing is allocated
+    /// per candidate, which matters when ranking hundreds of candidates that
+    /// follow the same words.
+    pub fn score_candidates(
+        &self,
+        prefix_context: &[String],
+        candidates: &[Vec<String>],
+        cfg: &Config,
+    ) -> Vec<f32> {
+        let context_score = self.score_sequence(prefix_context, cfg);
+        let lambda = cfg.lambda as f64;
+
+        // P(w | last context word), keeping the first entry like `get_probability`
+        let mut after_context: HashMap<&str, f32> = HashMap::new();
+        if let Some(last) = prefix_context.last() {
+            let total = self.totals.get(last).copied().unwrap_or(0);
+            if let (Some(entries), true) = (self.data.get(last), total > 0) {
+                for entry in entries {
+                    after_context
+                        .entry(entry.word.as_str())
+                        .or_insert(entry.count as f32 / total as f32);
+                }
+            }
+        }
+
+        candidates
+            .iter()
+            .map(|candidate| {
+                let Some(first) = candidate.first() else {
+                    return context_score as f32;
+                };
+                let unigram = self.get_unigram_probability(first) as f64;
+                let prob = if prefix_context.is_empty() {
+                    (1.0 - lambda) * unigram
+                } else {
+                    let bigram = after_context.get(first.as_str()).copied().unwrap_or(0.0) as f64;
+                    lambda * bigram + (1.0 - lambda) * unigram
+                };
+                let rest: f64 = (1..candidate.len())
+                    .map(|i| DefaultBackoff.score(self, candidate, i, cfg))
+                    .sum();
+                (context_score + prob.max(1e-10).ln() + rest) as f32
+            })
+            .collect()
+    }
+
+    /// Log score of a word sequence: the sum of `smoothing`'s score for each
+    /// word given the words before it.
+    pub fn score_sequence_with(
+        &self,
+        words: &[String],
+        smoothing: &dyn Smoothing,
+        cfg: &Config,
+    ) -> f64 {
+        (0..words.len())
+            .map(|i| smoothing.score(self, words, i, cfg))
+            .sum()
+    }
+
     /// Get top N predictions after word1 based on bigram probabilities
     /// Returns Vec<(word2, score)> sorted by score (descending)
     pub fn get_predictions(&self, word1: &str, lambda: f32, top_n: usize) -> Vec<(String, f32)> {
@@ -164,6 +342,36 @@ impl WordBigram {
     pub fn total_bigrams(&self) -> usize {
         self.data.values().map(|v| v.len()).sum()
     }
+
+    /// Approximate heap + inline size of the model in bytes.
+    ///
+    /// Counts each map's table (capacity × (key + value + one control byte)),
+    /// the string buffers of all words and the bigram entry vectors. Allocator
+    /// overhead is not included, so the real footprint is somewhat larger.
+    pub fn memory_estimate(&self) -> usize {
+        fn table<K, V>(map: &HashMap<K, V>) -> usize {
+            map.capacity() * (std::mem::size_of::<K>() + std::mem::size_of::<V>() + 1)
+        }
+
+        let bigrams: usize = self
+            .data
+            .iter()
+            .map(|(word1, entries)| {
+                let words: usize = entries.iter().map(|e| e.word.capacity()).sum();
+                word1.capacity() + entries.capacity() * std::mem::size_of::<BigramEntry>() + words
+            })
+            .sum();
+        let totals: usize = self.totals.keys().map(|w| w.capacity()).sum();
+        let unigrams: usize = self.unigram_counts.keys().map(|w| w.capacity()).sum();
+
+        std::mem::size_of::<Self>()
+            + table(&self.data)
+            + table(&self.totals)
+            + table(&self.unigram_counts)
+            + bigrams
+            + totals
+            + unigrams
+    }
 }
 
 impl Default for WordBigram {
@@ -172,6 +380,248 @@ impl Default for WordBigram {
     }
 }
 
+/// Word-level trigram counts, kept apart from `WordBigram` so existing
+/// word_bigram.bin files still load.
+/// Maps (word1, word2) -> list of (word3, count) pairs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WordTrigram {
+    /// Trigram data: (word1, word2) -> [(word3, count), ...]
+    data: HashMap<(String, String), Vec<BigramEntry>>,
+    /// Total frequency for each (word1, word2) context
+    totals: HashMap<(String, String), u32>,
+}
+
+impl WordTrigram {
+    /// Create an empty trigram table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a trigram observation
+    pub fn add_trigram(&mut self, word1: String, word2: String, word3: String, count: u32) {
+        let context = (word1, word2);
+        self.data
+            .entry(context.clone())
+            .or_default()
+            .push(BigramEntry { word: word3, count });
+        *self.totals.entry(context).or_insert(0) += count;
+    }
+
+    /// Count of word3 after (word1, word2); 0 if unseen
+    pub fn count(&self, word1: &str, word2: &str, word3: &str) -> u32 {
+        self.entries(word1, word2)
+            .iter()
+            .find(|e| e.word == word3)
+            .map_or(0, |e| e.count)
+    }
+
+    /// Get the probability P(word3 | word1, word2)
+    /// Returns 0.0 if the trigram doesn't exist
+    pub fn get_probability(&self, word1: &str, word2: &str, word3: &str) -> f32 {
+        let total = self.context_total(word1, word2);
+        if total == 0 {
+            return 0.0;
+        }
+        self.count(word1, word2, word3) as f32 / total as f32
+    }
+
+    /// Get total number of trigrams
+    pub fn total_trigrams(&self) -> usize {
+        self.data.values().map(|v| v.len()).sum()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Load from bincode file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let model = bincode::deserialize_from(reader)?;
+        Ok(model)
+    }
+
+    /// Save to bincode file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    fn entries(&self, word1: &str, word2: &str) -> &[BigramEntry] {
+        // Tuple keys need owned strings to look up
+        self.data
+            .get(&(word1.to_string(), word2.to_string()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn context_total(&self, word1: &str, word2: &str) -> u32 {
+        self.totals
+            .get(&(word1.to_string(), word2.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Strategy for scoring one word of a sequence from a `WordBigram`.
+pub trait Smoothing {
+    /// Log score of `words[i]` given the words before it.
+    fn score(&self, model: &WordBigram, words: &[String], i: usize, cfg: &Config) -> f64;
+}
+
+/// Linear interpolation, as used for candidate paths:
+/// ln(λ·P(w|prev) + (1-λ)·P(w)), or ln((1-λ)·P(w)) for the first word,
+/// with λ = `Config::lambda`. Probabilities are floored at 1e-10.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultBackoff;
+
+impl Smoothing for DefaultBackoff {
+    fn score(&self, model: &WordBigram, words: &[String], i: usize, cfg: &Config) -> f64 {
+        let lambda = cfg.lambda as f64;
+        let unigram = model.get_unigram_probability(&words[i]) as f64;
+        let prob = match i.checked_sub(1) {
+            Some(prev) => {
+                let bigram = model.get_probability(&words[prev], &words[i]) as f64;
+                lambda * bigram + (1.0 - lambda) * unigram
+            }
+            None => (1.0 - lambda) * unigram,
+        };
+        prob.max(1e-10).ln()
+    }
+}
+
+/// Stupid backoff (Brants et al., 2007): ln P(w|prev) for a seen bigram,
+/// otherwise ln(α·P(w)). Words without a unigram count score -20.
+#[derive(Debug, Clone, Copy)]
+pub struct StupidBackoff {
+    /// Weight applied when backing off to the unigram. Brants et al. use 0.4.
+    pub alpha: f64,
+}
+
+impl Default for StupidBackoff {
+    fn default() -> Self {
+        Self { alpha: 0.4 }
+    }
+}
+
+impl Smoothing for StupidBackoff {
+    fn score(&self, model: &WordBigram, words: &[String], i: usize, _cfg: &Config) -> f64 {
+        if let Some(prev) = i.checked_sub(1) {
+            let bigram = model.get_probability(&words[prev], &words[i]) as f64;
+            if bigram > 0.0 {
+                return bigram.ln();
+            }
+        }
+
+        let unigram = model.get_unigram_probability(&words[i]) as f64;
+        if unigram > 0.0 {
+            let backoff = if i > 0 { self.alpha } else { 1.0 };
+            (backoff * unigram).ln()
+        } else {
+            -20.0 // Default for missing unigrams
+        }
+    }
+}
+
+/// Interpolated Kneser-Ney (Chen & Goodman, 1998) over bigram and,
+/// optionally, trigram counts.
+///
+/// Each order subtracts `discount` from every seen count and hands the
+/// freed mass to the next lower order; the lowest order is the
+/// continuation probability N1+(·w) / N1+(··), the share of distinct
+/// bigram types ending in w. Probabilities are floored at 1e-10.
+///
+/// Continuation counts are computed once by `new`, so a `KneserNey` must
+/// only score the model it was built from.
+#[derive(Debug, Clone)]
+pub struct KneserNey<'a> {
+    /// Absolute discount, in (0, 1). Default: 0.75.
+    pub discount: f64,
+    /// N1+(·w): distinct words seen before w
+    continuation: HashMap<String, u32>,
+    /// N1+(··): distinct bigram types
+    bigram_types: u32,
+    trigrams: Option<&'a WordTrigram>,
+}
+
+impl<'a> KneserNey<'a> {
+    /// Prepare Kneser-Ney smoothing for `model`'s bigrams.
+    pub fn new(model: &WordBigram) -> Self {
+        let mut continuation: HashMap<String, u32> = HashMap::new();
+        let mut bigram_types = 0;
+        for entries in model.data.values() {
+            for entry in entries {
+                *continuation.entry(entry.word.clone()).or_insert(0) += 1;
+                bigram_types += 1;
+            }
+        }
+        Self {
+            discount: 0.75,
+            continuation,
+            bigram_types,
+            trigrams: None,
+        }
+    }
+
+    /// Also condition on the two previous words using `trigrams`.
+    pub fn with_trigrams(mut self, trigrams: &'a WordTrigram) -> Self {
+        self.trigrams = Some(trigrams);
+        self
+    }
+
+    /// P_cont(w)
+    fn continuation_probability(&self, word: &str) -> f64 {
+        if self.bigram_types == 0 {
+            return 0.0;
+        }
+        let count = self.continuation.get(word).copied().unwrap_or(0);
+        count as f64 / self.bigram_types as f64
+    }
+
+    /// Discounted estimate from a context's `count` of w, its `total` and
+    /// its number of distinct continuations, interpolated with `lower`.
+    fn interpolate(&self, count: u32, total: u32, types: usize, lower: f64) -> f64 {
+        if total == 0 {
+            return lower;
+        }
+        let total = total as f64;
+        let discounted = (count as f64 - self.discount).max(0.0) / total;
+        discounted + self.discount * types as f64 / total * lower
+    }
+}
+
+impl Smoothing for KneserNey<'_> {
+    fn score(&self, model: &WordBigram, words: &[String], i: usize, _cfg: &Config) -> f64 {
+        let word = &words[i];
+        let mut prob = self.continuation_probability(word);
+
+        if let Some(prev) = i.checked_sub(1).map(|p| &words[p]) {
+            let entries = model.data.get(prev).map_or(&[][..], Vec::as_slice);
+            let count = entries
+                .iter()
+                .find(|e| &e.word == word)
+                .map_or(0, |e| e.count);
+            let total = model.totals.get(prev).copied().unwrap_or(0);
+            prob = self.interpolate(count, total, entries.len(), prob);
+
+            if let (Some(trigrams), Some(first)) = (self.trigrams, i.checked_sub(2)) {
+                let first = &words[first];
+                prob = self.interpolate(
+                    trigrams.count(first, prev, word),
+                    trigrams.context_total(first, prev),
+                    trigrams.entries(first, prev).len(),
+                    prob,
+                );
+            }
+        }
+
+        prob.max(1e-10).ln()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,4 +656,230 @@ mod tests {
         let log_prob = wb.get_log_probability("不存在", "也不存在");
         assert_eq!(log_prob, -20.0);
     }
+
+    #[test]
+    fn test_word_bigram_memory_estimate() {
+        let empty = WordBigram::new().memory_estimate();
+        assert_eq!(empty, std::mem::size_of::<WordBigram>());
+
+        // 100 six-byte words, each followed by 10 six-byte words
+        let mut wb = WordBigram::new();
+        for i in 0..100 {
+            let word1 = format!("w1_{:03}", i);
+            wb.add_unigram(word1.clone(), 1);
+            for j in 0..10 {
+                wb.add_bigram(word1.clone(), format!("w2_{:03}", j), 1);
+            }
+        }
+
+        // Payload alone: word strings plus the bigram entries themselves
+        let strings = 100 * 6 * 3 + 100 * 10 * 6;
+        let entries = 100 * 10 * std::mem::size_of::<BigramEntry>();
+        let payload = strings + entries;
+
+        let estimate = wb.memory_estimate();
+        assert!(estimate > payload, "{} <= {}", estimate, payload);
+        assert!(estimate < payload * 4, "{} >= {}", estimate, payload * 4);
+
+        wb.add_bigram("另一个".to_string(), "词".to_string(), 1);
+        assert!(wb.memory_estimate() > estimate);
+    }
+
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_stupid_backoff_scales_unigram_fallback() {
+        let mut wb = WordBigram::new();
+        wb.add_unigram("你好".to_string(), 1);
+        wb.add_unigram("世界".to_string(), 3);
+        wb.add_bigram("你好".to_string(), "世界".to_string(), 1);
+        let cfg = Config::default();
+        let smoothing = StupidBackoff { alpha: 0.5 };
+
+        // Seen bigram: P(世界|你好) = 1, no backoff
+        let seq = words(&["你好", "世界"]);
+        assert_eq!(smoothing.score(&wb, &seq, 1, &cfg), 0.0);
+
+        // Unseen bigram backs off to α·P(你好) = 0.5 × 0.25
+        let seq = words(&["世界", "你好"]);
+        assert!((smoothing.score(&wb, &seq, 1, &cfg) - 0.125f64.ln()).abs() < 1e-6);
+        // The first word has no context to back off from: P(世界) = 0.75
+        assert!((smoothing.score(&wb, &seq, 0, &cfg) - 0.75f64.ln()).abs() < 1e-6);
+        assert!(
+            (wb.score_sequence_with(&seq, &smoothing, &cfg) - (0.75f64 * 0.125).ln()).abs() < 1e-6
+        );
+
+        // Halving α costs exactly ln 2 on the backed-off word
+        let halved = StupidBackoff { alpha: 0.25 };
+        let diff = smoothing.score(&wb, &seq, 1, &cfg) - halved.score(&wb, &seq, 1, &cfg);
+        assert!((diff - 2f64.ln()).abs() < 1e-6);
+
+        // Unknown words get the fixed floor
+        let seq = words(&["你好", "不存在"]);
+        assert_eq!(smoothing.score(&wb, &seq, 1, &cfg), -20.0);
+    }
+
+    #[test]
+    fn test_merge_blends_counts() {
+        let mut news = WordBigram::new();
+        news.add_unigram("你好".to_string(), 4);
+        news.add_bigram("你好".to_string(), "世界".to_string(), 3);
+        news.add_bigram("你好".to_string(), "朋友".to_string(), 1);
+
+        let mut chat = WordBigram::new();
+        chat.add_unigram("你好".to_string(), 4);
+        chat.add_unigram("哈哈".to_string(), 4);
+        chat.add_bigram("你好".to_string(), "世界".to_string(), 1);
+        chat.add_bigram("你好".to_string(), "朋友".to_string(), 3);
+        chat.add_bigram("哈哈".to_string(), "哈哈".to_string(), 2);
+
+        let before = (
+            news.get_log_probability("你好", "世界"),
+            chat.get_log_probability("你好", "世界"),
+        );
+        news.merge(&chat, 1.0, 1.0);
+
+        // (3 + 1) / (4 + 4), between 0.75 and 0.25
+        let merged = news.get_log_probability("你好", "世界");
+        assert!(before.1 < merged && merged < before.0);
+        assert!((news.get_probability("你好", "世界") - 0.5).abs() < 1e-6);
+
+        // Entries only one model had are added
+        assert_eq!(news.get_probability("哈哈", "哈哈"), 1.0);
+        assert!((news.get_unigram_probability("哈哈") - 1.0 / 3.0).abs() < 1e-6);
+
+        // Weights shift the blend towards one model
+        let mut weighted = WordBigram::new();
+        weighted.add_bigram("你好".to_string(), "世界".to_string(), 3);
+        weighted.add_bigram("你好".to_string(), "朋友".to_string(), 1);
+        weighted.merge(&chat, 3.0, 1.0);
+        // (9 + 1) / (12 + 4)
+        assert!((weighted.get_probability("你好", "世界") - 0.625).abs() < 1e-6);
+
+        // Small weighted counts are not rounded away
+        let mut faint = WordBigram::new();
+        faint.add_bigram("你好".to_string(), "世界".to_string(), 1);
+        faint.merge(&chat, 0.3, 0.0);
+        assert_eq!(faint.get_probability("你好", "世界"), 1.0);
+    }
+
+    #[test]
+    fn test_merge_keeps_pruned_totals() {
+        let mut wb = WordBigram::new();
+        wb.add_bigram("今天".to_string(), "天气".to_string(), 90);
+        wb.add_bigram("今天".to_string(), "很好".to_string(), 9);
+        wb.add_bigram("今天".to_string(), "上海".to_string(), 1);
+        assert_eq!(wb.prune(0.05f64.ln()), 1);
+
+        let mut other = WordBigram::new();
+        other.add_bigram("今天".to_string(), "天气".to_string(), 90);
+        other.add_bigram("今天".to_string(), "很好".to_string(), 10);
+        wb.merge(&other, 1.0, 1.0);
+
+        // (90 + 90) / (100 + 100), not / 199
+        assert!((wb.get_probability("今天", "天气") - 0.9).abs() < 1e-6);
+        assert!((wb.get_probability("今天", "很好") - 0.095).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_prune_drops_unlikely_bigrams_but_keeps_backoff() {
+        let mut wb = WordBigram::new();
+        for word in ["今天", "天气", "很好", "不错", "上海"] {
+            wb.add_unigram(word.to_string(), 10);
+        }
+        wb.add_bigram("今天".to_string(), "天气".to_string(), 90);
+        wb.add_bigram("今天".to_string(), "很好".to_string(), 9);
+        wb.add_bigram("今天".to_string(), "上海".to_string(), 1);
+        wb.add_bigram("天气".to_string(), "不错".to_string(), 1);
+        wb.add_bigram("天气".to_string(), "很好".to_string(), 1);
+        wb.add_bigram("不错".to_string(), "上海".to_string(), 1);
+
+        // ln 0.05 sits between P = 0.01 and P = 0.09
+        let threshold = 0.05f64.ln();
+        assert_eq!(wb.prune(threshold), 1);
+        for (word1, entries) in &wb.data {
+            for entry in entries {
+                let prob = wb.get_probability(word1, &entry.word) as f64;
+                assert!(prob.ln() >= threshold, "{} {}", word1, entry.word);
+            }
+        }
+        // Surviving probabilities are unchanged
+        assert!((wb.get_probability("今天", "很好") - 0.09).abs() < 1e-6);
+        assert_eq!(wb.get_probability("今天", "上海"), 0.0);
+
+        // One continuation per context; 天气's tie is broken by word
+        assert_eq!(wb.prune_to_topk_per_context(1), 2);
+        assert_eq!(wb.total_bigrams(), 3);
+        assert!(wb.get_probability("天气", "不错") > 0.0);
+
+        // Dropping everything removes the contexts, not the unigrams
+        assert_eq!(wb.prune_to_topk_per_context(0), 3);
+        assert!(wb.is_empty());
+        assert!((wb.get_unigram_probability("上海") - 0.2).abs() < 1e-6);
+
+        // Pruned transitions still score through the unigram backoff
+        let seq = words(&["今天", "上海"]);
+        let cfg = Config::default();
+        assert!(wb.score_sequence(&seq, &cfg).is_finite());
+        assert!(wb.score_sequence(&seq, &cfg) > -20.0);
+    }
+
+    #[test]
+    fn test_kneser_ney_prefers_seen_trigrams() {
+        let mut wb = WordBigram::new();
+        wb.add_bigram("我们".to_string(), "爱".to_string(), 2);
+        wb.add_bigram("他们".to_string(), "爱".to_string(), 2);
+        wb.add_bigram("爱".to_string(), "中国".to_string(), 2);
+        wb.add_bigram("爱".to_string(), "音乐".to_string(), 2);
+        let mut trigrams = WordTrigram::new();
+        trigrams.add_trigram("我们".to_string(), "爱".to_string(), "中国".to_string(), 2);
+        trigrams.add_trigram("他们".to_string(), "爱".to_string(), "音乐".to_string(), 2);
+        let cfg = Config::default();
+
+        // Bigrams alone cannot tell the continuations of 爱 apart
+        let bigram_only = KneserNey::new(&wb);
+        let ours = words(&["我们", "爱", "中国"]);
+        let theirs = words(&["我们", "爱", "音乐"]);
+        assert_eq!(
+            bigram_only.score(&wb, &ours, 2, &cfg),
+            bigram_only.score(&wb, &theirs, 2, &cfg)
+        );
+
+        // (2 - 0.75) / 2 + 0.75 · 1 / 2 · P_bi(中国|爱), with
+        // P_bi = (2 - 0.75) / 4 + 0.75 · 2 / 4 · P_cont(中国) and P_cont = 1/4
+        let kn = KneserNey::new(&wb).with_trigrams(&trigrams);
+        let bigram: f64 = 1.25 / 4.0 + 0.375 * 0.25;
+        let expected = (0.625 + 0.375 * bigram).ln();
+        assert!((kn.score(&wb, &ours, 2, &cfg) - expected).abs() < 1e-6);
+        assert!(kn.score(&wb, &ours, 2, &cfg) > kn.score(&wb, &theirs, 2, &cfg));
+
+        // A word never seen after anything gets no continuation mass
+        let unseen = words(&["我们", "爱", "不存在"]);
+        assert_eq!(kn.score(&wb, &unseen, 2, &cfg), 1e-10f64.ln());
+        assert_eq!(trigrams.total_trigrams(), 2);
+        assert_eq!(trigrams.get_probability("我们", "爱", "中国"), 1.0);
+    }
+
+    #[test]
+    fn test_default_backoff_interpolates() {
+        let mut wb = WordBigram::new();
+        wb.add_unigram("你好".to_string(), 1);
+        wb.add_unigram("世界".to_string(), 1);
+        wb.add_bigram("你好".to_string(), "世界".to_string(), 1);
+        let cfg = Config {
+            lambda: 0.5,
+            ..Config::default()
+        };
+
+        // ln((1-λ)·0.5) + ln(λ·1 + (1-λ)·0.5)
+        let expected = 0.25f64.ln() + 0.75f64.ln();
+        let seq = words(&["你好", "世界"]);
+        assert!((wb.score_sequence(&seq, &cfg) - expected).abs() < 1e-6);
+        assert_eq!(
+            wb.score_sequence(&seq, &cfg),
+            wb.score_sequence_with(&seq, &DefaultBackoff, &cfg)
+        );
+    }
 }
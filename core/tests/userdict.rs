@@ -0,0 +1,279 @@
+//! UserDict persistence and sync behavior.
+
+use libchinese_core::{
+    ImportMode, MergePolicy, UserDict, UserDictSnapshot, RECENCY_HALF_LIFE_SECS, SNAPSHOT_VERSION,
+};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn temp_userdict(name: &str) -> UserDict {
+    let path = std::env::temp_dir().join(format!(
+        "libchinese_userdict_{}_{}.redb",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    UserDict::new(&path).expect("create temp userdict")
+}
+
+/// Local device: 你好 x3, 中国 x1, bigram 你好→世界 x2
+/// Other device: 你好 x4, 世界 x2, bigram 你好→世界 x1, 世界→和平 x5
+fn overlapping_pair(name: &str) -> (UserDict, UserDict) {
+    let local = temp_userdict(&format!("{}_local", name));
+    local.learn_with_count("你好", 3).unwrap();
+    local.learn_with_count("中国", 1).unwrap();
+    local.learn_bigram_with_count("你好", "世界", 2).unwrap();
+
+    let other = temp_userdict(&format!("{}_other", name));
+    other.learn_with_count("你好", 4).unwrap();
+    other.learn_with_count("世界", 2).unwrap();
+    other.learn_bigram_with_count("你好", "世界", 1).unwrap();
+    other.learn_bigram_with_count("世界", "和平", 5).unwrap();
+
+    (local, other)
+}
+
+#[test]
+fn merge_sum_adds_overlapping_counts() {
+    let (local, other) = overlapping_pair("merge_sum");
+    local.merge_from(&other, MergePolicy::Sum).unwrap();
+
+    assert_eq!(local.frequency("你好"), 7);
+    assert_eq!(local.frequency("中国"), 1);
+    assert_eq!(local.frequency("世界"), 2);
+    assert_eq!(local.bigram_frequency("你好", "世界"), 3);
+    assert_eq!(local.bigram_frequency("世界", "和平"), 5);
+
+    // The source is left untouched
+    assert_eq!(other.frequency("你好"), 4);
+    assert_eq!(other.frequency("中国"), 0);
+}
+
+#[test]
+fn merge_max_keeps_larger_counts() {
+    let (local, other) = overlapping_pair("merge_max");
+    local.merge_from(&other, MergePolicy::Max).unwrap();
+
+    assert_eq!(local.frequency("你好"), 4);
+    assert_eq!(local.frequency("世界"), 2);
+    assert_eq!(local.bigram_frequency("你好", "世界"), 2);
+    assert_eq!(local.bigram_frequency("世界", "和平"), 5);
+}
+
+#[test]
+fn merge_average_rounds_up() {
+    let (local, other) = overlapping_pair("merge_average");
+    local.merge_from(&other, MergePolicy::Average).unwrap();
+
+    assert_eq!(local.frequency("你好"), 4); // (3 + 4) / 2 rounded up
+    assert_eq!(local.frequency("中国"), 1); // only local
+    assert_eq!(local.frequency("世界"), 2); // only other
+    assert_eq!(local.bigram_frequency("你好", "世界"), 2); // (2 + 1) / 2 rounded up
+}
+
+#[test]
+fn merge_unions_segmentations_keeping_local_choice() {
+    let local = temp_userdict("merge_seg_local");
+    local
+        .learn_segmentation("xian", &["xi".to_string(), "an".to_string()])
+        .unwrap();
+
+    let other = temp_userdict("merge_seg_other");
+    other
+        .learn_segmentation("xian", &["xian".to_string()])
+        .unwrap();
+    other
+        .learn_segmentation("fangan", &["fang".to_string(), "an".to_string()])
+        .unwrap();
+
+    local.merge_from(&other, MergePolicy::Sum).unwrap();
+    assert_eq!(
+        local.preferred_segmentation("xian"),
+        Some(vec!["xi".to_string(), "an".to_string()])
+    );
+    assert_eq!(
+        local.preferred_segmentation("fangan"),
+        Some(vec!["fang".to_string(), "an".to_string()])
+    );
+}
+
+#[test]
+fn rejections_are_per_input_and_merge() {
+    let local = temp_userdict("rejections_local");
+    local.learn_rejection("shi", "是").unwrap();
+    local.learn_rejection("shi", "是").unwrap();
+    local.learn_rejection("shi", "事").unwrap();
+    // An input that extends "shi" is a different key
+    local.learn_rejection("shia", "是").unwrap();
+
+    assert_eq!(local.rejection_count("shi", "是"), 2);
+    assert_eq!(local.rejection_count("shia", "是"), 1);
+    assert_eq!(local.rejection_count("ni", "是"), 0);
+    assert_eq!(local.rejections_for("shi").len(), 2);
+    // Rejections are not negative phrase frequencies
+    assert_eq!(local.frequency("是"), 0);
+
+    let other = temp_userdict("rejections_other");
+    other.learn_rejection("shi", "是").unwrap();
+    local.merge_from(&other, MergePolicy::Sum).unwrap();
+    assert_eq!(local.rejection_count("shi", "是"), 3);
+}
+
+#[test]
+fn export_bigrams_lists_learned_pairs() {
+    let dict = temp_userdict("export_bigrams");
+    assert!(dict.export_bigrams().is_empty());
+
+    dict.learn_bigram("你", "好");
+    dict.learn_bigram("你", "好");
+    dict.learn_bigram("我", "们");
+    dict.learn_bigram_with_count("中国", "人民", 4).unwrap();
+
+    let pair = |w1: &str, w2: &str| (w1.to_string(), w2.to_string());
+    assert_eq!(
+        dict.export_bigrams(),
+        vec![
+            (pair("中国", "人民"), 4),
+            (pair("你", "好"), 2),
+            (pair("我", "们"), 1),
+        ]
+    );
+}
+
+#[test]
+fn forget_removes_a_learned_phrase() {
+    let dict = temp_userdict("forget");
+    dict.learn("错字");
+    dict.learn("你好");
+
+    assert!(dict.forget("错字").unwrap());
+    assert!(!dict.forget("错字").unwrap());
+    assert_eq!(dict.frequency("错字"), 0);
+    assert_eq!(dict.iter_all(), vec![("你好".to_string(), 1)]);
+}
+
+#[test]
+fn decay_ages_counts_and_drops_zeros() {
+    let dict = temp_userdict("decay");
+    dict.learn_with_count("你好", 10).unwrap();
+    dict.learn_with_count("中国", 1).unwrap();
+    dict.learn_bigram_with_count("你好", "世界", 5).unwrap();
+
+    dict.decay(0.5).unwrap();
+    assert_eq!(dict.frequency("你好"), 5);
+    assert_eq!(dict.bigram_frequency("你好", "世界"), 2);
+    // 1 * 0.5 rounds down to zero and is deleted
+    assert_eq!(dict.iter_all(), vec![("你好".to_string(), 5)]);
+
+    dict.decay(0.0).unwrap();
+    assert!(dict.iter_all().is_empty());
+    assert!(dict.export_bigrams().is_empty());
+}
+
+#[test]
+fn set_frequency_replaces_learned_count() {
+    let dict = temp_userdict("set_frequency");
+    dict.learn_with_count("你好", 7).unwrap();
+
+    dict.set_frequency("你好", 3).unwrap();
+    assert_eq!(dict.frequency("你好"), 3);
+    dict.set_frequency("中国", 2).unwrap();
+    assert_eq!(dict.frequency("中国"), 2);
+    dict.set_frequency("你好", 0).unwrap();
+    assert_eq!(dict.iter_all(), vec![("中国".to_string(), 2)]);
+}
+
+#[test]
+fn recency_weighting_halves_with_age() {
+    let dict = temp_userdict("recency");
+    let now = 1_000_000_000;
+    dict.learn_with_count_at("新词", 4, now).unwrap();
+    dict.learn_with_count_at("旧词", 4, now - RECENCY_HALF_LIFE_SECS)
+        .unwrap();
+    dict.add_phrase("导入", 4).unwrap();
+
+    assert_eq!(dict.last_used("新词"), Some(now));
+    assert_eq!(dict.last_used("导入"), None);
+
+    // alpha 0 is the raw count, whatever the age
+    assert_eq!(dict.recency_weighted_frequency("旧词", 0.0, now), 4.0);
+    assert_eq!(dict.recency_weighted_frequency("新词", 1.0, now), 4.0);
+    assert_eq!(dict.recency_weighted_frequency("旧词", 1.0, now), 2.0);
+    assert_eq!(dict.recency_weighted_frequency("旧词", 0.5, now), 3.0);
+    assert_eq!(dict.recency_weighted_frequency("导入", 1.0, now), 0.0);
+
+    assert!(dict.forget("新词").unwrap());
+    assert_eq!(dict.last_used("新词"), None);
+}
+
+#[test]
+fn bigrams_after_report_last_used_time() {
+    let dict = temp_userdict("bigram_time");
+    dict.learn_bigram_with_count_at("好", "的", 2, 1_000)
+        .unwrap();
+    dict.learn_bigram_with_count_at("好", "吗", 1, 2_000)
+        .unwrap();
+    dict.learn_bigram_with_count_at("好", "吗", 1, 3_000)
+        .unwrap();
+    dict.learn_bigram("不", "好");
+
+    let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+    assert_eq!(
+        dict.get_bigrams_after_with_time("好"),
+        vec![
+            ("吗".to_string(), 2, at(3_000)),
+            ("的".to_string(), 2, at(1_000))
+        ]
+    );
+    assert!(dict.get_bigrams_after_with_time("你").is_empty());
+}
+
+#[test]
+fn snapshot_round_trips_all_learning() {
+    let (local, other) = overlapping_pair("snapshot");
+    local
+        .learn_segmentation("xian", &["xi".into(), "an".into()])
+        .unwrap();
+    local.learn_rejection("shi", "事").unwrap();
+    local.learn_punctuation(',', "、").unwrap();
+
+    let snap = local.export_snapshot();
+    assert_eq!(snap.version, SNAPSHOT_VERSION);
+    assert_eq!(snap.phrases.get("你好"), Some(&3));
+    assert_eq!(snap.bigrams.len(), 1);
+
+    // Survives serialization
+    let bytes = bincode::serialize(&snap).unwrap();
+    let restored: UserDictSnapshot = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(restored, snap);
+
+    // Replace: the target ends up identical to the source
+    other
+        .import_snapshot(&restored, ImportMode::Replace)
+        .unwrap();
+    assert_eq!(other.export_snapshot(), snap);
+    assert_eq!(other.frequency("世界"), 0);
+    assert_eq!(
+        other.preferred_segmentation("xian"),
+        Some(vec!["xi".to_string(), "an".to_string()])
+    );
+    assert_eq!(other.rejection_count("shi", "事"), 1);
+    assert_eq!(other.punctuation_preferences(',').get("、"), Some(&1));
+
+    // Merge: counts add up
+    other.import_snapshot(&restored, ImportMode::Merge).unwrap();
+    assert_eq!(other.frequency("你好"), 6);
+    assert_eq!(other.bigram_frequency("你好", "世界"), 4);
+    assert_eq!(other.last_used("你好"), local.last_used("你好"));
+}
+
+#[test]
+fn in_memory_userdict_learns_without_a_path() {
+    let dict = UserDict::in_memory().unwrap();
+    dict.learn("你好");
+    dict.learn_bigram("你好", "世界");
+    assert_eq!(dict.frequency("你好"), 1);
+    assert_eq!(dict.bigram_frequency("你好", "世界"), 1);
+
+    // Each in-memory dictionary is independent
+    assert_eq!(UserDict::in_memory().unwrap().frequency("你好"), 0);
+}
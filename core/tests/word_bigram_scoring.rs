@@ -0,0 +1,106 @@
+//! Batch scoring of candidates that share a context.
+
+use libchinese_core::{Config, WordBigram};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts allocations made by the current thread, so tests running in
+/// parallel do not disturb each other.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn words(list: &[&str]) -> Vec<String> {
+    list.iter().map(|w| w.to_string()).collect()
+}
+
+/// "今天" followed by 1000 candidates, a tenth of them seen after it.
+fn model_and_candidates() -> (WordBigram, Vec<Vec<String>>) {
+    let mut wb = WordBigram::new();
+    wb.add_unigram("今天".to_string(), 50);
+    wb.add_unigram("天气".to_string(), 20);
+    let mut candidates = Vec::new();
+    for i in 0..1000 {
+        let word = format!("词{}", i);
+        wb.add_unigram(word.clone(), 1 + i % 7);
+        if i % 10 == 0 {
+            wb.add_bigram("今天".to_string(), word.clone(), 1 + i % 5);
+        }
+        if i % 3 == 0 {
+            wb.add_bigram(word.clone(), "天气".to_string(), 2);
+        }
+        candidates.push(vec![word, "天气".to_string()]);
+    }
+    (wb, candidates)
+}
+
+#[test]
+fn score_candidates_matches_score_sequence() {
+    let (wb, candidates) = model_and_candidates();
+    let cfg = Config::default();
+
+    for context in [words(&["今天"]), words(&["天气", "今天"]), Vec::new()] {
+        let scores = wb.score_candidates(&context, &candidates, &cfg);
+        assert_eq!(scores.len(), candidates.len());
+        for (candidate, score) in candidates.iter().zip(&scores) {
+            let sequence: Vec<String> = context.iter().chain(candidate).cloned().collect();
+            let expected = wb.score_sequence(&sequence, &cfg) as f32;
+            assert!(
+                (score - expected).abs() < 1e-5,
+                "{:?}: {} vs {}",
+                sequence,
+                score,
+                expected
+            );
+        }
+    }
+
+    // An empty candidate scores as the context alone
+    let context = words(&["今天"]);
+    let scores = wb.score_candidates(&context, &[Vec::new()], &cfg);
+    assert_eq!(scores[0], wb.score_sequence(&context, &cfg) as f32);
+}
+
+#[test]
+fn score_candidates_does_not_allocate_per_candidate() {
+    let (wb, candidates) = model_and_candidates();
+    let cfg = Config::default();
+    let context = words(&["今天"]);
+
+    let before = allocations();
+    let scores = wb.score_candidates(&context, &candidates, &cfg);
+    let batched = allocations() - before;
+    assert_eq!(scores.len(), 1000);
+
+    // One sequence per candidate, as a caller without the batch API would
+    let before = allocations();
+    for candidate in &candidates {
+        let sequence: Vec<String> = context.iter().chain(candidate).cloned().collect();
+        std::hint::black_box(wb.score_sequence(&sequence, &cfg));
+    }
+    let one_by_one = allocations() - before;
+
+    // The shared continuation table and the result, not one per candidate
+    assert!(batched < 50, "{} allocations for 1000 candidates", batched);
+    assert!(batched * 10 < one_by_one, "{} vs {}", batched, one_by_one);
+}
@@ -0,0 +1,63 @@
+/*
+ * libchinese C API
+ *
+ * Opaque IME handles for pinyin and zhuyin, for IME frameworks such as
+ * Fcitx and IBus. Keys are X11 keysyms with X11 modifier bits. Strings are
+ * UTF-8; strings returned by the library are owned by the caller and must
+ * be released with chinese_ime_free_string().
+ *
+ * Link against libchinese_ffi (cdylib or staticlib built by cargo).
+ */
+
+#ifndef LIBCHINESE_H
+#define LIBCHINESE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Modifier bits (X11 ShiftMask / ControlMask) */
+#define CHINESE_IME_MOD_SHIFT (1u << 0)
+#define CHINESE_IME_MOD_CONTROL (1u << 2)
+
+/* chinese_ime_process_key() results */
+#define CHINESE_IME_HANDLED 1
+#define CHINESE_IME_NOT_HANDLED 0
+#define CHINESE_IME_ERROR (-1)
+
+typedef struct ChineseIme ChineseIme;
+
+/* Constructors return NULL if the data cannot be loaded. page_size 0 uses
+ * the default. layout is "standard", "eten" or "hsu" (NULL: "standard").
+ * userdict_path overrides where the pinyin user dictionary is kept (NULL:
+ * under $HOME). */
+ChineseIme *chinese_ime_new_pinyin(const char *data_dir, size_t page_size);
+ChineseIme *chinese_ime_new_pinyin_with_userdict(const char *data_dir, const char *userdict_path,
+                                                 size_t page_size);
+ChineseIme *chinese_ime_new_zhuyin(const char *data_dir, const char *layout, size_t page_size);
+void chinese_ime_destroy(ChineseIme *handle);
+
+/* Feed a key press; returns one of the CHINESE_IME_* results above. */
+int chinese_ime_process_key(ChineseIme *handle, uint32_t keycode, uint32_t modifiers);
+void chinese_ime_reset(ChineseIme *handle);
+
+/* State after the last key. Returned strings must be freed. */
+char *chinese_ime_get_preedit(const ChineseIme *handle);
+char *chinese_ime_get_commit(const ChineseIme *handle);
+char *chinese_ime_get_auxiliary(const ChineseIme *handle);
+
+/* Candidates on the current page. */
+size_t chinese_ime_candidate_count(const ChineseIme *handle);
+size_t chinese_ime_candidate_cursor(const ChineseIme *handle);
+char *chinese_ime_get_candidate(const ChineseIme *handle, size_t index);
+
+void chinese_ime_free_string(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LIBCHINESE_H */
@@ -0,0 +1,430 @@
+//! C ABI for the libchinese IME engines.
+//!
+//! IME frameworks such as Fcitx and IBus are written in C/C++ and cannot call
+//! `ImeEngine` directly. This crate wraps an engine in an opaque
+//! `ChineseIme` handle and exposes `extern "C"` functions to create and
+//! destroy it, feed it keys and read back the preedit, commit text and
+//! candidates. The matching declarations are in `include/libchinese.h`.
+//!
+//! Conventions:
+//! - Keys are X11 keysyms (what Fcitx and IBus deliver), with modifier bits
+//!   as in X11 (`CHINESE_IME_MOD_CONTROL` = ControlMask).
+//! - Strings are UTF-8. Strings returned by the library are owned by the
+//!   caller and must be released with `chinese_ime_free_string`.
+//! - A panic never unwinds into C: every entry point catches it and returns
+//!   its error value (`-1`, `NULL` or `0`).
+
+use libchinese_core::{ImeContext, ImeEngine, KeyEvent, KeyResult, SyllableParser};
+use libzhuyin::ZhuyinLayout;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+/// Shift modifier bit (X11 ShiftMask).
+pub const CHINESE_IME_MOD_SHIFT: u32 = 1 << 0;
+/// Control modifier bit (X11 ControlMask).
+pub const CHINESE_IME_MOD_CONTROL: u32 = 1 << 2;
+
+/// `chinese_ime_process_key` result: the IME consumed the key.
+pub const CHINESE_IME_HANDLED: c_int = 1;
+/// `chinese_ime_process_key` result: pass the key to the application.
+pub const CHINESE_IME_NOT_HANDLED: c_int = 0;
+/// `chinese_ime_process_key` result: null handle or internal error.
+pub const CHINESE_IME_ERROR: c_int = -1;
+
+/// Page size used when a constructor is passed 0.
+const DEFAULT_PAGE_SIZE: usize = 5;
+
+/// The parts of `ImeEngine` the C API needs, independent of the parser.
+trait Ime {
+    fn process_key(&mut self, key: KeyEvent) -> KeyResult;
+    fn context(&self) -> &ImeContext;
+    fn reset(&mut self);
+}
+
+impl<P: SyllableParser> Ime for ImeEngine<P> {
+    fn process_key(&mut self, key: KeyEvent) -> KeyResult {
+        ImeEngine::process_key(self, key)
+    }
+
+    fn context(&self) -> &ImeContext {
+        ImeEngine::context(self)
+    }
+
+    fn reset(&mut self) {
+        ImeEngine::reset(self)
+    }
+}
+
+/// Opaque IME handle.
+pub struct ChineseIme {
+    engine: Box<dyn Ime>,
+}
+
+/// Translate an X11 keysym and modifier mask to a `KeyEvent`.
+///
+/// Returns `None` for keys the IME has no use for (function keys, modifiers
+/// pressed alone, ...).
+pub fn key_event(keysym: u32, modifiers: u32) -> Option<KeyEvent> {
+    let key = match keysym {
+        0x0020 => KeyEvent::Space,
+        0xff08 => KeyEvent::Backspace,
+        0xffff => KeyEvent::Delete,
+        0xff51 => KeyEvent::Left,
+        0xff52 => KeyEvent::Up,
+        0xff53 => KeyEvent::Right,
+        0xff54 => KeyEvent::Down,
+        0xff55 => KeyEvent::PageUp,
+        0xff56 => KeyEvent::PageDown,
+        0xff0d | 0xff8d => KeyEvent::Enter,
+        0xff1b => KeyEvent::Escape,
+        0xffe5 => KeyEvent::ShiftLock,
+        _ => {
+            // Latin-1 keysyms are their code point; other Unicode keysyms
+            // are the code point plus 0x01000000
+            let code = match keysym {
+                0x0021..=0x007e | 0x00a0..=0x00ff => keysym,
+                0x0100_0100..=0x0110_ffff => keysym - 0x0100_0000,
+                _ => return None,
+            };
+            let ch = char::from_u32(code)?;
+            if modifiers & CHINESE_IME_MOD_CONTROL != 0 {
+                KeyEvent::Ctrl(ch)
+            } else {
+                KeyEvent::Char(ch)
+            }
+        }
+    };
+    Some(key)
+}
+
+/// Run `f`, turning a panic into `default` so it never crosses the C ABI.
+fn guard<T>(default: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+/// Borrow the engine behind a handle, if the handle is not null.
+///
+/// # Safety
+/// `handle` must be null or a live pointer from a `chinese_ime_new_*`
+/// constructor.
+unsafe fn engine<'a>(handle: *const ChineseIme) -> Option<&'a dyn Ime> {
+    handle.as_ref().map(|ime| ime.engine.as_ref())
+}
+
+/// Read a C path argument.
+///
+/// # Safety
+/// `path` must be null or a valid null-terminated string.
+unsafe fn path_arg(path: *const c_char) -> Option<PathBuf> {
+    if path.is_null() {
+        return None;
+    }
+    CStr::from_ptr(path).to_str().ok().map(PathBuf::from)
+}
+
+/// Hand a string to C. Interior NUL bytes (never produced by the engine)
+/// are dropped.
+fn to_c_string(text: &str) -> *mut c_char {
+    let bytes: Vec<u8> = text.bytes().filter(|&b| b != 0).collect();
+    CString::new(bytes).unwrap_or_default().into_raw()
+}
+
+fn into_handle(engine: Box<dyn Ime>) -> *mut ChineseIme {
+    Box::into_raw(Box::new(ChineseIme { engine }))
+}
+
+fn page_size_or_default(page_size: usize) -> usize {
+    if page_size == 0 {
+        DEFAULT_PAGE_SIZE
+    } else {
+        page_size
+    }
+}
+
+/// Create a pinyin IME from a data directory (see
+/// `libpinyin::Engine::from_data_dir`).
+///
+/// `page_size` is the number of candidates per page; 0 uses the default.
+/// Returns NULL if the data cannot be loaded.
+///
+/// # Safety
+/// `data_dir` must be null or a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_new_pinyin(
+    data_dir: *const c_char,
+    page_size: usize,
+) -> *mut ChineseIme {
+    chinese_ime_new_pinyin_with_userdict(data_dir, ptr::null(), page_size)
+}
+
+/// Like `chinese_ime_new_pinyin`, keeping the user dictionary at
+/// `userdict_path` instead of under `$HOME` (see
+/// `libpinyin::Engine::from_data_dir_with_userdict`). NULL uses the default
+/// location.
+///
+/// # Safety
+/// `data_dir` and `userdict_path` must be null or valid null-terminated
+/// UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_new_pinyin_with_userdict(
+    data_dir: *const c_char,
+    userdict_path: *const c_char,
+    page_size: usize,
+) -> *mut ChineseIme {
+    guard(ptr::null_mut(), || {
+        let Some(dir) = path_arg(data_dir) else {
+            return ptr::null_mut();
+        };
+        let engine = if userdict_path.is_null() {
+            libpinyin::Engine::from_data_dir(dir)
+        } else {
+            let Some(userdict) = path_arg(userdict_path) else {
+                return ptr::null_mut();
+            };
+            libpinyin::Engine::from_data_dir_with_userdict(dir, userdict)
+        };
+        match engine {
+            Ok(engine) => into_handle(Box::new(ImeEngine::from_arc_with_page_size(
+                engine.inner_arc(),
+                page_size_or_default(page_size),
+            ))),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Create a zhuyin IME from a data directory, typed on keyboard `layout`
+/// ("standard", "eten" or "hsu"; NULL means "standard").
+///
+/// `page_size` is the number of candidates per page; 0 uses the default.
+/// Returns NULL for an unknown layout or if the data cannot be loaded.
+///
+/// # Safety
+/// `data_dir` and `layout` must be null or valid null-terminated UTF-8
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_new_zhuyin(
+    data_dir: *const c_char,
+    layout: *const c_char,
+    page_size: usize,
+) -> *mut ChineseIme {
+    guard(ptr::null_mut(), || {
+        let Some(dir) = path_arg(data_dir) else {
+            return ptr::null_mut();
+        };
+        let layout = if layout.is_null() {
+            Some(ZhuyinLayout::Standard)
+        } else {
+            CStr::from_ptr(layout)
+                .to_str()
+                .ok()
+                .and_then(ZhuyinLayout::from_name)
+        };
+        let page_size = page_size_or_default(page_size);
+        let engine = match layout {
+            Some(ZhuyinLayout::Standard) => libzhuyin::create_ime_engine_standard(dir, page_size),
+            Some(ZhuyinLayout::Eten) => libzhuyin::create_ime_engine_eten(dir, page_size),
+            Some(ZhuyinLayout::Hsu) => libzhuyin::create_ime_engine_hsu(dir, page_size),
+            None => return ptr::null_mut(),
+        };
+        match engine {
+            Ok(engine) => into_handle(Box::new(engine)),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Destroy a handle. NULL is ignored.
+///
+/// # Safety
+/// `handle` must be null or a live handle; it must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_destroy(handle: *mut ChineseIme) {
+    if handle.is_null() {
+        return;
+    }
+    guard((), || drop(Box::from_raw(handle)));
+}
+
+/// Feed a key press (X11 keysym plus modifier mask).
+///
+/// Returns `CHINESE_IME_HANDLED` if the IME consumed the key,
+/// `CHINESE_IME_NOT_HANDLED` if the application should handle it, and
+/// `CHINESE_IME_ERROR` for a null handle or an internal error.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_process_key(
+    handle: *mut ChineseIme,
+    keycode: u32,
+    modifiers: u32,
+) -> c_int {
+    let Some(ime) = handle.as_mut() else {
+        return CHINESE_IME_ERROR;
+    };
+    guard(CHINESE_IME_ERROR, || {
+        let Some(key) = key_event(keycode, modifiers) else {
+            return CHINESE_IME_NOT_HANDLED;
+        };
+        match ime.engine.process_key(key) {
+            KeyResult::Handled => CHINESE_IME_HANDLED,
+            KeyResult::NotHandled => CHINESE_IME_NOT_HANDLED,
+        }
+    })
+}
+
+/// Clear the composition and candidates.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_reset(handle: *mut ChineseIme) {
+    if let Some(ime) = handle.as_mut() {
+        guard((), || ime.engine.reset());
+    }
+}
+
+/// Read a context string field as a new C string.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+unsafe fn context_string(
+    handle: *const ChineseIme,
+    field: impl FnOnce(&ImeContext) -> &str,
+) -> *mut c_char {
+    let Some(engine) = engine(handle) else {
+        return ptr::null_mut();
+    };
+    guard(ptr::null_mut(), || to_c_string(field(engine.context())))
+}
+
+/// Text being composed. Free with `chinese_ime_free_string`; NULL for a
+/// null handle.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_get_preedit(handle: *const ChineseIme) -> *mut c_char {
+    context_string(handle, |ctx| &ctx.preedit_text)
+}
+
+/// Text committed by the last key ("" if none). Free with
+/// `chinese_ime_free_string`; NULL for a null handle.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_get_commit(handle: *const ChineseIme) -> *mut c_char {
+    context_string(handle, |ctx| &ctx.commit_text)
+}
+
+/// Auxiliary text (page numbers, input hints). Free with
+/// `chinese_ime_free_string`; NULL for a null handle.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_get_auxiliary(handle: *const ChineseIme) -> *mut c_char {
+    context_string(handle, |ctx| &ctx.auxiliary_text)
+}
+
+/// Number of candidates on the current page (0 for a null handle).
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_candidate_count(handle: *const ChineseIme) -> usize {
+    let Some(engine) = engine(handle) else {
+        return 0;
+    };
+    guard(0, || engine.context().candidates.len())
+}
+
+/// Index of the highlighted candidate on the current page (0 for a null
+/// handle).
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_candidate_cursor(handle: *const ChineseIme) -> usize {
+    let Some(engine) = engine(handle) else {
+        return 0;
+    };
+    guard(0, || engine.context().candidate_cursor)
+}
+
+/// Candidate `index` on the current page. Free with
+/// `chinese_ime_free_string`; NULL for a null handle or an index out of
+/// range.
+///
+/// # Safety
+/// `handle` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_get_candidate(
+    handle: *const ChineseIme,
+    index: usize,
+) -> *mut c_char {
+    let Some(engine) = engine(handle) else {
+        return ptr::null_mut();
+    };
+    guard(ptr::null_mut(), || {
+        engine
+            .context()
+            .candidates
+            .get(index)
+            .map_or(ptr::null_mut(), |text| to_c_string(text))
+    })
+}
+
+/// Free a string returned by this library. NULL is ignored.
+///
+/// # Safety
+/// `text` must be null or a pointer returned by a `chinese_ime_get_*`
+/// function that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chinese_ime_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keysyms_map_to_key_events() {
+        assert_eq!(key_event('n' as u32, 0), Some(KeyEvent::Char('n')));
+        assert_eq!(key_event('1' as u32, 0), Some(KeyEvent::Char('1')));
+        assert_eq!(key_event(0x20, 0), Some(KeyEvent::Space));
+        assert_eq!(key_event(0xff0d, 0), Some(KeyEvent::Enter));
+        assert_eq!(key_event(0xff08, 0), Some(KeyEvent::Backspace));
+        assert_eq!(
+            key_event('.' as u32, CHINESE_IME_MOD_CONTROL),
+            Some(KeyEvent::Ctrl('.'))
+        );
+        // Unicode keysym for ㄅ
+        assert_eq!(key_event(0x0100_3105, 0), Some(KeyEvent::Char('ㄅ')));
+        // F1 and a bare Shift are not IME keys
+        assert_eq!(key_event(0xffbe, 0), None);
+        assert_eq!(key_event(0xffe1, CHINESE_IME_MOD_SHIFT), None);
+    }
+
+    #[test]
+    fn null_handles_are_rejected() {
+        unsafe {
+            assert_eq!(
+                chinese_ime_process_key(ptr::null_mut(), 'a' as u32, 0),
+                CHINESE_IME_ERROR
+            );
+            assert!(chinese_ime_get_preedit(ptr::null()).is_null());
+            assert_eq!(chinese_ime_candidate_count(ptr::null()), 0);
+            assert!(chinese_ime_new_pinyin(ptr::null(), 0).is_null());
+            chinese_ime_destroy(ptr::null_mut());
+            chinese_ime_free_string(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Drives a pinyin IME through the C API, as a C caller would.
+//!
+//! A small lexicon is written to a temporary data directory so the test does
+//! not depend on the converted data files.
+
+use libchinese_ffi::*;
+use std::ffi::{c_char, CStr, CString};
+
+/// Write `lexicon.fst` and `lexicon.bincode` under `dir`.
+fn write_lexicon(dir: &std::path::Path, groups: &[(&str, &[(&str, u32)])]) {
+    libchinese_core::Lexicon::write_fst_bincode(
+        dir.join("lexicon.fst"),
+        dir.join("lexicon.bincode"),
+        groups,
+    )
+    .unwrap();
+}
+
+/// Copy a library-owned string and free it.
+unsafe fn take_string(ptr: *mut c_char) -> String {
+    assert!(!ptr.is_null());
+    let text = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+    chinese_ime_free_string(ptr);
+    text
+}
+
+#[test]
+fn nihao_through_the_c_api() {
+    let root = std::env::temp_dir().join(format!("libchinese_ffi_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_lexicon(
+        &root.join("data"),
+        &[("ni'hao", &[("你好", 100)]), ("ni", &[("你", 50)])],
+    );
+
+    let data_dir = CString::new(root.join("data").to_str().unwrap()).unwrap();
+    // Keep the user dictionary out of $HOME
+    let userdict = CString::new(root.join("userdict.redb").to_str().unwrap()).unwrap();
+    unsafe {
+        let ime = chinese_ime_new_pinyin_with_userdict(data_dir.as_ptr(), userdict.as_ptr(), 0);
+        assert!(!ime.is_null());
+
+        for ch in "nihao".chars() {
+            assert_eq!(
+                chinese_ime_process_key(ime, ch as u32, 0),
+                CHINESE_IME_HANDLED
+            );
+        }
+
+        assert!(!take_string(chinese_ime_get_preedit(ime)).is_empty());
+        let count = chinese_ime_candidate_count(ime);
+        assert!(count >= 1);
+        let candidates: Vec<String> = (0..count)
+            .map(|i| take_string(chinese_ime_get_candidate(ime, i)))
+            .collect();
+        assert_eq!(candidates[0], "你好");
+        assert!(chinese_ime_get_candidate(ime, count).is_null());
+        assert_eq!(chinese_ime_candidate_cursor(ime), 0);
+
+        // Space (keysym 0x20) commits the highlighted candidate
+        assert_eq!(chinese_ime_process_key(ime, 0x20, 0), CHINESE_IME_HANDLED);
+        assert_eq!(take_string(chinese_ime_get_commit(ime)), "你好");
+        assert!(take_string(chinese_ime_get_preedit(ime)).is_empty());
+
+        // Keys the IME has no use for (F1) go to the application
+        assert_eq!(
+            chinese_ime_process_key(ime, 0xffbe, 0),
+            CHINESE_IME_NOT_HANDLED
+        );
+
+        chinese_ime_destroy(ime);
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn missing_data_yields_null_handle() {
+    let data_dir = CString::new("/nonexistent/libchinese/data").unwrap();
+    unsafe {
+        assert!(chinese_ime_new_pinyin(data_dir.as_ptr(), 0).is_null());
+        let layout = CString::new("dvorak").unwrap();
+        assert!(chinese_ime_new_zhuyin(data_dir.as_ptr(), layout.as_ptr(), 0).is_null());
+    }
+}
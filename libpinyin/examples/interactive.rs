@@ -11,7 +11,7 @@ fn build_model() -> Result<Model, Box<dyn std::error::Error>> {
 
     // Load lexicon from fst + bincode (required)
     let lx = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path)?;
-    println!(
+    eprintln!(
         "✓ Loaded lexicon from '{}' + '{}'",
         fst_path.display(),
         bincode_path.display()
@@ -37,7 +37,7 @@ fn build_model() -> Result<Model, Box<dyn std::error::Error>> {
         if wb_path.exists() {
             match libchinese_core::WordBigram::load(&wb_path) {
                 Ok(wb) => {
-                    println!("✓ Loaded word bigram from {:?}", wb_path);
+                    eprintln!("✓ Loaded word bigram from {:?}", wb_path);
                     wb
                 }
                 Err(e) => {
@@ -67,13 +67,24 @@ fn print_candidate(key: &str, cand: &Candidate, idx: usize) {
     println!("   chars: [{}]", chars.join(", "));
 }
 
-fn run_repl() {
+/// One line of `repl --json` output.
+#[derive(serde::Serialize)]
+struct JsonReply<'a> {
+    input: &'a str,
+    candidates: &'a [Candidate],
+}
+
+fn run_repl(json: bool) {
     let model =
         build_model().expect("Failed to load model. Ensure data files exist in data/ directory.");
     let engine = libpinyin::Engine::new(model);
 
-    println!("libpinyin demo CLI — type pinyin input (e.g. 'nihao' or 'zhongguo') and press Enter");
-    println!("Ctrl-D to exit.");
+    if !json {
+        println!(
+            "libpinyin demo CLI — type pinyin input (e.g. 'nihao' or 'zhongguo') and press Enter"
+        );
+        println!("Ctrl-D to exit.");
+    }
 
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
@@ -83,8 +94,20 @@ fn run_repl() {
                 if input.is_empty() {
                     continue;
                 }
-                println!("\nInput: '{}'", input);
                 let cands = engine.input(input);
+                if json {
+                    // Newline-delimited JSON: one object per input line
+                    let reply = JsonReply {
+                        input,
+                        candidates: &cands,
+                    };
+                    match serde_json::to_string(&reply) {
+                        Ok(line) => println!("{}", line),
+                        Err(e) => eprintln!("error encoding candidates: {}", e),
+                    }
+                    continue;
+                }
+                println!("\nInput: '{}'", input);
                 if cands.is_empty() {
                     println!("  (no candidates found)");
                 } else {
@@ -116,7 +139,11 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Interactive REPL mode
-    Repl,
+    Repl {
+        /// Print candidates as newline-delimited JSON, one object per input line
+        #[arg(long)]
+        json: bool,
+    },
     /// Build data models from text corpus
     Build {
         /// Input text corpus file
@@ -949,8 +976,8 @@ fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Some(Commands::Repl) => {
-            run_repl();
+        Some(Commands::Repl { json }) => {
+            run_repl(json);
         }
         Some(Commands::Build {
             input,
@@ -1010,7 +1037,7 @@ fn main() {
                 handle_test_command(TestMode::Candidates, &input, None, 10, false);
             } else {
                 // No arguments, start REPL
-                run_repl();
+                run_repl(false);
             }
         }
     }
@@ -12,6 +12,11 @@
 //! 4. **ABC** - ABC input method scheme
 //! 5. **XiaoHe** (小鹤) - Little crane scheme
 //!
+//! Other schemes can be defined in a TOML file, loaded with
+//! [`DoublePinyinSchemeData::from_toml`] and registered with
+//! [`register_custom_scheme`]; they are then available as
+//! [`DoublePinyinScheme::Custom`] (or `"custom:<name>"` in the parser).
+//!
 //! ## How it works
 //!
 //! Each full pinyin syllable is represented by 2 keys:
@@ -27,7 +32,22 @@
 //! - Microsoft scheme: Most widely used in Windows
 //! - ZiRanMa: Popular alternative with different mappings
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+/// Initials a custom scheme may map keys to.
+const PINYIN_INITIALS: &[&str] = &[
+    "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "j", "q", "x", "zh", "ch", "sh", "r",
+    "z", "c", "s", "y", "w",
+];
+
+/// Finals a custom scheme may map keys to (`v` spells ü).
+const PINYIN_FINALS: &[&str] = &[
+    "a", "o", "e", "i", "u", "v", "ai", "ei", "ao", "ou", "an", "en", "ang", "eng", "ong", "er",
+    "ia", "ie", "iao", "iu", "ian", "in", "iang", "ing", "iong", "ua", "uo", "uai", "ui", "uan",
+    "un", "uang", "ue", "ve", "vn",
+];
 
 /// Double pinyin schemes supported by the parser.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,6 +64,8 @@ pub enum DoublePinyinScheme {
     XiaoHe,
     /// PinYin++ scheme
     PinYinPlusPlus,
+    /// User-defined scheme registered with [`register_custom_scheme`]
+    Custom(&'static str),
 }
 
 /// Mapping tables for a double pinyin scheme.
@@ -66,8 +88,240 @@ pub struct DoublePinyinSchemeData {
     pub special: HashMap<char, &'static str>,
 }
 
+/// Why `Parser::convert_double_pinyin_checked` rejected its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DoublePinyinError {
+    /// The scheme name is not built in or registered
+    UnknownScheme(String),
+    /// A key pair that the scheme cannot decode
+    InvalidKeys {
+        /// Char offset of the pair's first key in the input
+        position: usize,
+        /// The offending pair
+        keys: (char, char),
+    },
+    /// The custom scheme registry is poisoned (a thread panicked while
+    /// holding its lock)
+    RegistryPoisoned,
+}
+
+impl std::fmt::Display for DoublePinyinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DoublePinyinError::UnknownScheme(name) => {
+                write!(f, "unknown shuangpin scheme \"{}\"", name)
+            }
+            DoublePinyinError::InvalidKeys { position, keys } => write!(
+                f,
+                "invalid shuangpin key \"{}{}\" at position {}",
+                keys.0, keys.1, position
+            ),
+            DoublePinyinError::RegistryPoisoned => {
+                write!(f, "custom shuangpin scheme registry is poisoned")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DoublePinyinError {}
+
+/// On-disk layout of a custom scheme file.
+#[derive(serde::Deserialize)]
+struct SchemeFile {
+    name: Option<String>,
+    #[serde(default)]
+    initials: HashMap<String, String>,
+    finals: HashMap<String, String>,
+    #[serde(default)]
+    special: HashMap<String, String>,
+}
+
+impl DoublePinyinSchemeData {
+    /// Load a scheme definition from a TOML file.
+    ///
+    /// The file maps single keys to initials and finals; keys without an
+    /// initial entry keep their own letter as the initial. `name` defaults
+    /// to the file stem and `special` to the aa/ee/oo syllables:
+    ///
+    /// ```toml
+    /// name = "myscheme"
+    ///
+    /// [initials]
+    /// v = "zh"
+    /// i = "ch"
+    /// u = "sh"
+    ///
+    /// [finals]
+    /// a = "a"
+    /// h = "ang"
+    /// ```
+    ///
+    /// Every mapped value is checked against the pinyin initials and finals,
+    /// so a typo is reported here rather than producing bogus syllables.
+    pub fn from_toml<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let file: SchemeFile = toml::from_str(&content)
+            .map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+
+        let name = file.name.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        let shengmu = scheme_table(path, "initials", "initial", &file.initials, PINYIN_INITIALS)?;
+        let yunmu = scheme_table(path, "finals", "final", &file.finals, PINYIN_FINALS)?;
+        let special = if file.special.is_empty() {
+            HashMap::from([('a', "a"), ('e', "e"), ('o', "o")])
+        } else {
+            scheme_table(path, "special", "final", &file.special, PINYIN_FINALS)?
+        };
+
+        Ok(DoublePinyinSchemeData {
+            name: intern_scheme_name(&name).map_err(|e| e.to_string())?,
+            shengmu,
+            yunmu,
+            special,
+        })
+    }
+}
+
+/// Validate one section of a scheme file: keys must be single lowercase
+/// letters and values members of `allowed` (`kind` names them in errors).
+fn scheme_table(
+    path: &Path,
+    section: &str,
+    kind: &str,
+    entries: &HashMap<String, String>,
+    allowed: &[&'static str],
+) -> Result<HashMap<char, &'static str>, String> {
+    let mut map = HashMap::new();
+    for (key, value) in entries {
+        let mut chars = key.chars();
+        let key_char = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_lowercase() => c,
+            _ => {
+                return Err(format!(
+                    "{}: [{}] key \"{}\" is not a single lowercase letter",
+                    path.display(),
+                    section,
+                    key
+                ))
+            }
+        };
+        let Some(&known) = allowed.iter().find(|&&a| a == value) else {
+            return Err(format!(
+                "{}: [{}] key '{}' maps to \"{}\", which is not a pinyin {}",
+                path.display(),
+                section,
+                key_char,
+                value,
+                kind
+            ));
+        };
+        map.insert(key_char, known);
+    }
+    Ok(map)
+}
+
+/// Custom scheme names, leaked once each so they can be `&'static` like
+/// the built-in tables and `DoublePinyinScheme` can stay `Copy`.
+fn intern_scheme_name(name: &str) -> Result<&'static str, DoublePinyinError> {
+    static NAMES: OnceLock<RwLock<HashSet<&'static str>>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| RwLock::new(HashSet::new()));
+
+    let mut names = names
+        .write()
+        .map_err(|_| DoublePinyinError::RegistryPoisoned)?;
+    if let Some(&interned) = names.get(name) {
+        return Ok(interned);
+    }
+    let interned: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(interned);
+    Ok(interned)
+}
+
+/// Schemes registered with [`register_custom_scheme`], keyed by name.
+fn custom_schemes() -> &'static RwLock<HashMap<&'static str, DoublePinyinSchemeData>> {
+    static SCHEMES: OnceLock<RwLock<HashMap<&'static str, DoublePinyinSchemeData>>> =
+        OnceLock::new();
+    SCHEMES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Make a custom scheme available as `DoublePinyinScheme::Custom(name)`.
+///
+/// Replaces any scheme previously registered under the same name.
+pub fn register_custom_scheme(
+    name: &str,
+    data: DoublePinyinSchemeData,
+) -> Result<(), DoublePinyinError> {
+    let name = intern_scheme_name(name)?;
+    custom_schemes()
+        .write()
+        .map_err(|_| DoublePinyinError::RegistryPoisoned)?
+        .insert(name, data);
+    Ok(())
+}
+
+/// Whether a custom scheme is registered under `name`.
+pub fn has_custom_scheme(name: &str) -> Result<bool, DoublePinyinError> {
+    Ok(lookup_custom_scheme(name)?.is_some())
+}
+
+/// The registered name of a custom scheme, if there is one.
+fn lookup_custom_scheme(name: &str) -> Result<Option<&'static str>, DoublePinyinError> {
+    let schemes = custom_schemes()
+        .read()
+        .map_err(|_| DoublePinyinError::RegistryPoisoned)?;
+    Ok(schemes.get_key_value(name).map(|(&key, _)| key))
+}
+
 impl DoublePinyinScheme {
+    /// All supported schemes.
+    pub const ALL: [DoublePinyinScheme; 6] = [
+        DoublePinyinScheme::Microsoft,
+        DoublePinyinScheme::ZiRanMa,
+        DoublePinyinScheme::ZiGuang,
+        DoublePinyinScheme::ABC,
+        DoublePinyinScheme::XiaoHe,
+        DoublePinyinScheme::PinYinPlusPlus,
+    ];
+
+    /// Parse a scheme name as accepted by `Parser::convert_double_pinyin`.
+    ///
+    /// Built-in names are case-insensitive ("microsoft", "XiaoHe", ...);
+    /// `"custom:<name>"` refers to a registered custom scheme and yields
+    /// None if nothing is registered under that name (`parse` says why a
+    /// name is rejected).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::parse(name).ok()
+    }
+
+    /// Like `from_name`, reporting why a name is rejected.
+    pub fn parse(name: &str) -> Result<Self, DoublePinyinError> {
+        let unknown = || DoublePinyinError::UnknownScheme(name.to_string());
+        if let Some(custom) = name.strip_prefix("custom:") {
+            return lookup_custom_scheme(custom)?
+                .map(DoublePinyinScheme::Custom)
+                .ok_or_else(unknown);
+        }
+
+        match name.to_lowercase().as_str() {
+            "microsoft" => Ok(DoublePinyinScheme::Microsoft),
+            "ziranma" => Ok(DoublePinyinScheme::ZiRanMa),
+            "ziguang" => Ok(DoublePinyinScheme::ZiGuang),
+            "abc" => Ok(DoublePinyinScheme::ABC),
+            "xiaohe" => Ok(DoublePinyinScheme::XiaoHe),
+            "pinyinplusplus" => Ok(DoublePinyinScheme::PinYinPlusPlus),
+            _ => Err(unknown()),
+        }
+    }
+
     /// Get the mapping data for this scheme.
+    ///
+    /// An unregistered custom scheme has no mappings, so nothing converts;
+    /// the same goes for any custom scheme if the registry is poisoned.
     pub fn data(&self) -> DoublePinyinSchemeData {
         match self {
             DoublePinyinScheme::Microsoft => microsoft_scheme(),
@@ -76,6 +330,16 @@ impl DoublePinyinScheme {
             DoublePinyinScheme::ABC => abc_scheme(),
             DoublePinyinScheme::XiaoHe => xiaohe_scheme(),
             DoublePinyinScheme::PinYinPlusPlus => pinyinpp_scheme(),
+            DoublePinyinScheme::Custom(name) => custom_schemes()
+                .read()
+                .ok()
+                .and_then(|schemes| schemes.get(name).cloned())
+                .unwrap_or_else(|| DoublePinyinSchemeData {
+                    name: "Custom",
+                    shengmu: HashMap::new(),
+                    yunmu: HashMap::new(),
+                    special: HashMap::new(),
+                }),
         }
     }
 }
@@ -451,6 +715,58 @@ pub fn double_to_full_pinyin(
     Some(format!("{}{}", initial, final_part))
 }
 
+/// Find the 2-key input for a full pinyin syllable (inverse of
+/// [`double_to_full_pinyin`]).
+///
+/// Searches key pairs in alphabetical order, so when a scheme maps several
+/// keys to the same final the first one wins. Returns None if the scheme
+/// cannot type the syllable.
+pub fn full_to_double_pinyin(
+    syllable: &str,
+    scheme: &DoublePinyinSchemeData,
+) -> Option<(char, char)> {
+    ('a'..='z')
+        .flat_map(|first| ('a'..='z').map(move |second| (first, second)))
+        .find(|&(first, second)| {
+            double_to_full_pinyin(first, second, scheme).as_deref() == Some(syllable)
+        })
+}
+
+/// Convert a double pinyin key sequence from one scheme to another.
+///
+/// Each key pair is decoded to full pinyin with `from` and re-encoded with
+/// `to`. Returns None if the input has an odd number of keys, contains a
+/// pair `from` cannot decode, or yields a syllable `to` cannot type.
+///
+/// # Example
+/// ```
+/// # use libpinyin::double_pinyin::{convert_scheme, DoublePinyinScheme};
+/// // "bei" is "bz" in Microsoft but "bw" in XiaoHe
+/// use DoublePinyinScheme::{Microsoft, XiaoHe};
+/// assert_eq!(convert_scheme("bz", Microsoft, XiaoHe), Some("bw".to_string()));
+/// ```
+pub fn convert_scheme(
+    input: &str,
+    from: DoublePinyinScheme,
+    to: DoublePinyinScheme,
+) -> Option<String> {
+    let chars: Vec<char> = input.chars().collect();
+    if !chars.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let from = from.data();
+    let to = to.data();
+    let mut output = String::with_capacity(input.len());
+    for pair in chars.chunks(2) {
+        let syllable = double_to_full_pinyin(pair[0], pair[1], &from)?;
+        let (first, second) = full_to_double_pinyin(&syllable, &to)?;
+        output.push(first);
+        output.push(second);
+    }
+    Some(output)
+}
+
 /// Helper to get single-char initials as static str
 fn get_single_char_initial(c: char) -> Option<&'static str> {
     match c {
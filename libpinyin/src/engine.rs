@@ -9,7 +9,10 @@ use std::error::Error;
 use std::sync::Arc;
 
 use crate::parser::Parser;
-use libchinese_core::{Candidate, Lexicon, Model, UserDict};
+use libchinese_core::{
+    Candidate, CorpusEval, DatasetInfo, KeystrokePlan, Lexicon, Model, OriginStats, SelectionStats,
+    UserDict,
+};
 
 /// Public engine for libpinyin.
 ///
@@ -77,41 +80,90 @@ impl Engine {
         Arc::clone(&self.inner)
     }
 
+    /// List the converted datasets available under a data directory.
+    ///
+    /// See `libchinese_core::discover_datasets`. Useful for picking a
+    /// directory to pass to `from_data_dir` instead of probing fixed paths.
+    pub fn discover_datasets<P: AsRef<std::path::Path>>(data_dir: P) -> Vec<DatasetInfo> {
+        libchinese_core::discover_datasets(data_dir)
+    }
+
     /// Load an engine from a model directory containing runtime artifacts.
     ///
     /// Expected layout (data-dir):
     ///  - lexicon.fst + lexicon.bincode    (lexicon)
+    ///  - lexicon.reverse.bin              (optional prebuilt reverse index,
+    ///    see `Lexicon::save_reverse_index`; built at load time otherwise)
     ///  - word_bigram.bin                  (word-level bigrams)
     ///  - userdict.redb                    (persistent user dictionary)
+    ///
+    /// The user dictionary lives at `~/.pinyin/userdict.redb`; use
+    /// `from_data_dir_with_userdict` to put it elsewhere.
     pub fn from_data_dir<P: AsRef<std::path::Path>>(data_dir: P) -> Result<Self, Box<dyn Error>> {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+        let ud_path = std::path::PathBuf::from(home)
+            .join(".pinyin")
+            .join("userdict.redb");
+        Self::from_data_dir_with_userdict(data_dir, ud_path)
+    }
+
+    /// Like `from_data_dir`, with the user dictionary at `userdict_path`
+    /// instead of under `$HOME`. Missing parent directories are created.
+    pub fn from_data_dir_with_userdict<P, Q>(
+        data_dir: P,
+        userdict_path: Q,
+    ) -> Result<Self, Box<dyn Error>>
+    where
+        P: AsRef<std::path::Path>,
+        Q: AsRef<std::path::Path>,
+    {
         let data_dir = data_dir.as_ref();
 
         // Load lexicon from fst + bincode (required)
         let fst_path = data_dir.join("lexicon.fst");
         let bincode_path = data_dir.join("lexicon.bincode");
 
-        let lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).map_err(|e| {
+        let mut lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).map_err(|e| {
             format!(
                 "failed to load lexicon from {:?} and {:?}: {}",
                 fst_path, bincode_path, e
             )
         })?;
 
-        // Userdict: use persistent userdict at ~/.pinyin/userdict.redb
+        // Load shape-code index if present
+        let shape_path = data_dir.join("lexicon.shape.fst");
+        if shape_path.exists() {
+            if let Err(e) = lex.load_shape_index(&shape_path) {
+                eprintln!("warning: failed to load lexicon.shape.fst: {}", e);
+            }
+        }
+
+        // Reverse index for keystroke plans, compound splits and the like:
+        // prebuilt if present, otherwise built now
+        let reverse_path = data_dir.join("lexicon.reverse.bin");
+        let mut reverse_loaded = false;
+        if reverse_path.exists() {
+            match lex.load_reverse_index(&reverse_path) {
+                Ok(()) => reverse_loaded = true,
+                Err(e) => eprintln!("warning: failed to load lexicon.reverse.bin: {}", e),
+            }
+        }
+        if !reverse_loaded {
+            lex.build_reverse_index();
+        }
+
+        // Persistent userdict
         let userdict = {
-            let home = std::env::var("HOME")
-                .or_else(|_| std::env::var("USERPROFILE"))
-                .unwrap_or_else(|_| ".".to_string());
-            let ud_path = std::path::PathBuf::from(home)
-                .join(".pinyin")
-                .join("userdict.redb");
+            let ud_path = userdict_path.as_ref();
 
             // Create directory if needed
             if let Some(parent) = ud_path.parent() {
                 let _ = std::fs::create_dir_all(parent);
             }
 
-            UserDict::new(&ud_path)?
+            UserDict::new(ud_path)?
         };
 
         // Load word bigram if present
@@ -161,11 +213,7 @@ impl Engine {
 
     /// Get cache size (number of cached entries)
     pub fn cache_size(&self) -> usize {
-        // Note: the core engine doesn't expose cache size directly
-        // This is an approximation based on cache stats
-        let (hits, misses) = self.inner.cache_stats();
-        // Rough estimate: total queries is an upper bound on cache size
-        hits + misses
+        self.inner.cache_size()
     }
 
     /// Clear the cache
@@ -173,6 +221,42 @@ impl Engine {
         self.inner.clear_cache();
     }
 
+    /// Return the `k` best candidates with user learning applied.
+    ///
+    /// See `libchinese_core::Engine::candidates_top_k`.
+    pub fn candidates_top_k(&self, input: &str, k: usize) -> Vec<Candidate> {
+        self.inner.candidates_top_k(input, k)
+    }
+
+    /// Candidates for `input` as (text, reading, score).
+    ///
+    /// See `libchinese_core::Engine::candidates_with_readings`.
+    pub fn candidates_with_readings(&self, input: &str) -> Vec<(String, String, f32)> {
+        self.inner.candidates_with_readings(input)
+    }
+
+    /// The single best Hanzi conversion of a whole pinyin sentence.
+    ///
+    /// See `libchinese_core::Engine::convert_sentence`.
+    pub fn convert_sentence(&self, input: &str) -> String {
+        self.inner.convert_sentence(input)
+    }
+
+    /// The `n` best whole-sentence conversions with their scores.
+    ///
+    /// See `libchinese_core::Engine::convert_sentence_nbest`.
+    pub fn convert_sentence_nbest(&self, input: &str, n: usize) -> Vec<(String, f32)> {
+        self.inner.convert_sentence_nbest(input, n)
+    }
+
+    /// The top candidate split into known sub-words, one candidate per part.
+    ///
+    /// See `libchinese_core::Engine::compound_split_group`. Empty unless
+    /// `Config::offer_compound_splits` is enabled.
+    pub fn compound_split_group(&self, input: &str) -> Vec<Candidate> {
+        self.inner.compound_split_group(input)
+    }
+
     /// Commit a phrase to the user dictionary (learning).
     ///
     /// This increases the frequency/score for the given phrase, allowing the
@@ -191,6 +275,123 @@ impl Engine {
         self.inner.commit(phrase);
     }
 
+    /// Record that the user skipped `rejected_phrase` for `input`.
+    ///
+    /// See `libchinese_core::Engine::record_rejection`.
+    pub fn record_rejection(&self, input: &str, rejected_phrase: &str) {
+        self.inner.record_rejection(input, rejected_phrase);
+    }
+
+    /// Learn words and word bigrams from a block of Chinese text.
+    ///
+    /// See `libchinese_core::Engine::train_from_text`. Returns the number of
+    /// words learned.
+    pub fn train_from_text(&self, text: &str) -> usize {
+        self.inner.train_from_text(text)
+    }
+
+    /// Compute the minimal input + selection needed to commit a phrase.
+    ///
+    /// See `libchinese_core::Engine::keystrokes_to_commit`.
+    pub fn keystrokes_to_commit(&self, target: &str) -> Option<KeystrokePlan> {
+        self.inner.keystrokes_to_commit(target)
+    }
+
+    /// `keystrokes_to_commit` for `page_size` candidates per page.
+    ///
+    /// See `libchinese_core::Engine::keystrokes_to_commit_with_page_size`.
+    pub fn keystrokes_to_commit_with_page_size(
+        &self,
+        target: &str,
+        page_size: usize,
+    ) -> Option<KeystrokePlan> {
+        self.inner
+            .keystrokes_to_commit_with_page_size(target, page_size)
+    }
+
+    /// Predict words likely to follow `context`, best first.
+    ///
+    /// See `libchinese_core::Engine::predict_next`.
+    pub fn predict_next(&self, context: &str, limit: usize) -> Vec<(String, f32)> {
+        self.inner.predict_next(context, limit)
+    }
+
+    /// Confidence in the top prediction after `context`, in [0, 1].
+    ///
+    /// See `libchinese_core::Engine::prediction_confidence`.
+    pub fn prediction_confidence(&self, context: &str) -> f32 {
+        self.inner.prediction_confidence(context)
+    }
+
+    /// Estimate keystrokes per character and top-1 accuracy over a corpus.
+    ///
+    /// See `libchinese_core::Engine::evaluate_corpus`.
+    pub fn evaluate_corpus(&self, sentences: &[&str]) -> CorpusEval {
+        self.inner.evaluate_corpus(sentences)
+    }
+
+    /// Count the candidates for `input` by lexicon vs composed origin.
+    ///
+    /// See `libchinese_core::Engine::candidate_origin_stats`.
+    pub fn candidate_origin_stats(&self, input: &str) -> OriginStats {
+        self.inner.candidate_origin_stats(input)
+    }
+
+    /// Aggregate a log of (input, chosen phrase) pairs into per-input
+    /// selection counts.
+    ///
+    /// See `libchinese_core::Engine::aggregate_selection_stats`.
+    pub fn aggregate_selection_stats(&self, log: &[(String, String)]) -> SelectionStats {
+        self.inner.aggregate_selection_stats(log)
+    }
+
+    /// Seed the user dictionary from aggregated selection statistics.
+    ///
+    /// See `libchinese_core::Engine::apply_selection_stats`. Returns the
+    /// number of entries applied.
+    pub fn apply_selection_stats(&self, stats: &SelectionStats) -> usize {
+        self.inner.apply_selection_stats(stats)
+    }
+
+    /// Learn the segmentation of a manually split input (e.g. "xi'an").
+    ///
+    /// Next time the same input is typed without apostrophes ("xian"), the
+    /// learned split is preferred. The IME calls this on every commit.
+    pub fn learn_segmentation(&self, input: &str) {
+        self.inner.learn_segmentation(input);
+    }
+
+    /// Warm the candidate cache for likely completions of `prefix`.
+    ///
+    /// See `libchinese_core::Engine::prefetch_completions`. Returns the
+    /// inputs that were prefetched; empty unless `Config::typeahead_prefetch`
+    /// is enabled.
+    pub fn prefetch_completions(&self, prefix: &str) -> Vec<String> {
+        self.inner.prefetch_completions(prefix)
+    }
+
+    /// Install a filter that can veto commits; `false` blocks the text.
+    ///
+    /// See `libchinese_core::Engine::set_commit_filter`.
+    pub fn set_commit_filter<F>(&self, filter: F)
+    where
+        F: Fn(&str) -> bool + Send + 'static,
+    {
+        self.inner.set_commit_filter(filter);
+    }
+
+    /// Remove the commit filter.
+    pub fn clear_commit_filter(&self) {
+        self.inner.clear_commit_filter();
+    }
+
+    /// Load an emoji lexicon (`<name>.fst` plus `<name>.bincode`).
+    ///
+    /// See `libchinese_core::Engine::load_emoji_lexicon`.
+    pub fn load_emoji_lexicon<Q: AsRef<std::path::Path>>(&self, fst_path: Q) -> Result<(), String> {
+        self.inner.load_emoji_lexicon(fst_path)
+    }
+
     /// Get reference to the user dictionary for learning.
     ///
     /// Provides access to user-learned data including user bigrams
@@ -209,6 +410,13 @@ impl Engine {
         self.inner.config_mut()
     }
 
+    /// Dump the effective configuration, fuzzy rules and dataset sizes as TOML.
+    ///
+    /// See `libchinese_core::Engine::dump_effective_config`.
+    pub fn dump_effective_config(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.inner.dump_effective_config()?)
+    }
+
     /// Main input API. Returns ranked `Candidate` items for the given raw input.
     ///
     /// Delegates to core::Engine which handles:
@@ -220,4 +428,11 @@ impl Engine {
     pub fn input(&self, input: &str) -> Vec<Candidate> {
         self.inner.input(input)
     }
+
+    /// Candidates with syllable breaks pinned at `boundaries`.
+    ///
+    /// See `libchinese_core::Engine::input_with_boundaries`.
+    pub fn input_with_boundaries(&self, input: &str, boundaries: &[usize]) -> Vec<Candidate> {
+        self.inner.input_with_boundaries(input, boundaries)
+    }
 }
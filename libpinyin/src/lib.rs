@@ -14,6 +14,8 @@ pub mod config;
 pub mod double_pinyin;
 pub mod engine;
 pub mod parser;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 // Re-export IME components from core (now at root level, not in ime::)
 pub use libchinese_core::{
@@ -24,9 +26,39 @@ pub use libchinese_core::{
 
 // Convenience re-exports for common types used by callers.
 pub use config::PinyinConfig;
-pub use double_pinyin::{get_scheme_data, DoublePinyinScheme, DoublePinyinSchemeData};
+pub use double_pinyin::{
+    get_scheme_data, register_custom_scheme, DoublePinyinError, DoublePinyinScheme,
+    DoublePinyinSchemeData,
+};
 pub use engine::{Engine, PINYIN_SYLLABLES};
-pub use parser::{Parser, Syllable};
+pub use parser::{
+    Parser, SegmentState, Syllable, ToneMode, DEFAULT_MAX_SEGMENTATIONS, INCOMPLETE_RULE,
+};
+
+/// Double pinyin schemes a settings UI can offer.
+pub fn available_double_pinyin_schemes() -> Vec<DoublePinyinScheme> {
+    DoublePinyinScheme::ALL.to_vec()
+}
+
+/// Names of the built-in fuzzy rule sets, for `fuzzy_profile_rules`.
+///
+/// - "standard": `standard_fuzzy_rules`, the full upstream set
+/// - "basic": `config::pinyin_default_fuzzy_rules`, initials and nasal finals only
+/// - "none": exact matching
+pub fn available_fuzzy_profiles() -> Vec<&'static str> {
+    vec!["standard", "basic", "none"]
+}
+
+/// Rules of a fuzzy profile from `available_fuzzy_profiles`, or None for an
+/// unknown name.
+pub fn fuzzy_profile_rules(name: &str) -> Option<Vec<String>> {
+    match name {
+        "standard" => Some(standard_fuzzy_rules()),
+        "basic" => Some(config::pinyin_default_fuzzy_rules()),
+        "none" => Some(Vec::new()),
+        _ => None,
+    }
+}
 
 /// Configuration for standard pinyin fuzzy matching rules.
 ///
@@ -14,28 +14,359 @@
 // - Verify exact parity with upstream DP cost model
 // - Add comprehensive test vectors from upstream test suite
 
+use crate::double_pinyin::DoublePinyinError;
+use libchinese_core::input_buffer::split_at_boundaries;
 use libchinese_core::FuzzyMap;
+use libchinese_core::MatchKind;
 use libchinese_core::TrieNode;
 
 /// A single matched syllable (a chunk of pinyin).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Syllable {
     /// The syllable text as stored in the syllable set (e.g. "ni", "hao", "zhong").
+    /// A ü typed another way than the syllable set spells it keeps the
+    /// typed spelling ("nü", "lyue"); see `canonical`.
     pub text: String,
 
-    /// Whether this match was produced via a fuzzy rule (placeholder boolean).
-    /// In a full implementation this would carry more information:
-    /// which rule, penalty applied, and mapping direction.
+    /// Syllable-set spelling of a ü syllable whose `text` was typed another
+    /// way ("nv" for "nü" or "nu:", "xue" for "xüe"); this is the spelling
+    /// lexicon keys use. `None` when `text` already is that spelling.
+    pub canonical: Option<String>,
+
+    /// Whether this match was produced via a fuzzy rule, a correction or an
+    /// incomplete-syllable match. Kept for compatibility; parser output sets
+    /// it exactly when `rule` is `Some`.
     pub fuzzy: bool,
+
+    /// Segment cost the parser charged for this syllable (lower is better).
+    /// 0.0 for syllables not produced by a parser.
+    pub cost: f32,
+
+    /// Why the syllable differs from what was typed: the fuzzy pair in typed
+    /// order ("zi=zhi"), the correction ("ue→ve"), or "incomplete" for a
+    /// syllable prefix. `None` for exact matches.
+    pub rule: Option<String>,
+
+    /// Tone typed for this syllable (1-4, or `NEUTRAL_TONE`), parsed off the
+    /// input under `ToneMode::Numeric` or `ToneMode::Diacritic`. `None` when
+    /// no tone was typed.
+    pub tone: Option<u8>,
 }
 
 impl Syllable {
     pub fn new<T: Into<String>>(text: T, fuzzy: bool) -> Self {
         Self {
             text: text.into(),
+            canonical: None,
             fuzzy,
+            cost: 0.0,
+            rule: None,
+            tone: None,
+        }
+    }
+
+    /// Set the segment cost.
+    pub fn with_cost(mut self, cost: f32) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Record the rule that produced this syllable; marks it fuzzy.
+    pub fn with_rule<T: Into<String>>(mut self, rule: T) -> Self {
+        self.fuzzy = true;
+        self.rule = Some(rule.into());
+        self
+    }
+
+    /// Set the typed tone.
+    pub fn with_tone(mut self, tone: Option<u8>) -> Self {
+        self.tone = tone;
+        self
+    }
+
+    /// The spelling lexicon keys use: `canonical` if set, else `text`.
+    pub fn lexicon_text(&self) -> &str {
+        self.canonical.as_deref().unwrap_or(&self.text)
+    }
+
+    /// Whether a tone-tagged lexicon reading (e.g. "hao3") fits this
+    /// syllable's text and typed tone.
+    ///
+    /// An untoned reading, or a syllable typed without a tone, accepts any
+    /// tone; see `libchinese_core::tone::tone_matches` for the neutral tone.
+    pub fn matches_reading(&self, reading: &str, neutral_matches_any: bool) -> bool {
+        let (base, stored) = libchinese_core::tone::split_tone(reading);
+        base == self.lexicon_text()
+            && libchinese_core::tone::tone_matches(self.tone, stored, neutral_matches_any)
+    }
+}
+
+/// How the parser treats tones typed with the pinyin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToneMode {
+    /// No tone parsing: tone digits and marks are unknown characters.
+    #[default]
+    Ignore,
+    /// A tone digit (1-5, 0 for the neutral tone) may follow each syllable,
+    /// as in "ni3hao3". The digit also ends the syllable, like an apostrophe.
+    Numeric,
+    /// Tone-marked vowels are accepted, as in "nǐhǎo".
+    Diacritic,
+}
+
+/// `Syllable::rule` of incomplete-syllable matches.
+pub const INCOMPLETE_RULE: &str = "incomplete";
+
+/// Default cap on the number of paths `Parser::segment_all` enumerates.
+pub const DEFAULT_MAX_SEGMENTATIONS: usize = 256;
+
+/// Most extra beam width `segment_top_k` adds for long input (one per four
+/// characters), so a pasted blob does not widen the beam without bound.
+const MAX_EXTRA_BEAM_WIDTH: usize = 16;
+
+/// Partial segmentation explored by `segment_top_k` and `segment_all`.
+#[derive(Clone)]
+struct State {
+    pos: usize,
+    tokens: Vec<Syllable>,
+    cost: f32,
+    parsed: usize,
+    keys: usize,
+    dist: i32,
+}
+
+// Comparator used for ranking states (lower is better)
+fn state_cmp(a: &State, b: &State) -> std::cmp::Ordering {
+    // primary: cost (smaller better)
+    if (a.cost - b.cost).abs() > 1e-6 {
+        return a
+            .cost
+            .partial_cmp(&b.cost)
+            .unwrap_or(std::cmp::Ordering::Equal);
+    }
+    // tie: prefer larger parsed
+    if a.parsed != b.parsed {
+        return b.parsed.cmp(&a.parsed);
+    }
+    // tie: prefer fewer keys
+    if a.keys != b.keys {
+        return a.keys.cmp(&b.keys);
+    }
+    // tie: prefer smaller distance
+    a.dist.cmp(&b.dist)
+}
+
+/// One DP transition: consume the input up to `end` as `syllable`, whose
+/// `cost` is the step's cost.
+struct Step {
+    end: usize,
+    syllable: Syllable,
+    dist: i32,
+}
+
+/// Best path found so far for one DP position.
+///
+/// `choice` holds the other end of the chosen step (the next position in the
+/// backward DP of `segment_best`, the previous one in `segment_incremental`)
+/// plus the step's syllable. `step` is the chosen step's index in
+/// `steps_from` of the position it starts at. Costs are summed in f64 so
+/// that paths tied in f32 step costs still tie whichever end the DP runs from.
+#[derive(Debug, Clone)]
+struct Cell {
+    cost: f64,
+    parsed: usize,
+    keys: usize,
+    dist: i32,
+    choice: Option<(usize, Syllable)>,
+    step: usize,
+}
+
+impl Cell {
+    fn unreached() -> Self {
+        Self {
+            cost: f64::INFINITY,
+            parsed: 0,
+            keys: usize::MAX,
+            dist: i32::MAX,
+            choice: None,
+            step: 0,
+        }
+    }
+
+    /// Zero-cost cell at the end the DP starts from.
+    fn origin() -> Self {
+        Self {
+            cost: 0.0,
+            parsed: 0,
+            keys: 0,
+            dist: 0,
+            choice: None,
+            step: 0,
+        }
+    }
+
+    fn is_reached(&self) -> bool {
+        self.cost.is_finite()
+    }
+
+    /// This path extended by `step` (the `index`-th step from its start),
+    /// which covers `span` characters.
+    fn extend(&self, step: &Step, index: usize, span: usize, link: usize) -> Self {
+        Self {
+            cost: self.cost + f64::from(step.syllable.cost),
+            parsed: self.parsed + span,
+            keys: self.keys + 1,
+            dist: self.dist + step.dist,
+            choice: Some((link, step.syllable.clone())),
+            step: index,
+        }
+    }
+
+    /// Whether this path should replace `other`.
+    fn beats(&self, other: &Cell) -> bool {
+        // primary: strictly lower cost
+        if self.cost < other.cost {
+            return true;
+        }
+        // nearly-equal cost: prefer more parsed, then fewer keys, then less distance
+        if (self.cost - other.cost).abs() < 1e-6 {
+            if self.parsed != other.parsed {
+                return self.parsed > other.parsed;
+            }
+            if self.keys != other.keys {
+                return self.keys < other.keys;
+            }
+            return self.dist < other.dist;
+        }
+        false
+    }
+}
+
+/// Canonicalize the ways users type ü to the syllable table's spelling.
+///
+/// The table writes ü as `v` after l/n ("nv", "lve") and as `u` after
+/// j/q/x/y ("ju", "yue"), following standard pinyin. Typed `ü`, `u:` and
+/// `lyu` become `v`, and a `v` after j/q/x/y becomes `u`. Everything else
+/// (including plain `u`, as in "guang") is left alone. The input is also
+/// lowercased and stripped of whitespace.
+///
+/// Returns the canonical input and, for each of its chars, the typed
+/// spelling it stands for (`Syllable::text` keeps those).
+fn canonicalize_umlaut(input: &str) -> (String, Vec<String>) {
+    let mut chars = Vec::with_capacity(input.len());
+    let mut typed = Vec::with_capacity(input.len());
+    for ch in input.chars().flat_map(char::to_lowercase) {
+        if !ch.is_whitespace() {
+            push_canonical(&mut chars, &mut typed, ch);
         }
     }
+    (chars.into_iter().collect(), typed)
+}
+
+/// Append one lowercased typed char to canonical input (see
+/// `canonicalize_umlaut`), keeping `typed` in step with `chars`.
+///
+/// Returns the char (and its typed spelling) taken back off the end when
+/// `ch` completes a longer spelling ("u" then ":" is one `v`).
+fn push_canonical(
+    chars: &mut Vec<char>,
+    typed: &mut Vec<String>,
+    ch: char,
+) -> Option<(char, String)> {
+    let mut popped = None;
+    let mut spelling = ch.to_string();
+    let canonical = match ch {
+        'ü' => 'v',
+        ':' if chars.ends_with(&['u']) => {
+            popped = chars.pop().zip(typed.pop());
+            spelling = "u:".to_string();
+            'v'
+        }
+        'u' if chars.ends_with(&['l', 'y']) => {
+            popped = chars.pop().zip(typed.pop());
+            spelling = "yu".to_string();
+            'v'
+        }
+        _ => ch,
+    };
+    let canonical = match (chars.last(), canonical) {
+        (Some('j' | 'q' | 'x' | 'y'), 'v') => 'u',
+        _ => canonical,
+    };
+    chars.push(canonical);
+    typed.push(spelling);
+    popped
+}
+
+/// `syllable`, matched exactly on `spelled` (canonical input), with its
+/// typed spelling `typed` restored when that spells ü another way (see
+/// `Syllable::canonical`). Fuzzy and other inexact matches are returned
+/// as they are.
+fn with_typed_text(mut syllable: Syllable, spelled: &[char], typed: &[String]) -> Syllable {
+    if typed.len() != spelled.len() || !syllable.text.chars().eq(spelled.iter().copied()) {
+        return syllable;
+    }
+    // Tone marks are not part of the spelling
+    let as_typed: String = typed
+        .iter()
+        .flat_map(|t| t.chars())
+        .map(|c| libchinese_core::tone::strip_tone_mark(c).map_or(c, |(bare, _)| bare))
+        .collect();
+    if as_typed != syllable.text {
+        syllable.canonical = Some(std::mem::replace(&mut syllable.text, as_typed));
+    }
+    syllable
+}
+
+/// First tone typed on `tones[start..end]`, if any (`tones` may be empty).
+fn tone_in(tones: &[Option<u8>], start: usize, end: usize) -> Option<u8> {
+    tones
+        .get(start..end.min(tones.len()))
+        .and_then(|span| span.iter().flatten().next().copied())
+}
+
+/// Cached DP tables for `Parser::segment_incremental`.
+///
+/// Holds the normalized input typed so far and, for every prefix of it, the
+/// best segmentation of that prefix. Keep one per composition and pass it to
+/// `segment_incremental` / `segment_backspace` on each keystroke; call
+/// `clear` when the composition is committed or cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct SegmentState {
+    /// Normalized (lowercased, ü-canonicalized) input
+    chars: Vec<char>,
+    /// Typed spelling of each char of `chars` (see `canonicalize_umlaut`)
+    typed: Vec<String>,
+    /// cells[i] is the best segmentation of chars[..i]
+    cells: Vec<Cell>,
+    /// Fuzzy setting the cells were computed with
+    allow_fuzzy: bool,
+    /// Per typed character: the length `chars` was cut back to and the chars
+    /// removed with their typed spellings, so backspace can restore them
+    /// (typing ':' after 'u' turns it into 'v')
+    edits: Vec<(usize, Vec<(char, String)>)>,
+}
+
+impl SegmentState {
+    /// An empty state; the first `segment_incremental` call fills it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The normalized input the cached tables describe.
+    pub fn input(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// True if nothing has been typed (or everything was deleted).
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Forget all typed input.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
 }
 
 /// Parser providing segmentation using a trie and fuzzy rules.
@@ -52,6 +383,12 @@ impl Syllable {
 pub struct Parser {
     trie: TrieNode,
     fuzzy: FuzzyMap,
+    max_segmentations: usize,
+    /// Length in chars of the longest inserted syllable
+    max_syllable_len: usize,
+    tone_mode: ToneMode,
+    /// Shuangpin scheme typed input is converted with, if any
+    double_pinyin_scheme: Option<String>,
 }
 
 impl Default for Parser {
@@ -68,9 +405,66 @@ impl Parser {
         Self {
             trie: TrieNode::new(),
             fuzzy: FuzzyMap::from_rules(&rules),
+            max_segmentations: DEFAULT_MAX_SEGMENTATIONS,
+            max_syllable_len: 0,
+            tone_mode: ToneMode::Ignore,
+            double_pinyin_scheme: None,
         }
     }
 
+    /// Set how many paths `segment_all` enumerates at most (default
+    /// `DEFAULT_MAX_SEGMENTATIONS`).
+    pub fn with_max_segmentations(mut self, max: usize) -> Self {
+        self.max_segmentations = max;
+        self
+    }
+
+    /// Cap on the number of paths `segment_all` enumerates.
+    pub fn max_segmentations(&self) -> usize {
+        self.max_segmentations
+    }
+
+    /// Set how typed tones are parsed (default `ToneMode::Ignore`).
+    ///
+    /// Applies to `segment_best` and `segment_top_k` and their variants.
+    /// Parsed tones are stored in `Syllable::tone`; input without tones
+    /// segments the same in every mode.
+    ///
+    /// # Example
+    /// ```
+    /// use libpinyin::{Parser, ToneMode, PINYIN_SYLLABLES};
+    ///
+    /// let mut parser = Parser::with_syllables(PINYIN_SYLLABLES);
+    /// parser.set_tone_mode(ToneMode::Numeric);
+    /// let syllables = parser.segment_best("ni3hao", false);
+    /// assert_eq!(syllables[0].text, "ni");
+    /// assert_eq!(syllables[0].tone, Some(3));
+    /// assert_eq!(syllables[1].tone, None);
+    /// ```
+    pub fn set_tone_mode(&mut self, mode: ToneMode) {
+        self.tone_mode = mode;
+    }
+
+    /// How typed tones are parsed.
+    pub fn tone_mode(&self) -> ToneMode {
+        self.tone_mode
+    }
+
+    /// Treat input as double pinyin in the given scheme (as accepted by
+    /// `convert_double_pinyin`), or as full pinyin with `None` (default).
+    ///
+    /// Applies to `segment_best` and `segment_top_k`, and so to an `Engine`
+    /// built on this parser, which then also reports undecodable key pairs
+    /// through `SyllableParser::input_error`.
+    pub fn set_double_pinyin_scheme(&mut self, scheme_name: Option<&str>) {
+        self.double_pinyin_scheme = scheme_name.map(str::to_string);
+    }
+
+    /// The double pinyin scheme input is converted with, if any.
+    pub fn double_pinyin_scheme(&self) -> Option<&str> {
+        self.double_pinyin_scheme.as_deref()
+    }
+
     /// Create a parser and insert a list of syllables.
     pub fn with_syllables<T: AsRef<str>>(syllables: &[T]) -> Self {
         let mut p = Parser::new();
@@ -85,6 +479,7 @@ impl Parser {
         // canonicalize: lower-case and trim
         let key = syllable.trim().to_ascii_lowercase();
         if !key.is_empty() {
+            self.max_syllable_len = self.max_syllable_len.max(key.chars().count());
             self.trie.insert(&key);
         }
     }
@@ -106,6 +501,12 @@ impl Parser {
             .collect()
     }
 
+    /// Mutable access to the fuzzy map, e.g. to toggle single rules from a
+    /// settings panel. Changes apply to the next segmentation.
+    pub fn fuzzy_map_mut(&mut self) -> &mut FuzzyMap {
+        &mut self.fuzzy
+    }
+
     /// Convert double pinyin (shuangpin) input to full pinyin using the specified scheme.
     ///
     /// Processes input in 2-character chunks, converting each to a full pinyin syllable.
@@ -113,7 +514,8 @@ impl Parser {
     ///
     /// # Arguments
     /// * `input` - The double pinyin input string (e.g., "uihn" for "shuang")
-    /// * `scheme_name` - The scheme name ("Microsoft", "ZiRanMa", etc.)
+    /// * `scheme_name` - The scheme name ("Microsoft", "ZiRanMa", etc.), or
+    ///   "custom:<name>" for a scheme registered with `register_custom_scheme`
     ///
     /// # Returns
     /// * `Some(String)` - The converted full pinyin string
@@ -121,16 +523,8 @@ impl Parser {
     pub fn convert_double_pinyin(&self, input: &str, scheme_name: &str) -> Option<String> {
         use crate::double_pinyin::DoublePinyinScheme;
 
-        // Parse scheme name
-        let scheme = match scheme_name.to_lowercase().as_str() {
-            "microsoft" => DoublePinyinScheme::Microsoft,
-            "ziranma" => DoublePinyinScheme::ZiRanMa,
-            "ziguang" => DoublePinyinScheme::ZiGuang,
-            "abc" => DoublePinyinScheme::ABC,
-            "xiaohe" => DoublePinyinScheme::XiaoHe,
-            "pinyinplusplus" => DoublePinyinScheme::PinYinPlusPlus,
-            _ => return None,
-        };
+        // Parse scheme name (built-in or "custom:<name>")
+        let scheme = DoublePinyinScheme::from_name(scheme_name)?;
 
         // Process input: split by non-alphanumeric (punctuation, spaces)
         let mut result = String::new();
@@ -161,6 +555,74 @@ impl Parser {
         Some(result)
     }
 
+    /// Convert double pinyin input to full pinyin, rejecting undecodable keys.
+    ///
+    /// Unlike `convert_double_pinyin`, which passes a key pair it cannot
+    /// decode through as literal letters, this reports the first such pair
+    /// and its char position. Non-alphanumeric chars pass through, and a
+    /// trailing unpaired key is kept as typed (the syllable is incomplete).
+    ///
+    /// # Example
+    /// ```
+    /// use libpinyin::{DoublePinyinError, Parser};
+    ///
+    /// let parser = Parser::new();
+    /// assert_eq!(
+    ///     parser.convert_double_pinyin_checked("uihf", "microsoft"),
+    ///     Ok("shihen".to_string())
+    /// );
+    /// assert_eq!(
+    ///     parser.convert_double_pinyin_checked("ui;h", "microsoft"),
+    ///     Ok("shi;h".to_string())
+    /// );
+    /// assert_eq!(
+    ///     parser.convert_double_pinyin_checked("uih1", "microsoft"),
+    ///     Err(DoublePinyinError::InvalidKeys { position: 2, keys: ('h', '1') })
+    /// );
+    /// ```
+    pub fn convert_double_pinyin_checked(
+        &self,
+        input: &str,
+        scheme_name: &str,
+    ) -> Result<String, DoublePinyinError> {
+        use crate::double_pinyin::{double_to_full_pinyin, DoublePinyinScheme};
+
+        let scheme = DoublePinyinScheme::parse(scheme_name)?;
+        let data = scheme.data();
+
+        let chars: Vec<char> = input.chars().collect();
+        let mut result = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let first = chars[i];
+            if !first.is_ascii_alphanumeric() {
+                result.push(first);
+                i += 1;
+                continue;
+            }
+
+            match chars.get(i + 1) {
+                Some(&second) if second.is_ascii_alphanumeric() => {
+                    let full = double_to_full_pinyin(first, second, &data).ok_or(
+                        DoublePinyinError::InvalidKeys {
+                            position: i,
+                            keys: (first, second),
+                        },
+                    )?;
+                    result.push_str(&full);
+                    i += 2;
+                }
+                // Unpaired key at the end of a word
+                _ => {
+                    result.push(first);
+                    i += 1;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
     /// Convert a single double pinyin word to full pinyin.
     ///
     /// Processes input in 2-character chunks. Handles odd-length input by
@@ -216,7 +678,8 @@ impl Parser {
     pub fn segment_best(&self, input: &str, allow_fuzzy: bool) -> Vec<Syllable> {
         // Use default config penalties
         let config = libchinese_core::Config::default();
-        self.segment_with_config(input, allow_fuzzy, None, &config)
+        let scheme = self.double_pinyin_scheme.as_deref();
+        self.segment_with_config(input, allow_fuzzy, scheme, &config)
     }
 
     /// Perform segmentation with custom config for penalty tuning.
@@ -231,6 +694,61 @@ impl Parser {
         self.segment_with_config(input, allow_fuzzy, None, config)
     }
 
+    /// Best segmentation with each syllable's cost and the rule, if any,
+    /// that produced it.
+    ///
+    /// Every `segment_*` method fills in `Syllable::cost` and
+    /// `Syllable::rule`; this is `segment_best` under a name that says so,
+    /// for callers such as IMEs highlighting corrected syllables.
+    ///
+    /// # Example
+    /// ```
+    /// use libpinyin::{Parser, PINYIN_SYLLABLES};
+    ///
+    /// let parser = Parser::with_syllables(PINYIN_SYLLABLES);
+    /// let syllables = parser.segment_best_detailed("tinhua", true);
+    /// assert_eq!(syllables[0].text, "ting");
+    /// assert_eq!(syllables[0].rule.as_deref(), Some("tin=ting"));
+    /// assert_eq!(syllables[1].rule, None);
+    /// ```
+    pub fn segment_best_detailed(&self, input: &str, allow_fuzzy: bool) -> Vec<Syllable> {
+        self.segment_best(input, allow_fuzzy)
+    }
+
+    /// Best segmentation with a syllable break at each of `boundaries`
+    /// (byte offsets into `input`, e.g. pinned by the user in the input
+    /// buffer). Each piece between boundaries is segmented on its own.
+    ///
+    /// Returns an empty vector if some piece cannot be segmented.
+    ///
+    /// # Example
+    /// ```
+    /// use libpinyin::{Parser, PINYIN_SYLLABLES};
+    ///
+    /// let parser = Parser::with_syllables(PINYIN_SYLLABLES);
+    /// let texts = |syllables: Vec<libpinyin::Syllable>| {
+    ///     syllables.into_iter().map(|s| s.text).collect::<Vec<_>>()
+    /// };
+    /// assert_eq!(texts(parser.segment_best_constrained("xian", &[], false)), ["xian"]);
+    /// assert_eq!(texts(parser.segment_best_constrained("xian", &[2], false)), ["xi", "an"]);
+    /// ```
+    pub fn segment_best_constrained(
+        &self,
+        input: &str,
+        boundaries: &[usize],
+        allow_fuzzy: bool,
+    ) -> Vec<Syllable> {
+        let mut syllables = Vec::new();
+        for piece in split_at_boundaries(input, boundaries) {
+            let seg = self.segment_best(piece, allow_fuzzy);
+            if seg.is_empty() {
+                return Vec::new();
+            }
+            syllables.extend(seg);
+        }
+        syllables
+    }
+
     /// Perform segmentation with optional double pinyin scheme conversion.
     ///
     /// # Arguments
@@ -274,16 +792,72 @@ impl Parser {
         };
 
         // Now perform standard segmentation on the processed input
-        self.segment_best_internal(&processed_input, allow_fuzzy, config)
+        let (processed_input, typed) = canonicalize_umlaut(&processed_input);
+        let (processed_input, tones) = self.strip_tones(&processed_input);
+        self.segment_best_internal(&processed_input, &tones, &typed, allow_fuzzy, config)
+    }
+
+    /// Take typed tones off canonicalized input, per `tone_mode`.
+    ///
+    /// Returns the toneless input and, for each of its chars, the tone typed
+    /// on it. A tone digit becomes an apostrophe, its tone recorded on the
+    /// char before it; a marked vowel becomes the bare vowel. Whitespace is
+    /// dropped so positions match the segmenters' normalized input. In
+    /// `ToneMode::Ignore` the input is returned as is with no tones.
+    fn strip_tones(&self, input: &str) -> (String, Vec<Option<u8>>) {
+        if self.tone_mode == ToneMode::Ignore {
+            return (input.to_string(), Vec::new());
+        }
+
+        let mut out = String::with_capacity(input.len());
+        let mut tones: Vec<Option<u8>> = Vec::with_capacity(input.len());
+        let mut prev: Option<char> = None;
+        for ch in input.chars().filter(|c| !c.is_whitespace()) {
+            let mut bare = ch;
+            let mut tone = None;
+            match self.tone_mode {
+                ToneMode::Numeric => {
+                    let digit = ch
+                        .to_digit(10)
+                        .and_then(|d| libchinese_core::tone::normalize_tone(d as u8));
+                    if let (Some(t), Some(p)) = (digit, prev) {
+                        if p.is_ascii_alphabetic() {
+                            if let Some(last) = tones.last_mut() {
+                                *last = Some(t);
+                            }
+                            bare = '\'';
+                        }
+                    }
+                }
+                ToneMode::Diacritic => {
+                    if let Some((vowel, t)) = libchinese_core::tone::strip_tone_mark(ch) {
+                        bare = match (vowel, prev) {
+                            ('ü', Some('j' | 'q' | 'x' | 'y')) => 'u',
+                            ('ü', _) => 'v',
+                            (v, _) => v,
+                        };
+                        tone = Some(t);
+                    }
+                }
+                ToneMode::Ignore => {}
+            }
+            out.push(bare);
+            tones.push(tone);
+            prev = Some(ch);
+        }
+        (out, tones)
     }
 
     /// Internal segmentation method that does the actual DP work.
     ///
     /// This is separated out so that both segment_best and segment_with_scheme
-    /// can use the same logic.
+    /// can use the same logic. `typed` holds the typed spelling of each char
+    /// of the canonicalized `input` (see `canonicalize_umlaut`).
     fn segment_best_internal(
         &self,
         input: &str,
+        tones: &[Option<u8>],
+        typed: &[String],
         allow_fuzzy: bool,
         config: &libchinese_core::Config,
     ) -> Vec<Syllable> {
@@ -299,276 +873,23 @@ impl Parser {
             return Vec::new();
         }
 
-        // Enhanced DP state per position with improved cost modeling:
-        // - best_cost[pos]: comprehensive cost including length, frequency, and penalty factors
-        // - best_parsed[pos]: total parsed characters (higher coverage is better)
-        // - best_num_keys[pos]: number of segments used (fewer segments preferred)
-        // - best_distance[pos]: accumulated fuzzy/edit distance penalty (lower is better)
-        // - best_choice[pos]: the chosen transition (end_pos, matched_string, fuzzy_flag)
-        let mut best_cost: Vec<f32> = vec![std::f32::INFINITY; n + 1];
-        let mut best_parsed: Vec<usize> = vec![0; n + 1];
-        let mut best_num_keys: Vec<usize> = vec![usize::MAX; n + 1];
-        let mut best_distance: Vec<i32> = vec![i32::MAX; n + 1];
-        let mut best_choice: Vec<Option<(usize, String, bool)>> = vec![None; n + 1];
-
-        // base: at end of input zero cost, zero parsed, zero keys, zero distance
-        best_cost[n] = 0.0;
-        best_parsed[n] = 0;
-        best_num_keys[n] = 0;
-        best_distance[n] = 0;
-
-        // helper to decide whether candidate should replace current best at pos
-        // Use a plain function that takes references to the best_* arrays to avoid
-        // closure-capture borrow conflicts when we need to mutate those arrays.
-        fn should_replace(
-            pos: usize,
-            cand_cost: f32,
-            cand_parsed: usize,
-            cand_keys: usize,
-            cand_dist: i32,
-            best_cost: &Vec<f32>,
-            best_parsed: &Vec<usize>,
-            best_num_keys: &Vec<usize>,
-            best_distance: &Vec<i32>,
-        ) -> bool {
-            // primary: strictly lower cost
-            if cand_cost < best_cost[pos] {
-                return true;
-            }
-            // nearly-equal cost: apply tie-breakers
-            if (cand_cost - best_cost[pos]).abs() < 1e-6 {
-                if cand_parsed > best_parsed[pos] {
-                    return true;
-                }
-                if cand_parsed == best_parsed[pos] {
-                    if cand_keys < best_num_keys[pos] {
-                        return true;
-                    }
-                    if cand_keys == best_num_keys[pos] && cand_dist < best_distance[pos] {
-                        return true;
-                    }
-                }
-            }
-            false
-        }
+        // DP over suffixes: cells[pos] is the best segmentation of
+        // normalized[pos..], its choice pointing at where the first step ends.
+        // Every position has at least one step (the unknown fallback), so
+        // every cell ends up reachable.
+        let mut cells: Vec<Cell> = vec![Cell::unreached(); n + 1];
+        cells[n] = Cell::origin();
 
         // iterate positions backward
         for pos in (0..n).rev() {
-            // First try all exact trie prefixes from pos
-            let prefixes = self.trie.walk_prefixes(&normalized, pos);
-
-            for (end, matched) in prefixes.iter() {
-                // Only consider suffixes that are reachable (best_cost[end] finite)
-                if best_cost[*end].is_infinite() {
+            let steps = self.steps_from(&normalized, pos, allow_fuzzy, config);
+            for (index, step) in steps.iter().enumerate() {
+                if !cells[step.end].is_reached() {
                     continue;
                 }
-
-                // Enhanced cost model based on segment length and frequency
-                let seg_len = end - pos;
-                let seg_cost = self.calculate_segment_cost(matched, seg_len, false);
-                let cand_cost = seg_cost + best_cost[*end];
-                let cand_parsed = seg_len + best_parsed[*end];
-                // num_keys: 1 for this segment + keys used from end
-                let cand_keys = 1 + best_num_keys[*end];
-                // distance: pass-through (exact match doesn't add distance)
-                let cand_dist = best_distance[*end];
-
-                if should_replace(
-                    pos,
-                    cand_cost,
-                    cand_parsed,
-                    cand_keys,
-                    cand_dist,
-                    &best_cost,
-                    &best_parsed,
-                    &best_num_keys,
-                    &best_distance,
-                ) {
-                    best_cost[pos] = cand_cost;
-                    best_parsed[pos] = cand_parsed;
-                    best_num_keys[pos] = cand_keys;
-                    best_distance[pos] = cand_dist;
-                    best_choice[pos] = Some((*end, matched.clone(), false));
-                }
-            }
-
-            // If fuzzy allowed, attempt fuzzy alternatives for substrings of varying lengths.
-            // This allows different-length substitutions (e.g., "zi" -> "zhi", "an" -> "ang")
-            // which is essential for comprehensive fuzzy matching in Chinese pinyin.
-            if allow_fuzzy {
-                for len in 1..=4 {
-                    if pos + len > n {
-                        break;
-                    }
-                    let substr: String = normalized[pos..pos + len].iter().collect();
-
-                    // Try pinyin corrections first (ue/ve, v/u) - these have lower penalty than fuzzy
-                    let corrections = self.apply_corrections(&substr);
-                    for corrected in corrections {
-                        if self.trie.contains_word(&corrected) && corrected != substr {
-                            let end = pos + len;
-                            if end <= n && !best_cost[end].is_infinite() {
-                                let seg_cost =
-                                    self.calculate_segment_cost(&corrected, len, false) + 0.5;
-                                let cand_cost = seg_cost + best_cost[end];
-                                let cand_parsed = len + best_parsed[end];
-                                let cand_keys = 1 + best_num_keys[end];
-                                // Correction penalty from config (default: 200)
-                                let correction_penalty = config.correction_penalty;
-                                let cand_dist = correction_penalty + best_distance[end];
-
-                                if should_replace(
-                                    pos,
-                                    cand_cost,
-                                    cand_parsed,
-                                    cand_keys,
-                                    cand_dist,
-                                    &best_cost,
-                                    &best_parsed,
-                                    &best_num_keys,
-                                    &best_distance,
-                                ) {
-                                    best_cost[pos] = cand_cost;
-                                    best_parsed[pos] = cand_parsed;
-                                    best_num_keys[pos] = cand_keys;
-                                    best_distance[pos] = cand_dist;
-                                    best_choice[pos] = Some((end, corrected.clone(), true));
-                                }
-                            }
-                        }
-                    }
-
-                    // Then try fuzzy alternatives
-                    let alts = self.fuzzy.alternatives(&substr);
-                    for (alt, penalty) in alts {
-                        if self.trie.contains_word(&alt) && alt != substr {
-                            // Calculate the actual end position based on the alternative's length
-                            // For same-length alternatives, use original end position
-                            // For different-length alternatives, adjust accordingly
-                            let alt_len = alt.chars().count();
-                            let original_len = substr.chars().count();
-
-                            // For now, handle same-length and different-length cases
-                            let end = if alt_len == original_len {
-                                pos + len
-                            } else {
-                                // For different lengths, we need to consider if we can consume
-                                // the alternative at this position. Since we're looking at
-                                // fuzzy alternatives, we treat this as a substitution at the
-                                // original position length.
-                                pos + len
-                            };
-
-                            if end <= n && !best_cost[end].is_infinite() {
-                                let seg_cost = self.calculate_segment_cost(&alt, alt_len, true);
-                                let cand_cost = seg_cost + best_cost[end];
-                                let cand_parsed = len + best_parsed[end]; // Use original length for parsing position
-                                let cand_keys = 1 + best_num_keys[end];
-                                // Use the per-rule penalty from fuzzy map, scaled by config multiplier
-                                let fuzzy_penalty =
-                                    (penalty * (config.fuzzy_penalty_multiplier as f32)) as i32;
-                                let cand_dist = fuzzy_penalty + best_distance[end];
-
-                                if should_replace(
-                                    pos,
-                                    cand_cost,
-                                    cand_parsed,
-                                    cand_keys,
-                                    cand_dist,
-                                    &best_cost,
-                                    &best_parsed,
-                                    &best_num_keys,
-                                    &best_distance,
-                                ) {
-                                    best_cost[pos] = cand_cost;
-                                    best_parsed[pos] = cand_parsed;
-                                    best_num_keys[pos] = cand_keys;
-                                    best_distance[pos] = cand_dist;
-                                    best_choice[pos] = Some((end, alt.clone(), true));
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Try incomplete syllable matching if enabled (for partial input like "n" → "ni")
-            // This should have higher penalty than complete matches but better than unknown fallback
-            if allow_fuzzy && best_choice[pos].is_none() {
-                for len in 1..=3 {
-                    // Try incomplete syllables up to 3 chars
-                    if pos + len > n {
-                        break;
-                    }
-                    let incomplete: String = normalized[pos..pos + len].iter().collect();
-
-                    // Find any syllable in the trie that starts with this prefix
-                    // We use the first completion as a representative match
-                    if let Some(completion) = self.find_syllable_completion(&incomplete) {
-                        let end = pos + len;
-                        if !best_cost[end].is_infinite() {
-                            // Incomplete match gets higher penalty than fuzzy but lower than unknown
-                            let seg_cost =
-                                self.calculate_segment_cost(&completion, len, false) + 2.0;
-                            let cand_cost = seg_cost + best_cost[end];
-                            let cand_parsed = len + best_parsed[end];
-                            let cand_keys = 1 + best_num_keys[end];
-                            // Incomplete penalty from config (default: 500)
-                            let incomplete_penalty = config.incomplete_penalty;
-                            let cand_dist = incomplete_penalty + best_distance[end];
-
-                            if should_replace(
-                                pos,
-                                cand_cost,
-                                cand_parsed,
-                                cand_keys,
-                                cand_dist,
-                                &best_cost,
-                                &best_parsed,
-                                &best_num_keys,
-                                &best_distance,
-                            ) {
-                                best_cost[pos] = cand_cost;
-                                best_parsed[pos] = cand_parsed;
-                                best_num_keys[pos] = cand_keys;
-                                best_distance[pos] = cand_dist;
-                                // Mark as fuzzy since it's an incomplete match
-                                best_choice[pos] = Some((end, incomplete.clone(), true));
-                            }
-                        }
-                    }
-                }
-            }
-
-            // If still no choice found, fallback: consume one character as unknown token
-            // We still allow this option but with a large penalty; it contributes 1 parsed char.
-            if best_choice[pos].is_none() {
-                let end = pos + 1;
-                if !best_cost[end].is_infinite() {
-                    let substr: String = normalized[pos..end].iter().collect();
-                    let seg_cost = config.unknown_cost; // penalty from config (default: 10.0)
-                    let cand_cost = seg_cost + best_cost[end];
-                    let cand_parsed = 1 + best_parsed[end];
-                    let cand_keys = 1 + best_num_keys[end];
-                    let cand_dist = config.unknown_penalty + best_distance[end]; // penalty from config (default: 1000)
-
-                    if should_replace(
-                        pos,
-                        cand_cost,
-                        cand_parsed,
-                        cand_keys,
-                        cand_dist,
-                        &best_cost,
-                        &best_parsed,
-                        &best_num_keys,
-                        &best_distance,
-                    ) {
-                        best_cost[pos] = cand_cost;
-                        best_parsed[pos] = cand_parsed;
-                        best_num_keys[pos] = cand_keys;
-                        best_distance[pos] = cand_dist;
-                        best_choice[pos] = Some((end, substr, false));
-                    }
+                let cand = cells[step.end].extend(step, index, step.end - pos, step.end);
+                if cand.beats(&cells[pos]) {
+                    cells[pos] = cand;
                 }
             }
         }
@@ -577,15 +898,17 @@ impl Parser {
         let mut out: Vec<Syllable> = Vec::new();
         let mut cur = 0usize;
         while cur < n {
-            if let Some((next, word, fuzzy)) = &best_choice[cur] {
+            if let Some((next, syllable)) = &cells[cur].choice {
                 // Treat apostrophe (') as an enforced separator and skip it in the final output.
                 // Upstream behavior propagates state across apostrophes but does not emit them
                 // as tokens; mimic that here by advancing the cursor without pushing a token.
-                if word == "'" {
+                if syllable.text == "'" {
                     cur = *next;
                     continue;
                 }
-                out.push(Syllable::new(word.clone(), *fuzzy));
+                let syllable = syllable.clone().with_tone(tone_in(tones, cur, *next));
+                let typed = typed.get(cur..*next).unwrap_or_default();
+                out.push(with_typed_text(syllable, &normalized[cur..*next], typed));
                 cur = *next;
             } else {
                 // defensive fallback (shouldn't happen)
@@ -598,6 +921,296 @@ impl Parser {
         out
     }
 
+    /// Segment `state`'s input after typing `new_char`, reusing cached work.
+    ///
+    /// Returns the same segmentation as `segment_best` on the whole input
+    /// typed so far. `segment_best` runs its DP from the end of the input, so
+    /// every keystroke invalidates all of it; here the DP runs from the start
+    /// and caches the best segmentation of every prefix. A prefix's cell only
+    /// depends on the input up to the longest syllable past it, so appending
+    /// a character recomputes that many trailing cells and nothing else.
+    ///
+    /// Whitespace is ignored, case is folded and ü spellings are canonicalized
+    /// as in `segment_best`. Changing `allow_fuzzy` between calls recomputes
+    /// the whole table.
+    ///
+    /// # Example
+    /// ```
+    /// use libpinyin::{Parser, SegmentState, PINYIN_SYLLABLES};
+    ///
+    /// let parser = Parser::with_syllables(PINYIN_SYLLABLES);
+    /// let mut state = SegmentState::new();
+    /// let mut segmentation = Vec::new();
+    /// for ch in "nihao".chars() {
+    ///     segmentation = parser.segment_incremental(&mut state, ch, false);
+    /// }
+    /// assert_eq!(segmentation, parser.segment_best("nihao", false));
+    /// ```
+    pub fn segment_incremental(
+        &self,
+        state: &mut SegmentState,
+        new_char: char,
+        allow_fuzzy: bool,
+    ) -> Vec<Syllable> {
+        let mut keep = state.chars.len();
+        let mut removed = Vec::new();
+        for ch in new_char.to_lowercase().filter(|c| !c.is_whitespace()) {
+            // Same rewrites as `canonicalize_umlaut`, applied one char at a time
+            if let Some(popped) = push_canonical(&mut state.chars, &mut state.typed, ch) {
+                // The popped char sat just before the one pushed
+                let at = state.chars.len() - 1;
+                if at < keep {
+                    keep = at;
+                    removed.insert(0, popped);
+                }
+            }
+        }
+        state.edits.push((keep, removed));
+
+        self.refresh_cells(state, keep, allow_fuzzy);
+        Self::cached_segmentation(state)
+    }
+
+    /// Undo the last character typed into `state` and return the new
+    /// segmentation. Cached cells before the deleted character are kept.
+    pub fn segment_backspace(&self, state: &mut SegmentState, allow_fuzzy: bool) -> Vec<Syllable> {
+        let Some((keep, removed)) = state.edits.pop() else {
+            return Vec::new();
+        };
+        state.chars.truncate(keep);
+        state.typed.truncate(keep);
+        for (ch, spelling) in removed {
+            state.chars.push(ch);
+            state.typed.push(spelling);
+        }
+
+        self.refresh_cells(state, keep, allow_fuzzy);
+        Self::cached_segmentation(state)
+    }
+
+    /// Bring `state.cells` up to date after `state.chars[changed..]` changed.
+    ///
+    /// Steps from a position never span more than `window` chars, so cells up
+    /// to `changed - window` cannot see the change and are kept.
+    fn refresh_cells(&self, state: &mut SegmentState, changed: usize, allow_fuzzy: bool) {
+        let config = libchinese_core::Config::default();
+        let window = self.max_syllable_len.max(4);
+        let n = state.chars.len();
+
+        let mut keep = changed.saturating_sub(window);
+        if state.allow_fuzzy != allow_fuzzy {
+            state.allow_fuzzy = allow_fuzzy;
+            keep = 0;
+        }
+        state.cells.truncate(keep + 1);
+        if state.cells.is_empty() {
+            state.cells.push(Cell::origin());
+        }
+
+        let first = (keep + 1).saturating_sub(window);
+        let steps: Vec<Vec<Step>> = (first..n)
+            .map(|pos| self.steps_from(&state.chars, pos, allow_fuzzy, &config))
+            .collect();
+
+        // DP over prefixes: cells[end] extends the best cell a step comes from
+        for end in keep + 1..=n {
+            let mut cell = Cell::unreached();
+            for start in end.saturating_sub(window).max(first)..end {
+                if !state.cells[start].is_reached() {
+                    continue;
+                }
+                for (index, step) in steps[start - first].iter().enumerate() {
+                    if step.end != end {
+                        continue;
+                    }
+                    let cand = state.cells[start].extend(step, index, end - start, start);
+                    let tied_but_first = || match &cell.choice {
+                        Some((prev, _)) => {
+                            !cell.beats(&cand)
+                                && Self::takes_earlier_step(
+                                    &state.cells,
+                                    (start, index),
+                                    (*prev, cell.step),
+                                )
+                        }
+                        None => false,
+                    };
+                    if cand.beats(&cell) || tied_but_first() {
+                        cell = cand;
+                    }
+                }
+            }
+            state.cells.push(cell);
+        }
+    }
+
+    /// Whether the path whose last step is `a` leaves the last boundary it
+    /// shares with the path whose last step is `b` by an earlier step. Both
+    /// are (start, step index) and end at the same position.
+    ///
+    /// `segment_best` keeps the first step it finds at the leftmost position
+    /// where fully tied paths differ. Kept paths agree up to every boundary
+    /// they share, so only the steps back to that boundary are compared
+    /// rather than the whole path.
+    fn takes_earlier_step(cells: &[Cell], a: (usize, usize), b: (usize, usize)) -> bool {
+        let ((mut pos_a, mut step_a), (mut pos_b, mut step_b)) = (a, b);
+        while pos_a != pos_b {
+            let later = if pos_a > pos_b {
+                (&mut pos_a, &mut step_a)
+            } else {
+                (&mut pos_b, &mut step_b)
+            };
+            let cell = &cells[*later.0];
+            *later.1 = cell.step;
+            *later.0 = cell.choice.as_ref().map_or(0, |(prev, _)| *prev);
+        }
+        step_a < step_b
+    }
+
+    /// Walk the cached choices back from the end of the input.
+    fn cached_segmentation(state: &SegmentState) -> Vec<Syllable> {
+        let mut out = Vec::new();
+        let mut cur = state.chars.len();
+        while let Some((prev, syllable)) = &state.cells[cur].choice {
+            // Apostrophes separate syllables but are not emitted
+            if syllable.text != "'" {
+                let spelled = &state.chars[*prev..cur];
+                out.push(with_typed_text(
+                    syllable.clone(),
+                    spelled,
+                    &state.typed[*prev..cur],
+                ));
+            }
+            cur = *prev;
+        }
+        out.reverse();
+        out
+    }
+
+    /// All DP steps starting at `pos` in `normalized`, in the order ties are
+    /// broken: exact trie prefixes, then corrections and fuzzy alternatives,
+    /// then incomplete syllables, then the unknown-character fallback.
+    ///
+    /// Which steps exist depends only on `normalized[pos..]` up to the longest
+    /// step, which is what lets `segment_incremental` reuse earlier cells.
+    fn steps_from(
+        &self,
+        normalized: &[char],
+        pos: usize,
+        allow_fuzzy: bool,
+        config: &libchinese_core::Config,
+    ) -> Vec<Step> {
+        let n = normalized.len();
+        let priority = config.match_priority;
+        let mut steps = Vec::new();
+
+        // First try all exact trie prefixes from pos
+        for (end, matched) in self.trie.walk_prefixes(normalized, pos) {
+            // Enhanced cost model based on segment length and frequency
+            let cost = self.calculate_segment_cost(&matched, end - pos, false);
+            // distance: exact match doesn't add distance
+            steps.push(Step {
+                end,
+                syllable: Syllable::new(matched, false).with_cost(cost),
+                dist: 0,
+            });
+        }
+
+        // If fuzzy allowed, attempt fuzzy alternatives for substrings of varying lengths.
+        // This allows different-length substitutions (e.g., "zi" -> "zhi", "an" -> "ang")
+        // which is essential for comprehensive fuzzy matching in Chinese pinyin.
+        if allow_fuzzy {
+            for len in 1..=4 {
+                if pos + len > n {
+                    break;
+                }
+                let substr: String = normalized[pos..pos + len].iter().collect();
+                let end = pos + len;
+
+                // Try pinyin corrections (ue/ve, v/u); by default these cost less than fuzzy
+                for (corrected, rule) in self.labeled_corrections(&substr) {
+                    if self.trie.contains_word(&corrected) && corrected != substr {
+                        let cost = self.calculate_segment_cost(&corrected, len, false)
+                            + priority.surcharge(MatchKind::Correction);
+                        // Correction penalty from config (default: 200)
+                        steps.push(Step {
+                            end,
+                            syllable: Syllable::new(corrected, true)
+                                .with_cost(cost)
+                                .with_rule(rule),
+                            dist: config.correction_penalty,
+                        });
+                    }
+                }
+
+                // Then try fuzzy alternatives. Alternatives of a different
+                // length are treated as a substitution of the typed span.
+                let alts = self
+                    .fuzzy
+                    .alternatives_limited(&substr, config.max_fuzzy_alternatives);
+                for (alt, penalty) in alts {
+                    if self.trie.contains_word(&alt) && alt != substr {
+                        // Cost by the span typed so match types compare on equal footing
+                        let cost = self.calculate_segment_cost(&alt, len, false)
+                            + priority.surcharge(MatchKind::Fuzzy);
+                        // Use the per-rule penalty from fuzzy map, scaled by config multiplier
+                        let dist = (penalty * (config.fuzzy_penalty_multiplier as f32)) as i32;
+                        let rule = format!("{}={}", substr, alt);
+                        steps.push(Step {
+                            end,
+                            syllable: Syllable::new(alt, true).with_cost(cost).with_rule(rule),
+                            dist,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Try incomplete syllable matching if enabled (for partial input like "n" → "ni")
+        // This should have higher penalty than complete matches but better than unknown
+        // fallback. When ranked last it is only a fallback for otherwise unmatched input.
+        let incomplete_last = priority.rank(MatchKind::Incomplete) == 2;
+        if allow_fuzzy && (steps.is_empty() || !incomplete_last) {
+            for len in 1..=3 {
+                // Try incomplete syllables up to 3 chars
+                if pos + len > n {
+                    break;
+                }
+                let incomplete: String = normalized[pos..pos + len].iter().collect();
+
+                // Find any syllable in the trie that starts with this prefix
+                // We use the first completion as a representative match
+                if let Some(completion) = self.find_syllable_completion(&incomplete) {
+                    // Incomplete match costs less than unknown; its rank decides vs fuzzy
+                    let cost = self.calculate_segment_cost(&completion, len, false)
+                        + priority.surcharge(MatchKind::Incomplete);
+                    // Incomplete penalty from config (default: 500); marked
+                    // fuzzy since it's an incomplete match
+                    steps.push(Step {
+                        end: pos + len,
+                        syllable: Syllable::new(incomplete, true)
+                            .with_cost(cost)
+                            .with_rule(INCOMPLETE_RULE),
+                        dist: config.incomplete_penalty,
+                    });
+                }
+            }
+        }
+
+        // If still no step found, fallback: consume one character as unknown token
+        // with a large penalty from config (default cost 10.0, distance 1000).
+        if steps.is_empty() {
+            let syllable = Syllable::new(normalized[pos], false).with_cost(config.unknown_cost);
+            steps.push(Step {
+                end: pos + 1,
+                syllable,
+                dist: config.unknown_penalty,
+            });
+        }
+
+        steps
+    }
+
     /// Return top-K segmentation alternatives (beam search).
     ///
     /// This implements a left-to-right beam search that expands exact trie
@@ -630,83 +1243,94 @@ impl Parser {
 
     /// Find a syllable completion for an incomplete prefix.
     ///
-    /// For example, "n" might complete to "ni", "nh" might complete to "nihao".
-    /// Returns the first completion found, or None if no completions exist.
+    /// For example, "zh" completes to "zha" and "n" to "na". Returns the
+    /// shortest completion (alphabetically first among equals), or None if no
+    /// syllable extends the prefix.
     pub fn find_syllable_completion(&self, prefix: &str) -> Option<String> {
-        // Walk the trie to find any syllable starting with this prefix
-        self.trie
-            .walk_prefixes(&prefix.chars().collect::<Vec<_>>(), 0)
-            .iter()
-            .find_map(|(_, matched)| {
-                if matched.starts_with(prefix) && matched.len() > prefix.len() {
-                    Some(matched.clone())
-                } else {
-                    None
-                }
-            })
+        self.trie.first_completion(prefix)
+    }
+
+    /// Character length of the longest prefix that segments fully into valid syllables.
+    ///
+    /// Only exact syllables count (no fuzzy, correction or incomplete matches);
+    /// apostrophes are accepted as separators between syllables. For example
+    /// "nihaox" returns 5 ("nihao") and "xyz" returns 0. Useful for deciding how
+    /// much of the input can be committed.
+    pub fn longest_valid_prefix(&self, input: &str) -> usize {
+        let normalized: Vec<char> = input.chars().map(|c| c.to_ascii_lowercase()).collect();
+        let n = normalized.len();
+
+        // reachable[i]: normalized[..i] splits into syllables (and apostrophes)
+        let mut reachable = vec![false; n + 1];
+        reachable[0] = true;
+
+        for pos in 0..n {
+            if !reachable[pos] {
+                continue;
+            }
+            if normalized[pos] == '\'' {
+                reachable[pos + 1] = true;
+                continue;
+            }
+            for (end, _) in self.trie.walk_prefixes(&normalized, pos) {
+                reachable[end] = true;
+            }
+        }
+
+        reachable.iter().rposition(|&ok| ok).unwrap_or(0)
     }
 
     /// Apply pinyin corrections (ue/ve, v/u) to a string.
     ///
     /// Returns corrected alternatives if applicable.
     pub fn apply_corrections(&self, s: &str) -> Vec<String> {
+        self.labeled_corrections(s)
+            .into_iter()
+            .map(|(corrected, _)| corrected)
+            .collect()
+    }
+
+    /// `apply_corrections` with the rule behind each result ("ue→ve").
+    fn labeled_corrections(&self, s: &str) -> Vec<(String, String)> {
         let mut results = Vec::new();
+        let mut correct = |from: &str, to: &str| {
+            if s.contains(from) {
+                results.push((s.replace(from, to), format!("{}→{}", from, to)));
+            }
+        };
 
         // Correction 1: ue ↔ ve (e.g., "nue" ↔ "nve", "lue" ↔ "lve")
-        if s.contains("ue") {
-            results.push(s.replace("ue", "ve"));
-        }
-        if s.contains("ve") {
-            results.push(s.replace("ve", "ue"));
-        }
+        correct("ue", "ve");
+        correct("ve", "ue");
 
         // Correction 2: v ↔ u in certain contexts (e.g., "nv" ↔ "nu", "lv" ↔ "lu")
         // This is context-sensitive: only after n, l
-        for &initial in &["n", "l"] {
+        for initial in ["n", "l"] {
             let vu_pattern = format!("{}u", initial);
             let vv_pattern = format!("{}v", initial);
-
-            if s.contains(&vu_pattern) {
-                results.push(s.replace(&vu_pattern, &vv_pattern));
-            }
-            if s.contains(&vv_pattern) {
-                results.push(s.replace(&vv_pattern, &vu_pattern));
-            }
+            correct(&vu_pattern, &vv_pattern);
+            correct(&vv_pattern, &vu_pattern);
         }
 
         // Correction 3: uen ↔ un (e.g., "juen" ↔ "jun", "chuen" ↔ "chun")
         // PINYIN_CORRECT_UEN_UN
-        if s.contains("uen") {
-            results.push(s.replace("uen", "un"));
-        }
-        if s.contains("un") {
-            results.push(s.replace("un", "uen"));
-        }
+        correct("uen", "un");
+        correct("un", "uen");
 
         // Correction 4: gn ↔ ng (e.g., "bagn" ↔ "bang", "hegn" ↔ "heng")
         // PINYIN_CORRECT_GN_NG
-        if s.contains("gn") {
-            results.push(s.replace("gn", "ng"));
-        }
-        if s.contains("ng") {
-            results.push(s.replace("ng", "gn"));
-        }
+        correct("gn", "ng");
+        correct("ng", "gn");
 
         // Correction 5: mg ↔ ng (e.g., "bamg" ↔ "bang", "hemg" ↔ "heng")
         // PINYIN_CORRECT_MG_NG
-        if s.contains("mg") {
-            results.push(s.replace("mg", "ng"));
-        }
         // Note: ng → mg already covered above in bidirectional ng corrections
+        correct("mg", "ng");
 
         // Correction 6: iou ↔ iu (e.g., "liou" ↔ "liu", "jiou" ↔ "jiu")
         // PINYIN_CORRECT_IOU_IU
-        if s.contains("iou") {
-            results.push(s.replace("iou", "iu"));
-        }
-        if s.contains("iu") {
-            results.push(s.replace("iu", "iou"));
-        }
+        correct("iou", "iu");
+        correct("iu", "iou");
 
         results
     }
@@ -726,7 +1350,8 @@ impl Parser {
     /// For custom penalty configuration, use `segment_top_k_with_config`.
     pub fn segment_top_k(&self, input: &str, k: usize, allow_fuzzy: bool) -> Vec<Vec<Syllable>> {
         let config = libchinese_core::Config::default();
-        self.segment_top_k_with_config(input, k, allow_fuzzy, None, &config)
+        let scheme = self.double_pinyin_scheme.as_deref();
+        self.segment_top_k_with_config(input, k, allow_fuzzy, scheme, &config)
     }
 
     /// Perform top-K segmentation with custom config for penalty tuning.
@@ -746,7 +1371,33 @@ impl Parser {
             input.to_string()
         };
 
-        self.segment_top_k_internal(&processed_input, k, allow_fuzzy, config)
+        let (processed_input, typed) = canonicalize_umlaut(&processed_input);
+        let (processed_input, tones) = self.strip_tones(&processed_input);
+        self.segment_top_k_internal(&processed_input, &tones, &typed, k, allow_fuzzy, config)
+    }
+
+    /// How ambiguous the segmentation of `input` is, in [0, 1].
+    ///
+    /// Compares the two best exact segmentations from `segment_top_k`
+    /// (fuzzy matching off) by total syllable cost: tied paths ("fangan" as
+    /// fang'an or fan'gan) score 1.0, and the score falls off as
+    /// `exp(-gap)` as the runner-up gets costlier. Input with a single
+    /// segmentation, or none, scores 0.0.
+    ///
+    /// # Example
+    /// ```
+    /// use libpinyin::{Parser, PINYIN_SYLLABLES};
+    ///
+    /// let parser = Parser::with_syllables(PINYIN_SYLLABLES);
+    /// assert!(parser.ambiguity_score("fangan") > parser.ambiguity_score("nihao"));
+    /// ```
+    pub fn ambiguity_score(&self, input: &str) -> f32 {
+        let segs = self.segment_top_k(input, 2, false);
+        let cost = |seg: &Vec<Syllable>| seg.iter().map(|s| s.cost).sum::<f32>();
+        match segs.as_slice() {
+            [best, second, ..] => (-(cost(second) - cost(best)).max(0.0)).exp(),
+            _ => 0.0,
+        }
     }
 
     /// Perform beam search segmentation with optional double pinyin scheme conversion.
@@ -769,10 +1420,133 @@ impl Parser {
         self.segment_top_k_with_config(input, k, allow_fuzzy, scheme_name, &config)
     }
 
+    /// Return every segmentation of the input into real syllables.
+    ///
+    /// Unlike `segment_top_k` there is no beam and no unknown-character
+    /// fallback: each path must cover the whole input with exact syllables
+    /// (or fuzzy alternatives when `allow_fuzzy` is set), and an input with
+    /// no such path yields an empty vec. Apostrophes are accepted between
+    /// syllables. Enumeration stops after `max_segmentations` paths;
+    /// identical syllable sequences are kept once, and results are sorted by
+    /// the same cost tuple as `segment_top_k`.
+    pub fn segment_all(&self, input: &str, allow_fuzzy: bool) -> Vec<Vec<Syllable>> {
+        let (canonical, typed) = canonicalize_umlaut(input);
+        let normalized: Vec<char> = canonical.chars().collect();
+        let n = normalized.len();
+        if n == 0 || self.max_segmentations == 0 {
+            return Vec::new();
+        }
+
+        // Edge out of a position: (end, syllable, cost, distance); an
+        // apostrophe is a free edge without a syllable
+        type Edge = (usize, Option<Syllable>, f32, i32);
+
+        let edges: Vec<Vec<Edge>> = (0..n)
+            .map(|pos| {
+                if normalized[pos] == '\'' {
+                    return vec![(pos + 1, None, 0.0, 0)];
+                }
+
+                let mut out: Vec<Edge> = self
+                    .trie
+                    .walk_prefixes(&normalized, pos)
+                    .into_iter()
+                    .map(|(end, matched)| {
+                        let syllable = Syllable::new(matched, false).with_cost(1.0);
+                        let syllable =
+                            with_typed_text(syllable, &normalized[pos..end], &typed[pos..end]);
+                        (end, Some(syllable), 1.0, 0)
+                    })
+                    .collect();
+
+                if allow_fuzzy {
+                    for len in 1..=std::cmp::min(4, n - pos) {
+                        let substr: String = normalized[pos..pos + len].iter().collect();
+                        for (alt, penalty) in self.fuzzy.alternatives(&substr) {
+                            if self.trie.contains_word(&alt) && alt != substr {
+                                let dist = (penalty * 100.0) as i32;
+                                let rule = format!("{}={}", substr, alt);
+                                let syllable =
+                                    Syllable::new(alt, true).with_cost(penalty).with_rule(rule);
+                                out.push((pos + len, Some(syllable), penalty, dist));
+                            }
+                        }
+                    }
+                }
+                out
+            })
+            .collect();
+
+        // Positions from which the end is reachable, so dead ends are never
+        // explored
+        let mut reachable = vec![false; n + 1];
+        reachable[n] = true;
+        for pos in (0..n).rev() {
+            reachable[pos] = edges[pos].iter().any(|(end, ..)| reachable[*end]);
+        }
+        if !reachable[0] {
+            return Vec::new();
+        }
+
+        let mut completed: Vec<State> = Vec::new();
+        let mut stack = vec![State {
+            pos: 0,
+            tokens: Vec::new(),
+            cost: 0.0,
+            parsed: 0,
+            keys: 0,
+            dist: 0,
+        }];
+
+        while let Some(st) = stack.pop() {
+            if st.pos == n {
+                completed.push(st);
+                if completed.len() >= self.max_segmentations {
+                    break;
+                }
+                continue;
+            }
+
+            // Pushed in reverse so exact, longer matches are explored first
+            for (end, syllable, cost, dist) in edges[st.pos].iter().rev() {
+                if !reachable[*end] {
+                    continue;
+                }
+                let mut tokens = st.tokens.clone();
+                tokens.extend(syllable.clone());
+                stack.push(State {
+                    pos: *end,
+                    keys: tokens.len(),
+                    tokens,
+                    cost: st.cost + cost,
+                    parsed: st.parsed + (end - st.pos),
+                    dist: st.dist + dist,
+                });
+            }
+        }
+
+        completed.sort_by(state_cmp);
+        let mut seen = std::collections::HashSet::new();
+        completed
+            .into_iter()
+            .filter(|st| {
+                let key: Vec<String> = st
+                    .tokens
+                    .iter()
+                    .map(|s| s.lexicon_text().to_string())
+                    .collect();
+                seen.insert(key)
+            })
+            .map(|st| st.tokens)
+            .collect()
+    }
+
     /// Internal beam search method that does the actual work.
     fn segment_top_k_internal(
         &self,
         input: &str,
+        tones: &[Option<u8>],
+        typed: &[String],
         k: usize,
         allow_fuzzy: bool,
         config: &libchinese_core::Config,
@@ -788,38 +1562,6 @@ impl Parser {
             return Vec::new();
         }
 
-        // Beam state
-        #[derive(Clone)]
-        struct State {
-            pos: usize,
-            tokens: Vec<Syllable>,
-            cost: f32,
-            parsed: usize,
-            keys: usize,
-            dist: i32,
-        }
-
-        // Comparator used for ranking states (lower is better)
-        fn state_cmp(a: &State, b: &State) -> std::cmp::Ordering {
-            // primary: cost (smaller better)
-            if (a.cost - b.cost).abs() > 1e-6 {
-                return a
-                    .cost
-                    .partial_cmp(&b.cost)
-                    .unwrap_or(std::cmp::Ordering::Equal);
-            }
-            // tie: prefer larger parsed
-            if a.parsed != b.parsed {
-                return b.parsed.cmp(&a.parsed);
-            }
-            // tie: prefer fewer keys
-            if a.keys != b.keys {
-                return a.keys.cmp(&b.keys);
-            }
-            // tie: prefer smaller distance
-            a.dist.cmp(&b.dist)
-        }
-
         // initial state
         let start = State {
             pos: 0,
@@ -835,8 +1577,9 @@ impl Parser {
 
         // beam width: allow more slack for longer inputs to avoid premature pruning
         // Scale with input length to handle phrases like "woshinuoweiren" (7 syllables)
+        // The extra width is capped so total work stays linear in the input length
         let base_width = std::cmp::max(16, k.saturating_mul(8));
-        let beam_width = base_width + (n / 4); // Add extra width for longer inputs
+        let beam_width = base_width + std::cmp::min(n / 4, MAX_EXTRA_BEAM_WIDTH);
 
         while !beam.is_empty() {
             let mut next_beam: Vec<State> = Vec::new();
@@ -848,12 +1591,28 @@ impl Parser {
                     continue;
                 }
 
+                // An apostrophe closes the previous syllable: step over it
+                // at no cost and without a token
+                if normalized[st.pos] == '\'' {
+                    next_beam.push(State {
+                        pos: st.pos + 1,
+                        parsed: st.parsed + 1,
+                        ..st
+                    });
+                    continue;
+                }
+
                 // Expand exact trie prefixes starting at st.pos
                 let prefixes = self.trie.walk_prefixes(&normalized, st.pos);
                 for (end, matched) in prefixes.into_iter() {
                     // only expand if suffix from `end` is reachable (we don't require that here)
+                    let syllable = Syllable::new(matched.clone(), false)
+                        .with_cost(1.0)
+                        .with_tone(tone_in(tones, st.pos, end));
+                    let spelled = &normalized[st.pos..end];
+                    let typed = typed.get(st.pos..end).unwrap_or_default();
                     let mut new_tokens = st.tokens.clone();
-                    new_tokens.push(Syllable::new(matched.clone(), false));
+                    new_tokens.push(with_typed_text(syllable, spelled, typed));
                     let new_state = State {
                         pos: end,
                         tokens: new_tokens,
@@ -871,8 +1630,14 @@ impl Parser {
                         if st.pos + len > n {
                             break;
                         }
+                        // No syllable spans an apostrophe
+                        if normalized[st.pos + len - 1] == '\'' {
+                            break;
+                        }
                         let substr: String = normalized[st.pos..st.pos + len].iter().collect();
-                        let alts = self.fuzzy.alternatives(&substr);
+                        let alts = self
+                            .fuzzy
+                            .alternatives_limited(&substr, config.max_fuzzy_alternatives);
                         for (alt, penalty) in alts.into_iter() {
                             // If the alt is an exact syllable in the trie, use it as a fuzzy match
                             if self.trie.contains_word(&alt) && alt != substr {
@@ -880,7 +1645,12 @@ impl Parser {
                                 // (e.g., lan -> nan via l=n rule if both are valid syllables)
                                 let end = st.pos + len;
                                 let mut new_tokens = st.tokens.clone();
-                                new_tokens.push(Syllable::new(alt.clone(), true));
+                                new_tokens.push(
+                                    Syllable::new(alt.clone(), true)
+                                        .with_cost(penalty)
+                                        .with_rule(format!("{}={}", substr, alt))
+                                        .with_tone(tone_in(tones, st.pos, end)),
+                                );
                                 let new_state = State {
                                     pos: end,
                                     tokens: new_tokens,
@@ -900,7 +1670,11 @@ impl Parser {
                 if end <= n {
                     let substr: String = normalized[st.pos..end].iter().collect();
                     let mut new_tokens = st.tokens.clone();
-                    new_tokens.push(Syllable::new(substr.clone(), false));
+                    new_tokens.push(
+                        Syllable::new(substr.clone(), false)
+                            .with_cost(10.0)
+                            .with_tone(tone_in(tones, st.pos, end)),
+                    );
                     let new_state = State {
                         pos: end,
                         tokens: new_tokens,
@@ -928,7 +1702,7 @@ impl Parser {
 
         // If no completed segmentation was found, fall back to best single segmentation
         if completed.is_empty() {
-            return vec![self.segment_best_internal(input, allow_fuzzy, config)];
+            return vec![self.segment_best_internal(input, tones, typed, allow_fuzzy, config)];
         }
 
         // Sort completed states and return top-k token sequences
@@ -990,6 +1764,16 @@ mod tests {
         assert_eq!(texts2, vec!["zhong".to_string(), "guo".to_string()]);
     }
 
+    #[test]
+    fn longest_valid_prefix_stops_at_invalid_input() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        assert_eq!(parser.longest_valid_prefix("nihaox"), 5);
+        assert_eq!(parser.longest_valid_prefix("xyz"), 0);
+        assert_eq!(parser.longest_valid_prefix("nihao"), 5);
+        assert_eq!(parser.longest_valid_prefix("xi'anq"), 5);
+        assert_eq!(parser.longest_valid_prefix(""), 0);
+    }
+
     #[test]
     fn parser_unknown_fallback() {
         let mut parser = Parser::new();
@@ -1000,6 +1784,525 @@ mod tests {
         assert_eq!(texts, vec!["ni".to_string(), "x".to_string()]);
     }
 
+    /// "lu" qualifies as a correction ("lv"), a fuzzy alternative ("nu") and
+    /// an incomplete syllable ("luo"), but is not a syllable itself.
+    fn all_match_types_parser() -> Parser {
+        let mut parser = Parser::with_syllables(&["lv", "nu", "luo"]);
+        parser.fuzzy = FuzzyMap::from_rules(&["lu=nu:1.0".to_string()]);
+        parser
+    }
+
+    fn segment_with_priority(parser: &Parser, priority: libchinese_core::MatchPriority) -> String {
+        let config = libchinese_core::Config {
+            match_priority: priority,
+            ..libchinese_core::Config::default()
+        };
+        let seg = parser.segment_best_with_config("lu", true, &config);
+        assert_eq!(seg.len(), 1);
+        seg[0].text.clone()
+    }
+
+    #[test]
+    fn match_priority_selects_match_type() {
+        use libchinese_core::MatchPriority;
+        let parser = all_match_types_parser();
+
+        assert_eq!(
+            segment_with_priority(&parser, MatchPriority::CorrectionFirst),
+            "lv"
+        );
+        assert_eq!(
+            segment_with_priority(&parser, MatchPriority::FuzzyFirst),
+            "nu"
+        );
+        // Incomplete matches keep the typed text
+        assert_eq!(
+            segment_with_priority(&parser, MatchPriority::IncompleteFirst),
+            "lu"
+        );
+    }
+
+    #[test]
+    fn match_priority_default_is_correction_first() {
+        let parser = all_match_types_parser();
+        let seg = parser.segment_best("lu", true);
+        let texts: Vec<String> = seg.into_iter().map(|s| s.text).collect();
+        assert_eq!(texts, vec!["lv".to_string()]);
+    }
+
+    #[test]
+    fn exact_syllable_beats_every_match_type() {
+        use libchinese_core::MatchPriority;
+        let mut parser = all_match_types_parser();
+        parser.insert_syllable("lu");
+
+        for priority in [
+            MatchPriority::CorrectionFirst,
+            MatchPriority::FuzzyFirst,
+            MatchPriority::IncompleteFirst,
+        ] {
+            assert_eq!(segment_with_priority(&parser, priority), "lu");
+        }
+    }
+
+    #[test]
+    fn syllable_completion_extends_prefix() {
+        let parser = Parser::with_syllables(&["zha", "zhang", "zh", "ni"]);
+        assert_eq!(
+            parser.find_syllable_completion("zh"),
+            Some("zha".to_string())
+        );
+        assert_eq!(
+            parser.find_syllable_completion("zhan"),
+            Some("zhang".to_string())
+        );
+        assert_eq!(parser.find_syllable_completion("ni"), None);
+        assert_eq!(parser.find_syllable_completion("x"), None);
+    }
+
+    fn texts(segs: Vec<Vec<Syllable>>) -> Vec<String> {
+        segs.into_iter()
+            .map(|seg| {
+                seg.into_iter()
+                    .map(|s| s.text)
+                    .collect::<Vec<_>>()
+                    .join("'")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apostrophe_is_a_hard_boundary_in_every_mode() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+
+        let best: Vec<String> = parser
+            .segment_best("xi'an", false)
+            .into_iter()
+            .map(|s| s.text)
+            .collect();
+        assert_eq!(best, vec!["xi", "an"]);
+
+        for allow_fuzzy in [false, true] {
+            let top = parser.segment_top_k("xi'an", 4, allow_fuzzy);
+            assert_eq!(texts(top.clone())[0], "xi'an");
+            assert!(texts(top).iter().all(|t| !t.contains("xian")));
+
+            let top = texts(parser.segment_top_k("fang'an", 8, allow_fuzzy));
+            assert_eq!(top[0], "fang'an");
+            assert!(!top.contains(&"fan'gan".to_string()), "{:?}", top);
+        }
+    }
+
+    #[test]
+    fn umlaut_spellings_share_one_syllable() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        let best = |input: &str| -> Vec<String> {
+            parser
+                .segment_best(input, false)
+                .iter()
+                .map(|s| s.lexicon_text().to_string())
+                .collect()
+        };
+        let keys = |segs: Vec<Vec<Syllable>>| -> Vec<String> {
+            segs.iter()
+                .map(|seg| {
+                    let keys: Vec<&str> = seg.iter().map(|s| s.lexicon_text()).collect();
+                    keys.join("'")
+                })
+                .collect()
+        };
+
+        for input in ["nv", "nü", "nu:", "NÜ"] {
+            assert_eq!(best(input), vec!["nv"], "{}", input);
+            assert_eq!(keys(parser.segment_top_k(input, 4, false))[0], "nv");
+        }
+        for input in ["lve", "lüe", "lyue"] {
+            assert_eq!(best(input), vec!["lve"], "{}", input);
+        }
+        // After j/q/x/y the table spells ü as u
+        assert_eq!(best("jv"), vec!["ju"]);
+        assert_eq!(best("xüe"), vec!["xue"]);
+        assert_eq!(keys(parser.segment_all("nüren", false))[0], "nv'ren");
+
+        // Plain u is untouched
+        assert_eq!(best("guangdong"), vec!["guang", "dong"]);
+        assert_eq!(best("nu"), vec!["nu"]);
+    }
+
+    #[test]
+    fn umlaut_syllables_keep_the_typed_spelling() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        let spellings = |seg: Vec<Syllable>| -> Vec<(String, Option<String>)> {
+            seg.into_iter().map(|s| (s.text, s.canonical)).collect()
+        };
+        let typed = |text: &str, canonical: &str| (text.to_string(), Some(canonical.to_string()));
+        let plain = |text: &str| (text.to_string(), None);
+
+        assert_eq!(
+            spellings(parser.segment_best("nü", false)),
+            vec![typed("nü", "nv")]
+        );
+        assert_eq!(
+            spellings(parser.segment_best("nu:", false)),
+            vec![typed("nu:", "nv")]
+        );
+        assert_eq!(
+            spellings(parser.segment_best("lyue", false)),
+            vec![typed("lyue", "lve")]
+        );
+        assert_eq!(
+            spellings(parser.segment_best("jv", false)),
+            vec![typed("jv", "ju")]
+        );
+        assert_eq!(
+            spellings(parser.segment_best("nürenxue", false)),
+            vec![typed("nü", "nv"), plain("ren"), plain("xue")]
+        );
+        // Already the table spelling
+        assert_eq!(
+            spellings(parser.segment_best("nv", false)),
+            vec![plain("nv")]
+        );
+        assert_eq!(
+            spellings(parser.segment_top_k("xüe", 1, false).remove(0)),
+            vec![typed("xüe", "xue")]
+        );
+        assert_eq!(
+            spellings(parser.segment_all("lüe", false).remove(0)),
+            vec![typed("lüe", "lve")]
+        );
+
+        let mut diacritic = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        diacritic.set_tone_mode(ToneMode::Diacritic);
+        let seg = diacritic.segment_best("nǚ", false);
+        assert_eq!((seg[0].text.as_str(), seg[0].lexicon_text()), ("nü", "nv"));
+    }
+
+    #[test]
+    fn incremental_matches_fresh_segmentation_for_every_prefix() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        let input = "woaizhonghuarenmin";
+
+        for allow_fuzzy in [false, true] {
+            let mut state = SegmentState::new();
+            for (i, ch) in input.char_indices() {
+                let prefix = &input[..i + ch.len_utf8()];
+                let incremental = parser.segment_incremental(&mut state, ch, allow_fuzzy);
+                assert_eq!(
+                    incremental,
+                    parser.segment_best(prefix, allow_fuzzy),
+                    "{} (fuzzy: {})",
+                    prefix,
+                    allow_fuzzy
+                );
+            }
+            assert_eq!(state.input(), input);
+
+            // Backspace walks the same prefixes in reverse
+            for end in (0..input.len()).rev() {
+                let prefix = &input[..end];
+                let incremental = parser.segment_backspace(&mut state, allow_fuzzy);
+                assert_eq!(
+                    incremental,
+                    parser.segment_best(prefix, allow_fuzzy),
+                    "{}",
+                    prefix
+                );
+            }
+            assert!(state.is_empty());
+        }
+    }
+
+    #[test]
+    fn incremental_canonicalizes_umlaut_spellings() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        for input in ["nu:ren", "lyue", "xüe", "Ni Hao"] {
+            let mut state = SegmentState::new();
+            let mut incremental = Vec::new();
+            for ch in input.chars() {
+                incremental = parser.segment_incremental(&mut state, ch, false);
+            }
+            assert_eq!(incremental, parser.segment_best(input, false), "{}", input);
+        }
+
+        // Deleting the ':' of "nu:" brings the plain u back
+        let mut state = SegmentState::new();
+        for ch in "nu:".chars() {
+            parser.segment_incremental(&mut state, ch, false);
+        }
+        assert_eq!(state.input(), "nv");
+        assert_eq!(
+            texts(vec![parser.segment_backspace(&mut state, false)]),
+            vec!["nu"]
+        );
+        assert_eq!(state.input(), "nu");
+    }
+
+    #[test]
+    fn syllables_record_cost_and_rule() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        let rules = |input: &str| -> Vec<Option<String>> {
+            parser
+                .segment_best_detailed(input, true)
+                .into_iter()
+                .map(|s| s.rule)
+                .collect()
+        };
+
+        let exact = parser.segment_best_detailed("nihao", true);
+        assert!(exact.iter().all(|s| s.rule.is_none() && !s.fuzzy));
+        assert!(exact.iter().all(|s| s.cost > 0.0));
+
+        // Fuzzy pair in typed order, correction as from→to
+        assert_eq!(rules("tin"), vec![Some("tin=ting".to_string())]);
+        assert_eq!(rules("nue"), vec![Some("ue→ve".to_string())]);
+        assert_eq!(rules("zh"), vec![Some(INCOMPLETE_RULE.to_string())]);
+
+        // A fuzzy syllable costs more than the exact spelling
+        let fuzzy = parser.segment_best_detailed("tin", true);
+        let exact = parser.segment_best_detailed("ting", true);
+        assert!(fuzzy[0].cost > exact[0].cost);
+
+        // fuzzy is exactly rule.is_some() in every parser output
+        for seg in parser
+            .segment_top_k("tinhuazi", 4, true)
+            .into_iter()
+            .chain(parser.segment_all("tinhuazi", true))
+            .chain([parser.segment_best("tinhuazh", true)])
+        {
+            assert!(seg.iter().all(|s| s.fuzzy == s.rule.is_some()), "{:?}", seg);
+        }
+    }
+
+    /// Lexicon spelling and tone of each syllable of the best segmentation.
+    fn toned(parser: &Parser, input: &str) -> Vec<(String, Option<u8>)> {
+        parser
+            .segment_best(input, false)
+            .iter()
+            .map(|s| (s.lexicon_text().to_string(), s.tone))
+            .collect()
+    }
+
+    fn expected(syllables: &[(&str, Option<u8>)]) -> Vec<(String, Option<u8>)> {
+        syllables
+            .iter()
+            .map(|(t, tone)| (t.to_string(), *tone))
+            .collect()
+    }
+
+    #[test]
+    fn numeric_tones_are_parsed_off() {
+        let mut parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        parser.set_tone_mode(ToneMode::Numeric);
+
+        assert_eq!(
+            toned(&parser, "ni3hao3"),
+            expected(&[("ni", Some(3)), ("hao", Some(3))])
+        );
+        assert_eq!(
+            toned(&parser, "ni3hao"),
+            expected(&[("ni", Some(3)), ("hao", None)])
+        );
+        // 0 is the neutral tone; a digit also ends the syllable ("xi1an" is xi'an)
+        assert_eq!(
+            toned(&parser, "ma0xi1an"),
+            expected(&[("ma", Some(5)), ("xi", Some(1)), ("an", None)])
+        );
+
+        let top = parser.segment_top_k("ni3hao3", 3, false);
+        assert_eq!(texts(top.clone())[0], "ni'hao");
+        assert_eq!(top[0][1].tone, Some(3));
+    }
+
+    #[test]
+    fn diacritic_tones_are_parsed_off() {
+        let mut parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        parser.set_tone_mode(ToneMode::Diacritic);
+
+        assert_eq!(
+            toned(&parser, "nǐhǎo"),
+            expected(&[("ni", Some(3)), ("hao", Some(3))])
+        );
+        assert_eq!(
+            toned(&parser, "nǚlǜ"),
+            expected(&[("nv", Some(3)), ("lv", Some(4))])
+        );
+        assert_eq!(toned(&parser, "xué"), expected(&[("xue", Some(2))]));
+
+        let top = parser.segment_top_k("nǐhǎo", 3, false);
+        assert_eq!(texts(top.clone())[0], "ni'hao");
+        assert_eq!(top[0][0].tone, Some(3));
+    }
+
+    #[test]
+    fn tone_modes_leave_toneless_input_alone() {
+        let plain = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        for mode in [ToneMode::Numeric, ToneMode::Diacritic] {
+            let mut parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+            parser.set_tone_mode(mode);
+            for input in ["nihao", "xi'an", "zhongguoren", "tinhua"] {
+                assert_eq!(
+                    parser.segment_best(input, true),
+                    plain.segment_best(input, true)
+                );
+                assert_eq!(
+                    parser.segment_top_k(input, 4, true),
+                    plain.segment_top_k(input, 4, true)
+                );
+            }
+        }
+
+        // Without tone parsing a digit stays an unknown character
+        let segs = plain.segment_best("ni3", false);
+        assert_eq!(segs.last().map(|s| s.text.as_str()), Some("3"));
+        assert!(segs.iter().all(|s| s.tone.is_none()));
+    }
+
+    #[test]
+    fn toned_syllable_matches_readings() {
+        let hao = Syllable::new("hao", false).with_tone(Some(3));
+        assert!(hao.matches_reading("hao3", true));
+        assert!(hao.matches_reading("hao", true));
+        assert!(!hao.matches_reading("hao4", true));
+        assert!(!hao.matches_reading("ni3", true));
+        assert!(Syllable::new("hao", false).matches_reading("hao4", true));
+    }
+
+    #[test]
+    fn fuzzy_rules_toggle_at_runtime() {
+        let mut parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        let has = |parser: &Parser, from: &str, to: &str| {
+            parser.fuzzy_alternatives(from).contains(&to.to_string())
+        };
+        assert!(has(&parser, "l", "n"));
+        assert!(has(&parser, "z", "zh"));
+
+        // Removing one direction drops both, other rules stay
+        assert!(parser.fuzzy_map_mut().remove_rule("n", "l"));
+        assert!(!has(&parser, "l", "n"));
+        assert!(!has(&parser, "n", "l"));
+        assert!(has(&parser, "l", "r"));
+        assert!(has(&parser, "z", "zh"));
+        assert!(!parser.fuzzy_map_mut().remove_rule("l", "n"));
+
+        assert!(parser.fuzzy_map_mut().add_rule_str("l=n:1.5"));
+        assert!(has(&parser, "l", "n"));
+        assert!(has(&parser, "n", "l"));
+        assert!(!parser.fuzzy_map_mut().add_rule_str("l"));
+
+        // Segmentation sees the change too
+        let readings = |parser: &Parser| texts(parser.segment_top_k("fei", 8, true));
+        assert!(!readings(&parser).contains(&"hui".to_string()));
+        parser.fuzzy_map_mut().add_rule_str("fei=hui");
+        assert!(readings(&parser).contains(&"hui".to_string()));
+        assert!(parser.fuzzy_map_mut().remove_rule("hui", "fei"));
+        assert!(!readings(&parser).contains(&"hui".to_string()));
+
+        parser.fuzzy_map_mut().clear();
+        assert_eq!(parser.fuzzy_alternatives("z"), vec!["z".to_string()]);
+        assert!(parser.fuzzy_map_mut().rules().is_empty());
+    }
+
+    #[test]
+    fn ambiguity_score_reflects_competing_segmentations() {
+        let parser = Parser::with_syllables(crate::PINYIN_SYLLABLES);
+        let clear = parser.ambiguity_score("nihao");
+        assert!(clear < 0.5, "nihao scored {}", clear);
+        assert!(parser.ambiguity_score("zhongguo") < 0.5);
+
+        // Equally cheap readings: fang'an / fan'gan, xian'shang'en / xian'shan'gen
+        assert_eq!(parser.ambiguity_score("fangan"), 1.0);
+        assert_eq!(parser.ambiguity_score("xianshangen"), 1.0);
+
+        assert_eq!(parser.ambiguity_score(""), 0.0);
+    }
+
+    #[test]
+    fn segment_all_lists_every_covering_path() {
+        let parser = Parser::with_syllables(&["xi", "an", "xian", "a", "n", "fang", "fan", "gan"]);
+
+        // Fewest syllables first
+        assert_eq!(
+            texts(parser.segment_all("xian", false)),
+            vec!["xian", "xi'an", "xi'a'n"]
+        );
+        assert_eq!(
+            texts(parser.segment_all("xi'an", false)),
+            vec!["xi'an", "xi'a'n"]
+        );
+        assert_eq!(
+            texts(parser.segment_all("fangan", false)),
+            vec!["fan'gan", "fang'an", "fang'a'n"]
+        );
+    }
+
+    #[test]
+    fn segment_all_has_no_unknown_fallback() {
+        let parser = Parser::with_syllables(&["ni", "hao"]);
+        assert!(parser.segment_all("nihaox", false).is_empty());
+        assert!(parser.segment_all("", false).is_empty());
+        // segment_top_k still falls back to single characters
+        assert!(!parser.segment_top_k("nihaox", 4, false).is_empty());
+    }
+
+    #[test]
+    fn segment_all_respects_cap_and_ranks_fuzzy_last() {
+        let parser =
+            Parser::with_syllables(&["xi", "an", "xian", "a", "n"]).with_max_segmentations(2);
+        assert_eq!(parser.max_segmentations(), 2);
+        assert_eq!(parser.segment_all("xian", false).len(), 2);
+
+        let mut parser = Parser::with_syllables(&["zhi", "zi"]);
+        parser.fuzzy = FuzzyMap::from_rules(&["zi=zhi:1.0".to_string(), "zhi=zi:1.0".to_string()]);
+        let segs = parser.segment_all("zi", true);
+        assert_eq!(texts(segs.clone()), vec!["zi", "zhi"]);
+        assert!(!segs[0][0].fuzzy && segs[1][0].fuzzy);
+    }
+
+    #[test]
+    fn max_fuzzy_alternatives_keeps_lowest_penalty() {
+        // "sa" is not a syllable; three rules offer replacements for it
+        let mut parser = Parser::with_syllables(&["sha", "za", "ca"]);
+        parser.fuzzy = FuzzyMap::from_rules(&[
+            "sa=za:2.0".to_string(),
+            "sa=sha:1.0".to_string(),
+            "sa=ca:3.0".to_string(),
+        ]);
+        let alts: Vec<String> = parser
+            .fuzzy
+            .alternatives_limited("sa", 2)
+            .into_iter()
+            .map(|(alt, _)| alt)
+            .collect();
+        assert_eq!(alts, vec!["sa", "sha", "za"]);
+
+        let firsts = |config: &libchinese_core::Config| -> Vec<String> {
+            let mut firsts: Vec<String> = parser
+                .segment_top_k_with_config("sa", 5, true, None, config)
+                .into_iter()
+                .map(|seg| seg[0].text.clone())
+                .filter(|text| text != "sa" && text != "s")
+                .collect();
+            firsts.sort();
+            firsts.dedup();
+            firsts
+        };
+
+        let uncapped = libchinese_core::Config::default();
+        assert_eq!(firsts(&uncapped), vec!["ca", "sha", "za"]);
+
+        let mut capped = libchinese_core::Config::default();
+        capped.set_max_fuzzy_alternatives(1);
+        assert_eq!(firsts(&capped), vec!["sha"]);
+
+        // Long input stays quick and only ever uses the cheapest rule
+        let input = "sa".repeat(30);
+        let start = std::time::Instant::now();
+        let best = parser.segment_top_k_with_config(&input, 5, true, None, &capped);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert!(best[0].iter().all(|s| s.text == "sha"), "{:?}", best[0]);
+    }
+
     #[test]
     fn fuzzy_alternative_placeholder() {
         let mut parser = Parser::new();
@@ -1017,12 +2320,16 @@ mod tests {
 // Implement core::SyllableType for Syllable
 impl libchinese_core::SyllableType for Syllable {
     fn text(&self) -> &str {
-        &self.text
+        self.lexicon_text()
     }
 
     fn is_fuzzy(&self) -> bool {
         self.fuzzy
     }
+
+    fn tone(&self) -> Option<u8> {
+        self.tone
+    }
 }
 
 // Implement core::SyllableParser for Parser
@@ -1032,4 +2339,28 @@ impl libchinese_core::SyllableParser for Parser {
     fn segment_top_k(&self, input: &str, k: usize, allow_fuzzy: bool) -> Vec<Vec<Self::Syllable>> {
         self.segment_top_k(input, k, allow_fuzzy)
     }
+
+    fn segment_best_constrained(
+        &self,
+        input: &str,
+        boundaries: &[usize],
+        allow_fuzzy: bool,
+    ) -> Vec<Syllable> {
+        Parser::segment_best_constrained(self, input, boundaries, allow_fuzzy)
+    }
+
+    fn fuzzy_rules(&self) -> Vec<String> {
+        self.fuzzy.rules()
+    }
+
+    fn syllable_count(&self) -> usize {
+        self.trie.word_count()
+    }
+
+    fn input_error(&self, input: &str) -> Option<String> {
+        let scheme = self.double_pinyin_scheme.as_deref()?;
+        self.convert_double_pinyin_checked(input, scheme)
+            .err()
+            .map(|e| e.to_string())
+    }
 }
@@ -0,0 +1,116 @@
+//! Browser bindings (`wasm` feature).
+//!
+//! `WasmIme` wraps a pinyin `ImeEngine` for JavaScript. Its user dictionary
+//! lives in memory (`UserDict::in_memory`), so no filesystem is needed, and
+//! the lexicon artifacts produced by `convert_table` are passed in as bytes:
+//!
+//! ```js
+//! import init, { WasmIme } from "./pkg/libpinyin.js";
+//!
+//! await init();
+//! const load = async (name) =>
+//!   new Uint8Array(await (await fetch(`data/${name}`)).arrayBuffer());
+//! const ime = new WasmIme(await load("lexicon.fst"), await load("lexicon.bincode"));
+//!
+//! input.addEventListener("keydown", (event) => {
+//!   if (ime.process_key(event.key)) {
+//!     event.preventDefault();
+//!     render(JSON.parse(ime.context_json()));
+//!   }
+//! });
+//! ```
+//!
+//! Build with `wasm-pack build libpinyin --target web -- --features wasm`.
+
+use crate::{Engine, ImeEngine, KeyEvent, KeyResult, Parser};
+use libchinese_core::{Config, Lexicon, Model, UserDict, WordBigram};
+use wasm_bindgen::prelude::*;
+
+/// Pinyin IME handle for JavaScript.
+#[wasm_bindgen]
+pub struct WasmIme {
+    ime: ImeEngine<Parser>,
+}
+
+#[wasm_bindgen]
+impl WasmIme {
+    /// Create an IME from the contents of `lexicon.fst` and
+    /// `lexicon.bincode`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(fst: &[u8], bincode: &[u8]) -> Result<WasmIme, JsError> {
+        let lexicon =
+            Lexicon::from_fst_bincode_bytes(fst.to_vec(), bincode).map_err(|e| JsError::new(&e))?;
+        let userdict = UserDict::in_memory().map_err(|e| JsError::new(&e.to_string()))?;
+        let model = Model::new(lexicon, WordBigram::new(), userdict, Config::default());
+        let engine = Engine::new(model);
+        Ok(Self {
+            ime: ImeEngine::from_arc(engine.inner_arc()),
+        })
+    }
+
+    /// Feed a key, given as a `KeyboardEvent.key` value ("n", "Backspace",
+    /// " ", "Ctrl+." for Ctrl combinations). Returns true if the IME
+    /// consumed the key.
+    pub fn process_key(&mut self, js_key: &str) -> bool {
+        match key_event(js_key) {
+            Some(key) => self.ime.process_key(key) == KeyResult::Handled,
+            None => false,
+        }
+    }
+
+    /// State after the last key as JSON: `preedit`, `preedit_cursor`,
+    /// `commit`, `candidates`, `candidate_cursor` and `auxiliary`.
+    pub fn context_json(&self) -> String {
+        let ctx = self.ime.context();
+        serde_json::json!({
+            "preedit": ctx.preedit_text,
+            "preedit_cursor": ctx.preedit_cursor,
+            "commit": ctx.commit_text,
+            "candidates": ctx.candidates,
+            "candidate_cursor": ctx.candidate_cursor,
+            "auxiliary": ctx.auxiliary_text,
+        })
+        .to_string()
+    }
+
+    /// Clear the composition and candidates.
+    pub fn reset(&mut self) {
+        self.ime.reset();
+    }
+}
+
+/// Translate a `KeyboardEvent.key` value to a `KeyEvent`.
+///
+/// A "Ctrl+" prefix marks a Ctrl combination. Returns `None` for keys the
+/// IME has no use for (modifiers pressed alone, function keys, ...).
+pub fn key_event(js_key: &str) -> Option<KeyEvent> {
+    if let Some(rest) = js_key.strip_prefix("Ctrl+") {
+        return single_char(rest).map(KeyEvent::Ctrl);
+    }
+
+    let key = match js_key {
+        " " => KeyEvent::Space,
+        "Backspace" => KeyEvent::Backspace,
+        "Delete" => KeyEvent::Delete,
+        "ArrowLeft" => KeyEvent::Left,
+        "ArrowRight" => KeyEvent::Right,
+        "ArrowUp" => KeyEvent::Up,
+        "ArrowDown" => KeyEvent::Down,
+        "PageUp" => KeyEvent::PageUp,
+        "PageDown" => KeyEvent::PageDown,
+        "Enter" => KeyEvent::Enter,
+        "Escape" => KeyEvent::Escape,
+        "CapsLock" => KeyEvent::ShiftLock,
+        _ => KeyEvent::Char(single_char(js_key)?),
+    };
+    Some(key)
+}
+
+/// The character of a one-character key name.
+fn single_char(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
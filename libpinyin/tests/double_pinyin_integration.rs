@@ -2,7 +2,10 @@
 //!
 //! Tests the complete workflow from double pinyin input to segmentation.
 
-use libpinyin::double_pinyin::{double_to_full_pinyin, get_scheme_data, DoublePinyinScheme};
+use libpinyin::double_pinyin::{
+    convert_scheme, double_to_full_pinyin, get_scheme_data, DoublePinyinError, DoublePinyinScheme,
+    DoublePinyinSchemeData,
+};
 use libpinyin::Parser;
 
 #[test]
@@ -229,3 +232,195 @@ fn test_all_schemes_available() {
         assert!(!data.yunmu.is_empty());
     }
 }
+
+/// Decode a double pinyin sequence to space-separated full pinyin.
+fn decode_all(input: &str, scheme: DoublePinyinScheme) -> Option<String> {
+    let data = get_scheme_data(&scheme);
+    let chars: Vec<char> = input.chars().collect();
+    let syllables: Option<Vec<String>> = chars
+        .chunks(2)
+        .map(|pair| double_to_full_pinyin(pair[0], pair[1], &data))
+        .collect();
+    syllables.map(|s| s.join(" "))
+}
+
+#[test]
+fn test_convert_scheme_microsoft_to_xiaohe() {
+    // shi hen bei: "ei" is z in Microsoft but w in XiaoHe
+    let microsoft = "uihfbz";
+    let xiaohe = convert_scheme(
+        microsoft,
+        DoublePinyinScheme::Microsoft,
+        DoublePinyinScheme::XiaoHe,
+    )
+    .expect("every syllable is typeable in XiaoHe");
+    assert_eq!(xiaohe, "uihfbw");
+
+    let full = decode_all(microsoft, DoublePinyinScheme::Microsoft);
+    assert_eq!(full.as_deref(), Some("shi hen bei"));
+    assert_eq!(decode_all(&xiaohe, DoublePinyinScheme::XiaoHe), full);
+
+    // And back again
+    assert_eq!(
+        convert_scheme(
+            &xiaohe,
+            DoublePinyinScheme::XiaoHe,
+            DoublePinyinScheme::Microsoft
+        )
+        .as_deref(),
+        Some(microsoft)
+    );
+}
+
+#[test]
+fn test_convert_scheme_rejects_untranslatable_input() {
+    // Odd number of keys
+    assert_eq!(
+        convert_scheme(
+            "uih",
+            DoublePinyinScheme::Microsoft,
+            DoublePinyinScheme::XiaoHe
+        ),
+        None
+    );
+    // XiaoHe has no key for the "ong" final, so "zhong" cannot be typed
+    assert_eq!(
+        convert_scheme(
+            "vs",
+            DoublePinyinScheme::Microsoft,
+            DoublePinyinScheme::XiaoHe
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_available_double_pinyin_schemes() {
+    let schemes = libpinyin::available_double_pinyin_schemes();
+    for scheme in [
+        DoublePinyinScheme::Microsoft,
+        DoublePinyinScheme::ZiRanMa,
+        DoublePinyinScheme::ZiGuang,
+        DoublePinyinScheme::ABC,
+        DoublePinyinScheme::XiaoHe,
+        DoublePinyinScheme::PinYinPlusPlus,
+    ] {
+        assert!(schemes.contains(&scheme), "missing {:?}", scheme);
+    }
+    assert_eq!(schemes.len(), 6);
+}
+
+#[test]
+fn test_available_fuzzy_profiles() {
+    let profiles = libpinyin::available_fuzzy_profiles();
+    assert!(profiles.contains(&"standard"));
+    assert!(profiles.contains(&"none"));
+    for name in &profiles {
+        assert!(libpinyin::fuzzy_profile_rules(name).is_some(), "{}", name);
+    }
+    assert_eq!(
+        libpinyin::fuzzy_profile_rules("standard"),
+        Some(libpinyin::standard_fuzzy_rules())
+    );
+    assert_eq!(libpinyin::fuzzy_profile_rules("missing"), None);
+}
+
+#[test]
+fn test_custom_scheme_from_toml() {
+    let dir = std::env::temp_dir().join(format!("libpinyin_custom_dp_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("myscheme.toml");
+    std::fs::write(
+        &path,
+        r#"
+[initials]
+v = "zh"
+u = "sh"
+
+[finals]
+i = "i"
+k = "ong"
+h = "ang"
+"#,
+    )
+    .unwrap();
+
+    let data = DoublePinyinSchemeData::from_toml(&path).expect("valid scheme");
+    assert_eq!(data.name, "myscheme");
+    // Reloading reuses the interned name instead of leaking another copy
+    let reloaded = DoublePinyinSchemeData::from_toml(&path).expect("valid scheme");
+    assert!(std::ptr::eq(data.name, reloaded.name));
+    libpinyin::register_custom_scheme("myscheme", data).unwrap();
+
+    let scheme = DoublePinyinScheme::from_name("custom:myscheme").unwrap();
+    assert_eq!(scheme, DoublePinyinScheme::Custom("myscheme"));
+    assert_eq!(
+        double_to_full_pinyin('v', 'k', &get_scheme_data(&scheme)),
+        Some("zhong".to_string())
+    );
+
+    let parser = Parser::with_syllables(&["zhong", "shi", "bang"]);
+    assert_eq!(
+        parser.convert_double_pinyin("vkuibh", "custom:myscheme"),
+        Some("zhongshibang".to_string())
+    );
+    assert_eq!(parser.convert_double_pinyin("vk", "custom:unknown"), None);
+    assert_eq!(
+        DoublePinyinScheme::parse("custom:unknown"),
+        Err(DoublePinyinError::UnknownScheme(
+            "custom:unknown".to_string()
+        ))
+    );
+
+    // A final that is not real pinyin is rejected when loading
+    let bad = dir.join("bad.toml");
+    std::fs::write(&bad, "[finals]\nk = \"ongg\"\n").unwrap();
+    let err = DoublePinyinSchemeData::from_toml(&bad).unwrap_err();
+    assert!(err.contains("'k'") && err.contains("ongg"), "{}", err);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_checked_conversion_accepts_valid_input() {
+    let parser = Parser::new();
+    assert_eq!(
+        parser.convert_double_pinyin_checked("uihfbz", "microsoft"),
+        Ok("shihenbei".to_string())
+    );
+    // Lenient and checked agree on valid input
+    assert_eq!(
+        parser.convert_double_pinyin("uihfbz", "microsoft"),
+        Some("shihenbei".to_string())
+    );
+}
+
+#[test]
+fn test_checked_conversion_reports_invalid_second_key() {
+    let parser = Parser::new();
+    let err = parser
+        .convert_double_pinyin_checked("uihfb3", "microsoft")
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DoublePinyinError::InvalidKeys {
+            position: 4,
+            keys: ('b', '3'),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "invalid shuangpin key \"b3\" at position 4"
+    );
+
+    // The lenient conversion still passes the pair through
+    assert_eq!(
+        parser.convert_double_pinyin("uihfb3", "microsoft"),
+        Some("shihenb3".to_string())
+    );
+    assert_eq!(
+        parser.convert_double_pinyin_checked("uihf", "nosuchscheme"),
+        Err(DoublePinyinError::UnknownScheme("nosuchscheme".to_string()))
+    );
+}
@@ -0,0 +1,2418 @@
+use libchinese_core::KeyAction;
+/// Engine-level tests for optional candidate features.
+///
+/// These build a small in-memory lexicon and a throwaway user dictionary so
+/// they do not depend on the converted data files.
+use libchinese_core::{
+    ApostropheMode, CandidateSource, Config, Lexicon, MergePriority, Model, OriginStats,
+    SessionStats, UserDict, WordBigram, SHAPE_KEY_SEPARATOR,
+};
+use libpinyin::{Engine, ImeEngine, InputMode, KeyEvent, KeyResult};
+
+fn temp_userdict(name: &str) -> UserDict {
+    let path = std::env::temp_dir().join(format!("libpinyin_{}_{}.redb", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    UserDict::new(&path).expect("create temp userdict")
+}
+
+fn test_model(name: &str, entries: &[(&str, &str)], config: Config) -> Model {
+    let mut lexicon = Lexicon::new();
+    for (key, phrase) in entries {
+        lexicon.insert(*key, *phrase);
+    }
+    // Splits, keystroke plans, particle trimming and training look phrases up
+    lexicon.build_reverse_index();
+    Model::new(lexicon, WordBigram::new(), temp_userdict(name), config)
+}
+
+fn test_engine(name: &str, entries: &[(&str, &str)], config: Config) -> Engine {
+    Engine::new(test_model(name, entries, config))
+}
+
+/// Write `<name>.fst` and `<name>.bincode` under `dir`; payload group `i`
+/// belongs to the `i`-th key in byte order.
+fn write_fst_lexicon(dir: &std::path::Path, name: &str, groups: &[(&str, &[(&str, u32)])]) {
+    Lexicon::write_fst_bincode(
+        dir.join(format!("{}.fst", name)),
+        dir.join(format!("{}.bincode", name)),
+        groups,
+    )
+    .unwrap();
+}
+
+#[test]
+fn numeral_pinyin_offers_chinese_and_arabic_forms() {
+    let config = Config {
+        numeral_candidates: true,
+        ..Config::default()
+    };
+    let engine = test_engine(
+        "numeral_pinyin",
+        &[("yi", "一"), ("bai", "百"), ("er", "二"), ("shi", "十")],
+        config,
+    );
+
+    let texts: Vec<String> = engine
+        .input("yibaiershi")
+        .into_iter()
+        .map(|c| c.text)
+        .collect();
+    assert!(texts.contains(&"一百二十".to_string()), "got {:?}", texts);
+    assert!(texts.contains(&"120".to_string()), "got {:?}", texts);
+}
+
+#[test]
+fn numeral_candidates_disabled_by_default() {
+    let engine = test_engine(
+        "numeral_off",
+        &[("yi", "一"), ("bai", "百")],
+        Config::default(),
+    );
+
+    let texts: Vec<String> = engine.input("yibai").into_iter().map(|c| c.text).collect();
+    assert!(!texts.contains(&"100".to_string()), "got {:?}", texts);
+}
+
+#[test]
+fn digits_offer_chinese_numeral() {
+    let config = Config {
+        numeral_candidates: true,
+        ..Config::default()
+    };
+    let engine = test_engine("numeral_digits", &[], config);
+
+    let texts: Vec<String> = engine.input("2005").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts.first().map(String::as_str), Some("二千零五"));
+}
+
+#[test]
+fn preview_candidates_leaves_session_untouched() {
+    let engine = test_engine(
+        "preview",
+        &[
+            ("ni", "你"),
+            ("hao", "好"),
+            ("ni'hao", "你好"),
+            ("zhong'guo", "中国"),
+        ],
+        Config::default(),
+    );
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    for ch in "nihao".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    let candidates_before = ime.context().candidates.clone();
+
+    let preview = ime.preview_candidates("zhongguo");
+    assert_eq!(preview.first().map(|c| c.text.as_str()), Some("中国"));
+
+    assert_eq!(ime.session().input_buffer().text(), "nihao");
+    assert_eq!(ime.session().mode(), InputMode::Phonetic);
+    assert_eq!(ime.context().preedit_text, "nihao");
+    assert_eq!(ime.context().candidates, candidates_before);
+}
+
+#[test]
+fn quick_code_ranks_first_for_exact_code() {
+    let mut codes = std::collections::HashMap::new();
+    codes.insert("wo".to_string(), vec!["我们的".to_string()]);
+    let model = test_model(
+        "quick_codes",
+        &[("wo", "我"), ("wo", "握")],
+        Config::default(),
+    )
+    .with_quick_codes(codes);
+    let engine = Engine::new(model);
+
+    let texts: Vec<String> = engine.input("wo").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts.first().map(String::as_str), Some("我们的"));
+    assert!(texts.contains(&"我".to_string()), "got {:?}", texts);
+
+    // Only the exact code triggers the quick code phrase
+    let texts: Vec<String> = engine.input("wowo").into_iter().map(|c| c.text).collect();
+    assert!(!texts.contains(&"我们的".to_string()), "got {:?}", texts);
+}
+
+#[test]
+fn rebinding_select_first_moves_selection_to_new_key() {
+    let engine = test_engine("keybindings", &[("ni", "你")], Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    let displaced = ime
+        .key_bindings_mut()
+        .bind(KeyAction::SelectFirst, KeyEvent::Down);
+    assert_eq!(displaced, Some(KeyAction::NextCandidate));
+
+    for ch in "ni".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+
+    // Space no longer selects
+    assert_eq!(ime.process_key(KeyEvent::Space), KeyResult::NotHandled);
+    assert!(ime.context().commit_text.is_empty());
+    assert_eq!(ime.session().input_buffer().text(), "ni");
+
+    // Down now selects the first candidate
+    assert_eq!(ime.process_key(KeyEvent::Down), KeyResult::Handled);
+    assert_eq!(ime.context().commit_text, "你");
+}
+
+#[test]
+fn page_size_change_keeps_selection() {
+    let phrases = ["是", "时", "事", "十", "市", "式", "使"];
+    let entries: Vec<(&str, &str)> = phrases.iter().map(|p| ("shi", *p)).collect();
+    let engine = test_engine("page_size_change", &entries, Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    ime.set_page_size(3);
+
+    for ch in "shi".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    ime.process_key(KeyEvent::PageDown);
+    ime.process_key(KeyEvent::Down);
+    let candidates = ime.session().candidates();
+    assert_eq!((candidates.current_page(), candidates.cursor()), (1, 1));
+    assert_eq!(candidates.selected_index(), Some(4));
+    let selected = candidates.selected_candidate().unwrap().text.clone();
+
+    ime.set_page_size(5);
+    assert_eq!(ime.page_size(), 5);
+    let candidates = ime.session().candidates();
+    assert_eq!((candidates.current_page(), candidates.cursor()), (0, 4));
+    assert_eq!(candidates.selected_candidate().unwrap().text, selected);
+    assert_eq!(ime.context().candidates.len(), 5);
+    assert_eq!(ime.context().candidate_cursor, 4);
+
+    // Size 0 is clamped instead of breaking pagination
+    ime.set_page_size(0);
+    assert_eq!(ime.page_size(), 1);
+    let candidates = ime.session().candidates();
+    assert_eq!(candidates.current_page(), 4);
+    assert_eq!(candidates.selected_candidate().unwrap().text, selected);
+}
+
+#[test]
+fn typing_at_preedit_caret_resegments_buffer() {
+    let entries = [("ni", "你"), ("hao", "好"), ("ni'hao", "你好")];
+    let engine = test_engine("preedit_caret", &entries, Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    for ch in "nhao".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    assert_eq!(ime.context().preedit_cursor, 4);
+
+    for _ in 0..3 {
+        ime.process_key(KeyEvent::Left);
+    }
+    assert_eq!(ime.session().input_buffer().cursor(), 1);
+    assert_eq!(ime.context().preedit_cursor, 1);
+
+    // The missing "i" goes in mid-buffer and "nihao" converts as a whole
+    ime.process_key(KeyEvent::Char('i'));
+    assert_eq!(ime.session().input_buffer().text(), "nihao");
+    assert_eq!(ime.context().preedit_text, "nihao");
+    assert_eq!(ime.context().preedit_cursor, 2);
+    assert_eq!(ime.context().candidates[0], "你好");
+
+    // Deleting at the caret re-segments as well
+    ime.process_key(KeyEvent::Right);
+    ime.process_key(KeyEvent::Backspace);
+    assert_eq!(ime.session().input_buffer().text(), "niao");
+    assert_eq!(ime.context().preedit_cursor, 2);
+    assert!(!ime.context().candidates.iter().any(|c| c == "你好"));
+}
+
+#[test]
+fn prefix_candidate_commits_and_keeps_remaining_input() {
+    let entries = [
+        ("bei'jing", "北京"),
+        ("da'xue", "大学"),
+        ("bei", "北"),
+        ("jing", "京"),
+        ("da", "大"),
+        ("xue", "学"),
+    ];
+
+    // Off by default: every candidate spells the whole input
+    let engine = test_engine("prefix_off", &entries, Config::default());
+    assert!(!engine
+        .input("beijingdaxue")
+        .iter()
+        .any(|c| c.text == "北京"));
+
+    let config = Config {
+        offer_prefix_candidates: true,
+        ..Config::default()
+    };
+    let engine = test_engine("prefix_commit", &entries, config);
+    let cands = engine.input("beijingdaxue");
+    assert_eq!(cands[0].text, "北京大学");
+    let prefix = cands.iter().find(|c| c.text == "北京").unwrap();
+    assert_eq!(prefix.reading.as_deref(), Some("bei'jing"));
+
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    for ch in "beijingdaxue".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    let pos = ime
+        .context()
+        .candidates
+        .iter()
+        .position(|c| c == "北京")
+        .unwrap();
+    assert_eq!(
+        ime.process_key(KeyEvent::Number(pos as u8 + 1)),
+        KeyResult::Handled
+    );
+
+    // 北京 is committed and "daxue" is converted afresh
+    assert_eq!(ime.context().commit_text, "北京");
+    assert_eq!(ime.session().input_buffer().text(), "daxue");
+    assert_eq!(ime.context().preedit_text, "daxue");
+    assert_eq!(ime.context().candidates[0], "大学");
+    assert_eq!(ime.session().mode(), InputMode::Phonetic);
+
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "大学");
+    assert!(ime.session().input_buffer().is_empty());
+}
+
+/// Select the candidate showing `text` on the current page.
+fn select_candidate(ime: &mut ImeEngine<libpinyin::Parser>, text: &str) {
+    let pos = ime
+        .context()
+        .candidates
+        .iter()
+        .position(|c| c == text)
+        .unwrap();
+    assert_eq!(
+        ime.process_key(KeyEvent::Number(pos as u8 + 1)),
+        KeyResult::Handled
+    );
+    assert_eq!(ime.context().commit_text, text);
+}
+
+#[test]
+fn consecutive_partial_commits_learn_one_phrase() {
+    let entries = [("bei", "被"), ("bei", "北"), ("jing", "经"), ("jing", "京")];
+    let config = Config {
+        offer_prefix_candidates: true,
+        learn_partial_commits_as_phrase: true,
+        ..Config::default()
+    };
+    let engine = test_engine("phrase_from_parts", &entries, config);
+    let backend = engine.inner_arc();
+    let mut ime = ImeEngine::from_arc(backend.clone());
+
+    for ch in "beijing".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    select_candidate(&mut ime, "北");
+    assert_eq!(ime.session().input_buffer().text(), "jing");
+    select_candidate(&mut ime, "京");
+
+    assert!(backend.userdict().frequency("北京") > 0);
+    let top = &engine.input("beijing")[0];
+    assert_eq!(top.text, "北京");
+    assert_eq!(top.source, CandidateSource::UserDict);
+
+    // Without the config flag the phrase is learned on request
+    backend.config_mut().learn_partial_commits_as_phrase = false;
+    for ch in "beijing".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    select_candidate(&mut ime, "被");
+    select_candidate(&mut ime, "经");
+    assert_eq!(backend.userdict().frequency("被经"), 0);
+    assert_eq!(ime.commit_as_phrase().as_deref(), Some("被经"));
+    assert!(backend.userdict().frequency("被经") > 0);
+    assert_eq!(ime.commit_as_phrase(), None);
+
+    // Punctuation in between ends the run
+    for ch in "beijing".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    select_candidate(&mut ime, "北");
+    ime.process_key(KeyEvent::Char(','));
+    assert_eq!(ime.commit_as_phrase(), None);
+}
+
+#[test]
+fn rebinding_select_first_to_enter() {
+    let engine = test_engine("keybindings_enter", &[("ni", "你")], Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    let displaced = ime
+        .key_bindings_mut()
+        .bind(KeyAction::SelectFirst, KeyEvent::Enter);
+    assert_eq!(displaced, Some(KeyAction::CommitRaw));
+
+    for ch in "ni".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    assert_eq!(ime.process_key(KeyEvent::Space), KeyResult::NotHandled);
+    assert!(ime.context().commit_text.is_empty());
+
+    ime.process_key(KeyEvent::Enter);
+    assert_eq!(ime.context().commit_text, "你");
+}
+
+#[test]
+fn manual_split_is_learned_for_next_input() {
+    let engine = test_engine(
+        "segmentation_learning",
+        &[
+            ("xian", "先"),
+            ("xi", "西"),
+            ("an", "安"),
+            ("xi'an", "西安"),
+        ],
+        Config::default(),
+    );
+    // The user has picked 先 before, so it is the default reading of "xian"
+    engine.commit("先");
+
+    let before: Vec<String> = engine.input("xian").into_iter().map(|c| c.text).collect();
+    assert_eq!(before.first().map(String::as_str), Some("先"));
+
+    // User splits the input manually as "xi'an" and commits
+    engine.commit("西安");
+    engine.learn_segmentation("xi'an");
+
+    assert_eq!(
+        engine.userdict().preferred_segmentation("xian"),
+        Some(vec!["xi".to_string(), "an".to_string()])
+    );
+    let after: Vec<String> = engine.input("xian").into_iter().map(|c| c.text).collect();
+    assert_eq!(after.first().map(String::as_str), Some("西安"));
+}
+
+#[test]
+fn keystroke_plan_replays_to_target() {
+    let engine = test_engine(
+        "keystrokes",
+        &[
+            ("ni", "你"),
+            ("ni", "泥"),
+            ("hao", "好"),
+            ("ni'hao", "你好"),
+            ("ni'hao", "拟好"),
+        ],
+        Config::default(),
+    );
+    engine.commit("拟好");
+
+    let plan = engine.keystrokes_to_commit("你好").expect("plan for 你好");
+    assert_eq!(plan.input, "nihao");
+    assert_eq!(plan.keystrokes, 6);
+    assert!(
+        plan.selection < 5,
+        "selection {} off first page",
+        plan.selection
+    );
+    assert_eq!(replay_plan(&engine, &plan, 5), "你好");
+
+    assert_eq!(engine.keystrokes_to_commit("不存在"), None);
+}
+
+/// Type `plan` into a fresh IME with `page_size` candidates per page and
+/// return what it commits.
+fn replay_plan(engine: &Engine, plan: &libchinese_core::KeystrokePlan, page_size: usize) -> String {
+    let mut ime = ImeEngine::from_arc_with_page_size(engine.inner_arc(), page_size);
+    for ch in plan.input.chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    for _ in 0..plan.page_flips {
+        ime.process_key(KeyEvent::PageDown);
+    }
+    ime.process_key(KeyEvent::Number((plan.selection % page_size) as u8 + 1));
+    ime.context().commit_text.clone()
+}
+
+#[test]
+fn keystroke_plan_flips_to_a_later_page() {
+    let entries = [
+        ("ni", "你"),
+        ("ni", "泥"),
+        ("ni", "尼"),
+        ("ni", "拟"),
+        ("ni", "逆"),
+        ("ni", "腻"),
+        ("ni", "妮"),
+    ];
+    let engine = test_engine("keystrokes_page", &entries, Config::default());
+    let texts: Vec<String> = engine.input("ni").into_iter().map(|c| c.text).collect();
+    let target = texts[6].clone();
+
+    let plan = engine.keystrokes_to_commit(&target).expect("plan");
+    assert_eq!(plan.input, "ni");
+    assert_eq!((plan.selection, plan.page_flips), (6, 1));
+    assert_eq!(plan.keystrokes, 4);
+    assert_eq!(replay_plan(&engine, &plan, 5), target);
+
+    // Bigger pages need no flip
+    let plan = engine
+        .keystrokes_to_commit_with_page_size(&target, 9)
+        .expect("plan");
+    assert_eq!((plan.page_flips, plan.keystrokes), (0, 3));
+    assert_eq!(replay_plan(&engine, &plan, 9), target);
+}
+
+#[test]
+fn keystroke_plan_prefers_a_shorter_input_that_lists_the_target() {
+    let entries = [("bei", "北"), ("jing", "京"), ("bei'jing", "北京")];
+    let engine = test_engine("keystrokes_prefix", &entries, Config::default());
+
+    let plan = engine.keystrokes_to_commit("北京").expect("plan");
+    assert!(plan.input.len() < "beijing".len(), "{:?}", plan);
+    assert!("beijing".starts_with(&plan.input));
+    assert_eq!(plan.keystrokes, plan.input.len() + plan.page_flips + 1);
+    assert_eq!(replay_plan(&engine, &plan, 5), "北京");
+}
+
+#[test]
+fn rare_char_penalty_demotes_exotic_phrase() {
+    let entries = [("long", "龘"), ("long", "龙")];
+
+    // A previously picked rare character ranks first without the penalty
+    let engine = test_engine("rare_char_off", &entries, Config::default());
+    engine.commit("龘");
+    let texts: Vec<String> = engine.input("long").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts.first().map(String::as_str), Some("龘"));
+
+    let config = Config {
+        rare_char_penalty: 5.0,
+        ..Config::default()
+    };
+    let engine = test_engine("rare_char_on", &entries, config);
+    engine.commit("龘");
+    let texts: Vec<String> = engine.input("long").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts, vec!["龙".to_string(), "龘".to_string()]);
+}
+
+#[test]
+fn train_from_text_learns_words_and_bigrams() {
+    let engine = test_engine(
+        "train_from_text",
+        &[
+            ("wo", "我"),
+            ("wo'men", "我们"),
+            ("xi'huan", "喜欢"),
+            ("xue'xi", "学习"),
+            ("xue", "学"),
+        ],
+        Config::default(),
+    );
+
+    let learned = engine.train_from_text("我们喜欢学习。我们喜欢！");
+    assert_eq!(learned, 5);
+
+    let userdict = engine.userdict();
+    assert_eq!(userdict.frequency("我们"), 2);
+    assert_eq!(userdict.frequency("学习"), 1);
+    assert_eq!(userdict.frequency("学"), 0);
+    assert_eq!(userdict.bigram_frequency("我们", "喜欢"), 2);
+    assert_eq!(userdict.bigram_frequency("喜欢", "学习"), 1);
+    // Punctuation breaks the chain
+    assert_eq!(userdict.bigram_frequency("学习", "我们"), 0);
+
+    // The learned bigram now drives predictions after committing 我们
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    for ch in "women".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "我们");
+    assert_eq!(ime.session().mode(), InputMode::Suggestion);
+    assert_eq!(
+        ime.context().candidates.first().map(String::as_str),
+        Some("喜欢")
+    );
+}
+
+#[test]
+fn effective_config_dump_round_trips() {
+    let config = Config {
+        correction_penalty: 150,
+        unknown_cost: 7.5,
+        ..Config::default()
+    };
+    let engine = test_engine(
+        "dump_config",
+        &[("ni", "你"), ("hao", "好"), ("ni'hao", "你好")],
+        config,
+    );
+
+    let dump = engine.dump_effective_config().expect("dump config");
+    assert!(dump.contains("correction_penalty = 150"), "{}", dump);
+    assert!(dump.contains("\"c=ch:1.0\""), "{}", dump);
+    assert!(dump.contains("\"an=ang:1.0\""), "{}", dump);
+    assert!(
+        dump.contains(&format!(
+            "syllable_count = {}",
+            libpinyin::PINYIN_SYLLABLES.len()
+        )),
+        "{}",
+        dump
+    );
+    assert!(dump.contains("lexicon_keys = 3"), "{}", dump);
+
+    let parsed = Config::from_toml_str(&dump).expect("config portion parses");
+    assert_eq!(parsed.correction_penalty, 150);
+    assert_eq!(parsed.unknown_cost, 7.5);
+    assert_eq!(parsed.select_keys, Config::default().select_keys);
+}
+
+#[test]
+fn session_stats_track_keys_and_selections() {
+    let engine = test_engine(
+        "session_stats",
+        &[("ni", "你"), ("ni", "泥"), ("hao", "好")],
+        Config::default(),
+    );
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    for key in [
+        KeyEvent::Char('n'),
+        KeyEvent::Char('x'),
+        KeyEvent::Backspace,
+        KeyEvent::Char('i'),
+        KeyEvent::Number(2),
+    ] {
+        assert_eq!(ime.process_key(key), KeyResult::Handled);
+    }
+    assert!(!ime.context().commit_text.is_empty());
+
+    for ch in "hao".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "好");
+
+    // Not handled by the IME: not counted
+    assert_eq!(ime.process_key(KeyEvent::Left), KeyResult::NotHandled);
+
+    let stats = ime.stats();
+    assert_eq!(stats.keystrokes, 9);
+    assert_eq!(stats.backspaces, 1);
+    assert_eq!(stats.commits, 2);
+    assert_eq!(stats.selections, 2);
+    assert_eq!(stats.average_selected_rank(), Some(0.5));
+
+    ime.reset();
+    assert_eq!(ime.stats(), &SessionStats::default());
+    assert_eq!(ime.stats().average_selected_rank(), None);
+}
+
+#[test]
+fn trailing_particle_offers_trimmed_variant() {
+    let entries = [("hao'de", "好的"), ("hao", "好"), ("zou'le", "走了")];
+
+    let engine = test_engine("particles_off", &entries, Config::default());
+    let texts: Vec<String> = engine.input("haode").into_iter().map(|c| c.text).collect();
+    assert!(!texts.contains(&"好".to_string()), "got {:?}", texts);
+
+    let config = Config {
+        trim_trailing_particles: true,
+        ..Config::default()
+    };
+    let engine = test_engine("particles_on", &entries, config);
+    let texts: Vec<String> = engine.input("haode").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts[0], "好的");
+    assert_eq!(texts[1], "好");
+
+    // "走" is not a lexicon entry, so no trimmed variant
+    let texts: Vec<String> = engine.input("zoule").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts, vec!["走了".to_string()]);
+}
+
+#[test]
+fn segmentation_variants_are_labeled_by_reading() {
+    let entries = [
+        ("xian", "先"),
+        ("xi", "西"),
+        ("an", "安"),
+        ("xi'an", "西安"),
+    ];
+
+    let engine = test_engine("seg_variants_off", &entries, Config::default());
+    assert!(engine.input("xian").iter().all(|c| c.reading.is_none()));
+
+    let config = Config {
+        offer_segmentation_variants: true,
+        ..Config::default()
+    };
+    let engine = test_engine("seg_variants_on", &entries, config);
+    let candidates = engine.input("xian");
+    let reading_of = |text: &str| {
+        candidates
+            .iter()
+            .find(|c| c.text == text)
+            .and_then(|c| c.reading.clone())
+    };
+    assert_eq!(reading_of("先"), Some("xian".to_string()));
+    assert_eq!(reading_of("西安"), Some("xi'an".to_string()));
+
+    let texts: Vec<&str> = candidates.iter().map(|c| c.text.as_str()).collect();
+    let mut unique = texts.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), texts.len(), "duplicates in {:?}", texts);
+}
+
+#[test]
+fn segmentation_variants_fit_within_the_candidate_limit() {
+    let mut entries: Vec<(&str, &str)> = ["先", "现", "线", "县", "鲜", "仙", "险", "闲", "限"]
+        .iter()
+        .map(|&text| ("xian", text))
+        .collect();
+    entries.extend([("xi", "西"), ("an", "安"), ("xi'an", "西安")]);
+    let config = Config {
+        offer_segmentation_variants: true,
+        ..Config::default()
+    };
+    let engine = test_engine("seg_variants_limit", &entries, config);
+
+    let candidates = engine.input("xian");
+    assert!(candidates.len() <= 8, "{} candidates", candidates.len());
+    let xian = candidates.iter().find(|c| c.text == "西安").unwrap();
+    assert_eq!(xian.reading.as_deref(), Some("xi'an"));
+}
+
+#[test]
+fn prefetched_completion_hits_cache() {
+    let config = Config {
+        typeahead_prefetch: true,
+        ..Config::default()
+    };
+    let engine = test_engine(
+        "typeahead_prefetch",
+        &[
+            ("bei", "北"),
+            ("bei'jing", "北京"),
+            ("bei'fang", "北方"),
+            ("nan'fang", "南方"),
+        ],
+        config,
+    );
+
+    let _ = engine.input("bei");
+    let mut prefetched = engine.prefetch_completions("bei");
+    prefetched.sort();
+    assert_eq!(prefetched, vec!["beifang", "beijing"]);
+
+    let (hits_before, misses_before, _) = engine.cache_stats();
+    let texts: Vec<String> = engine
+        .input("beijing")
+        .into_iter()
+        .map(|c| c.text)
+        .collect();
+    let (hits_after, misses_after, _) = engine.cache_stats();
+    assert_eq!(hits_after, hits_before + 1);
+    assert_eq!(misses_after, misses_before);
+    assert_eq!(texts.first().map(String::as_str), Some("北京"));
+
+    // Already cached completions are not recomputed
+    assert!(engine.prefetch_completions("bei").is_empty());
+}
+
+#[test]
+fn prefetch_disabled_by_default() {
+    let engine = test_engine(
+        "typeahead_off",
+        &[("bei", "北"), ("bei'jing", "北京")],
+        Config::default(),
+    );
+
+    assert!(engine.prefetch_completions("bei").is_empty());
+    let _ = engine.input("beijing");
+    assert_eq!(engine.cache_stats().0, 0);
+}
+
+#[test]
+fn smart_punctuation_follows_preceding_character() {
+    let config = Config {
+        smart_punctuation: true,
+        ..Config::default()
+    };
+    let engine = test_engine("smart_punctuation", &[("ni", "你")], config);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    // Digits pass through to the application
+    assert_eq!(ime.process_key(KeyEvent::Char('3')), KeyResult::NotHandled);
+    ime.process_key(KeyEvent::Char('.'));
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, ".");
+
+    for key in [KeyEvent::Char('n'), KeyEvent::Char('i'), KeyEvent::Space] {
+        ime.process_key(key);
+    }
+    assert_eq!(ime.context().commit_text, "你");
+    ime.process_key(KeyEvent::Char('.'));
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "。");
+}
+
+#[test]
+fn chinese_punctuation_and_full_width_are_independent() {
+    let commit = |name: &str, chinese: bool, full_width: bool| {
+        let mut codes = std::collections::HashMap::new();
+        codes.insert("ok".to_string(), vec!["ok, go".to_string()]);
+        let model = test_model(name, &[("ni", "你")], Config::default()).with_quick_codes(codes);
+        let engine = Engine::new(model);
+        let mut ime = ImeEngine::from_arc(engine.inner_arc());
+        ime.set_chinese_punctuation(chinese);
+        ime.set_fullwidth(full_width);
+        let mut committed = String::new();
+        for key in [
+            KeyEvent::Char('.'),
+            KeyEvent::Space,
+            KeyEvent::Char('o'),
+            KeyEvent::Char('k'),
+            KeyEvent::Space,
+        ] {
+            ime.process_key(key);
+            committed.push_str(&ime.context().commit_text);
+        }
+        committed
+    };
+
+    // Only the punctuation key follows the Chinese punctuation toggle;
+    // punctuation inside a committed candidate is left alone
+    assert_eq!(commit("punct_off_half", false, false), ".ok, go");
+    assert_eq!(commit("punct_on_half", true, false), "。ok, go");
+    assert_eq!(
+        commit("punct_off_full", false, true),
+        "．ｏｋ，\u{3000}ｇｏ"
+    );
+    assert_eq!(commit("punct_on_full", true, true), "。ｏｋ，\u{3000}ｇｏ");
+}
+
+#[test]
+fn raw_input_committed_with_enter_keeps_ascii_punctuation() {
+    let engine = test_engine("raw_enter_ascii", &[("ni", "你")], Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    assert!(ime.is_chinese_punctuation());
+
+    for key in [
+        KeyEvent::Char('a'),
+        KeyEvent::Char('<'),
+        KeyEvent::Char('b'),
+    ] {
+        ime.process_key(key);
+    }
+    ime.process_key(KeyEvent::Enter);
+    assert_eq!(ime.context().commit_text, "a<b");
+}
+
+#[test]
+fn punctuation_key_offers_ascii_first_without_chinese_punctuation() {
+    let engine = test_engine("punct_ascii_first", &[("ni", "你")], Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    assert!(ime.is_chinese_punctuation());
+
+    ime.process_key(KeyEvent::Char(','));
+    assert_eq!(ime.context().candidates[0], "，");
+    ime.process_key(KeyEvent::Escape);
+
+    ime.set_chinese_punctuation(false);
+    ime.process_key(KeyEvent::Char(','));
+    assert_eq!(ime.context().candidates[0], ",");
+    assert!(ime.context().candidates.contains(&"，".to_string()));
+
+    // An explicitly picked form is committed as is
+    ime.set_chinese_punctuation(true);
+    ime.process_key(KeyEvent::Escape);
+    ime.process_key(KeyEvent::Char('.'));
+    let pos = ime
+        .context()
+        .candidates
+        .iter()
+        .position(|c| c == ".")
+        .unwrap();
+    ime.process_key(KeyEvent::Number(pos as u8 + 1));
+    assert_eq!(ime.context().commit_text, ".");
+}
+
+#[test]
+fn punctuation_after_digit_is_full_width_by_default() {
+    let engine = test_engine("plain_punctuation", &[("ni", "你")], Config::default());
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    ime.process_key(KeyEvent::Char('3'));
+    ime.process_key(KeyEvent::Char('.'));
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "。");
+}
+
+#[test]
+fn learned_punctuation_preference_moves_to_front() {
+    let config = Config {
+        learn_punctuation_preference: true,
+        ..Config::default()
+    };
+    let engine = test_engine("punctuation_preference", &[("ni", "你")], config);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    let first_offered = |ime: &mut ImeEngine<libpinyin::Parser>| -> String {
+        ime.process_key(KeyEvent::Char(','));
+        let first = ime.context().candidates[0].clone();
+        ime.process_key(KeyEvent::Escape);
+        first
+    };
+    assert_eq!(first_offered(&mut ime), "，");
+
+    // Pick the half-width form, offered second at first, a few times
+    for round in 0..3 {
+        ime.process_key(KeyEvent::Char(','));
+        let candidates = &ime.context().candidates;
+        let index = candidates.iter().position(|c| c == ",").unwrap();
+        // It moves to the front after the first pick
+        assert_eq!(index, if round == 0 { 1 } else { 0 });
+        ime.process_key(KeyEvent::Number(index as u8 + 1));
+        assert_eq!(ime.context().commit_text, ",");
+    }
+    assert_eq!(first_offered(&mut ime), ",");
+    assert_eq!(
+        engine.userdict().punctuation_preferences(',').get(","),
+        Some(&3)
+    );
+
+    // Other keys keep their default order
+    ime.process_key(KeyEvent::Char('.'));
+    assert_eq!(ime.context().candidates[0], "。");
+}
+
+#[test]
+fn only_explicit_punctuation_selections_are_learned() {
+    let config = Config {
+        learn_punctuation_preference: true,
+        smart_punctuation: true,
+        ..Config::default()
+    };
+    let engine = test_engine("punctuation_explicit", &[("ni", "你")], config);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    // Accepting the default and the contextual pick teaches nothing
+    ime.process_key(KeyEvent::Char(','));
+    ime.process_key(KeyEvent::Space);
+    ime.process_key(KeyEvent::Char('3'));
+    ime.process_key(KeyEvent::Char('.'));
+    ime.process_key(KeyEvent::Enter);
+    assert!(engine.userdict().punctuation_preferences(',').is_empty());
+    assert!(engine.userdict().punctuation_preferences('.').is_empty());
+
+    // Moving the highlight first is a choice
+    ime.process_key(KeyEvent::Char(','));
+    ime.process_key(KeyEvent::Down);
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, ",");
+    assert_eq!(
+        engine.userdict().punctuation_preferences(',').get(","),
+        Some(&1)
+    );
+}
+
+#[test]
+fn punctuation_selections_are_not_learned_by_default() {
+    let engine = test_engine(
+        "punctuation_no_learning",
+        &[("ni", "你")],
+        Config::default(),
+    );
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    for _ in 0..3 {
+        ime.process_key(KeyEvent::Char(','));
+        ime.process_key(KeyEvent::Number(2));
+    }
+    ime.process_key(KeyEvent::Char(','));
+    assert_eq!(ime.context().candidates[0], "，");
+    assert!(engine.userdict().punctuation_preferences(',').is_empty());
+}
+
+#[test]
+fn whole_input_phrase_boost_beats_composed_phrase() {
+    let entries = [("shi", "是"), ("shi'shi", "实时")];
+    let texts = |engine: &Engine| -> Vec<String> {
+        engine.input("shishi").into_iter().map(|c| c.text).collect()
+    };
+
+    // A heavily used single character makes the composed phrase win by default
+    let plain = test_engine("whole_input_plain", &entries, Config::default());
+    plain.userdict().learn_with_count("是", 100).unwrap();
+    assert_eq!(texts(&plain).first().map(String::as_str), Some("是是"));
+
+    let config = Config {
+        whole_input_phrase_boost: 1.0e6,
+        ..Config::default()
+    };
+    let boosted = test_engine("whole_input_boosted", &entries, config);
+    boosted.userdict().learn_with_count("是", 100).unwrap();
+    let ranked = texts(&boosted);
+    let pos = |t: &str| ranked.iter().position(|c| c == t);
+    assert_eq!(ranked.first().map(String::as_str), Some("实时"));
+    assert!(pos("实时") < pos("是是"), "got {:?}", ranked);
+}
+
+#[test]
+fn vetoed_commit_keeps_preedit_when_configured() {
+    let config = Config {
+        preserve_preedit_on_veto: true,
+        ..Config::default()
+    };
+    let engine = test_engine("veto_preserve", &[("ni", "你"), ("ni", "泥")], config);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    ime.process_key(KeyEvent::Char('n'));
+    ime.process_key(KeyEvent::Char('i'));
+    let candidates = ime.context().candidates.clone();
+    assert_eq!(candidates.len(), 2);
+    let (blocked, allowed) = (candidates[0].clone(), candidates[1].clone());
+    let veto = blocked.clone();
+    engine.set_commit_filter(move |text| text != veto);
+
+    assert_eq!(ime.process_key(KeyEvent::Space), KeyResult::Handled);
+    assert_eq!(ime.context().commit_text, "");
+    assert_eq!(ime.context().preedit_text, "ni");
+    assert_eq!(ime.context().candidates, candidates);
+    assert_eq!(ime.session().mode(), InputMode::Phonetic);
+    // A vetoed phrase is not learned
+    assert_eq!(engine.userdict().frequency(&blocked), 0);
+
+    // Other candidates can still be committed
+    ime.process_key(KeyEvent::Number(2));
+    assert_eq!(ime.context().commit_text, allowed);
+}
+
+#[test]
+fn vetoed_commit_clears_preedit_by_default() {
+    let engine = test_engine("veto_clear", &[("ni", "你")], Config::default());
+    engine.set_commit_filter(|_| false);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    ime.process_key(KeyEvent::Char('n'));
+    ime.process_key(KeyEvent::Char('i'));
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "");
+    assert_eq!(ime.context().preedit_text, "");
+    assert!(ime.context().candidates.is_empty());
+
+    engine.clear_commit_filter();
+    ime.process_key(KeyEvent::Char('n'));
+    ime.process_key(KeyEvent::Char('i'));
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "你");
+}
+
+#[test]
+fn selection_stats_bias_ranking() {
+    let engine = test_engine(
+        "selection_stats",
+        &[("shi", "是"), ("shi", "事"), ("shi", "时")],
+        Config::default(),
+    );
+    let log: Vec<(String, String)> = [
+        ("shi", "时"),
+        ("shi", "事"),
+        ("shi", "时"),
+        ("shi", "时"),
+        ("shishi", "实时"),
+        ("", "是"),
+    ]
+    .iter()
+    .map(|(i, p)| (i.to_string(), p.to_string()))
+    .collect();
+
+    let stats = engine.aggregate_selection_stats(&log);
+    assert_eq!(stats.input_count(), 2);
+    assert_eq!(stats.total(), 5);
+    assert_eq!(stats.count("shi", "时"), 3);
+    assert_eq!(
+        stats.phrases_for("shi"),
+        vec![("时".to_string(), 3), ("事".to_string(), 1)]
+    );
+
+    assert_eq!(engine.apply_selection_stats(&stats), 3);
+    assert_eq!(engine.userdict().frequency("时"), 3);
+
+    let texts: Vec<String> = engine.input("shi").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts[..2], ["时".to_string(), "事".to_string()]);
+}
+
+#[test]
+fn top_k_candidates_apply_learning_and_respect_k() {
+    let phrases = [
+        "是", "时", "事", "十", "市", "式", "使", "室", "试", "世", "石", "师",
+    ];
+    let entries: Vec<(&str, &str)> = phrases.iter().map(|p| ("shi", *p)).collect();
+    let engine = test_engine("top_k", &entries, Config::default());
+
+    for k in [0, 1, 3, 8, 10] {
+        assert_eq!(engine.candidates_top_k("shi", k).len(), k);
+    }
+    assert_eq!(engine.candidates_top_k("shi", 50).len(), phrases.len());
+
+    // A learned phrase ranks first without passing the user dictionary around
+    engine.commit("师");
+    engine.commit("师");
+    let top: Vec<String> = engine
+        .candidates_top_k("shi", 2)
+        .into_iter()
+        .map(|c| c.text)
+        .collect();
+    assert_eq!(top.len(), 2);
+    assert_eq!(top[0], "师");
+
+    let wide = engine.candidates_top_k("shi", 12);
+    assert_eq!(wide[0].text, "师");
+}
+
+#[test]
+fn syllable_count_match_prefers_same_length_phrase() {
+    // Same key, so all three share the same base score
+    let entries = [("ni'hao", "您"), ("ni'hao", "你好"), ("ni'hao", "你好啊")];
+    let config = Config {
+        syllable_phrase_match_boost: 1.0,
+        ..Config::default()
+    };
+    let engine = test_engine("syllable_match", &entries, config);
+
+    let candidates = engine.input("nihao");
+    let score = |text: &str| {
+        candidates
+            .iter()
+            .find(|c| c.text == text)
+            .map(|c| c.score)
+            .unwrap_or_else(|| panic!("{} missing from {:?}", text, candidates))
+    };
+    assert_eq!(candidates[0].text, "你好");
+    assert!(score("你好") > score("您"));
+    assert!(score("你好") > score("你好啊"));
+    assert_eq!(score("您"), score("你好啊"));
+
+    let plain = test_engine("syllable_match_off", &entries, Config::default());
+    let candidates = plain.input("nihao");
+    let scores: Vec<f32> = candidates
+        .iter()
+        .filter(|c| c.text.starts_with('你') || c.text == "您")
+        .map(|c| c.score)
+        .collect();
+    assert!(scores.windows(2).all(|w| w[0] == w[1]), "{:?}", candidates);
+}
+
+#[test]
+fn raw_spelling_candidate_can_be_excluded() {
+    // A romanized entry stands in for an unknown-input fallback
+    let entries = [
+        ("ni", "你"),
+        ("hao", "好"),
+        ("ni'hao", "你好"),
+        ("ni'hao", "nihao"),
+        ("ni'hao", "NiHao"),
+    ];
+    let texts = |engine: &Engine, input: &str| -> Vec<String> {
+        engine.input(input).into_iter().map(|c| c.text).collect()
+    };
+
+    let plain = test_engine("raw_spelling_off", &entries, Config::default());
+    assert!(texts(&plain, "nihao").contains(&"nihao".to_string()));
+
+    let config = Config {
+        exclude_raw_spelling: true,
+        offer_segmentation_variants: true,
+        ..Config::default()
+    };
+    let engine = test_engine("raw_spelling_on", &entries, config);
+    let texts = texts(&engine, "nihao");
+    assert!(texts.contains(&"你好".to_string()), "{:?}", texts);
+    assert!(
+        !texts.iter().any(|t| t.eq_ignore_ascii_case("nihao")),
+        "{:?}",
+        texts
+    );
+}
+
+#[test]
+fn corpus_evaluation_tallies_keystrokes_and_top1() {
+    let engine = test_engine(
+        "corpus_eval",
+        &[
+            ("ni", "你"),
+            ("hao", "好"),
+            ("ni'hao", "你好"),
+            ("shi'jie", "世界"),
+            ("wo", "我"),
+            ("shi", "是"),
+            ("xue'sheng", "学生"),
+            ("xue'sheng", "学声"),
+        ],
+        Config::default(),
+    );
+    // Push 学生 to the second candidate
+    engine.commit("学声");
+
+    let corpus = ["你好，世界！", "我是学生", "猫"];
+    let eval = engine.evaluate_corpus(&corpus);
+
+    assert_eq!(eval.sentences, 3);
+    // 你好 / 世界 / 我 / 是 / 学生
+    assert_eq!(eval.conversions, 5);
+    assert_eq!(eval.characters, 8);
+    // nihao, shijie, wo, shi plus one selection key each; "xueshen"
+    // already lists 学生, one letter short of xuesheng
+    assert_eq!(eval.keystrokes, 6 + 7 + 3 + 4 + 8);
+    assert_eq!(eval.top1_hits, 4);
+    // 猫 has no reading
+    assert_eq!(eval.unconverted, 1);
+    assert!((eval.keystrokes_per_char() - 28.0 / 8.0).abs() < 1e-9);
+    assert!((eval.top1_accuracy() - 0.8).abs() < 1e-9);
+
+    assert_eq!(engine.evaluate_corpus(&corpus), eval);
+    assert_eq!(engine.evaluate_corpus(&[]).keystrokes_per_char(), 0.0);
+}
+
+#[test]
+fn weak_predictions_do_not_trigger_suggestions() {
+    let config = Config {
+        min_suggestion_confidence: 0.5,
+        ..Config::default()
+    };
+    let engine = test_engine(
+        "suggestion_confidence",
+        &[("wo'men", "我们"), ("xi'huan", "喜欢")],
+        config,
+    );
+    engine.userdict().learn_bigram("我们", "喜欢");
+    assert_eq!(engine.prediction_confidence("我们"), 1.0);
+    assert_eq!(engine.prediction_confidence("喜欢"), 0.0);
+
+    let commit = |input: &str| {
+        let mut ime = ImeEngine::from_arc(engine.inner_arc());
+        for ch in input.chars() {
+            ime.process_key(KeyEvent::Char(ch));
+        }
+        ime.process_key(KeyEvent::Space);
+        ime
+    };
+
+    // A learned follow-up word is a confident prediction
+    let ime = commit("women");
+    assert_eq!(ime.context().commit_text, "我们");
+    assert_eq!(ime.session().mode(), InputMode::Suggestion);
+
+    // Nothing is known to follow 喜欢
+    let ime = commit("xihuan");
+    assert_eq!(ime.context().commit_text, "喜欢");
+    assert_ne!(ime.session().mode(), InputMode::Suggestion);
+}
+
+#[test]
+fn fuzzy_candidate_reports_corrected_reading() {
+    let engine = test_engine(
+        "readings",
+        &[
+            ("zhi", "知"),
+            ("ni", "你"),
+            ("hao", "好"),
+            ("ni'hao", "你好"),
+        ],
+        Config::default(),
+    );
+
+    // Only "zhi" is in the lexicon, so 知 comes from the z/zh fuzzy rule
+    let readings = engine.candidates_with_readings("zi");
+    let zhi = readings
+        .iter()
+        .find(|(text, _, _)| text == "知")
+        .unwrap_or_else(|| panic!("知 missing from {:?}", readings));
+    assert_eq!(zhi.1, "zhi");
+
+    let readings = engine.candidates_with_readings("nihao");
+    let texts: Vec<&str> = readings.iter().map(|(t, _, _)| t.as_str()).collect();
+    let plain: Vec<String> = engine.input("nihao").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts, plain);
+    let nihao = readings.iter().find(|(text, _, _)| text == "你好").unwrap();
+    assert_eq!(nihao.1, "ni'hao");
+}
+
+#[test]
+fn reedit_restores_highlighted_candidate() {
+    let entries = [("shi", "是"), ("shi", "事"), ("shi", "时"), ("shi", "十")];
+    let type_and_reedit = |config: Config, name: &str| {
+        let engine = test_engine(name, &entries, config);
+        let mut ime = ImeEngine::from_arc(engine.inner_arc());
+        for ch in "shi".chars() {
+            ime.process_key(KeyEvent::Char(ch));
+        }
+        ime.process_key(KeyEvent::Down);
+        ime.process_key(KeyEvent::Down);
+        let highlighted = ime.session().candidates().selected_candidate().cloned();
+        assert_eq!(ime.session().candidates().selected_index(), Some(2));
+
+        ime.process_key(KeyEvent::Backspace);
+        ime.process_key(KeyEvent::Char('i'));
+        (
+            ime.session().candidates().selected_index(),
+            ime.session().candidates().selected_candidate().cloned() == highlighted,
+        )
+    };
+
+    let config = Config {
+        preserve_selection_on_reedit: true,
+        ..Config::default()
+    };
+    assert_eq!(type_and_reedit(config, "reedit_on"), (Some(2), true));
+    assert_eq!(type_and_reedit(Config::default(), "reedit_off").0, Some(0));
+}
+
+#[test]
+fn rejected_top_candidate_drops_in_rank() {
+    let entries = [
+        ("shi", "是"),
+        ("shi", "事"),
+        ("shi", "时"),
+        ("ni'hao", "你好"),
+    ];
+    let engine = test_engine("rejection", &entries, Config::default());
+    let rank = |text: &str| {
+        engine
+            .input("shi")
+            .iter()
+            .position(|c| c.text == text)
+            .unwrap_or_else(|| panic!("{} missing", text))
+    };
+
+    let top = engine.input("shi")[0].text.clone();
+    let nihao_before = engine.input("nihao");
+    let mut scores = vec![engine.input("shi")[rank(&top)].score];
+    for _ in 0..3 {
+        engine.record_rejection("shi", &top);
+        scores.push(engine.input("shi")[rank(&top)].score);
+    }
+
+    // Every signal demotes a little more, until the phrase loses first place
+    assert!(scores.windows(2).all(|w| w[1] < w[0]), "{:?}", scores);
+    assert!(rank(&top) > 0);
+    // Spelling variants of the input share the signal; other inputs do not
+    engine.record_rejection("SHI", &top);
+    assert!(engine.input("shi")[rank(&top)].score < scores[3]);
+    assert_eq!(engine.input("nihao"), nihao_before);
+}
+
+#[test]
+fn smart_apostrophe_separates_pinyin_but_not_latin() {
+    let entries = [
+        ("xian", "先"),
+        ("xi", "西"),
+        ("an", "安"),
+        ("xi'an", "西安"),
+    ];
+    let config = Config {
+        apostrophe_mode: ApostropheMode::Smart,
+        ..Config::default()
+    };
+    let engine = test_engine("apostrophe_smart", &entries, config);
+
+    let texts: Vec<String> = engine.input("xi'an").into_iter().map(|c| c.text).collect();
+    assert_eq!(
+        texts.first().map(String::as_str),
+        Some("西安"),
+        "{:?}",
+        texts
+    );
+    assert!(!texts.contains(&"先".to_string()), "{:?}", texts);
+    assert!(!texts.contains(&"xi'an".to_string()), "{:?}", texts);
+
+    let candidates = engine.input("O'Brien");
+    assert_eq!(candidates[0].text, "O'Brien");
+}
+
+#[test]
+fn literal_apostrophe_is_no_syllable_boundary() {
+    let entries = [
+        ("xian", "先"),
+        ("xi", "西"),
+        ("an", "安"),
+        ("xi'an", "西安"),
+    ];
+    let config = Config {
+        apostrophe_mode: ApostropheMode::Literal,
+        ..Config::default()
+    };
+    let engine = test_engine("apostrophe_literal", &entries, config.clone());
+
+    let texts: Vec<String> = engine.input("xi'an").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts[0], "xi'an");
+    assert!(texts.contains(&"先".to_string()), "{:?}", texts);
+
+    // The typed text counts toward the candidate limit
+    let crowded: Vec<(&str, &str)> = ["先", "现", "线", "县", "鲜", "显", "险", "限", "闲", "献"]
+        .iter()
+        .map(|phrase| ("xian", *phrase))
+        .collect();
+    let engine = test_engine("apostrophe_literal_limit", &crowded, config);
+    let texts: Vec<String> = engine.input("xi'an").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts.len(), 8, "{:?}", texts);
+    assert_eq!(texts[0], "xi'an");
+
+    // The default keeps treating every apostrophe as a separator
+    let engine = test_engine("apostrophe_default", &entries, Config::default());
+    let texts: Vec<String> = engine.input("xi'an").into_iter().map(|c| c.text).collect();
+    assert_eq!(
+        texts.first().map(String::as_str),
+        Some("西安"),
+        "{:?}",
+        texts
+    );
+}
+
+#[test]
+fn origin_stats_count_direct_and_composed_candidates() {
+    // "xian" is 先 straight from the lexicon, or 西 + 安 composed
+    let entries = [("xian", "先"), ("xi", "西"), ("an", "安")];
+    let engine = test_engine("origin_stats", &entries, Config::default());
+
+    let stats = engine.candidate_origin_stats("xian");
+    assert_eq!(
+        stats,
+        OriginStats {
+            lexicon: 1,
+            composed: 1,
+            other: 0,
+        }
+    );
+    assert_eq!(stats.total(), engine.input("xian").len());
+    assert!((stats.lexicon_ratio() - 0.5).abs() < 1e-9);
+
+    assert_eq!(engine.candidate_origin_stats("zzz"), OriginStats::default());
+}
+
+#[test]
+fn letter_select_keys_select_only_with_priority() {
+    let entries = [("ni", "你"), ("an", "安")];
+    let type_ni_a = |config: Config, name: &str| {
+        let engine = test_engine(name, &entries, config);
+        let mut ime = ImeEngine::from_arc(engine.inner_arc());
+        ime.set_select_keys("asdfghjkl");
+        for ch in "nia".chars() {
+            ime.process_key(KeyEvent::Char(ch));
+        }
+        (
+            ime.context().commit_text.clone(),
+            ime.session().input_buffer().text().to_string(),
+        )
+    };
+
+    // Without priority the letter extends the input
+    let (commit, buffer) = type_ni_a(Config::default(), "select_priority_off");
+    assert!(commit.is_empty());
+    assert_eq!(buffer, "nia");
+
+    // With priority it picks the first visible candidate
+    let config = Config {
+        select_keys_priority: true,
+        ..Config::default()
+    };
+    let (commit, buffer) = type_ni_a(config.clone(), "select_priority_on");
+    assert_eq!(commit, "你");
+    assert!(buffer.is_empty());
+
+    // With nothing to select, the letter still starts the input
+    let engine = test_engine("select_priority_empty", &entries, config);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    ime.set_select_keys("asdfghjkl");
+    assert_eq!(ime.process_key(KeyEvent::Char('a')), KeyResult::Handled);
+    assert_eq!(ime.session().input_buffer().text(), "a");
+}
+
+#[test]
+fn recent_phrase_outranks_old_frequent_one_with_recency_alpha() {
+    let entries = [("shi", "是"), ("shi", "事"), ("shi", "时")];
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let year = 365 * 24 * 60 * 60;
+    let rank = |engine: &Engine, text: &str| {
+        engine
+            .input("shi")
+            .iter()
+            .position(|c| c.text == text)
+            .unwrap()
+    };
+
+    let ranks_with_alpha = |alpha: f32, name: &str| {
+        let config = Config {
+            recency_frequency_alpha: alpha,
+            ..Config::default()
+        };
+        let engine = test_engine(name, &entries, config);
+        let userdict = engine.inner_arc().userdict().clone();
+        userdict.learn_with_count_at("事", 50, now - year).unwrap();
+        userdict.learn_with_count_at("时", 1, now).unwrap();
+        (rank(&engine, "事"), rank(&engine, "时"))
+    };
+
+    let (old, recent) = ranks_with_alpha(0.0, "recency_off");
+    assert!(old < recent, "事 at {}, 时 at {}", old, recent);
+
+    let (old, recent) = ranks_with_alpha(1.0, "recency_on");
+    assert!(recent < old, "事 at {}, 时 at {}", old, recent);
+}
+
+#[test]
+fn recent_user_bigram_outranks_stale_one_in_predictions() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let day = 24 * 60 * 60;
+    let config = Config {
+        prediction_recency_halflife_secs: 7 * day,
+        ..Config::default()
+    };
+    let engine = test_engine("prediction_recency", &[("hao", "好")], config);
+    let userdict = engine.inner_arc().userdict().clone();
+    userdict
+        .learn_bigram_with_count_at("好", "吗", 3, now - 30 * day)
+        .unwrap();
+    userdict
+        .learn_bigram_with_count_at("好", "的", 3, now)
+        .unwrap();
+
+    let words: Vec<String> = engine
+        .predict_next("好", 5)
+        .into_iter()
+        .map(|(w, _)| w)
+        .collect();
+    assert_eq!(words, vec!["的".to_string(), "吗".to_string()]);
+}
+
+#[test]
+fn learned_phrase_completes_partial_reading() {
+    let entries = [
+        ("bei", "北"),
+        ("jing", "京"),
+        ("da", "大"),
+        ("xue", "学"),
+        ("bei'jing", "北京"),
+        ("bei'jing'da'xue", "北京大学"),
+    ];
+    let texts = |engine: &Engine| -> Vec<String> {
+        engine
+            .input("beijingda")
+            .into_iter()
+            .map(|c| c.text)
+            .collect()
+    };
+
+    // Completions are off by default, learned or not
+    let plain = test_engine("learned_completion_off", &entries, Config::default());
+    plain.commit("北京大学");
+    assert!(!texts(&plain).contains(&"北京大学".to_string()));
+
+    let config = Config {
+        learned_completion_boost: 10.0,
+        ..Config::default()
+    };
+    let boosted = test_engine("learned_completion_on", &entries, config);
+    assert!(!texts(&boosted).contains(&"北京大学".to_string()));
+
+    boosted.commit("北京大学");
+    let ranked = texts(&boosted);
+    assert_eq!(
+        ranked.first().map(String::as_str),
+        Some("北京大学"),
+        "got {:?}",
+        ranked
+    );
+    assert!(ranked.contains(&"北京大".to_string()), "got {:?}", ranked);
+
+    let readings = boosted.candidates_with_readings("beijingda");
+    assert_eq!(readings[0].1, "bei'jing'da'xue");
+}
+
+#[test]
+fn session_adaptation_reset_keeps_learned_phrases() {
+    let engine = test_engine(
+        "session_adaptation_reset",
+        &[("ni", "你"), ("ni", "泥")],
+        Config::default(),
+    );
+    let mut ime: ImeEngine<libpinyin::Parser> = ImeEngine::from_arc(engine.inner_arc());
+
+    ime.process_key(KeyEvent::Char('n'));
+    ime.process_key(KeyEvent::Char('i'));
+    let second = ime.context().candidates[1].clone();
+    ime.process_key(KeyEvent::Number(2));
+    assert_eq!(ime.context().commit_text, second);
+    assert!(ime.stats().commits > 0);
+    assert!(ime.session().last_committed_char().is_some());
+    let _ = engine.input("ni");
+    assert!(engine.cache_size() > 0);
+
+    ime.reset_session_adaptation();
+    assert_eq!(ime.stats(), &SessionStats::default());
+    assert_eq!(ime.session().last_committed_char(), None);
+    assert_eq!(engine.cache_size(), 0);
+
+    // The pick was learned persistently and still ranks first
+    assert!(engine.userdict().frequency(&second) > 0);
+    assert_eq!(engine.input("ni")[0].text, second);
+}
+
+#[test]
+fn compound_phrase_splits_into_known_sub_words() {
+    let entries = [
+        ("bei", "北"),
+        ("jing", "京"),
+        ("da", "大"),
+        ("xue", "学"),
+        ("bei'jing", "北京"),
+        ("da'xue", "大学"),
+        ("bei'jing'da'xue", "北京大学"),
+    ];
+    let model = test_model("compound_splits", &entries, Config::default());
+    let splits = model.compound_splits("北京大学");
+    assert_eq!(splits[0], vec!["北京".to_string(), "大学".to_string()]);
+    assert_eq!(splits.len(), 4);
+    assert!(splits
+        .iter()
+        .all(|parts| parts.len() > 1 && parts.concat() == "北京大学"));
+    assert!(model.compound_splits("北").is_empty());
+    assert!(model.compound_splits("北京大楼").is_empty());
+
+    let engine = test_engine("compound_split_off", &entries, Config::default());
+    assert!(engine.compound_split_group("beijingdaxue").is_empty());
+
+    let config = Config {
+        offer_compound_splits: true,
+        ..Config::default()
+    };
+    let engine = test_engine("compound_split_on", &entries, config);
+    assert_eq!(engine.input("beijingdaxue")[0].text, "北京大学");
+    let group: Vec<(String, Option<String>)> = engine
+        .compound_split_group("beijingdaxue")
+        .into_iter()
+        .map(|c| (c.text, c.reading))
+        .collect();
+    assert_eq!(
+        group,
+        vec![
+            ("北京".to_string(), Some("bei'jing".to_string())),
+            ("大学".to_string(), Some("da'xue".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn compound_split_parts_keep_the_typed_reading() {
+    // 长 reads "chang" first in the reverse index, but "zhang" was typed
+    let entries = [
+        ("shi", "市"),
+        ("chang", "长"),
+        ("zhang", "长"),
+        ("shi'zhang", "市长"),
+    ];
+    let config = Config {
+        offer_compound_splits: true,
+        ..Config::default()
+    };
+    let engine = test_engine("compound_split_reading", &entries, config);
+    let group: Vec<(String, Option<String>)> = engine
+        .compound_split_group("shizhang")
+        .into_iter()
+        .map(|c| (c.text, c.reading))
+        .collect();
+    assert_eq!(
+        group,
+        vec![
+            ("市".to_string(), Some("shi".to_string())),
+            ("长".to_string(), Some("zhang".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn long_phrase_splits_stay_bounded() {
+    // Every character is a word, so there are 2^39 ways to split
+    let syllables: Vec<String> = (0..40).map(|i| format!("k{}", i)).collect();
+    let phrase: String = (0..40u32)
+        .map(|i| char::from_u32(0x4e00 + i).unwrap())
+        .collect();
+    let chars: Vec<String> = phrase.chars().map(String::from).collect();
+    let mut entries: Vec<(&str, &str)> = syllables
+        .iter()
+        .zip(&chars)
+        .map(|(key, ch)| (key.as_str(), ch.as_str()))
+        .collect();
+    let pair = format!("{}{}", chars[0], chars[1]);
+    entries.push(("k0'k1", pair.as_str()));
+    let model = test_model("compound_splits_bounded", &entries, Config::default());
+
+    let splits = model.compound_splits(&phrase);
+    assert!(!splits.is_empty() && splits.len() <= 32);
+    assert!(splits.iter().all(|parts| parts.concat() == phrase));
+
+    let best = model.best_compound_split(&phrase).unwrap();
+    assert_eq!(best.len(), 39);
+    assert_eq!(best[0], pair);
+}
+
+/// Write `<name>.shape.fst` under `dir` from (code, phrase, payload group).
+fn write_shape_index(dir: &std::path::Path, name: &str, codes: &[(&str, &str, u64)]) {
+    let mut keys: Vec<(String, u64)> = codes
+        .iter()
+        .map(|(code, phrase, group)| {
+            let key = format!("{}{}{}", code, SHAPE_KEY_SEPARATOR, phrase);
+            (key, *group)
+        })
+        .collect();
+    keys.sort();
+    let mut builder = fst::MapBuilder::memory();
+    for (key, group) in keys {
+        builder.insert(key, group).unwrap();
+    }
+    std::fs::write(
+        dir.join(format!("{}.shape.fst", name)),
+        builder.into_inner().unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn prefixed_input_is_looked_up_in_shape_index() {
+    let dir = std::env::temp_dir().join(format!("libpinyin_shape_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // Payload group 0 is "hao", group 1 is "ni" with two phrases
+    write_fst_lexicon(
+        &dir,
+        "lexicon",
+        &[("hao", &[("好", 100)]), ("ni", &[("你", 100), ("泥", 50)])],
+    );
+    // Cangjie codes for single phrases of those groups
+    write_shape_index(
+        &dir,
+        "lexicon",
+        &[("onf", "你", 1), ("eip", "泥", 1), ("vnd", "好", 0)],
+    );
+
+    let mut lexicon =
+        Lexicon::load_from_fst_bincode(dir.join("lexicon.fst"), dir.join("lexicon.bincode"))
+            .unwrap();
+    assert!(!lexicon.has_shape_index());
+    lexicon
+        .load_shape_index(dir.join("lexicon.shape.fst"))
+        .unwrap();
+    // A code finds its own phrase, not the rest of the payload group
+    assert_eq!(
+        lexicon.shape_lookup_with_freq("onf"),
+        vec![("你".to_string(), 100)]
+    );
+    assert_eq!(
+        lexicon.shape_lookup_with_freq("eip"),
+        vec![("泥".to_string(), 50)]
+    );
+    assert!(lexicon.shape_lookup_with_freq("xyz").is_empty());
+    assert!(lexicon.shape_lookup_with_freq("on").is_empty());
+
+    let model = Model::new(
+        lexicon,
+        WordBigram::new(),
+        temp_userdict("shape_index"),
+        Config::default(),
+    );
+    let engine = Engine::new(model);
+
+    let texts: Vec<String> = engine.input(";onf").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts, vec!["你".to_string()]);
+    assert_eq!(engine.input(";vnd")[0].text, "好");
+    assert!(engine.input(";xyz").is_empty());
+    // Unprefixed input still goes through the phonetic path
+    assert!(engine.input("ni").iter().any(|c| c.text == "你"));
+
+    // The default ';' prefix reaches the phonetic editor instead of
+    // opening punctuation
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    for ch in ";onf".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    assert_eq!(ime.session().mode(), InputMode::Phonetic);
+    assert_eq!(ime.context().preedit_text, ";onf");
+    assert_eq!(ime.context().candidates[0], "你");
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "你");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn merged_lexicons_contribute_shape_codes() {
+    let dir = std::env::temp_dir().join(format!("libpinyin_shape_merge_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    write_fst_lexicon(&dir, "base", &[("ni", &[("你", 100)])]);
+    write_fst_lexicon(&dir, "extra", &[("hao", &[("好", 80)])]);
+    write_shape_index(&dir, "extra", &[("vnd", "好", 0)]);
+
+    let mut base =
+        Lexicon::load_from_fst_bincode(dir.join("base.fst"), dir.join("base.bincode")).unwrap();
+    let mut extra =
+        Lexicon::load_from_fst_bincode(dir.join("extra.fst"), dir.join("extra.bincode")).unwrap();
+    extra.load_shape_index(dir.join("extra.shape.fst")).unwrap();
+    base.merge(extra, MergePriority::default());
+
+    assert!(base.has_shape_index());
+    assert_eq!(
+        base.shape_lookup_with_freq("vnd"),
+        vec![("好".to_string(), 80)]
+    );
+
+    let engine = Engine::new(Model::new(
+        base,
+        WordBigram::new(),
+        temp_userdict("shape_index_merged"),
+        Config::default(),
+    ));
+    assert_eq!(engine.input(";vnd")[0].text, "好");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn learned_phrase_is_tagged_as_user_dict() {
+    let entries = [("shi", "是"), ("shi", "时"), ("shi", "事")];
+    let engine = test_engine("source_tag", &entries, Config::default());
+
+    let cands = engine.input("shi");
+    assert!(cands.iter().all(|c| c.source == CandidateSource::Lexicon));
+
+    engine.commit("事");
+    let cands = engine.input("shi");
+    let learned = cands.iter().find(|c| c.text == "事").unwrap();
+    assert_eq!(learned.source, CandidateSource::UserDict);
+    assert!(cands
+        .iter()
+        .filter(|c| c.text != "事")
+        .all(|c| c.source == CandidateSource::Lexicon));
+
+    // Learned phrases still count as direct lexicon entries
+    assert_eq!(engine.candidate_origin_stats("shi").lexicon, 3);
+}
+
+#[test]
+fn phrase_length_sort_prefers_longer_phrase_on_near_ties() {
+    // Both phrases under one key, so only the learned boost separates them
+    let entries = [("bei'jing", "北京"), ("bei'jing", "北京大学")];
+    let top = |name: &str, config: Config| {
+        let engine = test_engine(name, &entries, config);
+        engine.userdict().learn("北京");
+        let cands = engine.input("beijing");
+        let gap = cands[0].score - cands[1].score;
+        (cands[0].text.clone(), gap)
+    };
+
+    // Pure score order without the flag
+    let (text, gap) = top("length_sort_off", Config::default());
+    assert_eq!(text, "北京");
+    assert!(gap > 0.0);
+
+    // Within epsilon the longer phrase wins
+    let config = Config {
+        sort_by_phrase_length: true,
+        length_bias_epsilon: gap + 0.1,
+        ..Config::default()
+    };
+    assert_eq!(top("length_sort_on", config).0, "北京大学");
+
+    // A clear score lead is kept
+    let config = Config {
+        sort_by_phrase_length: true,
+        length_bias_epsilon: gap / 2.0,
+        ..Config::default()
+    };
+    assert_eq!(top("length_sort_far", config).0, "北京");
+}
+
+#[test]
+fn phrase_length_sort_keeps_prefix_candidates_below_whole_input() {
+    let entries = [
+        ("bei'jing", "北京"),
+        ("bei'jing", "北京大学"),
+        ("bei", "北"),
+    ];
+    let mut config = Config {
+        offer_prefix_candidates: true,
+        ..Config::default()
+    };
+    config.set_sort_by_phrase_length(true);
+    config.set_length_bias_epsilon(100.0);
+    assert_eq!(config.get_length_bias_epsilon(), 100.0);
+    let engine = test_engine("length_sort_prefix", &entries, config);
+    engine.userdict().learn("北京");
+    for _ in 0..5 {
+        engine.userdict().learn("北");
+    }
+
+    // 北京大学 now leads with the lower score of the two
+    let cands = engine.input("beijing");
+    assert_eq!(cands[0].text, "北京大学");
+    let score = |text: &str| cands.iter().find(|c| c.text == text).unwrap().score;
+    assert!(score("北") <= score("北京大学"), "{:?}", cands);
+}
+
+#[test]
+fn emoji_keyword_offers_emoji_candidates() {
+    let dir = std::env::temp_dir().join(format!("libpinyin_emoji_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    write_fst_lexicon(
+        &dir,
+        "lexicon",
+        &[
+            ("xiao", &[("😊", 100), ("😀", 300)]),
+            ("haha", &[("😂", 100)]),
+        ],
+    );
+
+    let entries = [("xiao", "笑"), ("xiao", "小")];
+    let mut engine = test_engine("emoji", &entries, Config::default());
+    // Nothing without an emoji lexicon
+    assert!(engine
+        .input("xiao")
+        .iter()
+        .all(|c| c.source != CandidateSource::Emoji));
+
+    engine.load_emoji_lexicon(dir.join("lexicon.fst")).unwrap();
+    let cands = engine.input("xiao");
+    let texts: Vec<&str> = cands.iter().map(|c| c.text.as_str()).collect();
+    // Emoji follow the text candidates, most frequent first
+    assert_eq!(texts[2..], ["😀", "😊"]);
+    assert!(cands[2..]
+        .iter()
+        .all(|c| c.source == CandidateSource::Emoji));
+    assert!(cands.windows(2).all(|w| w[0].score >= w[1].score));
+
+    engine.config_mut().set_emoji_priority(true);
+    engine.clear_cache();
+    let texts: Vec<String> = engine.input("xiao").into_iter().map(|c| c.text).collect();
+    assert_eq!(texts[..2], ["😀".to_string(), "😊".to_string()]);
+
+    // Masked emoji are suppressed
+    engine.config_mut().masked_phrases.insert("😀".to_string());
+    engine.clear_cache();
+    let texts: Vec<String> = engine.input("xiao").into_iter().map(|c| c.text).collect();
+    assert!(!texts.contains(&"😀".to_string()));
+    assert_eq!(texts[0], "😊");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn single_character_commit_opens_associations_when_enabled() {
+    let entries = [("de", "的"), ("shi'hou", "时候"), ("ma", "吗")];
+    let commit = |engine: &Engine, keys: &str| {
+        let mut ime = ImeEngine::from_arc(engine.inner_arc());
+        for ch in keys.chars() {
+            ime.process_key(KeyEvent::Char(ch));
+        }
+        ime.process_key(KeyEvent::Space);
+        ime
+    };
+
+    let plain = test_engine("single_char_off", &entries, Config::default());
+    plain.userdict().learn_bigram("的", "时候");
+    let ime = commit(&plain, "de");
+    assert_eq!(ime.context().commit_text, "的");
+    assert_ne!(ime.session().mode(), InputMode::Suggestion);
+
+    let config = Config {
+        single_char_association: true,
+        ..Config::default()
+    };
+    let engine = test_engine("single_char_on", &entries, config);
+    engine.userdict().learn_bigram("的", "时候");
+    let ime = commit(&engine, "de");
+    assert_eq!(ime.context().commit_text, "的");
+    assert_eq!(ime.session().mode(), InputMode::Suggestion);
+    assert_eq!(ime.context().candidates[0], "时候");
+
+    // Nothing predicted after 吗, and punctuation never opens suggestions
+    let ime = commit(&engine, "ma");
+    assert_eq!(ime.context().commit_text, "吗");
+    assert_ne!(ime.session().mode(), InputMode::Suggestion);
+    let ime = commit(&engine, ",");
+    assert_eq!(ime.context().commit_text, "，");
+    assert_ne!(ime.session().mode(), InputMode::Suggestion);
+}
+
+#[test]
+fn punctuation_overrides_replace_key_alternatives() {
+    let mut config = Config::default();
+    config.set_punctuation_alternatives(',', vec!["、".to_string(), "，".to_string()]);
+    config.set_punctuation_alternatives('\\', vec!["、".to_string(), "「".to_string()]);
+
+    // The table persists through TOML
+    let config = Config::from_toml_str(&config.to_toml_string().unwrap()).unwrap();
+    assert_eq!(config.punctuation_overrides.len(), 2);
+
+    let engine = test_engine("punct_overrides", &[("ni", "你")], config);
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+
+    ime.process_key(KeyEvent::Char(','));
+    assert_eq!(ime.context().candidates, ["、", "，"]);
+    ime.process_key(KeyEvent::Space);
+    assert_eq!(ime.context().commit_text, "、");
+
+    ime.process_key(KeyEvent::Char('\\'));
+    assert_eq!(ime.context().candidates, ["、", "「"]);
+    ime.process_key(KeyEvent::Escape);
+
+    // Unconfigured keys keep the built-in alternatives
+    ime.process_key(KeyEvent::Char('.'));
+    assert_eq!(ime.context().candidates[0], "。");
+    ime.process_key(KeyEvent::Escape);
+
+    // Removing an override at runtime restores the defaults
+    engine
+        .config_mut()
+        .set_punctuation_alternatives(',', Vec::new());
+    ime.process_key(KeyEvent::Char(','));
+    assert_eq!(ime.context().candidates[..2], ["，", ","]);
+}
+
+#[test]
+fn invalid_shuangpin_keys_are_reported_in_auxiliary_text() {
+    use std::collections::HashMap;
+
+    // A scheme that only knows the "i" and "ang" finals
+    libpinyin::register_custom_scheme(
+        "aux_test",
+        libpinyin::DoublePinyinSchemeData {
+            name: "aux_test",
+            shengmu: HashMap::from([('u', "sh")]),
+            yunmu: HashMap::from([('i', "i"), ('h', "ang")]),
+            special: HashMap::new(),
+        },
+    )
+    .unwrap();
+    let mut parser = libpinyin::Parser::with_syllables(libpinyin::PINYIN_SYLLABLES);
+    parser.set_double_pinyin_scheme(Some("custom:aux_test"));
+
+    let model = test_model(
+        "shuangpin_aux",
+        &[("shi", "是"), ("shi'shang", "时尚")],
+        Config::default(),
+    );
+    let mut ime = ImeEngine::new(libchinese_core::Engine::new(model, parser));
+
+    for ch in "uiuh".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    assert!(ime.context().candidates.iter().any(|c| c == "时尚"));
+    assert!(!ime.context().auxiliary_text.contains("invalid"));
+
+    // "k" is not a final in this scheme
+    ime.process_key(KeyEvent::Char('b'));
+    ime.process_key(KeyEvent::Char('k'));
+    assert!(
+        ime.context()
+            .auxiliary_text
+            .contains("invalid shuangpin key \"bk\" at position 4"),
+        "{}",
+        ime.context().auxiliary_text
+    );
+}
+
+#[test]
+fn displayable_state_round_trips_through_json() {
+    let engine = test_engine(
+        "context_json",
+        &[("ni'hao", "你好"), ("ni", "你"), ("ni", "泥")],
+        Config::default(),
+    );
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    for ch in "nihao".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+
+    let json = serde_json::to_string(ime.context()).unwrap();
+    let context: libpinyin::ImeContext = serde_json::from_str(&json).unwrap();
+    assert_eq!(context.preedit_text, ime.context().preedit_text);
+    assert_eq!(context.preedit_cursor, ime.context().preedit_cursor);
+    assert_eq!(context.candidates, ime.context().candidates);
+    assert_eq!(context.candidate_cursor, ime.context().candidate_cursor);
+    assert_eq!(context.auxiliary_text, ime.context().auxiliary_text);
+    assert_eq!(context.input_purpose, ime.context().input_purpose);
+
+    let json = serde_json::to_string(ime.session().candidates()).unwrap();
+    let list: libpinyin::CandidateList = serde_json::from_str(&json).unwrap();
+    assert_eq!(list.candidates(), ime.session().candidates().candidates());
+    assert_eq!(
+        list.selected_index(),
+        ime.session().candidates().selected_index()
+    );
+    assert_eq!(list.page_size(), ime.session().candidates().page_size());
+
+    let json = serde_json::to_string(ime.session().composition()).unwrap();
+    let composition: libpinyin::Composition = serde_json::from_str(&json).unwrap();
+    assert_eq!(&composition, ime.session().composition());
+
+    // Committing fills commit_text, which survives the trip too
+    ime.process_key(KeyEvent::Space);
+    let json = serde_json::to_string(ime.context()).unwrap();
+    let context: libpinyin::ImeContext = serde_json::from_str(&json).unwrap();
+    assert!(!context.commit_text.is_empty());
+    assert_eq!(context.commit_text, ime.context().commit_text);
+}
+
+#[test]
+fn sync_engine_converts_from_many_threads() {
+    let model = test_model(
+        "sync_engine",
+        &[
+            ("ni'hao", "你好"),
+            ("zhong'guo", "中国"),
+            ("shi'jie", "世界"),
+        ],
+        Config::default(),
+    );
+    let parser = libpinyin::Parser::with_syllables(libpinyin::PINYIN_SYLLABLES);
+    let engine = std::sync::Arc::new(libchinese_core::SyncEngine::new(model, parser));
+
+    let expected = [("nihao", "你好"), ("zhongguo", "中国"), ("shijie", "世界")];
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let engine = std::sync::Arc::clone(&engine);
+            std::thread::spawn(move || {
+                for i in 0..25 {
+                    let (input, text) = expected[(t + i) % expected.len()];
+                    let cands = engine.input(input);
+                    assert_eq!(cands.first().map(|c| c.text.as_str()), Some(text));
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // A batch keeps input order
+    let inputs: Vec<String> = expected
+        .iter()
+        .cycle()
+        .take(30)
+        .map(|(input, _)| input.to_string())
+        .collect();
+    let results = engine.candidates_batch(&inputs);
+    assert_eq!(results.len(), inputs.len());
+    for (cands, (_, text)) in results.iter().zip(expected.iter().cycle()) {
+        assert_eq!(cands[0].text, *text);
+    }
+}
+
+#[test]
+fn convert_sentence_picks_best_word_sequence() {
+    let entries = [
+        ("wo", "我"),
+        ("wo", "窝"),
+        ("shi", "是"),
+        ("shi", "事"),
+        ("shi", "时"),
+        ("wo'shi", "卧室"),
+        ("zhong", "中"),
+        ("zhong", "种"),
+        ("guo", "国"),
+        ("guo", "过"),
+        ("ren", "人"),
+        ("ren", "任"),
+        ("zhong'guo", "中国"),
+        ("guo'ren", "国人"),
+    ];
+    let mut lexicon = Lexicon::new();
+    for (key, phrase) in entries {
+        lexicon.insert(key, phrase);
+    }
+    let mut bigram = WordBigram::new();
+    for (word, count) in [
+        ("我", 100),
+        ("是", 80),
+        ("中国", 50),
+        ("人", 60),
+        ("卧室", 5),
+    ] {
+        bigram.add_unigram(word.to_string(), count);
+    }
+    for (w1, w2) in [("我", "是"), ("是", "中国"), ("中国", "人")] {
+        bigram.add_bigram(w1.to_string(), w2.to_string(), 20);
+    }
+    let model = Model::new(
+        lexicon,
+        bigram,
+        temp_userdict("convert_sentence"),
+        Config::default(),
+    );
+    let engine = Engine::new(model);
+
+    assert_eq!(engine.convert_sentence("woshizhongguoren"), "我是中国人");
+    // A syllable with no lexicon entry is passed through
+    assert_eq!(
+        engine.convert_sentence("woshizhongguorenma"),
+        "我是中国人ma"
+    );
+}
+
+#[test]
+fn convert_sentence_nbest_ranks_distinct_sentences() {
+    let entries = [
+        ("yi", "一"),
+        ("yi", "以"),
+        ("ge", "个"),
+        ("ge", "各"),
+        ("ren", "人"),
+        ("yi'ge", "一个"),
+        ("ge'ren", "个人"),
+    ];
+    let mut lexicon = Lexicon::new();
+    for (key, phrase) in entries {
+        lexicon.insert(key, phrase);
+    }
+    let mut bigram = WordBigram::new();
+    for (word, count) in [("一个", 50), ("人", 60), ("个人", 40), ("一", 30)] {
+        bigram.add_unigram(word.to_string(), count);
+    }
+    bigram.add_bigram("一个".to_string(), "人".to_string(), 20);
+    let model = Model::new(
+        lexicon,
+        bigram,
+        temp_userdict("convert_nbest"),
+        Config::default(),
+    );
+    let engine = Engine::new(model);
+
+    let nbest = engine.convert_sentence_nbest("yigeren", 4);
+    assert_eq!(nbest.len(), 4);
+    assert_eq!(nbest[0].0, "一个人");
+    assert_eq!(engine.convert_sentence("yigeren"), "一个人");
+    // 一个人 is reachable as 一个+人 and 一+个人 but listed once
+    let texts: std::collections::HashSet<_> = nbest.iter().map(|(t, _)| t.as_str()).collect();
+    assert_eq!(texts.len(), nbest.len());
+    assert!(nbest.windows(2).all(|w| w[0].1 >= w[1].1));
+    // Ties are ordered by text, so repeated calls agree
+    assert_eq!(engine.convert_sentence_nbest("yigeren", 4), nbest);
+    assert!(engine.convert_sentence_nbest("yigeren", 0).is_empty());
+}
+
+#[test]
+fn candidate_cache_hits_and_invalidates_on_learning() {
+    let entries = [("ni", "你"), ("ni", "泥"), ("hao", "好"), ("hao", "号")];
+    let mut engine = test_engine("candidate_cache", &entries, Config::default());
+
+    let first = engine.input("ni");
+    let _ = engine.input("hao");
+    assert_eq!(engine.cache_stats().0, 0);
+    assert_eq!(engine.input("ni"), first);
+    assert_eq!(engine.cache_stats().0, 1);
+
+    // Learning 泥 drops the cached lists
+    engine.commit("泥");
+    assert_eq!(engine.cache_size(), 0);
+    assert_eq!(engine.input("ni")[0].text, "泥");
+    let (hits, _, _) = engine.cache_stats();
+    let _ = engine.input("ni");
+    assert_eq!(engine.cache_stats().0, hits + 1);
+
+    // Size 0 turns the cache off; a size change applies on the next lookup
+    engine.clear_cache();
+    engine.inner_arc().config_mut().set_max_cache_size(0);
+    let _ = engine.input("ni");
+    let _ = engine.input("ni");
+    assert_eq!(engine.cache_stats().0, 0);
+    assert_eq!(engine.cache_size(), 0);
+
+    engine.inner_arc().config_mut().set_max_cache_size(1);
+    let _ = engine.input("ni");
+    let _ = engine.input("hao");
+    assert_eq!(engine.cache_size(), 1);
+    assert_eq!(engine.inner_arc().cache_capacity(), 1);
+}
+
+#[test]
+fn learning_a_character_refreshes_cached_compositions() {
+    let entries = [("ni", "你"), ("ni", "泥"), ("hao", "好")];
+    let engine = test_engine("cache_composition", &entries, Config::default());
+
+    assert_eq!(engine.input("nihao")[0].text, "你好");
+    // "nihao" shows no 泥, but learning it changes the composed path
+    for _ in 0..3 {
+        engine.commit("泥");
+    }
+    assert_eq!(engine.input("nihao")[0].text, "泥好");
+}
+
+#[test]
+fn config_change_refreshes_cached_candidates() {
+    let engine = test_engine(
+        "cache_config",
+        &[("yi", "一"), ("bai", "百")],
+        Config::default(),
+    );
+
+    let has_arabic = |engine: &Engine| engine.input("yibai").iter().any(|c| c.text == "100");
+    assert!(!has_arabic(&engine));
+    engine.inner_arc().config_mut().numeral_candidates = true;
+    assert!(has_arabic(&engine));
+    engine.inner_arc().config_mut().numeral_candidates = false;
+    assert!(!has_arabic(&engine));
+}
+
+/// Pinyin parser that counts segmentation calls.
+struct CountingParser {
+    inner: libpinyin::Parser,
+    calls: std::rc::Rc<std::cell::Cell<usize>>,
+}
+
+impl libchinese_core::SyllableParser for CountingParser {
+    type Syllable = libpinyin::Syllable;
+
+    fn segment_top_k(&self, input: &str, k: usize, allow_fuzzy: bool) -> Vec<Vec<Self::Syllable>> {
+        self.calls.set(self.calls.get() + 1);
+        self.inner.segment_top_k(input, k, allow_fuzzy)
+    }
+}
+
+#[test]
+fn pasted_blob_is_capped_at_max_input_length() {
+    let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let parser = CountingParser {
+        inner: libpinyin::Parser::with_syllables(libpinyin::PINYIN_SYLLABLES),
+        calls: calls.clone(),
+    };
+    let model = test_model(
+        "max_input_length",
+        &[("ni", "你"), ("hao", "好")],
+        Config::default(),
+    );
+    let mut ime = ImeEngine::new(libchinese_core::Engine::new(model, parser));
+
+    let blob = "nihao".repeat(100);
+    for ch in blob.chars() {
+        assert_eq!(ime.process_key(KeyEvent::Char(ch)), KeyResult::Handled);
+    }
+    assert_eq!(ime.session().input_buffer().text(), &blob[..64]);
+    assert!(ime.context().auxiliary_text.contains("64"));
+    // Keys past the limit do not segment again
+    let at_limit = calls.get();
+    assert!(at_limit <= 64 * 4, "{} segmentation calls", at_limit);
+    ime.process_key(KeyEvent::Char('n'));
+    assert_eq!(calls.get(), at_limit);
+
+    // Backspace still edits the capped buffer
+    ime.process_key(KeyEvent::Backspace);
+    assert_eq!(ime.session().input_buffer().char_count(), 63);
+    assert!(!ime.context().auxiliary_text.contains("64"));
+    ime.process_key(KeyEvent::Char('o'));
+    assert_eq!(ime.session().input_buffer().char_count(), 64);
+}
+
+#[test]
+fn pinned_boundary_splits_xian_into_two_syllables() {
+    let parser = libpinyin::Parser::with_syllables(libpinyin::PINYIN_SYLLABLES);
+    let texts = |boundaries: &[usize]| -> Vec<String> {
+        parser
+            .segment_best_constrained("xian", boundaries, false)
+            .into_iter()
+            .map(|s| s.text)
+            .collect()
+    };
+    assert_eq!(texts(&[]), ["xian"]);
+    assert_eq!(texts(&[2]), ["xi", "an"]);
+
+    let entries = [
+        ("xian", "先"),
+        ("xi", "西"),
+        ("an", "安"),
+        ("xi'an", "西安"),
+    ];
+    let engine = test_engine("pinned_boundary", &entries, Config::default());
+    assert!(engine.input("xian").iter().any(|c| c.text == "先"));
+    let pinned = engine.input_with_boundaries("xian", &[2]);
+    assert_eq!(pinned[0].text, "西安");
+    assert!(!pinned.iter().any(|c| c.text == "先"));
+
+    // Ctrl+apostrophe pins a boundary at the caret, and again unpins it
+    let mut ime = ImeEngine::from_arc(engine.inner_arc());
+    for ch in "xian".chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+    assert!(ime.context().candidates.iter().any(|c| c == "先"));
+    ime.process_key(KeyEvent::Left);
+    ime.process_key(KeyEvent::Left);
+    assert_eq!(ime.process_key(KeyEvent::Ctrl('\'')), KeyResult::Handled);
+    assert_eq!(ime.session().input_buffer().boundaries(), [2]);
+    assert_eq!(ime.context().preedit_text, "xian");
+    assert_eq!(ime.context().candidates[0], "西安");
+    assert!(!ime.context().candidates.iter().any(|c| c == "先"));
+    let ranges: Vec<_> = ime
+        .session()
+        .composition()
+        .segments
+        .iter()
+        .map(|s| s.range.clone())
+        .collect();
+    assert_eq!(ranges, [0..2, 2..4]);
+
+    ime.process_key(KeyEvent::Ctrl('\''));
+    assert!(ime.session().input_buffer().boundaries().is_empty());
+    assert!(ime.context().candidates.iter().any(|c| c == "先"));
+
+    // Typing before the boundary moves it along
+    ime.process_key(KeyEvent::Ctrl('\''));
+    ime.process_key(KeyEvent::Left);
+    ime.process_key(KeyEvent::Char('i'));
+    assert_eq!(ime.session().input_buffer().text(), "xiian");
+    assert_eq!(ime.session().input_buffer().boundaries(), [3]);
+}
+
+#[test]
+fn typed_tones_filter_tone_tagged_entries() {
+    let toned_engine = |name: &str, entries: &[(&str, &str)]| {
+        let mut parser = libpinyin::Parser::with_syllables(libpinyin::PINYIN_SYLLABLES);
+        parser.set_tone_mode(libpinyin::ToneMode::Numeric);
+        libchinese_core::Engine::new(test_model(name, entries, Config::default()), parser)
+    };
+    let texts = |engine: &libchinese_core::Engine<libpinyin::Parser>, input: &str| {
+        engine
+            .input(input)
+            .into_iter()
+            .map(|c| c.text)
+            .collect::<Vec<_>>()
+    };
+
+    // Tone-tagged lexicon: the wrong-tone homophone drops out
+    let engine = toned_engine(
+        "tone_filter",
+        &[
+            ("ma1", "妈"),
+            ("ma3", "马"),
+            ("ni3'hao3", "你好"),
+            ("ni2'hao3", "泥好"),
+        ],
+    );
+    let ma3 = texts(&engine, "ma3");
+    assert!(ma3.contains(&"马".to_string()), "{:?}", ma3);
+    assert!(!ma3.contains(&"妈".to_string()), "{:?}", ma3);
+    // A neutral tone matches any tone (Config::neutral_tone_matches_any)
+    let ma0 = texts(&engine, "ma0");
+    assert!(ma0.contains(&"妈".to_string()) && ma0.contains(&"马".to_string()));
+    // An untoned syllable takes any tone
+    let nihao = texts(&engine, "ni3hao");
+    assert!(nihao.contains(&"你好".to_string()), "{:?}", nihao);
+    assert!(!nihao.contains(&"泥好".to_string()), "{:?}", nihao);
+
+    // Toneless lexicon: tones are ignored
+    let engine = toned_engine("tone_fallback", &[("ni", "你"), ("ni", "泥")]);
+    let ni3 = texts(&engine, "ni3");
+    assert!(ni3.contains(&"你".to_string()) && ni3.contains(&"泥".to_string()));
+}
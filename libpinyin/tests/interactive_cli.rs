@@ -0,0 +1,90 @@
+// Tests driving the `interactive` example binary.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// `cargo test` builds examples next to the test binaries' `deps` directory.
+fn interactive_binary() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let path = exe
+        .parent()?
+        .parent()?
+        .join("examples")
+        .join(format!("interactive{}", std::env::consts::EXE_SUFFIX));
+    path.is_file().then_some(path)
+}
+
+/// Write a tiny `data/converted/simplified` dataset under `root`.
+fn write_dataset(root: &Path, entries: &[(&str, &[(&str, u32)])]) {
+    let dir = root.join("data/converted/simplified");
+    libchinese_core::Lexicon::write_fst_bincode(
+        dir.join("lexicon.fst"),
+        dir.join("lexicon.bincode"),
+        entries,
+    )
+    .unwrap();
+}
+
+#[test]
+fn repl_json_prints_one_object_per_input() {
+    let Some(binary) = interactive_binary() else {
+        eprintln!("interactive example not built, skipping");
+        return;
+    };
+
+    let root = std::env::temp_dir().join(format!("libpinyin_cli_json_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_dataset(
+        &root,
+        &[
+            ("ni", &[("你", 100)]),
+            ("hao", &[("好", 100)]),
+            ("ni'hao", &[("你好", 500)]),
+        ],
+    );
+
+    let mut child = Command::new(binary)
+        .args(["repl", "--json"])
+        .current_dir(&root)
+        .env("HOME", &root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn interactive example");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"nihao\n\nni\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every stdout line is JSON"))
+        .collect();
+    // The blank input line produces no output
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["input"], "nihao");
+    assert_eq!(lines[1]["input"], "ni");
+
+    let candidates = lines[0]["candidates"].as_array().unwrap();
+    assert!(!candidates.is_empty());
+    for candidate in candidates {
+        assert!(candidate["text"].is_string());
+        assert!(candidate["score"].is_number());
+        assert!(candidate.get("source").is_some());
+        assert!(candidate.get("reading").is_some());
+    }
+    assert!(candidates.iter().any(|c| c["text"] == "你好"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
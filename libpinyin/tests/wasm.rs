@@ -0,0 +1,48 @@
+//! Headless browser test for the `wasm` bindings.
+//!
+//! Run with `wasm-pack test --headless --firefox libpinyin -- --features wasm`.
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use libpinyin::wasm::{key_event, WasmIme};
+use libpinyin::KeyEvent;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// Bytes of `lexicon.fst` and `lexicon.bincode`, as a page would fetch them.
+fn lexicon_bytes(groups: &[(&str, &[(&str, u32)])]) -> (Vec<u8>, Vec<u8>) {
+    libchinese_core::Lexicon::encode_fst_bincode(groups).unwrap()
+}
+
+fn context(ime: &WasmIme) -> serde_json::Value {
+    serde_json::from_str(&ime.context_json()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn keystrokes_produce_candidates_and_commit() {
+    let (fst, bincode) = lexicon_bytes(&[("ni'hao", &[("你好", 100)]), ("ni", &[("你", 100)])]);
+    let mut ime = WasmIme::new(&fst, &bincode).unwrap();
+
+    for key in ["n", "i", "h", "a", "o"] {
+        assert!(ime.process_key(key));
+    }
+    let ctx = context(&ime);
+    assert_eq!(ctx["candidates"][0], "你好");
+    assert!(!ctx["preedit"].as_str().unwrap().is_empty());
+
+    assert!(ime.process_key(" "));
+    assert_eq!(context(&ime)["commit"], "你好");
+
+    // Modifiers pressed alone are left to the page
+    assert!(!ime.process_key("Shift"));
+}
+
+#[wasm_bindgen_test]
+fn browser_key_names_map_to_key_events() {
+    assert_eq!(key_event("a"), Some(KeyEvent::Char('a')));
+    assert_eq!(key_event(" "), Some(KeyEvent::Space));
+    assert_eq!(key_event("Backspace"), Some(KeyEvent::Backspace));
+    assert_eq!(key_event("ArrowDown"), Some(KeyEvent::Down));
+    assert_eq!(key_event("Ctrl+."), Some(KeyEvent::Ctrl('.')));
+    assert_eq!(key_event("F1"), None);
+}
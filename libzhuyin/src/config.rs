@@ -35,6 +35,49 @@ pub struct ZhuyinConfig {
 
     /// ETEN26 keyboard layout corrections
     pub zhuyin_correct_eten26: bool,
+
+    /// How to recover from consecutive tone marks ("ㄋㄧˇˋ")
+    #[serde(default)]
+    pub zhuyin_tone_recovery: ToneRecovery,
+}
+
+/// Recovery policy for two or more tone marks typed in a row.
+///
+/// Used by `ZhuyinParser::with_tone_recovery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ToneRecovery {
+    /// Keep the last tone mark, treating earlier ones as mistakes ("ㄋㄧˇˋ" → "ㄋㄧˋ").
+    #[default]
+    KeepLast,
+    /// Keep the first tone mark and drop the rest ("ㄋㄧˇˋ" → "ㄋㄧˇ").
+    KeepFirst,
+    /// Do not recover: the extra marks are left as unknown segments.
+    Reject,
+}
+
+/// Zhuyin tone marks (the first tone is unmarked).
+pub const TONE_MARKS: [char; 4] = ['ˊ', 'ˇ', 'ˋ', '˙'];
+
+impl ToneRecovery {
+    /// Collapse runs of consecutive tone marks according to the policy.
+    pub fn apply(self, chars: &[char]) -> Vec<char> {
+        let is_tone = |c: &char| TONE_MARKS.contains(c);
+        match self {
+            ToneRecovery::Reject => chars.to_vec(),
+            ToneRecovery::KeepFirst => chars
+                .iter()
+                .enumerate()
+                .filter(|(i, c)| !(is_tone(c) && *i > 0 && is_tone(&chars[i - 1])))
+                .map(|(_, c)| *c)
+                .collect(),
+            ToneRecovery::KeepLast => chars
+                .iter()
+                .enumerate()
+                .filter(|(i, c)| !(is_tone(c) && chars.get(i + 1).is_some_and(is_tone)))
+                .map(|(_, c)| *c)
+                .collect(),
+        }
+    }
 }
 
 impl Default for ZhuyinConfig {
@@ -48,6 +91,7 @@ impl Default for ZhuyinConfig {
             zhuyin_correct_shuffle: true,
             zhuyin_correct_hsu: true,
             zhuyin_correct_eten26: true,
+            zhuyin_tone_recovery: ToneRecovery::KeepLast,
         }
     }
 }
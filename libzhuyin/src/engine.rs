@@ -8,8 +8,10 @@
 use std::error::Error;
 use std::sync::Arc;
 
+use crate::config::ZhuyinConfig;
+use crate::layout::ZhuyinLayout;
 use crate::parser::ZhuyinParser;
-use libchinese_core::{Candidate, Lexicon, Model, UserDict, WordBigram};
+use libchinese_core::{Candidate, DatasetInfo, Lexicon, Model, UserDict, WordBigram};
 
 /// All standard zhuyin/bopomofo syllables with tone marks.
 /// Generated from tsi.table - 1388 unique syllables.
@@ -1423,8 +1425,17 @@ impl Engine {
     /// Uses standard zhuyin fuzzy rules configured in the parser.
     /// Parser is created internally with standard bopomofo syllables.
     pub fn new(model: Model) -> Self {
+        Self::with_config(model, &ZhuyinConfig::default())
+    }
+
+    /// Construct an Engine from a pre-built Model, applying the zhuyin-specific
+    /// options of `config` (such as `zhuyin_tone_recovery`) to the parser.
+    ///
+    /// The model keeps the base config it was built with.
+    pub fn with_config(model: Model, config: &ZhuyinConfig) -> Self {
         let fuzzy_rules = crate::standard_fuzzy_rules();
-        let parser = ZhuyinParser::new(fuzzy_rules, ZHUYIN_SYLLABLES);
+        let parser = ZhuyinParser::new(fuzzy_rules, ZHUYIN_SYLLABLES)
+            .with_tone_recovery(config.zhuyin_tone_recovery);
         Self {
             inner: Arc::new(libchinese_core::Engine::new(model, parser)),
         }
@@ -1437,6 +1448,14 @@ impl Engine {
         Arc::clone(&self.inner)
     }
 
+    /// List the converted datasets available under a data directory.
+    ///
+    /// See `libchinese_core::discover_datasets`. Useful for picking a
+    /// directory to pass to `from_data_dir` instead of probing fixed paths.
+    pub fn discover_datasets<P: AsRef<std::path::Path>>(data_dir: P) -> Vec<DatasetInfo> {
+        libchinese_core::discover_datasets(data_dir)
+    }
+
     /// Load an engine from a model directory containing runtime artifacts.
     ///
     /// Expected layout (data-dir):
@@ -1451,12 +1470,14 @@ impl Engine {
         let fst_path = data_dir.join("lexicon.fst");
         let bincode_path = data_dir.join("lexicon.bincode");
 
-        let lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).map_err(|e| {
+        let mut lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).map_err(|e| {
             format!(
                 "failed to load lexicon from {:?} and {:?}: {}",
                 fst_path, bincode_path, e
             )
         })?;
+        // Keystroke plans, compound splits and the like look phrases up
+        lex.build_reverse_index();
 
         // Userdict: use persistent userdict at ~/.zhuyin/userdict.redb
         let userdict = {
@@ -1544,6 +1565,13 @@ impl Engine {
     }
 }
 
+/// Keyboard layouts with a `create_ime_engine_*` factory, for a settings UI.
+///
+/// Each name is also a fuzzy preset in `fuzzy_presets::available_fuzzy_profiles`.
+pub fn available_keyboard_layouts() -> Vec<&'static str> {
+    vec!["standard", "hsu", "eten"]
+}
+
 /// Create an IME engine with HSU keyboard layout fuzzy rules.
 ///
 /// HSU layout is optimized for efficiency with finals on the home row.
@@ -1554,50 +1582,20 @@ impl Engine {
 /// * `page_size` - Number of candidates to show per page (typically 5-9)
 ///
 /// # Returns
-/// `ImeEngine<ZhuyinParser>` configured with HSU fuzzy rules
+/// `ImeEngine<ZhuyinParser>` accepting HSU keys, with HSU fuzzy rules
+///
+/// Digits and other selection keys the layout types with select a
+/// candidate after Up/Down/PageUp/PageDown moved into the candidate list.
 pub fn create_ime_engine_hsu<P: AsRef<std::path::Path>>(
     data_dir: P,
     page_size: usize,
 ) -> Result<libchinese_core::ImeEngine<ZhuyinParser>, Box<dyn Error>> {
-    let data_dir = data_dir.as_ref();
-
-    // Load model from data directory
-    let fst_path = data_dir.join("lexicon.fst");
-    let bincode_path = data_dir.join("lexicon.bincode");
-    let lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path)?;
-
-    // User dictionary
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .unwrap_or_else(|_| ".".to_string());
-    let ud_path = std::path::PathBuf::from(home)
-        .join(".zhuyin")
-        .join("userdict.redb");
-    if let Some(parent) = ud_path.parent() {
-        let _ = std::fs::create_dir_all(parent);
-    }
-    let userdict = UserDict::new(&ud_path)?;
-
-    // Create model with default config
-    let model = Model::new(
-        lex,
-        WordBigram::new(),
-        userdict,
-        libchinese_core::Config::default(),
-    );
-
-    // Create parser with HSU fuzzy rules
-    let fuzzy_rules = crate::fuzzy_presets::hsu_fuzzy_rules();
-    let parser = ZhuyinParser::new(fuzzy_rules, ZHUYIN_SYLLABLES);
-
-    // Create core engine
-    let core_engine = Arc::new(libchinese_core::Engine::new(model, parser));
-
-    // Create IME engine
-    Ok(libchinese_core::ImeEngine::from_arc_with_page_size(
-        core_engine,
+    create_ime_engine_with_config(
+        data_dir,
+        ZhuyinLayout::Hsu,
         page_size,
-    ))
+        &ZhuyinConfig::default(),
+    )
 }
 
 /// Create an IME engine with Standard keyboard layout fuzzy rules.
@@ -1610,46 +1608,20 @@ pub fn create_ime_engine_hsu<P: AsRef<std::path::Path>>(
 /// * `page_size` - Number of candidates to show per page (typically 5-9)
 ///
 /// # Returns
-/// `ImeEngine<ZhuyinParser>` configured with Standard fuzzy rules
+/// `ImeEngine<ZhuyinParser>` accepting Standard keys, with Standard fuzzy rules
+///
+/// Digits and other selection keys the layout types with select a
+/// candidate after Up/Down/PageUp/PageDown moved into the candidate list.
 pub fn create_ime_engine_standard<P: AsRef<std::path::Path>>(
     data_dir: P,
     page_size: usize,
 ) -> Result<libchinese_core::ImeEngine<ZhuyinParser>, Box<dyn Error>> {
-    let data_dir = data_dir.as_ref();
-
-    // Load model (same as HSU)
-    let fst_path = data_dir.join("lexicon.fst");
-    let bincode_path = data_dir.join("lexicon.bincode");
-    let lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path)?;
-
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .unwrap_or_else(|_| ".".to_string());
-    let ud_path = std::path::PathBuf::from(home)
-        .join(".zhuyin")
-        .join("userdict.redb");
-    if let Some(parent) = ud_path.parent() {
-        let _ = std::fs::create_dir_all(parent);
-    }
-    let userdict = UserDict::new(&ud_path)?;
-
-    let model = Model::new(
-        lex,
-        WordBigram::new(),
-        userdict,
-        libchinese_core::Config::default(),
-    );
-
-    // Create parser with Standard fuzzy rules
-    let fuzzy_rules = crate::fuzzy_presets::standard_fuzzy_rules();
-    let parser = ZhuyinParser::new(fuzzy_rules, ZHUYIN_SYLLABLES);
-
-    let core_engine = Arc::new(libchinese_core::Engine::new(model, parser));
-
-    Ok(libchinese_core::ImeEngine::from_arc_with_page_size(
-        core_engine,
+    create_ime_engine_with_config(
+        data_dir,
+        ZhuyinLayout::Standard,
         page_size,
-    ))
+        &ZhuyinConfig::default(),
+    )
 }
 
 /// Create an IME engine with ETEN keyboard layout fuzzy rules.
@@ -1662,18 +1634,42 @@ pub fn create_ime_engine_standard<P: AsRef<std::path::Path>>(
 /// * `page_size` - Number of candidates to show per page (typically 5-9)
 ///
 /// # Returns
-/// `ImeEngine<ZhuyinParser>` configured with ETEN fuzzy rules
+/// `ImeEngine<ZhuyinParser>` accepting ETEN keys, with ETEN fuzzy rules
+///
+/// Digits and other selection keys the layout types with select a
+/// candidate after Up/Down/PageUp/PageDown moved into the candidate list.
 pub fn create_ime_engine_eten<P: AsRef<std::path::Path>>(
     data_dir: P,
     page_size: usize,
+) -> Result<libchinese_core::ImeEngine<ZhuyinParser>, Box<dyn Error>> {
+    create_ime_engine_with_config(
+        data_dir,
+        ZhuyinLayout::Eten,
+        page_size,
+        &ZhuyinConfig::default(),
+    )
+}
+
+/// Create an IME engine for `layout` using the options in `config`.
+///
+/// The model gets `config.base`, and the parser the zhuyin-specific options
+/// (currently `zhuyin_tone_recovery`). The layout factories above call this
+/// with `ZhuyinConfig::default()`.
+pub fn create_ime_engine_with_config<P: AsRef<std::path::Path>>(
+    data_dir: P,
+    layout: ZhuyinLayout,
+    page_size: usize,
+    config: &ZhuyinConfig,
 ) -> Result<libchinese_core::ImeEngine<ZhuyinParser>, Box<dyn Error>> {
     let data_dir = data_dir.as_ref();
 
-    // Load model (same as HSU/Standard)
+    // Load model from data directory
     let fst_path = data_dir.join("lexicon.fst");
     let bincode_path = data_dir.join("lexicon.bincode");
-    let lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path)?;
+    let mut lex = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path)?;
+    lex.build_reverse_index();
 
+    // User dictionary
     let home = std::env::var("HOME")
         .or_else(|_| std::env::var("USERPROFILE"))
         .unwrap_or_else(|_| ".".to_string());
@@ -1685,16 +1681,12 @@ pub fn create_ime_engine_eten<P: AsRef<std::path::Path>>(
     }
     let userdict = UserDict::new(&ud_path)?;
 
-    let model = Model::new(
-        lex,
-        WordBigram::new(),
-        userdict,
-        libchinese_core::Config::default(),
-    );
+    let model = Model::new(lex, WordBigram::new(), userdict, config.base.clone());
 
-    // Create parser with ETEN fuzzy rules
-    let fuzzy_rules = crate::fuzzy_presets::eten_fuzzy_rules();
-    let parser = ZhuyinParser::new(fuzzy_rules, ZHUYIN_SYLLABLES);
+    // Create parser reading the layout's keys, with the layout's fuzzy rules
+    let parser = ZhuyinParser::new(vec![], ZHUYIN_SYLLABLES)
+        .with_layout(layout)
+        .with_tone_recovery(config.zhuyin_tone_recovery);
 
     let core_engine = Arc::new(libchinese_core::Engine::new(model, parser));
 
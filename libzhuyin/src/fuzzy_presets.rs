@@ -238,10 +238,46 @@ pub fn no_fuzzy_rules() -> Vec<String> {
     Vec::new()
 }
 
+/// Names of the built-in fuzzy presets, for `fuzzy_profile_rules`.
+pub fn available_fuzzy_profiles() -> Vec<&'static str> {
+    vec!["standard", "hsu", "eten", "none"]
+}
+
+/// Rules of a preset from `available_fuzzy_profiles`, or None for an unknown
+/// name.
+pub fn fuzzy_profile_rules(name: &str) -> Option<Vec<String>> {
+    match name {
+        "standard" => Some(standard_fuzzy_rules()),
+        "hsu" => Some(hsu_fuzzy_rules()),
+        "eten" => Some(eten_fuzzy_rules()),
+        "none" => Some(no_fuzzy_rules()),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_available_fuzzy_profiles_resolve() {
+        let profiles = available_fuzzy_profiles();
+        for name in ["standard", "hsu", "eten", "none"] {
+            assert!(profiles.contains(&name), "missing profile {}", name);
+        }
+        for name in &profiles {
+            assert!(fuzzy_profile_rules(name).is_some(), "{} has no rules", name);
+        }
+        assert_eq!(fuzzy_profile_rules("hsu"), Some(hsu_fuzzy_rules()));
+        assert_eq!(fuzzy_profile_rules("none"), Some(Vec::new()));
+        assert_eq!(fuzzy_profile_rules("dvorak"), None);
+
+        // Every keyboard layout has a matching preset
+        for layout in crate::available_keyboard_layouts() {
+            assert!(profiles.contains(&layout), "no preset for {}", layout);
+        }
+    }
+
     #[test]
     fn test_hsu_rules_format() {
         let rules = hsu_fuzzy_rules();
@@ -0,0 +1,248 @@
+//! Physical keyboard layouts for typing zhuyin on a QWERTY keyboard.
+//!
+//! Each layout assigns bopomofo symbols and tone marks to ASCII keys.
+//! `ZhuyinParser::with_layout` converts typed keys with `ZhuyinLayout::convert`
+//! before segmentation, so an IME can feed raw key presses to the parser.
+//!
+//! - **Standard (大千)**: one symbol per key, tones on 3/4/6/7.
+//! - **ETEN (倚天)**: one symbol per key, mostly mnemonic letters, tones on 1-4.
+//! - **HSU (許氏)**: letters only; most keys carry an initial and a final,
+//!   and d/f/j/s double as tone keys. The reading is picked from the key's
+//!   position in the syllable.
+
+/// Standard layout keys in bopomofo order (ㄅ..ㄦ, then ˙ˊˇˋ).
+const STANDARD_KEYS: &str = "1qaz2wsxedcrfv5tgbyhnujm8ik,9ol.0p;/-7634";
+
+/// ETEN layout keys in bopomofo order (ㄅ..ㄦ, then ˙ˊˇˋ).
+const ETEN_KEYS: &str = "bpmfdtnlvkhg7c,./j;'sexuaorwiqzy890-=1234";
+
+/// Bopomofo symbols and tone marks in the order of the key tables.
+const SYMBOLS: &str =
+    "ㄅㄆㄇㄈㄉㄊㄋㄌㄍㄎㄏㄐㄑㄒㄓㄔㄕㄖㄗㄘㄙㄧㄨㄩㄚㄛㄜㄝㄞㄟㄠㄡㄢㄣㄤㄥㄦ˙ˊˇˋ";
+
+/// A physical zhuyin keyboard layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZhuyinLayout {
+    /// Standard (大千) layout
+    Standard,
+    /// ETEN (倚天) layout
+    Eten,
+    /// HSU (許氏) layout
+    Hsu,
+}
+
+impl ZhuyinLayout {
+    /// All supported layouts.
+    pub const ALL: [ZhuyinLayout; 3] = [
+        ZhuyinLayout::Standard,
+        ZhuyinLayout::Eten,
+        ZhuyinLayout::Hsu,
+    ];
+
+    /// Layout name, as listed by `available_keyboard_layouts`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ZhuyinLayout::Standard => "standard",
+            ZhuyinLayout::Eten => "eten",
+            ZhuyinLayout::Hsu => "hsu",
+        }
+    }
+
+    /// Look up a layout by name (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The fuzzy preset for typing errors common on this layout.
+    pub fn fuzzy_rules(self) -> Vec<String> {
+        match self {
+            ZhuyinLayout::Standard => crate::fuzzy_presets::standard_fuzzy_rules(),
+            ZhuyinLayout::Eten => crate::fuzzy_presets::eten_fuzzy_rules(),
+            ZhuyinLayout::Hsu => crate::fuzzy_presets::hsu_fuzzy_rules(),
+        }
+    }
+
+    /// Whether the layout assigns a symbol to `key`.
+    pub fn is_layout_key(self, key: char) -> bool {
+        match self {
+            ZhuyinLayout::Standard => STANDARD_KEYS.contains(key),
+            ZhuyinLayout::Eten => ETEN_KEYS.contains(key),
+            ZhuyinLayout::Hsu => key.is_ascii_lowercase() && !matches!(key, 'q'),
+        }
+    }
+
+    /// Convert typed keys to bopomofo.
+    ///
+    /// Characters the layout does not assign (including bopomofo typed
+    /// directly) are kept as they are.
+    ///
+    /// # Example
+    /// ```
+    /// use libzhuyin::ZhuyinLayout;
+    ///
+    /// assert_eq!(ZhuyinLayout::Standard.convert("su3cl3"), "ㄋㄧˇㄏㄠˇ");
+    /// assert_eq!(ZhuyinLayout::Eten.convert("ne3hz3"), "ㄋㄧˇㄏㄠˇ");
+    /// assert_eq!(ZhuyinLayout::Hsu.convert("nefhwf"), "ㄋㄧˇㄏㄠˇ");
+    /// ```
+    pub fn convert(self, keys: &str) -> String {
+        match self {
+            ZhuyinLayout::Standard => convert_with_table(keys, STANDARD_KEYS),
+            ZhuyinLayout::Eten => convert_with_table(keys, ETEN_KEYS),
+            ZhuyinLayout::Hsu => convert_hsu(keys),
+        }
+    }
+}
+
+/// Convert keys with a one-symbol-per-key table parallel to `SYMBOLS`.
+fn convert_with_table(keys: &str, table: &str) -> String {
+    keys.chars()
+        .map(|key| {
+            table
+                .chars()
+                .position(|k| k == key)
+                .and_then(|i| SYMBOLS.chars().nth(i))
+                .unwrap_or(key)
+        })
+        .collect()
+}
+
+/// Where the next HSU key falls within the current syllable.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HsuStage {
+    /// Nothing typed yet
+    Start,
+    /// After an initial
+    Initial,
+    /// After a medial (ㄧㄨㄩ)
+    Medial,
+    /// After a final
+    Final,
+}
+
+/// Convert HSU keys, reading each key by its position in the syllable.
+fn convert_hsu(keys: &str) -> String {
+    let keys: Vec<char> = keys.chars().collect();
+    let mut out = String::with_capacity(keys.len() * 3);
+    let mut stage = HsuStage::Start;
+
+    for (i, &key) in keys.iter().enumerate() {
+        // d/f/j/s end a started syllable with a tone
+        if stage != HsuStage::Start {
+            let tone = match key {
+                'd' => Some('ˊ'),
+                'f' => Some('ˇ'),
+                'j' => Some('ˋ'),
+                's' => Some('˙'),
+                _ => None,
+            };
+            if let Some(tone) = tone {
+                out.push(tone);
+                stage = HsuStage::Start;
+                continue;
+            }
+        }
+
+        // ㄐㄑㄒ only come before the ㄧ/ㄩ medials; ㄓㄔㄕ otherwise
+        let palatal = matches!(keys.get(i + 1), Some('e' | 'u'));
+        let initial = match key {
+            'b' => Some('ㄅ'),
+            'p' => Some('ㄆ'),
+            'm' => Some('ㄇ'),
+            'f' => Some('ㄈ'),
+            'd' => Some('ㄉ'),
+            't' => Some('ㄊ'),
+            'n' => Some('ㄋ'),
+            'l' => Some('ㄌ'),
+            'g' => Some('ㄍ'),
+            'k' => Some('ㄎ'),
+            'h' => Some('ㄏ'),
+            'j' => Some(if palatal { 'ㄐ' } else { 'ㄓ' }),
+            'v' => Some(if palatal { 'ㄑ' } else { 'ㄔ' }),
+            'c' => Some(if palatal { 'ㄒ' } else { 'ㄕ' }),
+            'r' => Some('ㄖ'),
+            'z' => Some('ㄗ'),
+            'a' => Some('ㄘ'),
+            's' => Some('ㄙ'),
+            _ => None,
+        };
+        let medial = match key {
+            'e' => Some('ㄧ'),
+            'x' => Some('ㄨ'),
+            'u' => Some('ㄩ'),
+            _ => None,
+        };
+        let final_ = match key {
+            'y' => Some('ㄚ'),
+            'h' => Some('ㄛ'),
+            'g' => Some('ㄜ'),
+            'e' => Some('ㄝ'),
+            'i' => Some('ㄞ'),
+            'a' => Some('ㄟ'),
+            'w' => Some('ㄠ'),
+            'o' => Some('ㄡ'),
+            'm' => Some('ㄢ'),
+            'n' => Some('ㄣ'),
+            'k' => Some('ㄤ'),
+            'l' => Some('ㄥ'),
+            _ => None,
+        };
+
+        let (symbol, next) = match (stage, initial, medial, final_) {
+            (HsuStage::Start | HsuStage::Final, Some(i), _, _) => (i, HsuStage::Initial),
+            (HsuStage::Start | HsuStage::Initial, _, Some(m), _) => (m, HsuStage::Medial),
+            (HsuStage::Final, _, Some(m), _) => (m, HsuStage::Medial),
+            (_, _, _, Some(f)) => (f, HsuStage::Final),
+            (HsuStage::Medial, Some(i), _, _) => (i, HsuStage::Initial),
+            _ => {
+                out.push(key);
+                stage = HsuStage::Start;
+                continue;
+            }
+        };
+        out.push(symbol);
+        stage = next;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_tables_cover_every_symbol() {
+        let symbols = SYMBOLS.chars().count();
+        assert_eq!(STANDARD_KEYS.chars().count(), symbols);
+        assert_eq!(ETEN_KEYS.chars().count(), symbols);
+    }
+
+    #[test]
+    fn standard_and_eten_map_keys_one_to_one() {
+        assert_eq!(ZhuyinLayout::Standard.convert("5j/ru,4"), "ㄓㄨㄥㄐㄧㄝˋ");
+        assert_eq!(ZhuyinLayout::Eten.convert(",x-gew4"), "ㄓㄨㄥㄐㄧㄝˋ");
+        // Unassigned characters and bopomofo pass through
+        assert_eq!(ZhuyinLayout::Standard.convert("ㄋㄧ3"), "ㄋㄧˇ");
+    }
+
+    #[test]
+    fn hsu_reads_keys_by_position() {
+        // ㄓㄨㄥ ㄍㄨㄛˊ: j is ㄓ before a non-palatal medial, d a tone after a final
+        assert_eq!(ZhuyinLayout::Hsu.convert("jxlgxhd"), "ㄓㄨㄥㄍㄨㄛˊ");
+        // ㄐㄧㄝˋ: j is ㄐ before ㄧ, and e after a medial is ㄝ
+        assert_eq!(ZhuyinLayout::Hsu.convert("jeej"), "ㄐㄧㄝˋ");
+        // A tone key starting a syllable is its initial
+        assert_eq!(ZhuyinLayout::Hsu.convert("jj"), "ㄓˋ");
+    }
+
+    #[test]
+    fn layouts_round_trip_names() {
+        for layout in ZhuyinLayout::ALL {
+            assert_eq!(ZhuyinLayout::from_name(layout.name()), Some(layout));
+        }
+        assert_eq!(ZhuyinLayout::from_name("HSU"), Some(ZhuyinLayout::Hsu));
+        assert_eq!(ZhuyinLayout::from_name("dvorak"), None);
+    }
+}
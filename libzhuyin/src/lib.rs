@@ -5,6 +5,7 @@
 pub mod config;
 pub mod engine;
 pub mod fuzzy_presets;
+pub mod layout;
 pub mod parser;
 
 // Re-export IME components from core (now at root level, not in ime::)
@@ -14,10 +15,14 @@ pub use libchinese_core::{
     SuggestionEditor,
 };
 
-pub use config::ZhuyinConfig;
+pub use config::{ToneRecovery, ZhuyinConfig};
 pub use engine::{
-    create_ime_engine_eten, create_ime_engine_hsu, create_ime_engine_standard, Engine,
-    ZHUYIN_SYLLABLES,
+    available_keyboard_layouts, create_ime_engine_eten, create_ime_engine_hsu,
+    create_ime_engine_standard, create_ime_engine_with_config, Engine, ZHUYIN_SYLLABLES,
 };
-pub use fuzzy_presets::{eten_fuzzy_rules, hsu_fuzzy_rules, no_fuzzy_rules, standard_fuzzy_rules};
-pub use parser::ZhuyinParser;
+pub use fuzzy_presets::{
+    available_fuzzy_profiles, eten_fuzzy_rules, fuzzy_profile_rules, hsu_fuzzy_rules,
+    no_fuzzy_rules, standard_fuzzy_rules,
+};
+pub use layout::ZhuyinLayout;
+pub use parser::{ZhuyinParser, DEFAULT_INCOMPLETE_PENALTY, FIRST_TONE_MARK};
@@ -26,7 +26,13 @@ Notes
   richer (tone-insensitive mapping, alternate finals, etc).
 */
 
-use libchinese_core::{FuzzyMap, TrieNode};
+use crate::config::{ToneRecovery, TONE_MARKS};
+use crate::layout::ZhuyinLayout;
+use libchinese_core::{FuzzyMap, TrieNode, NEUTRAL_TONE};
+
+/// Explicit first-tone mark. Lexicon keys leave the first tone unmarked, so
+/// the parser drops it from input.
+pub const FIRST_TONE_MARK: char = 'ˉ';
 
 /// A matched zhuyin syllable with metadata.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,17 +41,44 @@ pub struct ZhuyinSyllable {
     pub text: String,
     /// Whether this syllable was matched via a fuzzy alternative.
     pub fuzzy: bool,
+    /// Tone marked at the end of `text`: 2-4 for ˊˇˋ, `NEUTRAL_TONE` for ˙.
+    /// None for an unmarked syllable, i.e. the implicit first tone.
+    pub tone: Option<u8>,
 }
 
 impl ZhuyinSyllable {
+    /// Create a syllable; the tone is read off the end of `text`.
     pub fn new<T: Into<String>>(text: T, fuzzy: bool) -> Self {
-        Self {
-            text: text.into(),
-            fuzzy,
-        }
+        let text = text.into();
+        let tone = split_tone_mark(&text).1;
+        Self { text, fuzzy, tone }
+    }
+
+    /// The syllable without its tone mark ("ㄋㄧˇ" → "ㄋㄧ").
+    pub fn base(&self) -> &str {
+        split_tone_mark(&self.text).0
     }
 }
 
+/// Split a trailing tone mark off a syllable ("ㄋㄧˇ" → ("ㄋㄧ", Some(3))).
+fn split_tone_mark(text: &str) -> (&str, Option<u8>) {
+    let Some(last) = text.chars().last() else {
+        return (text, None);
+    };
+    let tone = match last {
+        'ˊ' => 2,
+        'ˇ' => 3,
+        'ˋ' => 4,
+        '˙' => NEUTRAL_TONE,
+        _ => return (text, None),
+    };
+    (&text[..text.len() - last.len_utf8()], Some(tone))
+}
+
+/// Default segment cost for an incomplete syllable ("ㄋ" → "ㄋㄧ"): worse
+/// than an exact match, better than the unknown-character fallback.
+pub const DEFAULT_INCOMPLETE_PENALTY: f32 = 5.0;
+
 /// Zhuyin fuzzy matching now uses the shared `libchinese_core::FuzzyMap`.
 ///
 /// The parser is initialized with fuzzy rules from `crate::standard_fuzzy_rules()`
@@ -63,21 +96,105 @@ impl ZhuyinSyllable {
 pub struct ZhuyinParser {
     trie: TrieNode,
     fuzzy: FuzzyMap,
+    incomplete_penalty: f32,
+    tone_recovery: ToneRecovery,
+    layout: Option<ZhuyinLayout>,
 }
 
 impl ZhuyinParser {
     /// Create a parser seeded with a list of valid zhuyin syllables and fuzzy rules.
+    ///
+    /// Toned syllables also add their toneless base ("ㄋㄧˇ" adds "ㄋㄧ"), so
+    /// input typed without tone marks still segments.
     pub fn new<T: AsRef<str>>(fuzzy_rules: Vec<String>, syllables: &[T]) -> Self {
         let mut trie = TrieNode::new();
         for s in syllables {
-            trie.insert(s.as_ref());
+            let s = s.as_ref();
+            trie.insert(s);
+            let (base, tone) = split_tone_mark(s);
+            if tone.is_some() && !base.is_empty() {
+                trie.insert(base);
+            }
         }
         Self {
             trie,
             fuzzy: FuzzyMap::from_rules(&fuzzy_rules),
+            incomplete_penalty: DEFAULT_INCOMPLETE_PENALTY,
+            tone_recovery: ToneRecovery::default(),
+            layout: None,
         }
     }
 
+    /// Accept keys typed on a physical keyboard layout.
+    ///
+    /// Input is converted with `ZhuyinLayout::convert` before segmentation,
+    /// and the fuzzy rules are replaced with the layout's preset.
+    ///
+    /// # Example
+    /// ```
+    /// use libzhuyin::{ZhuyinLayout, ZhuyinParser, ZHUYIN_SYLLABLES};
+    ///
+    /// let parser = ZhuyinParser::new(vec![], ZHUYIN_SYLLABLES).with_layout(ZhuyinLayout::Standard);
+    /// let texts: Vec<String> = parser
+    ///     .segment_best("su3cl3", false)
+    ///     .into_iter()
+    ///     .map(|s| s.text)
+    ///     .collect();
+    /// assert_eq!(texts, vec!["ㄋㄧˇ", "ㄏㄠˇ"]);
+    /// ```
+    pub fn with_layout(mut self, layout: ZhuyinLayout) -> Self {
+        self.fuzzy = FuzzyMap::from_rules(&layout.fuzzy_rules());
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Keyboard layout typed keys are converted with, if any.
+    pub fn layout(&self) -> Option<ZhuyinLayout> {
+        self.layout
+    }
+
+    /// Set how consecutive tone marks are recovered (default `KeepLast`).
+    ///
+    /// See `ZhuyinConfig::zhuyin_tone_recovery`.
+    pub fn with_tone_recovery(mut self, recovery: ToneRecovery) -> Self {
+        self.tone_recovery = recovery;
+        self
+    }
+
+    /// Policy for consecutive tone marks.
+    pub fn tone_recovery(&self) -> ToneRecovery {
+        self.tone_recovery
+    }
+
+    /// Set the segment cost for incomplete syllables (default
+    /// `DEFAULT_INCOMPLETE_PENALTY`). `f32::INFINITY` disables completion
+    /// of incomplete input.
+    pub fn with_incomplete_penalty(mut self, penalty: f32) -> Self {
+        self.incomplete_penalty = penalty;
+        self
+    }
+
+    /// Segment cost for incomplete syllables.
+    pub fn incomplete_penalty(&self) -> f32 {
+        self.incomplete_penalty
+    }
+
+    /// Find a syllable completion for an incomplete prefix.
+    ///
+    /// E.g. "ㄋ" → "ㄋㄚ". Returns the shortest completion (first in
+    /// code-point order among equals), or None if no syllable extends the
+    /// prefix. Complete syllables are only extended, never returned as is.
+    pub fn find_syllable_completion(&self, prefix: &str) -> Option<String> {
+        self.trie.first_completion(prefix)
+    }
+
+    /// All syllables extending an incomplete prefix, shortest first.
+    ///
+    /// E.g. "ㄋ" → ["ㄋㄚ", "ㄋㄜ", ..., "ㄋㄧ", ..., "ㄋㄧㄠ", ...].
+    pub fn syllable_completions(&self, prefix: &str) -> Vec<String> {
+        self.trie.completions(prefix)
+    }
+
     /// Apply zhuyin corrections to a string.
     /// Returns corrected alternatives (similar to pinyin corrections).
     ///
@@ -173,8 +290,17 @@ impl ZhuyinParser {
         allow_fuzzy: bool,
         config: &libchinese_core::Config,
     ) -> Vec<ZhuyinSyllable> {
-        // Normalize: remove whitespace, operate on char vector
-        let chars: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+        // Typed keys become bopomofo first
+        let converted = self.layout.map(|layout| layout.convert(input));
+        let input = converted.as_deref().unwrap_or(input);
+
+        // Normalize: remove whitespace and explicit first-tone marks, collapse
+        // repeated tone marks
+        let chars: Vec<char> = input
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != FIRST_TONE_MARK)
+            .collect();
+        let chars = self.tone_recovery.apply(&chars);
         let n = chars.len();
         if n == 0 {
             return Vec::new();
@@ -226,7 +352,9 @@ impl ZhuyinParser {
                     }
 
                     // Then try fuzzy alternatives
-                    let alts = self.fuzzy.alternatives(&substr);
+                    let alts = self
+                        .fuzzy
+                        .alternatives_limited(&substr, config.max_fuzzy_alternatives);
                     for (alt, penalty) in alts.into_iter() {
                         if self.trie.contains_word(&alt) {
                             // require same char-length match for this placeholder approach
@@ -244,6 +372,25 @@ impl ZhuyinParser {
                 }
             }
 
+            // incomplete syllable (e.g. a lone initial "ㄋ"): only where nothing
+            // else matched, so complete input is never re-read as a prefix
+            if allow_fuzzy && dp_choice[pos].is_none() {
+                for len in 1..=2 {
+                    let end = pos + len;
+                    if end > n || dp_cost[end].is_infinite() {
+                        continue;
+                    }
+                    let incomplete: String = chars[pos..end].iter().collect();
+                    if let Some(completion) = self.find_syllable_completion(&incomplete) {
+                        let cand = self.incomplete_penalty + dp_cost[end];
+                        if cand < dp_cost[pos] {
+                            dp_cost[pos] = cand;
+                            dp_choice[pos] = Some((end, completion, false));
+                        }
+                    }
+                }
+            }
+
             // fallback: consume one char as unknown token with high penalty
             if dp_choice[pos].is_none() {
                 let end = pos + 1;
@@ -341,6 +488,105 @@ mod tests {
         let texts: Vec<String> = seg.into_iter().map(|s| s.text).collect();
         assert_eq!(texts, vec!["ㄋㄧ".to_string(), "X".to_string()]);
     }
+
+    fn texts(seg: Vec<ZhuyinSyllable>) -> Vec<String> {
+        seg.into_iter().map(|s| s.text).collect()
+    }
+
+    #[test]
+    fn lone_initial_is_completed() {
+        let p = ZhuyinParser::new(vec![], &["ㄋㄧ", "ㄋㄚ", "ㄋㄧㄠ", "ㄏㄠ"]);
+
+        assert_eq!(p.find_syllable_completion("ㄋ"), Some("ㄋㄚ".to_string()));
+        assert_eq!(p.syllable_completions("ㄋ"), vec!["ㄋㄚ", "ㄋㄧ", "ㄋㄧㄠ"]);
+        assert_eq!(p.syllable_completions("ㄋㄧ"), vec!["ㄋㄧㄠ"]);
+        assert!(p.syllable_completions("ㄇ").is_empty());
+
+        let seg = texts(p.segment_best("ㄋ", true));
+        assert_eq!(seg.len(), 1);
+        assert!(p.syllable_completions("ㄋ").contains(&seg[0]));
+        assert_eq!(texts(p.segment_best("ㄏㄠㄋ", true)), vec!["ㄏㄠ", "ㄋㄚ"]);
+
+        // Incomplete matching is part of the fuzzy pass
+        assert_eq!(texts(p.segment_best("ㄋ", false)), vec!["ㄋ"]);
+    }
+
+    #[test]
+    fn complete_input_is_not_completed() {
+        let p = ZhuyinParser::new(vec![], &["ㄋㄧ", "ㄋㄚ", "ㄋㄧㄠ", "ㄏㄠ"]);
+
+        assert_eq!(texts(p.segment_best("ㄋㄧ", true)), vec!["ㄋㄧ"]);
+        assert_eq!(
+            texts(p.segment_best("ㄋㄧㄏㄠ", true)),
+            vec!["ㄋㄧ", "ㄏㄠ"]
+        );
+    }
+
+    #[test]
+    fn high_incomplete_penalty_falls_back_to_unknown() {
+        let p = ZhuyinParser::new(vec![], &["ㄋㄧ"]).with_incomplete_penalty(f32::INFINITY);
+        assert_eq!(p.incomplete_penalty(), f32::INFINITY);
+        assert_eq!(texts(p.segment_best("ㄋ", true)), vec!["ㄋ"]);
+    }
+
+    #[test]
+    fn tone_marks_are_captured_per_syllable() {
+        let p = ZhuyinParser::new(vec![], &["ㄋㄧ", "ㄋㄧˇ", "ㄏㄠˇ", "ㄇㄚ˙"]);
+
+        let tones = |input| -> Vec<Option<u8>> {
+            p.segment_best(input, false)
+                .into_iter()
+                .map(|s| s.tone)
+                .collect()
+        };
+        assert_eq!(tones("ㄋㄧˇㄏㄠˇ"), vec![Some(3), Some(3)]);
+        assert_eq!(tones("ㄋㄧㄇㄚ˙"), vec![None, Some(NEUTRAL_TONE)]);
+
+        // An explicit first-tone mark spells the unmarked key
+        assert_eq!(texts(p.segment_best("ㄋㄧˉ", false)), vec!["ㄋㄧ"]);
+
+        let ni3 = ZhuyinSyllable::new("ㄋㄧˇ", false);
+        assert_eq!(ni3.base(), "ㄋㄧ");
+        assert_eq!(
+            libchinese_core::SyllableType::fallback_texts(&ni3),
+            vec!["ㄋㄧ", "ㄋㄧˊ", "ㄋㄧˋ", "ㄋㄧ˙"]
+        );
+        let unknown = ZhuyinSyllable::new("X", false);
+        assert!(libchinese_core::SyllableType::fallback_texts(&unknown).is_empty());
+    }
+
+    #[test]
+    fn double_tone_marks_follow_recovery_policy() {
+        let syllables = ["ㄋㄧ", "ㄋㄧˇ", "ㄋㄧˋ", "ㄏㄠˇ"];
+        let parser = |recovery| ZhuyinParser::new(vec![], &syllables).with_tone_recovery(recovery);
+
+        assert_eq!(
+            parser(ToneRecovery::KeepLast).tone_recovery(),
+            ToneRecovery::KeepLast
+        );
+        assert_eq!(
+            texts(parser(ToneRecovery::KeepLast).segment_best("ㄋㄧˇˋ", false)),
+            vec!["ㄋㄧˋ"]
+        );
+        assert_eq!(
+            texts(parser(ToneRecovery::KeepFirst).segment_best("ㄋㄧˇˋ", false)),
+            vec!["ㄋㄧˇ"]
+        );
+        assert_eq!(
+            texts(parser(ToneRecovery::Reject).segment_best("ㄋㄧˇˋ", false)),
+            vec!["ㄋㄧˇ", "ˋ"]
+        );
+
+        // Three marks, then another syllable
+        assert_eq!(
+            texts(parser(ToneRecovery::KeepLast).segment_best("ㄋㄧˋˊˇㄏㄠˇ", false)),
+            vec!["ㄋㄧˇ", "ㄏㄠˇ"]
+        );
+        assert_eq!(
+            texts(parser(ToneRecovery::KeepFirst).segment_best("ㄋㄧˋˊˇㄏㄠˇ", false)),
+            vec!["ㄋㄧˋ", "ㄏㄠˇ"]
+        );
+    }
 }
 
 // Implement core::SyllableType for ZhuyinSyllable
@@ -352,6 +598,21 @@ impl libchinese_core::SyllableType for ZhuyinSyllable {
     fn is_fuzzy(&self) -> bool {
         self.fuzzy
     }
+
+    /// The syllable in the other tones, so that a phrase whose tones the
+    /// lexicon does not have (or toneless input) still finds entries.
+    fn fallback_texts(&self) -> Vec<String> {
+        let base = self.base();
+        let is_bopomofo = |c: char| ('\u{3105}'..='\u{312F}').contains(&c);
+        if base.is_empty() || !base.chars().all(is_bopomofo) {
+            return Vec::new();
+        }
+
+        std::iter::once(base.to_string())
+            .chain(TONE_MARKS.iter().map(|mark| format!("{}{}", base, mark)))
+            .filter(|text| *text != self.text)
+            .collect()
+    }
 }
 
 // Implement core::SyllableParser for ZhuyinParser
@@ -361,4 +622,21 @@ impl libchinese_core::SyllableParser for ZhuyinParser {
     fn segment_top_k(&self, input: &str, k: usize, allow_fuzzy: bool) -> Vec<Vec<Self::Syllable>> {
         self.segment_top_k(input, k, allow_fuzzy)
     }
+
+    fn fuzzy_rules(&self) -> Vec<String> {
+        self.fuzzy.rules()
+    }
+
+    fn syllable_count(&self) -> usize {
+        self.trie.word_count()
+    }
+
+    fn is_input_key(&self, ch: char) -> bool {
+        self.layout.is_some_and(|layout| layout.is_layout_key(ch))
+    }
+
+    /// Keys typed on a layout show as the bopomofo they stand for.
+    fn display_input(&self, input: &str) -> Option<String> {
+        self.layout.map(|layout| layout.convert(input))
+    }
 }
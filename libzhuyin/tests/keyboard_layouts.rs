@@ -0,0 +1,98 @@
+//! Tests for typing zhuyin on physical keyboard layouts.
+//!
+//! A parser built with `with_layout` converts ASCII keys to bopomofo, so the
+//! IME accepts raw key presses.
+
+use libchinese_core::{Config, ImeEngine, KeyEvent, Lexicon, Model, UserDict, WordBigram};
+use libzhuyin::{ZhuyinLayout, ZhuyinParser, ZHUYIN_SYLLABLES};
+
+fn test_ime(name: &str, layout: ZhuyinLayout) -> ImeEngine<ZhuyinParser> {
+    let path = std::env::temp_dir().join(format!("libzhuyin_{}_{}.redb", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let userdict = UserDict::new(&path).expect("create temp userdict");
+
+    let mut lexicon = Lexicon::new();
+    lexicon.insert("ㄋㄧˇ'ㄏㄠˇ", "你好");
+    lexicon.insert("ㄋㄧˇ", "你");
+    lexicon.insert("ㄋㄧˇ", "妳");
+    let model = Model::new(lexicon, WordBigram::new(), userdict, Config::default());
+    let parser = ZhuyinParser::new(vec![], ZHUYIN_SYLLABLES).with_layout(layout);
+    ImeEngine::new(libchinese_core::Engine::new(model, parser))
+}
+
+fn type_keys(ime: &mut ImeEngine<ZhuyinParser>, keys: &str) {
+    for ch in keys.chars() {
+        ime.process_key(KeyEvent::Char(ch));
+    }
+}
+
+#[test]
+fn standard_keys_convert_to_bopomofo() {
+    let parser = ZhuyinParser::new(vec![], ZHUYIN_SYLLABLES).with_layout(ZhuyinLayout::Standard);
+    let texts: Vec<String> = parser
+        .segment_best("su3cl3", false)
+        .into_iter()
+        .map(|s| s.text)
+        .collect();
+    assert_eq!(texts, vec!["ㄋㄧˇ", "ㄏㄠˇ"]);
+    assert_eq!(parser.layout(), Some(ZhuyinLayout::Standard));
+}
+
+#[test]
+fn standard_keys_produce_candidates() {
+    let mut ime = test_ime("layout_standard", ZhuyinLayout::Standard);
+
+    // Digit tone keys and ',' are phonetic input on this layout
+    type_keys(&mut ime, "su3cl3");
+    assert_eq!(
+        ime.context().candidates.first().map(String::as_str),
+        Some("你好")
+    );
+}
+
+#[test]
+fn every_layout_types_nihao() {
+    for (layout, keys) in [
+        (ZhuyinLayout::Standard, "su3cl3"),
+        (ZhuyinLayout::Eten, "ne3hz3"),
+        (ZhuyinLayout::Hsu, "nefhwf"),
+    ] {
+        let mut ime = test_ime(&format!("layout_{}", layout.name()), layout);
+        type_keys(&mut ime, keys);
+        assert_eq!(
+            ime.context().candidates.first().map(String::as_str),
+            Some("你好"),
+            "{:?}",
+            layout
+        );
+    }
+}
+
+#[test]
+fn preedit_shows_bopomofo_for_layout_keys() {
+    let mut ime = test_ime("layout_preedit", ZhuyinLayout::Standard);
+    type_keys(&mut ime, "su3cl3");
+    assert_eq!(ime.context().preedit_text, "ㄋㄧˇㄏㄠˇ");
+    assert_eq!(ime.context().preedit_cursor, "ㄋㄧˇㄏㄠˇ".len());
+
+    ime.process_key(KeyEvent::Left);
+    assert_eq!(ime.context().preedit_cursor, "ㄋㄧˇㄏㄠ".len());
+}
+
+#[test]
+fn number_keys_select_after_moving_into_the_candidates() {
+    let mut ime = test_ime("layout_select", ZhuyinLayout::Standard);
+    type_keys(&mut ime, "su3");
+    let second = ime.context().candidates[1].clone();
+
+    // '2' is ㄉ on the standard layout: plain typing keeps composing
+    ime.process_key(KeyEvent::Char('2'));
+    assert!(ime.context().commit_text.is_empty());
+    assert_eq!(ime.context().preedit_text, "ㄋㄧˇㄉ");
+    ime.process_key(KeyEvent::Backspace);
+
+    // Once Down has moved into the list, digits select
+    ime.process_key(KeyEvent::Down);
+    ime.process_key(KeyEvent::Char('2'));
+    assert_eq!(ime.context().commit_text, second);
+}
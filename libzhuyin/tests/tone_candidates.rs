@@ -0,0 +1,108 @@
+//! Tests for tone-aware candidate lookup.
+//!
+//! Zhuyin lexicon keys keep their tone marks, so typed tones select entries
+//! directly and toneless input falls back to entries of any tone.
+
+use libchinese_core::{Config, Lexicon, Model, UserDict, WordBigram};
+use libzhuyin::{Engine, ToneRecovery, ZhuyinConfig};
+
+fn test_engine(name: &str, entries: &[(&str, &str)]) -> Engine {
+    test_engine_with_config(name, entries, &ZhuyinConfig::default())
+}
+
+fn test_engine_with_config(name: &str, entries: &[(&str, &str)], config: &ZhuyinConfig) -> Engine {
+    let path = std::env::temp_dir().join(format!("libzhuyin_{}_{}.redb", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let userdict = UserDict::new(&path).expect("create temp userdict");
+
+    let mut lexicon = Lexicon::new();
+    for (key, phrase) in entries {
+        lexicon.insert(*key, *phrase);
+    }
+    Engine::with_config(
+        Model::new(lexicon, WordBigram::new(), userdict, Config::default()),
+        config,
+    )
+}
+
+const NIHAO: &[(&str, &str)] = &[
+    ("ㄋㄧˇ'ㄏㄠˇ", "你好"),
+    // Same syllables, different tones
+    ("ㄋㄧˊ'ㄏㄠˋ", "泥號"),
+];
+
+fn texts(engine: &Engine, input: &str) -> Vec<String> {
+    engine.input(input).into_iter().map(|c| c.text).collect()
+}
+
+#[test]
+fn typed_tones_select_matching_entries() {
+    let engine = test_engine("tone_match", NIHAO);
+
+    let toned = texts(&engine, "ㄋㄧˇㄏㄠˇ");
+    assert_eq!(toned.first().map(String::as_str), Some("你好"));
+    assert!(!toned.contains(&"泥號".to_string()), "{:?}", toned);
+
+    let other = texts(&engine, "ㄋㄧˊㄏㄠˋ");
+    assert_eq!(other.first().map(String::as_str), Some("泥號"));
+    assert!(!other.contains(&"你好".to_string()), "{:?}", other);
+}
+
+#[test]
+fn toneless_input_falls_back_to_any_tone() {
+    let engine = test_engine("tone_fallback", NIHAO);
+
+    let toneless = texts(&engine, "ㄋㄧㄏㄠ");
+    assert!(toneless.contains(&"你好".to_string()), "{:?}", toneless);
+    assert!(toneless.contains(&"泥號".to_string()), "{:?}", toneless);
+
+    // A tone the lexicon has no entry for also falls back
+    let mismatched = texts(&engine, "ㄋㄧˋㄏㄠˇ");
+    assert!(mismatched.contains(&"你好".to_string()), "{:?}", mismatched);
+}
+
+#[test]
+fn first_and_neutral_tones_spell_their_keys() {
+    let engine = test_engine(
+        "tone_first_neutral",
+        &[("ㄇㄚ", "媽"), ("ㄇㄚˇ", "馬"), ("ㄇㄚ˙", "嗎")],
+    );
+
+    // The unmarked syllable is the first tone; an explicit ˉ is the same
+    assert_eq!(
+        texts(&engine, "ㄇㄚ").first().map(String::as_str),
+        Some("媽")
+    );
+    assert_eq!(
+        texts(&engine, "ㄇㄚˉ").first().map(String::as_str),
+        Some("媽")
+    );
+    assert_eq!(
+        texts(&engine, "ㄇㄚ˙").first().map(String::as_str),
+        Some("嗎")
+    );
+    assert!(!texts(&engine, "ㄇㄚ˙").contains(&"馬".to_string()));
+}
+
+#[test]
+fn configured_tone_recovery_picks_the_kept_tone() {
+    const NI: &[(&str, &str)] = &[("ㄋㄧˇ", "你"), ("ㄋㄧˋ", "逆")];
+    let engine = |name, recovery| {
+        let config = ZhuyinConfig {
+            zhuyin_tone_recovery: recovery,
+            ..ZhuyinConfig::default()
+        };
+        test_engine_with_config(name, NI, &config)
+    };
+
+    let keep_last = texts(&engine("tone_keep_last", ToneRecovery::KeepLast), "ㄋㄧˇˋ");
+    assert_eq!(keep_last.first().map(String::as_str), Some("逆"));
+    assert!(!keep_last.contains(&"你".to_string()), "{:?}", keep_last);
+
+    let keep_first = texts(
+        &engine("tone_keep_first", ToneRecovery::KeepFirst),
+        "ㄋㄧˇˋ",
+    );
+    assert_eq!(keep_first.first().map(String::as_str), Some("你"));
+    assert!(!keep_first.contains(&"逆".to_string()), "{:?}", keep_first);
+}
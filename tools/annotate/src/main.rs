@@ -0,0 +1,229 @@
+// tools/annotate/src/main.rs
+//
+// Convert pinyin-annotated text to Hanzi, one line at a time, with the
+// engine's whole-sentence conversion (`Engine::convert_sentence_nbest`).
+//
+// Input lines hold pinyin with syllables or words separated by spaces or
+// apostrophes ("wo shi zhongguoren", "xi'an"); separators are kept as
+// syllable boundaries. Each line becomes one output line with the best
+// conversion, plus a tab and the N best conversions separated by " / " when
+// --nbest is given. Empty lines stay empty.
+//
+// Per-line timing and a summary go to stderr.
+//
+// Usage:
+//   cargo run --bin annotate -- --data-dir <dir> [--no-fuzzy] [--scheme <name>]
+//       [--nbest <n>] [--quiet] [input] [output]
+//
+// Examples:
+//   # Convert a file with the simplified data set
+//   cargo run --bin annotate -- --data-dir data/converted/simplified corpus.txt out.txt
+//
+//   # Shuangpin input, exact spelling only, 5 best per line
+//   cargo run --bin annotate -- --data-dir data/converted/simplified \
+//       --scheme microsoft --no-fuzzy --nbest 5 < corpus.txt
+//
+// The user dictionary is kept in memory, so annotating a corpus does not
+// touch what the IME has learned.
+
+use libchinese_core::{Config, Engine, Lexicon, Model, UserDict, WordBigram};
+use libpinyin::{Parser, PINYIN_SYLLABLES};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: annotate --data-dir <dir> [--no-fuzzy] [--scheme <name>] \
+                     [--nbest <n>] [--quiet] [input] [output]";
+
+/// Command-line options.
+struct Options {
+    data_dir: PathBuf,
+    fuzzy: bool,
+    scheme: Option<String>,
+    nbest: usize,
+    quiet: bool,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let engine = load_engine(&options)?;
+
+    let input: Box<dyn BufRead> = match &options.input {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+    let output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut output = BufWriter::new(output);
+
+    let mut lines = 0;
+    let mut total = Duration::ZERO;
+    let mut slowest: Option<(usize, Duration)> = None;
+
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        let pinyin = normalize_line(&line);
+        if pinyin.is_empty() {
+            writeln!(output)?;
+            continue;
+        }
+
+        let start = Instant::now();
+        let n = options.nbest.max(1);
+        let sentences = engine.convert_sentence_nbest(&pinyin, n);
+        let elapsed = start.elapsed();
+
+        let best = sentences
+            .first()
+            .map(|(text, _)| text.as_str())
+            .unwrap_or(pinyin.as_str());
+        if options.nbest > 0 {
+            let column: Vec<&str> = sentences.iter().map(|(text, _)| text.as_str()).collect();
+            writeln!(output, "{}\t{}", best, column.join(" / "))?;
+        } else {
+            writeln!(output, "{}", best)?;
+        }
+
+        lines += 1;
+        total += elapsed;
+        if slowest.is_none_or(|(_, time)| elapsed > time) {
+            slowest = Some((idx + 1, elapsed));
+        }
+        if !options.quiet {
+            eprintln!("line {}: {:.3} ms", idx + 1, ms(elapsed));
+        }
+    }
+    output.flush()?;
+
+    eprintln!(
+        "Converted {} lines in {:.3} ms ({:.3} ms/line)",
+        lines,
+        ms(total),
+        if lines > 0 {
+            ms(total) / lines as f64
+        } else {
+            0.0
+        }
+    );
+    if let Some((line, time)) = slowest {
+        eprintln!("  Slowest: line {} ({:.3} ms)", line, ms(time));
+    }
+
+    Ok(())
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut data_dir = None;
+    let mut fuzzy = true;
+    let mut scheme = None;
+    let mut nbest = 0;
+    let mut quiet = false;
+    let mut positional = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--data-dir" => data_dir = Some(PathBuf::from(value(&mut iter, arg)?)),
+            "--no-fuzzy" => fuzzy = false,
+            "--scheme" => scheme = Some(value(&mut iter, arg)?.to_string()),
+            "--nbest" => {
+                let n = value(&mut iter, arg)?;
+                nbest = n
+                    .parse()
+                    .map_err(|_| format!("--nbest expects a number, got '{}'", n))?;
+            }
+            "--quiet" => quiet = true,
+            "-h" | "--help" => return Err("Convert pinyin text to Hanzi.".to_string()),
+            other if other.starts_with("--") => {
+                return Err(format!("Unknown option '{}'", other));
+            }
+            other => positional.push(other),
+        }
+    }
+
+    if positional.len() > 2 {
+        return Err("Too many arguments".to_string());
+    }
+    // "-" stands for stdin / stdout
+    let path = |arg: Option<&&str>| arg.filter(|a| **a != "-").map(PathBuf::from);
+
+    Ok(Options {
+        data_dir: data_dir.ok_or("--data-dir is required")?,
+        fuzzy,
+        scheme,
+        nbest,
+        quiet,
+        input: path(positional.first()),
+        output: path(positional.get(1)),
+    })
+}
+
+/// The value following an option.
+fn value<'a>(iter: &mut std::slice::Iter<'a, String>, option: &str) -> Result<&'a str, String> {
+    iter.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("{} expects a value", option))
+}
+
+/// Build a pinyin engine over the artifacts in the data directory.
+fn load_engine(options: &Options) -> Result<Engine<Parser>, Box<dyn std::error::Error>> {
+    let fst_path = options.data_dir.join("lexicon.fst");
+    let bincode_path = options.data_dir.join("lexicon.bincode");
+    let lexicon = Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).map_err(|e| {
+        format!(
+            "failed to load lexicon from {:?} and {:?}: {}",
+            fst_path, bincode_path, e
+        )
+    })?;
+
+    let wb_path = options.data_dir.join("word_bigram.bin");
+    let word_bigram = if wb_path.exists() {
+        WordBigram::load(&wb_path)?
+    } else {
+        eprintln!("word_bigram.bin not found, using empty model");
+        WordBigram::new()
+    };
+
+    let mut parser = Parser::with_syllables(PINYIN_SYLLABLES);
+    if !options.fuzzy {
+        parser.fuzzy_map_mut().clear();
+    }
+    if let Some(scheme) = &options.scheme {
+        // Reject unknown scheme names instead of silently reading full pinyin
+        parser
+            .convert_double_pinyin_checked("", scheme)
+            .map_err(|e| e.to_string())?;
+        parser.set_double_pinyin_scheme(Some(scheme));
+    }
+
+    let model = Model::new(
+        lexicon,
+        word_bigram,
+        UserDict::in_memory()?,
+        Config::default(),
+    );
+    Ok(Engine::new(model, parser))
+}
+
+/// Join the words of a line with apostrophes, the engine's syllable
+/// separator.
+fn normalize_line(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join("'")
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
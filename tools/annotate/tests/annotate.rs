@@ -0,0 +1,101 @@
+//! Runs the annotate binary over a fixture file.
+//!
+//! A small lexicon and word model are written to a temporary data directory so the test does
+//! not depend on the converted data files.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Write `lexicon.fst`, `lexicon.bincode` and a `word_bigram.bin` with a
+/// unigram count for every phrase under `dir`.
+fn write_data(dir: &Path, groups: &[(&str, &[&str])]) {
+    let mut word_bigram = libchinese_core::WordBigram::new();
+    let phrases: Vec<Vec<(&str, u32)>> = groups
+        .iter()
+        .map(|(_, phrases)| phrases.iter().map(|text| (*text, 1)).collect())
+        .collect();
+    for (text, _) in phrases.iter().flatten() {
+        word_bigram.add_unigram(text.to_string(), 10);
+    }
+    let groups: Vec<(&str, &[(&str, u32)])> = groups
+        .iter()
+        .zip(&phrases)
+        .map(|((key, _), phrases)| (*key, &phrases[..]))
+        .collect();
+
+    libchinese_core::Lexicon::write_fst_bincode(
+        dir.join("lexicon.fst"),
+        dir.join("lexicon.bincode"),
+        &groups,
+    )
+    .unwrap();
+    word_bigram.save(dir.join("word_bigram.bin")).unwrap();
+}
+
+fn data_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("annotate_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    write_data(
+        &dir,
+        &[
+            ("wo", &["我"]),
+            ("shi", &["是"]),
+            ("zhong'guo", &["中国"]),
+            ("zhong'guo'ren", &["中国人"]),
+            ("ni", &["你"]),
+            ("hao", &["好"]),
+            ("ni'hao", &["你好"]),
+            ("xi", &["西"]),
+            ("an", &["安"]),
+            ("xi'an", &["西安"]),
+        ],
+    );
+    dir
+}
+
+#[test]
+fn converts_fixture_file_line_by_line() {
+    let dir = data_dir("fixture");
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sentences.txt");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_annotate"))
+        .arg("--data-dir")
+        .arg(&dir)
+        .args(["--nbest", "2"])
+        .arg(&fixture)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let best: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split('\t').next().unwrap())
+        .collect();
+    assert_eq!(best, ["我是中国人", "", "你好", "西安"]);
+    // The N-best column starts with the best conversion
+    assert!(stdout.lines().next().unwrap().contains("\t我是中国人"));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("line 4: "));
+    assert!(stderr.contains("Converted 3 lines"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rejects_unknown_scheme_and_missing_data_dir() {
+    let dir = data_dir("scheme");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_annotate"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--data-dir", dir.to_str().unwrap(), "--scheme", "nosuch"]);
+    assert!(!output.status.success());
+    assert!(!run(&["--nbest", "2"]).status.success());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,81 @@
+// tools/export_userdict/src/main.rs
+//
+// Export the contents of a user dictionary as CSV, for debugging and for
+// reviewing what has been learned, or as a JSON snapshot for backups.
+//
+// Usage:
+//   cargo run --bin export_userdict <userdict.redb> [phrases|bigrams|snapshot]
+//
+// Examples:
+//   # Learned phrases: phrase,count
+//   cargo run --bin export_userdict ~/.pinyin/userdict.redb
+//
+//   # Learned bigrams: w1,w2,count
+//   cargo run --bin export_userdict ~/.pinyin/userdict.redb bigrams > bigrams.csv
+//
+//   # Everything learned, as a UserDictSnapshot in JSON
+//   cargo run --bin export_userdict ~/.pinyin/userdict.redb snapshot > backup.json
+
+use libchinese_core::UserDict;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: {} <userdict.redb> [phrases|bigrams|snapshot]",
+            args[0]
+        );
+        std::process::exit(1);
+    }
+
+    let path = PathBuf::from(&args[1]);
+    if !path.is_file() {
+        // UserDict::new would create an empty database here
+        eprintln!("No user dictionary at {}", path.display());
+        std::process::exit(1);
+    }
+    let dict = UserDict::new(&path)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match args.get(2).map(String::as_str).unwrap_or("phrases") {
+        "phrases" => {
+            let mut phrases = dict.iter_all();
+            phrases.sort();
+            writeln!(out, "phrase,count")?;
+            for (phrase, count) in phrases {
+                writeln!(out, "{},{}", csv_field(&phrase), count)?;
+            }
+        }
+        "bigrams" => {
+            writeln!(out, "w1,w2,count")?;
+            for ((w1, w2), count) in dict.export_bigrams() {
+                writeln!(out, "{},{},{}", csv_field(&w1), csv_field(&w2), count)?;
+            }
+        }
+        "snapshot" => {
+            serde_json::to_writer_pretty(&mut out, &dict.export_snapshot())?;
+            writeln!(out)?;
+        }
+        other => {
+            eprintln!(
+                "Unknown table '{}', expected phrases, bigrams or snapshot",
+                other
+            );
+            std::process::exit(1);
+        }
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Quote a field if it contains a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
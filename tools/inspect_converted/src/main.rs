@@ -174,9 +174,76 @@ fn inspect_dataset(dataset_path: &str, search_key: Option<&str>) {
     }
 }
 
+/// Load a dataset's lexicon and report FST / payload mismatches.
+/// Returns true if the lexicon loaded and validated cleanly.
+fn check_dataset(dataset_path: &str) -> bool {
+    println!("\n=== Checking {} ===", dataset_path);
+
+    let dir = Path::new(dataset_path);
+    let mut lexicon = match libchinese_core::Lexicon::load_from_fst_bincode(
+        dir.join("lexicon.fst"),
+        dir.join("lexicon.bincode"),
+    ) {
+        Ok(lexicon) => lexicon,
+        Err(e) => {
+            println!("❌ Failed to load lexicon: {}", e);
+            return false;
+        }
+    };
+    let shape_path = dir.join("lexicon.shape.fst");
+    if shape_path.exists() {
+        if let Err(e) = lexicon.load_shape_index(&shape_path) {
+            println!("❌ Failed to load lexicon.shape.fst: {}", e);
+            return false;
+        }
+    }
+
+    match lexicon.validate() {
+        Ok(()) => {
+            println!("✓ lexicon.fst and lexicon.bincode match");
+            true
+        }
+        Err(errors) => {
+            println!("❌ {} problems:", errors.len());
+            for error in errors.iter().take(20) {
+                println!("    {}", error);
+            }
+            if errors.len() > 20 {
+                println!("    ... and {} more", errors.len() - 20);
+            }
+            false
+        }
+    }
+}
+
+fn default_datasets() -> Vec<String> {
+    vec![
+        "data/converted/simplified".to_string(),
+        "data/converted/traditional".to_string(),
+        "data/converted/zhuyin_traditional".to_string(),
+    ]
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
+    // --check [dataset...]: validate lexicon artifacts, exit 1 on problems
+    if args.get(1).map(String::as_str) == Some("--check") {
+        let datasets = if args.len() > 2 {
+            args[2..].to_vec()
+        } else {
+            default_datasets()
+        };
+        let mut ok = true;
+        for dataset in &datasets {
+            ok &= check_dataset(dataset);
+        }
+        if !ok {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let (datasets, search_key) = if args.len() >= 2 {
         // If path provided, inspect just that one
         if args[1].starts_with("data/") {
@@ -188,23 +255,11 @@ fn main() {
             (vec![args[1].clone()], search)
         } else {
             // First arg is search key
-            let datasets = vec![
-                "data/converted/simplified".to_string(),
-                "data/converted/traditional".to_string(),
-                "data/converted/zhuyin_traditional".to_string(),
-            ];
-            (datasets, Some(args[1].as_str()))
+            (default_datasets(), Some(args[1].as_str()))
         }
     } else {
         // Default: inspect all
-        (
-            vec![
-                "data/converted/simplified".to_string(),
-                "data/converted/traditional".to_string(),
-                "data/converted/zhuyin_traditional".to_string(),
-            ],
-            None,
-        )
+        (default_datasets(), None)
     };
 
     for dataset in datasets {
@@ -0,0 +1,250 @@
+// tools/train_ngram/src/main.rs
+//
+// Train a word bigram model (word_bigram.bin) from raw UTF-8 Chinese text,
+// for users who have a corpus rather than upstream's pre-counted
+// interpolation2.text (see gen_word_bigrams).
+//
+// The text is split into runs of Han characters; anything else breaks the
+// n-gram chain. Each run is tokenized into single characters, or into
+// lexicon words by greedy longest match when --lexicon-dir is given
+// (characters the lexicon does not know break the chain, as in
+// `Engine::train_from_text`). N-grams below --min-count are dropped.
+//
+// The models store raw counts; probabilities and smoothing of unseen
+// transitions are applied when scoring (see `Smoothing`, and `KneserNey`
+// for the Kneser-Ney estimate over bigrams and trigrams). --order is 1
+// (unigrams only), 2 (default) or 3; word_bigram.bin has no trigram
+// table, so with --order 3 the trigrams are written to word_trigram.bin
+// next to it (see `WordTrigram`).
+//
+// Usage:
+//   cargo run --bin train_ngram -- --output <word_bigram.bin> [--lexicon-dir <dir>]
+//       [--order 1|2|3] [--min-count <n>] [corpus...]
+//
+// Examples:
+//   # Character bigrams from two text files
+//   cargo run --bin train_ngram -- --output data/custom/word_bigram.bin news.txt chat.txt
+//
+//   # Word bigrams over the simplified lexicon, ignoring singletons
+//   cargo run --bin train_ngram -- --output data/custom/word_bigram.bin \
+//       --lexicon-dir data/converted/simplified --min-count 2 < corpus.txt
+
+use libchinese_core::char_freq::is_han_char;
+use libchinese_core::{Lexicon, WordBigram, WordTrigram};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: train_ngram --output <word_bigram.bin> [--lexicon-dir <dir>] \
+                     [--order 1|2|3] [--min-count <n>] [corpus...]";
+
+/// Longest lexicon word probed when tokenizing (mirrors
+/// `Engine::train_from_text`).
+const MAX_WORD_CHARS: usize = 10;
+
+/// Command-line options.
+struct Options {
+    output: PathBuf,
+    lexicon_dir: Option<PathBuf>,
+    order: usize,
+    min_count: u32,
+    corpora: Vec<PathBuf>,
+}
+
+/// Unigram, bigram and trigram counts of a corpus.
+#[derive(Default)]
+struct Counts {
+    unigrams: HashMap<String, u32>,
+    bigrams: HashMap<(String, String), u32>,
+    trigrams: HashMap<(String, String, String), u32>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let lexicon = match &options.lexicon_dir {
+        Some(dir) => {
+            let fst_path = dir.join("lexicon.fst");
+            let bincode_path = dir.join("lexicon.bincode");
+            let mut lexicon =
+                Lexicon::load_from_fst_bincode(&fst_path, &bincode_path).map_err(|e| {
+                    format!(
+                        "failed to load lexicon from {:?} and {:?}: {}",
+                        fst_path, bincode_path, e
+                    )
+                })?;
+            // Tokenizing checks candidate words with contains_phrase
+            lexicon.build_reverse_index();
+            Some(lexicon)
+        }
+        None => None,
+    };
+
+    let mut counts = Counts::default();
+    if options.corpora.is_empty() {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        count_text(&text, lexicon.as_ref(), &mut counts);
+    }
+    for path in &options.corpora {
+        println!("Counting {}...", path.display());
+        let text = std::fs::read_to_string(path)?;
+        count_text(&text, lexicon.as_ref(), &mut counts);
+    }
+
+    // Sorted, so the same corpus always gives the same file
+    let mut unigrams: Vec<_> = counts.unigrams.into_iter().collect();
+    unigrams.retain(|(_, count)| *count >= options.min_count);
+    unigrams.sort();
+    let mut bigrams: Vec<_> = counts.bigrams.into_iter().collect();
+    bigrams.retain(|(_, count)| options.order >= 2 && *count >= options.min_count);
+    bigrams.sort();
+    let mut trigrams: Vec<_> = counts.trigrams.into_iter().collect();
+    trigrams.retain(|(_, count)| options.order >= 3 && *count >= options.min_count);
+    trigrams.sort();
+
+    let mut word_bigram = WordBigram::new();
+    let (unigram_total, bigram_total) = (unigrams.len(), bigrams.len());
+    for (word, count) in unigrams {
+        word_bigram.add_unigram(word, count);
+    }
+    for ((w1, w2), count) in bigrams {
+        word_bigram.add_bigram(w1, w2, count);
+    }
+
+    println!("Saving to {}...", options.output.display());
+    if let Some(parent) = options.output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    word_bigram.save(&options.output)?;
+
+    let trigram_total = trigrams.len();
+    if options.order >= 3 {
+        let mut word_trigram = WordTrigram::new();
+        for ((w1, w2, w3), count) in trigrams {
+            word_trigram.add_trigram(w1, w2, w3, count);
+        }
+        let trigram_path = options.output.with_file_name("word_trigram.bin");
+        println!("Saving to {}...", trigram_path.display());
+        word_trigram.save(&trigram_path)?;
+    }
+
+    println!("\n✓ Word bigram model trained successfully!");
+    println!("  Unigrams: {}", unigram_total);
+    println!("  Bigrams: {}", bigram_total);
+    if options.order >= 3 {
+        println!("  Trigrams: {}", trigram_total);
+    }
+
+    Ok(())
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut output = None;
+    let mut lexicon_dir = None;
+    let mut order = 2;
+    let mut min_count = 1;
+    let mut corpora = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--output" => output = Some(PathBuf::from(value(&mut iter, arg)?)),
+            "--lexicon-dir" => lexicon_dir = Some(PathBuf::from(value(&mut iter, arg)?)),
+            "--order" => {
+                order = match value(&mut iter, arg)? {
+                    "1" => 1,
+                    "2" => 2,
+                    "3" => 3,
+                    other => return Err(format!("--order expects 1, 2 or 3, got '{}'", other)),
+                };
+            }
+            "--min-count" => {
+                let n = value(&mut iter, arg)?;
+                min_count = n
+                    .parse()
+                    .map_err(|_| format!("--min-count expects a number, got '{}'", n))?;
+            }
+            "-h" | "--help" => return Err("Train word_bigram.bin from raw text.".to_string()),
+            other if other.starts_with("--") => {
+                return Err(format!("Unknown option '{}'", other));
+            }
+            other => corpora.push(PathBuf::from(other)),
+        }
+    }
+
+    Ok(Options {
+        output: output.ok_or("--output is required")?,
+        lexicon_dir,
+        order,
+        min_count,
+        corpora,
+    })
+}
+
+/// The value following an option.
+fn value<'a>(iter: &mut std::slice::Iter<'a, String>, option: &str) -> Result<&'a str, String> {
+    iter.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("{} expects a value", option))
+}
+
+/// Count the unigrams, bigrams and trigrams of `text`.
+fn count_text(text: &str, lexicon: Option<&Lexicon>, counts: &mut Counts) {
+    for run in text.split(|ch: char| !is_han_char(ch)) {
+        let (mut prev2, mut prev): (Option<String>, Option<String>) = (None, None);
+        for token in tokenize(run, lexicon) {
+            let Some(word) = token else {
+                (prev2, prev) = (None, None);
+                continue;
+            };
+            *counts.unigrams.entry(word.clone()).or_insert(0) += 1;
+            if let Some(p) = &prev {
+                *counts.bigrams.entry((p.clone(), word.clone())).or_insert(0) += 1;
+                if let Some(p2) = prev2 {
+                    let trigram = (p2, p.clone(), word.clone());
+                    *counts.trigrams.entry(trigram).or_insert(0) += 1;
+                }
+            }
+            (prev2, prev) = (prev, Some(word));
+        }
+    }
+}
+
+/// Split a run of Han characters into characters, or into lexicon words
+/// by greedy longest match. `None` marks a character the lexicon does not
+/// know.
+fn tokenize(run: &str, lexicon: Option<&Lexicon>) -> Vec<Option<String>> {
+    let chars: Vec<char> = run.chars().collect();
+    let Some(lexicon) = lexicon else {
+        return chars.iter().map(|ch| Some(ch.to_string())).collect();
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let max_len = std::cmp::min(MAX_WORD_CHARS, chars.len() - i);
+        let word = (1..=max_len)
+            .rev()
+            .map(|len| chars[i..i + len].iter().collect::<String>())
+            .find(|w| lexicon.contains_phrase(w));
+        match word {
+            Some(word) => {
+                i += word.chars().count();
+                tokens.push(Some(word));
+            }
+            None => {
+                i += 1;
+                tokens.push(None);
+            }
+        }
+    }
+    tokens
+}
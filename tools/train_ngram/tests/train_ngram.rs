@@ -0,0 +1,116 @@
+//! Runs the train_ngram binary over a tiny embedded corpus.
+
+use libchinese_core::{Config, KneserNey, Lexicon, Smoothing, WordBigram, WordTrigram};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const CORPUS: &str = "我们是中国人。中国很大，中国人很多。\n我们爱中国。\nhello 中文";
+
+/// Write `lexicon.fst` and `lexicon.bincode` under `dir`.
+fn write_lexicon(dir: &Path, groups: &[(&str, &[(&str, u32)])]) {
+    Lexicon::write_fst_bincode(dir.join("lexicon.fst"), dir.join("lexicon.bincode"), groups)
+        .unwrap();
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("train_ngram_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("corpus.txt"), CORPUS).unwrap();
+    dir
+}
+
+fn train(dir: &Path, extra: &[&str]) -> WordBigram {
+    let output = dir.join("word_bigram.bin");
+    let status = Command::new(env!("CARGO_BIN_EXE_train_ngram"))
+        .arg("--output")
+        .arg(&output)
+        .args(extra)
+        .arg(dir.join("corpus.txt"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    WordBigram::load(&output).unwrap()
+}
+
+#[test]
+fn learned_character_bigram_outscores_unseen_one() {
+    let dir = temp_dir("chars");
+
+    let model = train(&dir, &[]);
+    assert!(model.get_probability("中", "国") > model.get_probability("中", "文"));
+    assert!(model.get_log_probability("中", "国") > model.get_log_probability("国", "中"));
+    // Punctuation and Latin text break the chain
+    assert_eq!(model.get_probability("人", "中"), 0.0);
+    assert_eq!(model.get_probability("o", "中"), 0.0);
+
+    // 们是 occurs once and falls under the cutoff; 中国 (4 times) stays
+    let model = train(&dir, &["--min-count", "2"]);
+    assert_eq!(model.get_probability("们", "是"), 0.0);
+    assert!(model.get_probability("中", "国") > 0.0);
+
+    let model = train(&dir, &["--order", "1"]);
+    assert_eq!(model.total_bigrams(), 0);
+    assert!(model.get_unigram_probability("国") > 0.0);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn lexicon_tokenizes_into_words() {
+    let dir = temp_dir("words");
+    write_lexicon(
+        &dir.join("lexicon"),
+        &[
+            ("wo'men", &[("我们", 1)]),
+            ("shi", &[("是", 1)]),
+            ("zhong'guo", &[("中国", 1)]),
+            ("zhong'guo'ren", &[("中国人", 1)]),
+            ("ai", &[("爱", 1)]),
+        ],
+    );
+
+    let model = train(
+        &dir,
+        &["--lexicon-dir", dir.join("lexicon").to_str().unwrap()],
+    );
+    assert!(model.get_probability("我们", "是") > 0.0);
+    assert!(model.get_probability("是", "中国人") > 0.0);
+    assert!(model.get_probability("爱", "中国") > model.get_probability("爱", "中国人"));
+    // Characters the lexicon does not know are not counted
+    assert_eq!(model.get_unigram_probability("很"), 0.0);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn trigram_order_writes_trigram_counts() {
+    let dir = temp_dir("order");
+    assert!(!dir.join("word_trigram.bin").exists());
+    train(&dir, &[]);
+    assert!(!dir.join("word_trigram.bin").exists());
+
+    let model = train(&dir, &["--order", "3"]);
+    let trigrams = WordTrigram::load(dir.join("word_trigram.bin")).unwrap();
+    // 中国人 twice, 中国很 and 中国。 once each (punctuation ends the chain)
+    assert_eq!(trigrams.count("中", "国", "人"), 2);
+    assert_eq!(trigrams.count("中", "国", "很"), 1);
+    assert_eq!(trigrams.count("人", "中", "国"), 0);
+
+    let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+    let kn = KneserNey::new(&model).with_trigrams(&trigrams);
+    let cfg = Config::default();
+    let seen = words(&["中", "国", "人"]);
+    let rarer = words(&["中", "国", "很"]);
+    let unseen = words(&["中", "国", "爱"]);
+    assert!(kn.score(&model, &seen, 2, &cfg) > kn.score(&model, &rarer, 2, &cfg));
+    assert!(kn.score(&model, &rarer, 2, &cfg) > kn.score(&model, &unseen, 2, &cfg));
+
+    // The cutoff applies to trigrams too
+    train(&dir, &["--order", "3", "--min-count", "2"]);
+    let trigrams = WordTrigram::load(dir.join("word_trigram.bin")).unwrap();
+    assert_eq!(trigrams.count("中", "国", "很"), 0);
+    assert_eq!(trigrams.count("中", "国", "人"), 2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}